@@ -37,7 +37,7 @@ fn main_with_type<F>() -> Result<()> where F: BaseFloat + 'static {
 
     // material
     {
-        let material: Material<F> = Material { material_impl: Box::new(DiffuseBRDFMaterial::new(Vector3::new(f!(1.0), f!(0.8), f!(0.2))) ) };
+        let material: Material<F> = Material::new(Box::new(DiffuseBRDFMaterial::new(Vector3::new(f!(1.0), f!(0.8), f!(0.2))) ));
         // let material: Material<F> = Material { material_impl: Box::new(AbsorptionVolumeMaterial::new(Vector3::new(f!(1.0), f!(0.5), f!(0.2)))) };
         // let material: Material<F> = Material {
         //     material_impl: Box::new(ConductorBRDF::gold_in_air())
@@ -7,7 +7,7 @@ use aika_core::scene::{GameObject, Scene};
 use anyhow::Result;
 use image::RgbImage;
 use indicatif::ProgressBar;
-use aika_core::component::{MeshFilter, Transform};
+use aika_core::component::{MeshFilter, ShapeFilter, Transform};
 use aika_core::lighting::{DirectionalLightComponent, SphericalLight, SphericalLightComponent};
 use aika_core::material::{AbsorptionVolumeMaterial, ConductorBRDF, DielectricMaterial, DiffuseBRDF, DiffuseBRDFMaterial, Material, MaterialConstants, MaterialType, MetallicRoughnessBRDFMaterial, RoughConductorBRDF, RoughConductorBRDFMaterial, RoughDielectricBSDFMaterial, UniformEmitMaterial};
 use aika_core::material_graph::Texture2DNode;
@@ -50,7 +50,7 @@ fn get_plane<F>() -> GameObject<F> where F: BaseFloat + 'static {
     // material
     {
         // let material = Material { material_impl: Box::new(RoughConductorBRDFMaterial::new(f!(0.2), MaterialConstants::gold_ior())) };
-        let material: Material<F> = Material { material_impl: Box::new(DiffuseBRDFMaterial::new(Vector3::new(f!(0.1), f!(0.8), f!(0.6))) ) };
+        let material: Material<F> = Material::new(Box::new(DiffuseBRDFMaterial::new(Vector3::new(f!(0.1), f!(0.8), f!(0.6))) ));
         // let material: Material<F> = Material { material_impl: Box::new(AbsorptionVolumeMaterial::new(Vector3::new(f!(1.0), f!(0.5), f!(0.2)))) };
         // let material = Material { material_impl: Box::new(UniformEmitMaterial::new(Vector3::new(f!(1), f!(0), f!(0)))) };
         game_object.add_component_owned(material);
@@ -88,7 +88,7 @@ fn get_sphere<F>() -> GameObject<F> where F: BaseFloat + 'static {
         // let material = Material { material_impl: Box::new(RoughConductorBRDFMaterial::new(f!(0.2), MaterialConstants::gold_ior())) };
         // let material: Material<F> = Material { material_impl: Box::new(DiffuseBRDFMaterial::new(Vector3::new(f!(1.0), f!(0.5), f!(0.2))) ) };
         // let material: Material<F> = Material { material_impl: Box::new(AbsorptionVolumeMaterial::new(Vector3::new(f!(1.0), f!(0.5), f!(0.2)))) };
-        let material = Material { material_impl: Box::new(UniformEmitMaterial::new(Vector3::new(f!(5), f!(5), f!(6)) * f!(0.2))) };
+        let material = Material::new(Box::new(UniformEmitMaterial::new(Vector3::new(f!(5), f!(5), f!(6)) * f!(0.2))));
         game_object.add_component_owned(material);
     }
 
@@ -147,10 +147,8 @@ fn get_torus<F: BaseFloat + 'static>() -> GameObject<F> {
     {
         // let mesh: DynMesh<F> = WavefrontMeshLoader::torus().unwrap().to_dyn_mesh();
         // let mesh: DynMesh<F> = WavefrontMeshLoader::lucy().unwrap().to_dyn_mesh();
-        let mesh: DynMesh<F> = WavefrontMeshLoader::sphere().unwrap().to_dyn_mesh();
         // let mesh: DynMesh<F> = WavefrontMeshLoader::suzanne().unwrap().to_dyn_mesh();
-        let mesh_filter = MeshFilter::new(mesh);
-        game_object.add_component_owned(mesh_filter);
+        game_object.add_component_owned(ShapeFilter::sphere(F::one()));
     }
 
     // material
@@ -162,7 +160,7 @@ fn get_torus<F: BaseFloat + 'static>() -> GameObject<F> {
                 Vector3::new(F::one(), F::one(), F::one())
             ));
         // let material: Material<F> = Material { material_impl: Box::new(DiffuseBRDFMaterial::new(Vector3::new(f!(1.0), f!(0.8), f!(0.2))) ) };
-        let material: Material<F> = Material { material_impl: Box::new(AbsorptionVolumeMaterial::new(Vector3::new(f!(0.1), f!(0.5), f!(0.2)))) };
+        let material: Material<F> = Material::new(Box::new(AbsorptionVolumeMaterial::new(Vector3::new(f!(0.1), f!(0.5), f!(0.2)))));
         // let material: Material<F> = Material { material_impl: Box::new(AbsorptionVolumeMaterial::new(Vector3::new(f!(0), f!(0), f!(0)))) };
         // let material: Material<F> = Material {
         //     material_impl: Box::new(ConductorBRDF::gold_in_air())
@@ -43,7 +43,7 @@ fn get_plane<F>() -> GameObject<F> where F: BaseFloat + 'static {
     // material
     {
         // let material = Material { material_impl: Box::new(RoughConductorBRDFMaterial::new(f!(0.2), MaterialConstants::gold_ior())) };
-        let material: Material<F> = Material { material_impl: Box::new(DiffuseBRDFMaterial::new(Vector3::new(f!(1.0), f!(0.5), f!(0.2))) ) };
+        let material: Material<F> = Material::new(Box::new(DiffuseBRDFMaterial::new(Vector3::new(f!(1.0), f!(0.5), f!(0.2))) ));
         // let material: Material<F> = Material { material_impl: Box::new(AbsorptionVolumeMaterial::new(Vector3::new(f!(1.0), f!(0.5), f!(0.2)))) };
         // let material = Material { material_impl: Box::new(UniformEmitMaterial::new(Vector3::new(f!(1), f!(0), f!(0)))) };
         game_object.add_component_owned(material);
@@ -81,7 +81,7 @@ fn get_sphere<F>() -> GameObject<F> where F: BaseFloat + 'static {
         // let material = Material { material_impl: Box::new(RoughConductorBRDFMaterial::new(f!(0.2), MaterialConstants::gold_ior())) };
         // let material: Material<F> = Material { material_impl: Box::new(DiffuseBRDFMaterial::new(Vector3::new(f!(1.0), f!(0.5), f!(0.2))) ) };
         // let material: Material<F> = Material { material_impl: Box::new(AbsorptionVolumeMaterial::new(Vector3::new(f!(1.0), f!(0.5), f!(0.2)))) };
-        let material = Material { material_impl: Box::new(UniformEmitMaterial::new(Vector3::new(f!(1), f!(1), f!(1)))) };
+        let material = Material::new(Box::new(UniformEmitMaterial::new(Vector3::new(f!(1), f!(1), f!(1)))));
         game_object.add_component_owned(material);
     }
 
@@ -116,7 +116,7 @@ fn get_torus<F>() -> GameObject<F> where F: BaseFloat + 'static {
         // let material: Material<F> = Material {
         //     material_impl: Box::new(ConductorBRDF::gold_in_air())
         // };
-        let material = Material { material_impl: Box::new(DielectricMaterial::new(Vector3::new(f!(2.0), f!(2.0), f!(2.0)))) };
+        let material = Material::new(Box::new(DielectricMaterial::new(Vector3::new(f!(2.0), f!(2.0), f!(2.0)))));
         // let material = Material { material_impl: Box::new(RoughConductorBRDFMaterial::new(f!(0.2), MaterialConstants::gold_ior())) };
         game_object.add_component_owned(material);
     }
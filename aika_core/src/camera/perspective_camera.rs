@@ -1,7 +1,7 @@
 use cgmath::{BaseFloat, Matrix4, Rad, Rotation, Vector2, Vector3};
 use num_traits::Zero;
 use aika_math::Ray;
-use crate::component::Transform;
+use crate::component::{ComponentData, Transform};
 
 /// we assume, initial, the camera is looking at (0, 0, -1) (-z), with right hand coordinate system
 /// and the up vector in (0, 1, 0) (+y)
@@ -14,6 +14,8 @@ pub struct PerspectiveCamera<F> {
     pub aspect: F,
 }
 
+impl<F> ComponentData for PerspectiveCamera<F> where F: BaseFloat + 'static {}
+
 struct FToRad<F> {
     value: F,
 }
@@ -87,6 +87,14 @@ impl<F> Component<F> where F: BaseFloat {
         }
     }
 
+    pub fn downcast_mut<C: ComponentData>(&self) -> ComponentDowncastRefMut<'_, F, C> {
+        let borrow = self.c.borrow_mut();
+        ComponentDowncastRefMut {
+            r: borrow,
+            _phantom: PhantomData
+        }
+    }
+
     pub fn new_owned<C: ComponentData>(go: GameObject<F>, data: C) -> Component<F> {
         let c: Box<dyn Any> = Box::new(data);
         let internal_component = ComponentInternal {
@@ -1,20 +1,69 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use anyhow::Result;
 use cgmath::BaseFloat;
 use crate::component::{ComponentData};
-use crate::mesh::DynMesh;
+use crate::mesh::{CommonVertex, DynMesh, Mesh, PolygonMesh, SubdivisionScheme};
+
+/// A subdivision surface which is computed when the scene is mashed
+pub struct MeshSubdivision<F> {
+    pub scheme: SubdivisionScheme,
+    pub levels: usize,
+    /// The control mesh with its polygons and creases.
+    /// If None, the triangles of `MeshFilter::mesh` are the control mesh
+    pub control_mesh: Option<PolygonMesh<F>>,
+}
+
+impl<F> MeshSubdivision<F> where F: BaseFloat {
+    pub fn subdivide(&self, mesh: &DynMesh<F>) -> Result<Mesh<Vec<CommonVertex<F>>>> {
+        let subdivided = match &self.control_mesh {
+            Some(control_mesh) => control_mesh.subdivide(self.scheme, self.levels)?,
+            None => PolygonMesh::from_mesh(mesh).subdivide(self.scheme, self.levels)?,
+        };
+        subdivided.to_mesh()
+    }
+}
 
 pub struct MeshFilter<F> {
     // pub mesh: Rc<RefCell<DynMesh<F>>>,
     pub mesh: DynMesh<F>,
+    pub subdivision: Option<MeshSubdivision<F>>,
+    /// `mesh` after the subdivision and the displacement of the material, filled when the scene is mashed
+    pub built_mesh: Option<DynMesh<F>>,
 }
 
 impl<F> MeshFilter<F> where F: BaseFloat {
     pub fn new(mesh: DynMesh<F>) -> Self {
         Self {
-            mesh
+            mesh,
+            subdivision: None,
+            built_mesh: None,
         }
     }
+
+    /// `mesh` is the triangulated control mesh, which is rendered if the subdivision fails
+    pub fn from_polygon_mesh(control_mesh: PolygonMesh<F>, scheme: SubdivisionScheme, levels: usize) -> Result<Self> where F: 'static {
+        let mut mesh_filter = MeshFilter::new(control_mesh.to_mesh()?.to_dyn_mesh());
+        mesh_filter.subdivision = Some(MeshSubdivision {
+            scheme,
+            levels,
+            control_mesh: Some(control_mesh),
+        });
+        Ok(mesh_filter)
+    }
+
+    /// Subdivide the triangles of `mesh`
+    pub fn with_subdivision(mut self, scheme: SubdivisionScheme, levels: usize) -> Self {
+        self.subdivision = Some(MeshSubdivision {
+            scheme,
+            levels,
+            control_mesh: None,
+        });
+        self
+    }
+
+    /// The mesh which is rendered
+    pub fn get_mesh(&self) -> &DynMesh<F> {
+        self.built_mesh.as_ref().unwrap_or(&self.mesh)
+    }
 }
 
 impl<F> ComponentData for MeshFilter<F> where F: BaseFloat + 'static {}
@@ -1,7 +1,9 @@
 pub use component::{ComponentData, Component, ComponentDowncastRef};
 pub use transform::Transform;
-pub use mesh_filter::MeshFilter;
+pub use mesh_filter::{MeshFilter, MeshSubdivision};
+pub use shape_filter::{AnalyticShape, ShapeFilter};
 
 mod component;
 mod mesh_filter;
+mod shape_filter;
 mod transform;
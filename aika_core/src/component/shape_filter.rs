@@ -0,0 +1,186 @@
+use cgmath::{BaseFloat, InnerSpace, One, Quaternion, Vector2, Vector3, Vector4};
+use num_traits::Zero;
+use aika_math::{AABB, Bounded, Cylinder, Disk, HitRecord, Hittable, Ray, Rectangle, Sphere};
+use aika_math::utils::{abs_vector3, gamma, max_component_index};
+use crate::component::ComponentData;
+
+/// An analytic shape in object space, centered at the origin
+#[derive(Clone, Copy, Debug)]
+pub enum AnalyticShape<F> {
+    Sphere { radius: F },
+    /// A disk in the xy plane, facing +z
+    Disk { radius: F },
+    /// A rectangle in the xy plane, facing +z
+    Rectangle { x_width: F, y_width: F },
+    /// An open cylinder around the z axis, without caps
+    Cylinder { radius: F, height: F },
+    /// An axis aligned box, `extent` is the half size on each axis
+    Box { extent: Vector3<F> },
+}
+
+impl<F> AnalyticShape<F> where F: BaseFloat {
+    fn get_box(extent: Vector3<F>) -> AABB<F> {
+        AABB {
+            center: Vector3::zero(),
+            extent
+        }
+    }
+
+    /// Intersect with a ray in object space, the record carries the normal, the uv and the refined point
+    pub fn hit(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, ()>> {
+        match *self {
+            AnalyticShape::Sphere { radius } => Sphere::new(Vector3::zero(), radius).hit(ray, min, max),
+            AnalyticShape::Disk { radius } => Disk::new(Vector3::zero(), radius).hit(ray, min, max),
+            AnalyticShape::Rectangle { x_width, y_width } => {
+                Rectangle::new(x_width, y_width, Vector3::zero(), Quaternion::one()).hit(ray, min, max)
+            },
+            AnalyticShape::Cylinder { radius, height } => Cylinder::new(Vector3::zero(), radius, height).hit(ray, min, max),
+            AnalyticShape::Box { extent } => {
+                let (t0, t1) = AnalyticShape::get_box(extent).hit_interval(ray, min, max)?;
+                // t0 is clamped to `min` when the ray starts inside the box
+                let t = if t0 > min { t0 } else { t1 };
+                if t < min || t > max {
+                    return None;
+                }
+
+                let p = ray.origin + ray.direction * t;
+                let axis = AnalyticShape::get_box_face_axis(extent, p);
+                let mut point = p;
+                point[axis] = extent[axis].copysign(p[axis]);
+                let mut point_error = abs_vector3(point) * gamma::<F>(3);
+                point_error[axis] = F::zero();
+                let normal = self.get_normal(point);
+
+                Some(HitRecord {
+                    t,
+                    normal: Some(normal),
+                    uv: Some(self.get_uv(point)),
+                    back_facing: Some(normal.dot(ray.direction) > F::zero()),
+                    point: Some(point),
+                    point_error: Some(point_error),
+                    hit_object: None,
+                })
+            },
+        }
+    }
+
+    /// The axis of the face of the box which `point` lies on
+    fn get_box_face_axis(extent: Vector3<F>, point: Vector3<F>) -> usize {
+        let relative = Vector3::new(point.x / extent.x, point.y / extent.y, point.z / extent.z);
+        max_component_index(abs_vector3(relative))
+    }
+
+    /// The outward normal at a point on the surface, in object space
+    pub fn get_normal(&self, point: Vector3<F>) -> Vector3<F> {
+        match *self {
+            AnalyticShape::Sphere { .. } => point.normalize(),
+            AnalyticShape::Disk { .. } | AnalyticShape::Rectangle { .. } => Vector3::unit_z(),
+            AnalyticShape::Cylinder { .. } => Vector3::new(point.x, point.y, F::zero()).normalize(),
+            AnalyticShape::Box { extent } => {
+                let axis = AnalyticShape::get_box_face_axis(extent, point);
+                let mut normal = Vector3::zero();
+                normal[axis] = F::one().copysign(point[axis]);
+                normal
+            },
+        }
+    }
+
+    /// The direction in which u increases at a point on the surface, in object space.
+    /// w is the sign of the bitangent `w * cross(normal, tangent)`, which follows the direction in which v increases
+    pub fn get_tangent(&self, point: Vector3<F>) -> Vector4<F> {
+        match *self {
+            AnalyticShape::Sphere { .. } | AnalyticShape::Disk { .. } | AnalyticShape::Cylinder { .. } => {
+                let t = Vector3::new(-point.y, point.x, F::zero());
+                // the poles and the center of the disk
+                let t = if t.magnitude2() == F::zero() {
+                    Vector3::unit_x()
+                } else {
+                    t.normalize()
+                };
+                // v goes from +z to -z on the sphere
+                let w = match *self {
+                    AnalyticShape::Sphere { .. } => -F::one(),
+                    _ => F::one(),
+                };
+                t.extend(w)
+            },
+            AnalyticShape::Rectangle { .. } => Vector4::unit_x() + Vector4::unit_w(),
+            AnalyticShape::Box { extent } => {
+                let axis = AnalyticShape::get_box_face_axis(extent, point);
+                let mut t = Vector3::zero();
+                t[(axis + 1) % 3] = F::one();
+                t.extend(F::one().copysign(point[axis]))
+            },
+        }
+    }
+
+    pub fn get_uv(&self, point: Vector3<F>) -> Vector2<F> {
+        match *self {
+            AnalyticShape::Sphere { radius } => Sphere::new(Vector3::zero(), radius).get_uv(point),
+            AnalyticShape::Disk { radius } => Disk::new(Vector3::zero(), radius).get_uv(point),
+            AnalyticShape::Rectangle { x_width, y_width } => {
+                Rectangle::new(x_width, y_width, Vector3::zero(), Quaternion::one()).get_uv(point)
+            },
+            AnalyticShape::Cylinder { radius, height } => Cylinder::new(Vector3::zero(), radius, height).get_uv(point),
+            AnalyticShape::Box { extent } => {
+                // each face is mapped to the whole uv square
+                let axis = AnalyticShape::get_box_face_axis(extent, point);
+                let u_axis = (axis + 1) % 3;
+                let v_axis = (axis + 2) % 3;
+                let half = F::from(0.5).unwrap();
+                Vector2::new(
+                    point[u_axis] / extent[u_axis] * half + half,
+                    point[v_axis] / extent[v_axis] * half + half
+                )
+            },
+        }
+    }
+
+    /// The bounding box in object space
+    pub fn get_bv(&self) -> AABB<F> {
+        match *self {
+            AnalyticShape::Sphere { radius } => Sphere::new(Vector3::zero(), radius).get_bv(),
+            AnalyticShape::Disk { radius } => Disk::new(Vector3::zero(), radius).get_bv(),
+            AnalyticShape::Rectangle { x_width, y_width } => {
+                Rectangle::new(x_width, y_width, Vector3::zero(), Quaternion::one()).get_bv()
+            },
+            AnalyticShape::Cylinder { radius, height } => Cylinder::new(Vector3::zero(), radius, height).get_bv(),
+            AnalyticShape::Box { extent } => AnalyticShape::get_box(extent),
+        }
+    }
+}
+
+/// Attach an analytic shape to a game object, it is rendered like a mesh in `MeshFilter`
+pub struct ShapeFilter<F> {
+    pub shape: AnalyticShape<F>,
+}
+
+impl<F> ShapeFilter<F> where F: BaseFloat {
+    pub fn new(shape: AnalyticShape<F>) -> Self {
+        Self {
+            shape
+        }
+    }
+
+    pub fn sphere(radius: F) -> Self {
+        ShapeFilter::new(AnalyticShape::Sphere { radius })
+    }
+
+    pub fn disk(radius: F) -> Self {
+        ShapeFilter::new(AnalyticShape::Disk { radius })
+    }
+
+    pub fn rectangle(x_width: F, y_width: F) -> Self {
+        ShapeFilter::new(AnalyticShape::Rectangle { x_width, y_width })
+    }
+
+    pub fn cylinder(radius: F, height: F) -> Self {
+        ShapeFilter::new(AnalyticShape::Cylinder { radius, height })
+    }
+
+    pub fn cuboid(extent: Vector3<F>) -> Self {
+        ShapeFilter::new(AnalyticShape::Box { extent })
+    }
+}
+
+impl<F> ComponentData for ShapeFilter<F> where F: BaseFloat + 'static {}
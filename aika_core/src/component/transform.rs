@@ -1,11 +1,14 @@
-use cgmath::{BaseFloat, Deg, Euler, Matrix3, Matrix4, Point3, Quaternion, Rotation, SquareMatrix, Vector3};
+use cgmath::{BaseFloat, Deg, ElementWise, Euler, InnerSpace, Matrix3, Matrix4, Point3, Quaternion, Rotation, SquareMatrix, Vector3};
 use num_traits::{Zero};
 use crate::component::{ComponentData};
 
+/// The local transform of a game object, applied in the order scale, rotation, translation.
+/// If the game object has a parent, it is relative to the parent's world transform
 #[derive(Clone)]
 pub struct Transform<F> {
     pub position: Vector3<F>,
-    pub scale: F,
+    /// Scale on each local axis
+    pub scale: Vector3<F>,
     // Euler angle
     pub rotation: Quaternion<F>,
 }
@@ -14,7 +17,7 @@ impl<F> Default for Transform<F> where F: BaseFloat {
     fn default() -> Self {
         Transform {
             position: Vector3::zero(),
-            scale: F::zero(),
+            scale: Vector3::zero(),
             // todo
             rotation: Euler::new(Deg(F::zero()), Deg(F::zero()), Deg(F::zero())).into(),
         }
@@ -37,7 +40,16 @@ impl<F> Transform<F> where F: BaseFloat {
 }
 
 impl<F> Transform<F> where F: BaseFloat {
+    /// A transform with uniform scale
     pub fn new(position: Vector3<F>, scale: F, rotation: Quaternion<F>) -> Self {
+        Self {
+            position,
+            scale: Vector3::new(scale, scale, scale),
+            rotation
+        }
+    }
+
+    pub fn new_non_uniform(position: Vector3<F>, scale: Vector3<F>, rotation: Quaternion<F>) -> Self {
         Self {
             position,
             scale,
@@ -46,23 +58,37 @@ impl<F> Transform<F> where F: BaseFloat {
     }
 
     pub fn get_transform_matrix(&self) -> Matrix4<F> {
-        // todo
         let translate = Matrix4::from_translation(self.position);
-        let scale = Matrix4::from_scale(self.scale);
+        let rotate = Matrix4::from(self.rotation);
+        let scale = Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z);
+
+        translate * rotate * scale
+    }
 
-        translate * scale
+    /// The same transform as `transform_point`, as a matrix with its inverse
+    pub fn get_math_transform(&self) -> aika_math::Transform<F> {
+        aika_math::Transform::translate(self.position)
+            * aika_math::Transform::from_quaternion(self.rotation)
+            * aika_math::Transform::scale(self.scale)
     }
 
+    /// Rotate a direction, the scale is ignored
     pub fn transform_direction(&self, dir: Vector3<F>) -> Vector3<F> {
         self.rotation.rotate_vector(dir)
     }
 
     pub fn transform_point(&self, point: Vector3<F>) -> Vector3<F> {
-        let point = point * self.scale;
+        let point = point.mul_element_wise(self.scale);
         let after_rotation = self.rotation.rotate_point(Point3::new(point.x, point.y, point.z));
         let after_translate = Vector3::new(after_rotation.x + self.position.x, after_rotation.y + self.position.y, after_rotation.z + self.position.z);
         after_translate
     }
+
+    /// Transform a normal with the inverse transpose, the result is normalized
+    pub fn transform_normal(&self, normal: Vector3<F>) -> Vector3<F> {
+        let n = normal.div_element_wise(self.scale);
+        self.rotation.rotate_vector(n).normalize()
+    }
 }
 
 impl<F> ComponentData for Transform<F> where F: BaseFloat + 'static {}
\ No newline at end of file
@@ -0,0 +1,104 @@
+use cgmath::{BaseFloat, InnerSpace, Vector3, Vector4};
+use aika_math::{AABB, Bounded, HaveCenter, HitRecord, Hittable, Ray};
+use crate::mashed_scene::{MashedShape, MashedTriangle};
+use crate::material::{MaterialSlots, SubMeshMaterial};
+use crate::scene::GameObject;
+
+/// Anything in the mashed scene, triangles and analytic shapes share one spatial structure
+pub enum MashedPrimitive<F> {
+    Triangle(MashedTriangle<F>),
+    Shape(MashedShape<F>),
+}
+
+impl<F> MashedPrimitive<F> where F: BaseFloat + 'static {
+    pub fn get_game_object(&self) -> &GameObject<F> {
+        match self {
+            MashedPrimitive::Triangle(t) => &t.go,
+            MashedPrimitive::Shape(s) => &s.go,
+        }
+    }
+
+    pub fn as_triangle(&self) -> Option<&MashedTriangle<F>> {
+        match self {
+            MashedPrimitive::Triangle(t) => Some(t),
+            MashedPrimitive::Shape(_) => None,
+        }
+    }
+
+    /// Shapes are a single sub mesh
+    pub fn get_sub_mesh_index(&self) -> usize {
+        match self {
+            MashedPrimitive::Triangle(t) => t.sub_mesh_index,
+            MashedPrimitive::Shape(_) => 0,
+        }
+    }
+
+    /// The material of the primitive, from `MaterialSlots` or the `Material` component
+    pub fn get_material(&self) -> Option<SubMeshMaterial<F>> {
+        MaterialSlots::get_material_component(self.get_game_object(), self.get_sub_mesh_index())
+    }
+
+    /// The normal of the surface itself, used for ray offsets and side tests
+    pub fn get_geometric_normal(&self, point: Vector3<F>) -> Vector3<F> {
+        match self {
+            MashedPrimitive::Triangle(t) => t.triangle.get_normal(),
+            MashedPrimitive::Shape(s) => s.get_normal(point),
+        }
+    }
+
+    /// Interpolated vertex normals for triangles, the exact normal for analytic shapes.
+    /// Triangles without vertex normals use the face normal
+    pub fn get_shading_normal(&self, point: Vector3<F>) -> Vector3<F> {
+        match self {
+            MashedPrimitive::Triangle(t) => {
+                let uvw = t.triangle.get_bary_centric_coordinate(point);
+                match t.interpolate_normal(uvw) {
+                    // opposite vertex normals can cancel out
+                    Some(n) if n.magnitude2() > F::zero() => n.normalize(),
+                    // flat shading for meshes without normals
+                    _ => t.triangle.get_normal(),
+                }
+            },
+            MashedPrimitive::Shape(s) => s.get_normal(point),
+        }
+    }
+
+    /// A direction on the surface, not necessarily orthogonal to the shading normal.
+    /// w is the sign of the bitangent `w * cross(normal, tangent)`
+    pub fn get_tangent(&self, point: Vector3<F>) -> Vector4<F> {
+        match self {
+            MashedPrimitive::Triangle(t) => {
+                let uvw = t.triangle.get_bary_centric_coordinate(point);
+                t.interpolate_tangent(uvw).unwrap_or_else(|| t.get_face_tangent())
+            },
+            MashedPrimitive::Shape(s) => s.get_tangent(point),
+        }
+    }
+}
+
+impl<F> Bounded<AABB<F>> for MashedPrimitive<F> where F: BaseFloat {
+    fn get_bv(&self) -> AABB<F> {
+        match self {
+            MashedPrimitive::Triangle(t) => t.get_bv(),
+            MashedPrimitive::Shape(s) => s.get_bv(),
+        }
+    }
+}
+
+impl<F> HaveCenter<F> for MashedPrimitive<F> where F: BaseFloat {
+    fn get_center(&self) -> Vector3<F> {
+        match self {
+            MashedPrimitive::Triangle(t) => t.get_center(),
+            MashedPrimitive::Shape(s) => s.get_center(),
+        }
+    }
+}
+
+impl<F> Hittable<F, GameObject<F>> for MashedPrimitive<F> where F: BaseFloat + 'static {
+    fn hit(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, GameObject<F>>> {
+        match self {
+            MashedPrimitive::Triangle(t) => t.hit(ray, min, max),
+            MashedPrimitive::Shape(s) => s.hit(ray, min, max),
+        }
+    }
+}
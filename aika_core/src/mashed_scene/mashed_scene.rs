@@ -1,3 +1,4 @@
+use std::path::Path;
 use std::rc::Rc;
 use cgmath::BaseFloat;
 use num_traits::Float;
@@ -5,64 +6,245 @@ use aika_math::{AABB, HitRecord, Hittable, Ray, Triangle};
 use crate::scene::{GameObject, Scene};
 use aika_spatial_structure::bvh::{BVHBuilder, BVHTree, DefaultBVHSplitHeuristic};
 use aika_spatial_structure::naive::NaiveSpatialStructure;
-use crate::component::{MeshFilter, Transform};
-use crate::mashed_scene::mashed_triangle::MashedTriangle;
+use aika_spatial_structure::kd_tree::KdTreeBuilder;
+use aika_spatial_structure::grid::UniformGridBuilder;
+use aika_spatial_structure::stats::{HittableWithStats, TraversalStats};
+use crate::component::{MeshFilter, ShapeFilter, Transform};
+use crate::material::Material;
+use crate::mashed_scene::{MashedPrimitive, MashedShape, MashedTransform, MashedTriangle};
+use crate::mashed_scene::mashed_scene_cache::{get_cache_key, read_cache, write_cache, MashedBVH};
+
+/// Decides whether a hit blocks an occlusion query
+pub type OcclusionFilter<'a, F> = &'a dyn Fn(&HitRecord<F, Rc<MashedPrimitive<F>>>) -> bool;
+
+/// Max number of primitives in a BVH leaf
+const BVH_MAX_SPAN: usize = 4;
+
+/// The acceleration structure used to intersect the primitives
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpatialStructureType {
+    Naive,
+    BVH,
+    KdTree,
+    UniformGrid,
+}
 
 pub struct MashedScene<F> {
-    spatial_structure: Box<dyn Hittable<F, Rc<MashedTriangle<F>>>>,
+    spatial_structure: Box<dyn HittableWithStats<F, Rc<MashedPrimitive<F>>>>,
     // bvh: BVHTree<AABB<F>, MashedTriangle<F>>,
     triangle_count: usize,
+    primitive_count: usize,
 }
 
 impl<F> MashedScene<F> where F: BaseFloat + 'static {
-    pub fn hit(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, Rc<MashedTriangle<F>>>> {
-        self.spatial_structure.hit(&ray, min, max)
+    pub fn hit(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, Rc<MashedPrimitive<F>>>> {
+        self.spatial_structure.hit(ray, min, max)
+    }
+
+    pub fn hit_with_stats(&self, ray: &Ray<F>, min: F, max: F, stats: &mut TraversalStats) -> Option<HitRecord<F, Rc<MashedPrimitive<F>>>> {
+        self.spatial_structure.hit_with_stats(ray, min, max, stats)
+    }
+
+    pub fn occluded(&self, ray: &Ray<F>, min: F, max: F, filter: OcclusionFilter<F>) -> bool {
+        self.spatial_structure.occluded_filtered(ray, min, max, filter)
     }
 
     pub fn get_triangle_count(&self) -> usize {
         self.triangle_count
     }
 
-    pub fn from_scene_bvh(scene: &Scene<F>) -> MashedScene<F> {
-        let mut mashed_triangles: Vec<Rc<MashedTriangle<F>>> = Vec::new();
+    /// Triangles and analytic shapes
+    pub fn get_primitive_count(&self) -> usize {
+        self.primitive_count
+    }
+
+    fn count_triangles(primitives: &[Rc<MashedPrimitive<F>>]) -> usize {
+        primitives.iter().filter(|p| p.as_triangle().is_some()).count()
+    }
+
+    /// The game objects whose primitives are collected, in order, each once.
+    /// A game object with both a `MeshFilter` and a `ShapeFilter` is mashed from its mesh
+    fn get_primitive_game_objects(scene: &Scene<F>) -> Vec<GameObject<F>> {
+        let mut game_objects = scene.get_game_objects_of_type::<MeshFilter<F>>();
+        for go in scene.get_game_objects_of_type::<ShapeFilter<F>>() {
+            if go.has_component::<MeshFilter<F>>() {
+                log::warn!("game object {} has both a mesh filter and a shape filter, the shape is ignored", go.go.borrow().name);
+                continue;
+            }
+            game_objects.push(go);
+        }
+        game_objects
+    }
+
+    /// Fill `MeshFilter::built_mesh` of the game objects with a subdivision, or whose material has a displacement.
+    /// The mesh is subdivided first, then displaced.
+    /// Both always start from `MeshFilter::mesh`, so mashing a scene again does not accumulate them
+    fn build_meshes(scene: &Scene<F>) {
         for go in scene.get_game_objects_of_type::<MeshFilter<F>>() {
             let mesh_component = go.get_component::<MeshFilter<F>>().unwrap();
-            let mesh = mesh_component.downcast::<MeshFilter<F>>();
+            let built_mesh = {
+                let mesh_filter = mesh_component.downcast::<MeshFilter<F>>();
+                // a mesh which can not be subdivided or displaced is rendered as it is
+                let subdivided = match mesh_filter.subdivision.as_ref().map(|s| s.subdivide(&mesh_filter.mesh)) {
+                    Some(Ok(subdivided)) => Some(subdivided),
+                    Some(Err(e)) => {
+                        log::warn!("failed to subdivide the mesh of game object {}: {}", go.go.borrow().name, e);
+                        None
+                    },
+                    None => None,
+                };
+                let material_component = go.get_component::<Material<F>>().ok();
+                let material = material_component.as_ref().map(|c| c.downcast::<Material<F>>());
+                match material.as_ref().and_then(|m| m.displacement.as_ref()) {
+                    Some(d) => {
+                        let mesh = subdivided.unwrap_or_else(|| mesh_filter.mesh.to_common_mesh());
+                        match mesh.displace(d.height.as_ref(), d.scale, d.subdivision) {
+                            Ok(displaced) => Some(displaced.to_dyn_mesh()),
+                            Err(e) => {
+                                log::warn!("failed to displace the mesh of game object {}: {}", go.go.borrow().name, e);
+                                Some(mesh.to_dyn_mesh())
+                            },
+                        }
+                    },
+                    None => subdivided.map(|m| m.to_dyn_mesh()),
+                }
+            };
+            mesh_component.downcast_mut::<MeshFilter<F>>().built_mesh = built_mesh;
+        }
+    }
 
-            // let transform_component = go.get_component::<Transform<F>>().unwrap();
-            // let transform = mesh_component.downcast::<Transform<F>>();
-            let transform = go.get_transform().unwrap();
+    /// `build_meshes` has to be called before
+    fn collect_primitives(scene: &Scene<F>) -> Vec<Rc<MashedPrimitive<F>>> {
+        let mut mashed_primitives: Vec<Rc<MashedPrimitive<F>>> = Vec::new();
+        for go in MashedScene::get_primitive_game_objects(scene) {
+            let Ok(mesh_component) = go.get_component::<MeshFilter<F>>() else {
+                let shape_component = go.get_component::<ShapeFilter<F>>().unwrap();
+                let shape = shape_component.downcast::<ShapeFilter<F>>().shape;
+                let transform = go.get_world_transform();
+                mashed_primitives.push(Rc::new(MashedPrimitive::Shape(MashedShape::new(go.clone(), shape, transform))));
+                continue;
+            };
+            let mesh_filter = mesh_component.downcast::<MeshFilter<F>>();
+            let mesh = mesh_filter.get_mesh();
 
-            for (triangle, indices) in mesh.mesh.iter_triangles().zip(mesh.mesh.iter_triangle_indices()).skip(0) {
-                let a = transform.transform_point(triangle.a);
-                let b = transform.transform_point(triangle.b);
-                let c = transform.transform_point(triangle.c);
+            let transform = Rc::new(MashedTransform::new(go.get_world_transform()));
+
+            let sub_mesh_indices = mesh.get_triangle_sub_mesh_indices();
+            for ((triangle, indices), sub_mesh_index) in mesh.iter_triangles().zip(mesh.iter_triangle_indices()).zip(sub_mesh_indices) {
+                let a = transform.transform.transform_point(triangle.a);
+                let b = transform.transform.transform_point(triangle.b);
+                let c = transform.transform.transform_point(triangle.c);
                 let new_triangle = Triangle {
                     a, b, c
                 };
 
-                mashed_triangles.push(Rc::new(MashedTriangle {
+                mashed_primitives.push(Rc::new(MashedPrimitive::Triangle(MashedTriangle {
                     go: go.clone(),
+                    transform: transform.clone(),
                     triangle: new_triangle,
-                    vertex_index: indices
-                }));
+                    vertex_index: indices,
+                    sub_mesh_index,
+                })));
             }
         }
 
-        let triangle_count = mashed_triangles.len();
+        mashed_primitives
+    }
+
+    /// Collects the primitives of `scene` and puts them into the spatial structure made by `build`,
+    /// `build_meshes` has to be called before
+    fn from_primitives<B>(scene: &Scene<F>, build: B) -> MashedScene<F>
+    where
+        B: FnOnce(Vec<Rc<MashedPrimitive<F>>>) -> Box<dyn HittableWithStats<F, Rc<MashedPrimitive<F>>>>
+    {
+        let mashed_primitives = MashedScene::collect_primitives(scene);
+        let triangle_count = MashedScene::count_triangles(&mashed_primitives);
+        let primitive_count = mashed_primitives.len();
+
+        MashedScene {
+            spatial_structure: build(mashed_primitives),
+            triangle_count,
+            primitive_count
+        }
+    }
+
+    fn get_bvh_builder(mashed_primitives: &[Rc<MashedPrimitive<F>>]) -> BVHBuilder<F, AABB<F>, MashedPrimitive<F>, GameObject<F>> {
+        let mut builder = BVHBuilder::new(BVH_MAX_SPAN);
+        builder.add_objects(mashed_primitives);
+        builder
+    }
+
+    pub fn from_scene(scene: &Scene<F>, structure_type: SpatialStructureType) -> MashedScene<F> {
+        MashedScene::build_meshes(scene);
+        MashedScene::from_primitives(scene, |mashed_primitives| match structure_type {
+            SpatialStructureType::Naive => {
+                let mut naive_structure: NaiveSpatialStructure<F, MashedPrimitive<F>, GameObject<F>> = NaiveSpatialStructure::new();
+                naive_structure.add_objects(mashed_primitives);
+                Box::new(naive_structure)
+            },
+            SpatialStructureType::BVH => {
+                let mut split_heuristic = DefaultBVHSplitHeuristic::default();
+                Box::new(MashedScene::get_bvh_builder(&mashed_primitives).build(&mut split_heuristic))
+            },
+            SpatialStructureType::KdTree => {
+                let mut builder: KdTreeBuilder<F, MashedPrimitive<F>, GameObject<F>> = KdTreeBuilder::new();
+                builder.add_objects(&mashed_primitives);
+                Box::new(builder.build())
+            },
+            SpatialStructureType::UniformGrid => {
+                let mut builder: UniformGridBuilder<F, MashedPrimitive<F>, GameObject<F>> = UniformGridBuilder::new();
+                builder.add_objects(&mashed_primitives);
+                Box::new(builder.build())
+            },
+        })
+    }
+
+    pub fn from_scene_bvh(scene: &Scene<F>) -> MashedScene<F> {
+        MashedScene::from_scene(scene, SpatialStructureType::BVH)
+    }
+}
 
+impl<F> MashedScene<F> where F: BaseFloat + Send + Sync + 'static {
+    fn build_bvh_parallel(mashed_primitives: &[Rc<MashedPrimitive<F>>]) -> MashedBVH<F> {
         let mut split_heuristic = DefaultBVHSplitHeuristic::default();
-        let mut builder: BVHBuilder<F, AABB<F>, MashedTriangle<F>, GameObject<F>> = BVHBuilder::new(4);
-        builder.add_objects(&mashed_triangles);
-        let tree = builder.build(&mut split_heuristic);
+        MashedScene::get_bvh_builder(mashed_primitives).build_parallel(&mut split_heuristic)
+    }
 
-        let mut naive_structure: NaiveSpatialStructure<F, MashedTriangle<F>, GameObject<F>> = NaiveSpatialStructure::new();
-        naive_structure.add_objects(mashed_triangles);
+    /// Same as `from_scene_bvh`, but the BVH is built on multiple threads
+    pub fn from_scene_bvh_parallel(scene: &Scene<F>) -> MashedScene<F> {
+        MashedScene::build_meshes(scene);
+        MashedScene::from_primitives(scene, |mashed_primitives| {
+            Box::new(MashedScene::build_bvh_parallel(&mashed_primitives))
+        })
+    }
 
-        MashedScene {
-            spatial_structure: Box::new(tree),
-            // spatial_structure: Box::new(naive_structure),
-            triangle_count
+    /// Same as `from_scene_bvh_parallel`, but the built BVH is stored in `cache_dir`,
+    /// and loaded back if the meshes, the shapes, the transforms and the build settings are not changed.
+    /// A cache which can not be written is logged, the built scene is still returned
+    pub fn from_scene_bvh_cached(scene: &Scene<F>, cache_dir: &Path) -> MashedScene<F> {
+        // the key covers the subdivided and displaced meshes
+        MashedScene::build_meshes(scene);
+        let game_objects = MashedScene::get_primitive_game_objects(scene);
+        let key = get_cache_key(&game_objects, BVH_MAX_SPAN);
+        let path = cache_dir.join(format!("{:016x}.bvh", key));
+
+        if path.exists() {
+            // a broken cache file is rebuilt
+            if let Ok((primitives, tree)) = read_cache(&path, key, &game_objects) {
+                return MashedScene {
+                    spatial_structure: Box::new(tree),
+                    triangle_count: MashedScene::count_triangles(&primitives),
+                    primitive_count: primitives.len()
+                };
+            }
         }
+
+        MashedScene::from_primitives(scene, |mashed_primitives| {
+            let tree = MashedScene::build_bvh_parallel(&mashed_primitives);
+            if let Err(e) = write_cache(&path, key, &game_objects, &mashed_primitives, &tree) {
+                log::warn!("failed to write the BVH cache {}: {}", path.display(), e);
+            }
+            Box::new(tree)
+        })
     }
-}
\ No newline at end of file
+}
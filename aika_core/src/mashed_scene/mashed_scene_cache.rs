@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+use anyhow::{bail, Result};
+use cgmath::BaseFloat;
+use aika_math::{AABB, Triangle};
+use aika_spatial_structure::bvh::BVHTree;
+use aika_spatial_structure::serialize::*;
+use crate::component::{AnalyticShape, MeshFilter, ShapeFilter};
+use crate::mashed_scene::{MashedPrimitive, MashedShape, MashedTransform, MashedTriangle};
+use crate::scene::GameObject;
+
+const CACHE_MAGIC: &[u8; 8] = b"AIKAMSC\0";
+/// Bump this whenever the layout or the way the BVH is built changes
+const CACHE_VERSION: u32 = 4;
+
+const PRIMITIVE_TRIANGLE: u32 = 0;
+const PRIMITIVE_SHAPE: u32 = 1;
+
+pub type MashedBVH<F> = BVHTree<F, AABB<F>, MashedPrimitive<F>, GameObject<F>>;
+/// The mashed primitives and the BVH built over them
+pub type CachedBVH<F> = (Vec<Rc<MashedPrimitive<F>>>, MashedBVH<F>);
+
+fn hash_float<F: BaseFloat>(hasher: &mut FnvHasher, value: F) {
+    hasher.write(&value.to_f64().unwrap().to_le_bytes());
+}
+
+fn hash_shape<F: BaseFloat>(hasher: &mut FnvHasher, shape: &AnalyticShape<F>) {
+    match *shape {
+        AnalyticShape::Sphere { radius } => {
+            hasher.write_u8(0);
+            hash_float(hasher, radius);
+        },
+        AnalyticShape::Disk { radius } => {
+            hasher.write_u8(1);
+            hash_float(hasher, radius);
+        },
+        AnalyticShape::Rectangle { x_width, y_width } => {
+            hasher.write_u8(2);
+            hash_float(hasher, x_width);
+            hash_float(hasher, y_width);
+        },
+        AnalyticShape::Cylinder { radius, height } => {
+            hasher.write_u8(3);
+            hash_float(hasher, radius);
+            hash_float(hasher, height);
+        },
+        AnalyticShape::Box { extent } => {
+            hasher.write_u8(4);
+            for i in 0..3 {
+                hash_float(hasher, extent[i]);
+            }
+        },
+    }
+}
+
+/// Hash of the mesh data, the shapes, the transforms and the build settings
+/// `game_objects` are the game objects with a mesh filter or a shape filter, in the order the primitives are collected
+pub fn get_cache_key<F>(game_objects: &[GameObject<F>], max_span: usize) -> u64 where F: BaseFloat + 'static {
+    let mut hasher = FnvHasher::default();
+    hasher.write(&CACHE_VERSION.to_le_bytes());
+    hasher.write(&(max_span as u64).to_le_bytes());
+    hasher.write(&(std::mem::size_of::<F>() as u64).to_le_bytes());
+
+    for go in game_objects.iter() {
+        // the world matrix covers the transforms of the parents as well
+        let transform = go.get_world_transform();
+        for i in 0..4 {
+            for j in 0..4 {
+                hash_float(&mut hasher, transform.mat[i][j]);
+            }
+        }
+
+        if let Ok(shape_component) = go.get_component::<ShapeFilter<F>>() {
+            hash_shape(&mut hasher, &shape_component.downcast::<ShapeFilter<F>>().shape);
+        }
+
+        let Ok(mesh_component) = go.get_component::<MeshFilter<F>>() else {
+            continue;
+        };
+        let mesh_filter = mesh_component.downcast::<MeshFilter<F>>();
+        let mesh = mesh_filter.get_mesh();
+        hasher.write(&(mesh.face_count() as u64).to_le_bytes());
+        for (triangle, indices) in mesh.iter_triangles().zip(mesh.iter_triangle_indices()) {
+            for p in [triangle.a, triangle.b, triangle.c] {
+                hash_float(&mut hasher, p.x);
+                hash_float(&mut hasher, p.y);
+                hash_float(&mut hasher, p.z);
+            }
+            for index in indices {
+                hasher.write(&(index as u64).to_le_bytes());
+            }
+        }
+        for [a, b] in mesh.sub_mesh.iter() {
+            hasher.write(&(*a as u64).to_le_bytes());
+            hasher.write(&(*b as u64).to_le_bytes());
+        }
+    }
+
+    hasher.finish()
+}
+
+pub fn write_cache<F>(path: &Path, key: u64, game_objects: &[GameObject<F>], primitives: &[Rc<MashedPrimitive<F>>], tree: &MashedBVH<F>) -> Result<()>
+where
+    F: BaseFloat + 'static
+{
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    write_header(&mut writer, CACHE_MAGIC, CACHE_VERSION)?;
+    write_u64(&mut writer, key)?;
+    write_u64(&mut writer, primitives.len() as u64)?;
+
+    let go_indices: HashMap<_, _> = game_objects.iter()
+        .enumerate()
+        .map(|(i, go)| (Rc::as_ptr(&go.go), i))
+        .collect();
+    for primitive in primitives.iter() {
+        let go_index = go_indices[&Rc::as_ptr(&primitive.get_game_object().go)];
+        match primitive.as_ref() {
+            MashedPrimitive::Triangle(t) => {
+                write_u32(&mut writer, PRIMITIVE_TRIANGLE)?;
+                write_u64(&mut writer, go_index as u64)?;
+                for index in t.vertex_index {
+                    write_u64(&mut writer, index as u64)?;
+                }
+                write_u64(&mut writer, t.sub_mesh_index as u64)?;
+                write_vector3(&mut writer, t.triangle.a)?;
+                write_vector3(&mut writer, t.triangle.b)?;
+                write_vector3(&mut writer, t.triangle.c)?;
+            },
+            // shapes are cheap to rebuild from the game object
+            MashedPrimitive::Shape(_) => {
+                write_u32(&mut writer, PRIMITIVE_SHAPE)?;
+                write_u64(&mut writer, go_index as u64)?;
+            },
+        }
+    }
+
+    tree.write_to(primitives, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read_cache<F>(path: &Path, key: u64, game_objects: &[GameObject<F>]) -> Result<CachedBVH<F>>
+where
+    F: BaseFloat + 'static
+{
+    let mut reader = BufReader::new(File::open(path)?);
+
+    read_header(&mut reader, CACHE_MAGIC, CACHE_VERSION)?;
+    let file_key = read_u64(&mut reader)?;
+    if file_key != key {
+        bail!("cache key mismatch");
+    }
+
+    let count = read_u64(&mut reader)? as usize;
+    let mut primitives = Vec::new();
+    // the triangles of a game object share its transform
+    let mut transforms: Vec<Option<Rc<MashedTransform<F>>>> = vec![None; game_objects.len()];
+    for _ in 0..count {
+        let tag = read_u32(&mut reader)?;
+        let go_index = read_u64(&mut reader)? as usize;
+        if go_index >= game_objects.len() {
+            bail!("game object index {} out of range", go_index);
+        }
+        let go = &game_objects[go_index];
+
+        if tag == PRIMITIVE_SHAPE {
+            let shape_component = go.get_component::<ShapeFilter<F>>()?;
+            let shape = shape_component.downcast::<ShapeFilter<F>>().shape;
+            let transform = go.get_world_transform();
+            primitives.push(Rc::new(MashedPrimitive::Shape(MashedShape::new(go.clone(), shape, transform))));
+            continue;
+        } else if tag != PRIMITIVE_TRIANGLE {
+            bail!("unknown primitive tag {}", tag);
+        }
+
+        let mut vertex_index = [0_usize; 3];
+        for item in vertex_index.iter_mut() {
+            *item = read_u64(&mut reader)? as usize;
+        }
+        let sub_mesh_index = read_u64(&mut reader)? as usize;
+        let a = read_vector3(&mut reader)?;
+        let b = read_vector3(&mut reader)?;
+        let c = read_vector3(&mut reader)?;
+        let transform = transforms[go_index]
+            .get_or_insert_with(|| Rc::new(MashedTransform::new(go.get_world_transform())))
+            .clone();
+
+        primitives.push(Rc::new(MashedPrimitive::Triangle(MashedTriangle {
+            go: go.clone(),
+            transform,
+            triangle: Triangle { a, b, c },
+            vertex_index,
+            sub_mesh_index,
+        })));
+    }
+
+    let tree = BVHTree::read_from(&primitives, &mut reader)?;
+    Ok((primitives, tree))
+}
@@ -0,0 +1,90 @@
+use cgmath::{BaseFloat, InnerSpace, Vector3, Vector4};
+use aika_math::{AABB, Bounded, HaveCenter, HitRecord, Hittable, Ray};
+use crate::component::AnalyticShape;
+use crate::scene::GameObject;
+
+/// An analytic shape placed in the world.
+/// Rays are transformed into object space, so the shape keeps its exact surface
+pub struct MashedShape<F> {
+    pub go: GameObject<F>,
+    pub shape: AnalyticShape<F>,
+    /// object space to world space
+    pub transform: aika_math::Transform<F>,
+    bounds: AABB<F>,
+}
+
+impl<F> MashedShape<F> where F: BaseFloat {
+    pub fn new(go: GameObject<F>, shape: AnalyticShape<F>, transform: aika_math::Transform<F>) -> Self {
+        let vertices = shape.get_bv().get_vertices().map(|v| transform.transform_point(v));
+        let bounds = AABB::from_points(&vertices);
+        Self {
+            go,
+            shape,
+            transform,
+            bounds
+        }
+    }
+
+    fn to_object_space(&self, point: Vector3<F>) -> Vector3<F> {
+        self.transform.transform_point_inverse(point).unwrap_or(point)
+    }
+
+    /// The outward normal at a world space point on the surface
+    pub fn get_normal(&self, point: Vector3<F>) -> Vector3<F> {
+        let n = self.shape.get_normal(self.to_object_space(point));
+        self.transform.transform_normal(n).unwrap_or(n).normalize()
+    }
+
+    /// The direction in which u increases at a world space point on the surface,
+    /// w is the sign of the bitangent
+    pub fn get_tangent(&self, point: Vector3<F>) -> Vector4<F> {
+        let t = self.shape.get_tangent(self.to_object_space(point));
+        let xyz = self.transform.transform_vector(t.truncate()).normalize();
+        let w = if self.transform.swaps_handedness() { -t.w } else { t.w };
+        Vector4::new(xyz.x, xyz.y, xyz.z, w)
+    }
+}
+
+impl<F> Bounded<AABB<F>> for MashedShape<F> where F: BaseFloat {
+    fn get_bv(&self) -> AABB<F> {
+        self.bounds.clone()
+    }
+}
+
+impl<F> HaveCenter<F> for MashedShape<F> where F: BaseFloat {
+    fn get_center(&self) -> Vector3<F> {
+        self.bounds.center
+    }
+}
+
+impl<F> Hittable<F, GameObject<F>> for MashedShape<F> where F: BaseFloat + 'static {
+    fn hit(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, GameObject<F>>> {
+        let origin = self.transform.transform_point_inverse(ray.origin)?;
+        let direction = self.transform.transform_vector_inverse(ray.direction)?;
+        let length = direction.magnitude();
+        if length == F::zero() {
+            return None;
+        }
+        // the shapes expect a normalized direction, so the distances are scaled accordingly
+        let local_ray = Ray {
+            origin,
+            direction: direction / length,
+        };
+        let r = self.shape.hit(&local_ray, min * length, max * length)?;
+
+        let local_point = r.get_hit_point(&local_ray);
+        let (point, point_error) = self.transform.transform_point_with_error(local_point, r.get_point_error());
+        let local_normal = r.normal.unwrap();
+        let normal = self.transform.transform_normal(local_normal)?.normalize();
+
+        Some(HitRecord {
+            t: r.t / length,
+            normal: Some(normal),
+            uv: r.uv,
+            back_facing: Some(normal.dot(ray.direction) > F::zero()),
+            point: Some(point),
+            point_error: Some(point_error),
+            hit_object: Some(self.go.clone()),
+        })
+    }
+}
@@ -1,57 +1,136 @@
 use std::rc::Rc;
-use cgmath::{BaseFloat, Rotation, Vector2, Vector3};
+use cgmath::{BaseFloat, InnerSpace, Matrix, Matrix3, Rotation, SquareMatrix, Vector2, Vector3, Vector4};
 use aika_math::{AABB, Bounded, HaveCenter, HitRecord, Hittable, Ray, Triangle};
-use crate::component::{MeshFilter, Transform};
+use crate::component::MeshFilter;
 use crate::mesh::VertexBuffer;
 use crate::scene::{GameObject};
 
+/// The world transform of a mesh, computed once when the scene is mashed and shared by its triangles
+pub struct MashedTransform<F> {
+    /// Object space to world space
+    pub transform: aika_math::Transform<F>,
+    /// The inverse transpose of the upper 3x3 matrix, None if the transform is singular
+    pub normal_matrix: Option<Matrix3<F>>,
+    pub swaps_handedness: bool,
+}
+
+impl<F> MashedTransform<F> where F: BaseFloat {
+    pub fn new(transform: aika_math::Transform<F>) -> Self {
+        let m = &transform.mat;
+        let upper = Matrix3::new(
+            m[0][0], m[0][1], m[0][2],
+            m[1][0], m[1][1], m[1][2],
+            m[2][0], m[2][1], m[2][2],
+        );
+        let normal_matrix = upper.invert().map(|inv| inv.transpose());
+        let swaps_handedness = upper.determinant() < F::zero();
+        MashedTransform {
+            transform,
+            normal_matrix,
+            swaps_handedness,
+        }
+    }
+
+    /// The returned normal is not normalized
+    pub fn transform_normal(&self, n: Vector3<F>) -> Option<Vector3<F>> {
+        self.normal_matrix.map(|m| m * n)
+    }
+}
+
 pub struct MashedTriangle<F> {
     pub go: GameObject<F>,
+    pub transform: Rc<MashedTransform<F>>,
     pub triangle: Triangle<F>,
     pub vertex_index: [usize; 3],
+    /// the range of `Mesh::sub_mesh` which holds the triangle, selects the slot of `MaterialSlots`
+    pub sub_mesh_index: usize,
 }
 
 impl<F> MashedTriangle<F> where F: BaseFloat + 'static {
-    /// the returned normal is not normalized
+    /// the returned normal is not normalized.
+    /// None if the mesh has no normals
     pub fn interpolate_normal(&self, uvw: (F, F, F)) -> Option<Vector3<F>> {
-        let n1 = self.get_vertex_normal(0);
-        let n2 = self.get_vertex_normal(1);
-        let n3 = self.get_vertex_normal(2);
+        let n1 = self.get_vertex_normal(0)?;
+        let n2 = self.get_vertex_normal(1)?;
+        let n3 = self.get_vertex_normal(2)?;
 
         Some(n1 * uvw.0 + n2 * uvw.1 + n3 * uvw.2)
     }
 
+    /// None if the mesh has no uv0
     pub fn interpolate_uv0(&self, bc: Vector3<F>) -> Option<Vector2<F>> {
-        let uv1 = self.get_vertex_uv(0);
-        let uv2 = self.get_vertex_uv(1);
-        let uv3 = self.get_vertex_uv(2);
+        let uv1 = self.get_vertex_uv(0)?;
+        let uv2 = self.get_vertex_uv(1)?;
+        let uv3 = self.get_vertex_uv(2)?;
 
         Some(uv1 * bc[0] + uv2 * bc[1] + uv3 * bc[2])
     }
 
-    pub fn get_transform(&self) -> Transform<F> {
-        let transform = self.go.get_transform().unwrap();
-        transform
+    /// Interpolated vertex tangent in world space, w is the sign of the bitangent.
+    /// None if the mesh has no tangents
+    pub fn interpolate_tangent(&self, uvw: (F, F, F)) -> Option<Vector4<F>> {
+        let mesh_component = self.go.get_component::<MeshFilter<F>>().unwrap();
+        let mesh = mesh_component.downcast::<MeshFilter<F>>();
+        let vertex_buffer = &mesh.get_mesh().vertices;
+        let t1 = vertex_buffer.get_tangent(self.vertex_index[0])?;
+        let t2 = vertex_buffer.get_tangent(self.vertex_index[1])?;
+        let t3 = vertex_buffer.get_tangent(self.vertex_index[2])?;
+
+        let t = t1.truncate() * uvw.0 + t2.truncate() * uvw.1 + t3.truncate() * uvw.2;
+        let t = self.transform.transform.transform_vector(t);
+        // the sign is constant over a triangle after the vertices are split by handedness
+        let w = if self.transform.swaps_handedness { -t1.w } else { t1.w };
+        Some(Vector4::new(t.x, t.y, t.z, w))
+    }
+
+    /// Tangent from the uv mapping of the world space triangle, w is the sign of the bitangent.
+    /// Falls back to an edge of the triangle if there is no usable uv
+    pub fn get_face_tangent(&self) -> Vector4<F> {
+        let edge = self.triangle.a - self.triangle.b;
+        let fallback = Vector4::new(edge.x, edge.y, edge.z, F::one());
+
+        let mesh_component = self.go.get_component::<MeshFilter<F>>().unwrap();
+        let mesh = mesh_component.downcast::<MeshFilter<F>>();
+        let vertex_buffer = &mesh.get_mesh().vertices;
+        let uv = self.vertex_index.map(|i| vertex_buffer.get_uv0(i));
+        let (Some(uv1), Some(uv2), Some(uv3)) = (uv[0], uv[1], uv[2]) else {
+            return fallback;
+        };
+
+        let e1 = self.triangle.b - self.triangle.a;
+        let e2 = self.triangle.c - self.triangle.a;
+        let duv1 = uv2 - uv1;
+        let duv2 = uv3 - uv1;
+        let r = duv1.x * duv2.y - duv2.x * duv1.y;
+        if r == F::zero() {
+            return fallback;
+        }
+        let t = (e1 * duv2.y - e2 * duv1.y) / r;
+        let b = (e2 * duv1.x - e1 * duv2.x) / r;
+        let w = if self.triangle.get_normal().cross(t).dot(b) < F::zero() { -F::one() } else { F::one() };
+        Vector4::new(t.x, t.y, t.z, w)
+    }
+
+    /// Object space to world space
+    pub fn get_transform(&self) -> &aika_math::Transform<F> {
+        &self.transform.transform
     }
 
-    pub fn get_vertex_uv(&self, index: usize) -> Vector2<F> {
+    pub fn get_vertex_uv(&self, index: usize) -> Option<Vector2<F>> {
         let mesh_component = self.go.get_component::<MeshFilter<F>>().unwrap();
         let mesh = mesh_component.downcast::<MeshFilter<F>>();
-        let vertex_buffer = &mesh.mesh.vertices;
-        let uv = vertex_buffer.get_uv0(self.vertex_index[index]).unwrap();
-        uv
+        let vertex_buffer = &mesh.get_mesh().vertices;
+        vertex_buffer.get_uv0(self.vertex_index[index])
     }
 
-    pub fn get_vertex_normal(&self, index: usize) -> Vector3<F> {
+    /// The vertex normal in world space, None if the mesh has no normals
+    pub fn get_vertex_normal(&self, index: usize) -> Option<Vector3<F>> {
         let mesh_component = self.go.get_component::<MeshFilter<F>>().unwrap();
         let mesh = mesh_component.downcast::<MeshFilter<F>>();
-        let vertex_buffer = &mesh.mesh.vertices;
-        let n = vertex_buffer.get_normal(self.vertex_index[index]).unwrap();
+        let vertex_buffer = &mesh.get_mesh().vertices;
+        let n = vertex_buffer.get_normal(self.vertex_index[index])?;
 
-        // since we only support uniform scaling
-        let transform = self.get_transform();
-        let transformed_normal = transform.rotation.rotate_vector(n);
-        transformed_normal
+        Some(self.transform.transform_normal(n).unwrap_or(n).normalize())
     }
 }
 
@@ -1,6 +1,11 @@
-pub use mashed_triangle::MashedTriangle;
-pub use mashed_scene::MashedScene;
+pub use mashed_triangle::{MashedTransform, MashedTriangle};
+pub use mashed_shape::MashedShape;
+pub use mashed_primitive::MashedPrimitive;
+pub use mashed_scene::{MashedScene, OcclusionFilter, SpatialStructureType};
 
 mod mashed_triangle;
+mod mashed_shape;
+mod mashed_primitive;
 mod mashed_scene;
+mod mashed_scene_cache;
 mod test;
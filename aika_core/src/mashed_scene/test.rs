@@ -1,9 +1,12 @@
 use std::rc::Rc;
-use cgmath::Vector3;
-use aika_math::Triangle;
-use crate::component::Transform;
+use cgmath::{InnerSpace, One, Quaternion, Vector3};
+use aika_math::{Ray, Triangle};
+use crate::component::{MeshFilter, ShapeFilter, Transform};
+use crate::mesh::{CommonVertex, Mesh, SubdivisionScheme, WavefrontMeshLoader};
 use crate::scene::{GameObject, GameObjectInternal, Scene};
-use crate::mashed_scene::MashedScene;
+use crate::mashed_scene::{MashedScene, MashedTransform, SpatialStructureType};
+use crate::material::{DiffuseBRDFMaterial, Material, MaterialSlots, RoughDielectricBSDFMaterial};
+use crate::material_graph::{MaterialGraphContext, OutputValue};
 
 #[test]
 fn test_mashed_scene1() {
@@ -20,3 +23,306 @@ fn test_mashed_scene1() {
     // assert_eq!(triangle1.vertex_index, [0, 1, 2]);
     // assert_eq!(triangle1.triangle.a, Vector3::new(0.5, 0.5, 0.0));
 }
+
+#[test]
+fn test_mashed_scene_mesh_and_shape() {
+    // a game object with both filters is mashed once, from its mesh
+    let mut scene = Scene::new();
+    let mut go = GameObject::new_plane(String::from("plane"), 1.0, 1.0);
+    go.add_component_owned(ShapeFilter::sphere(1.0));
+    go.add_component_owned::<Transform<f64>>(Transform::default());
+    scene.add_game_object(go);
+
+    let mashed_scene = MashedScene::from_scene_bvh(&scene);
+    assert_eq!(mashed_scene.get_triangle_count(), 2);
+    assert_eq!(mashed_scene.get_primitive_count(), 2);
+}
+
+#[test]
+fn test_mashed_scene_cache() {
+    let mut scene = Scene::new();
+    let mut go = GameObject::new_plane(String::from("plane"), 1.0, 1.0);
+    go.add_component_owned::<Transform<f64>>(Transform::new(Vector3::new(0.0, 0.0, 1.0), 1.0, Quaternion::one()));
+    scene.add_game_object(go);
+
+    let cache_dir = std::env::temp_dir().join(format!("aika_test_mashed_scene_cache_{}", std::process::id()));
+    // the first call builds and writes the cache, the second call loads it
+    let built = MashedScene::from_scene_bvh_cached(&scene, &cache_dir);
+    assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 1);
+    let loaded = MashedScene::from_scene_bvh_cached(&scene, &cache_dir);
+    assert_eq!(built.get_triangle_count(), loaded.get_triangle_count());
+
+    let ray = Ray::new(Vector3::new(0.1, 0.2, 0.0), Vector3::new(0.0, 0.0, 1.0).normalize());
+    let a = built.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    let b = loaded.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    assert_eq!(a.t, b.t);
+    assert_eq!(a.hit_object.unwrap().as_triangle().unwrap().vertex_index, b.hit_object.unwrap().as_triangle().unwrap().vertex_index);
+
+    // a cache which can not be written does not fail the build
+    let blocked_dir = cache_dir.join("file");
+    std::fs::write(&blocked_dir, b"").unwrap();
+    let unwritten = MashedScene::from_scene_bvh_cached(&scene, &blocked_dir.join("cache"));
+    assert_eq!(unwritten.get_triangle_count(), built.get_triangle_count());
+
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+}
+
+#[test]
+fn test_mashed_scene_structure_types() {
+    let mut scene = Scene::new();
+    let mut go = GameObject::new_plane(String::from("plane"), 1.0, 1.0);
+    go.add_component_owned::<Transform<f64>>(Transform::new(Vector3::new(0.0, 0.0, 1.0), 1.0, Quaternion::one()));
+    scene.add_game_object(go);
+
+    let ray = Ray::new(Vector3::new(0.1, 0.2, 0.0), Vector3::new(0.0, 0.0, 1.0));
+    for structure_type in [SpatialStructureType::Naive, SpatialStructureType::BVH, SpatialStructureType::KdTree, SpatialStructureType::UniformGrid] {
+        let mashed_scene = MashedScene::from_scene(&scene, structure_type);
+        assert_eq!(mashed_scene.get_triangle_count(), 2);
+        let hit = mashed_scene.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-9);
+    }
+}
+
+
+fn get_shape_scene() -> Scene<f64> {
+    let mut scene = Scene::new();
+    let mut plane = GameObject::new_plane(String::from("plane"), 10.0, 10.0);
+    plane.add_component_owned::<Transform<f64>>(Transform::new(Vector3::new(0.0, 0.0, -1.0), 1.0, Quaternion::one()));
+    scene.add_game_object(plane);
+
+    let mut sphere = GameObject::new_empty(String::from("sphere"));
+    sphere.add_component_owned(ShapeFilter::sphere(0.5));
+    sphere.add_component_owned::<Transform<f64>>(Transform::new(Vector3::new(0.0, 0.0, 3.0), 2.0, Quaternion::one()));
+    scene.add_game_object(sphere);
+
+    let mut cuboid = GameObject::new_empty(String::from("box"));
+    cuboid.add_component_owned(ShapeFilter::cuboid(Vector3::new(0.5, 0.5, 0.5)));
+    cuboid.add_component_owned::<Transform<f64>>(Transform::new(Vector3::new(3.0, 0.0, 3.0), 1.0, Quaternion::one()));
+    scene.add_game_object(cuboid);
+
+    scene
+}
+
+#[test]
+fn test_mashed_scene_shapes() {
+    let scene = get_shape_scene();
+    let down = Vector3::new(0.0, 0.0, -1.0);
+    for structure_type in [SpatialStructureType::Naive, SpatialStructureType::BVH, SpatialStructureType::KdTree, SpatialStructureType::UniformGrid] {
+        let mashed_scene = MashedScene::from_scene(&scene, structure_type);
+        assert_eq!(mashed_scene.get_triangle_count(), 2);
+        assert_eq!(mashed_scene.get_primitive_count(), 4);
+
+        // the sphere has radius 1 in world space
+        let hit = mashed_scene.hit(&Ray::new(Vector3::new(0.0, 0.0, 10.0), down), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 6.0).abs() < 1e-9);
+        assert!((hit.normal.unwrap() - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+        assert!(hit.uv.unwrap().y.abs() < 1e-9);
+        assert!(hit.hit_object.unwrap().as_triangle().is_none());
+
+        let hit = mashed_scene.hit(&Ray::new(Vector3::new(3.25, 0.25, 10.0), down), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 6.5).abs() < 1e-9);
+        assert!((hit.get_hit_point(&Ray::new(Vector3::new(3.25, 0.25, 10.0), down)).z - 3.5).abs() < 1e-9);
+        assert!((hit.uv.unwrap() - cgmath::Vector2::new(0.75, 0.75)).magnitude() < 1e-9);
+
+        // misses both shapes and lands on the plane
+        let hit = mashed_scene.hit(&Ray::new(Vector3::new(1.5, 0.0, 10.0), down), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 11.0).abs() < 1e-9);
+        assert!(hit.hit_object.unwrap().as_triangle().is_some());
+    }
+}
+
+#[test]
+fn test_mashed_scene_cache_shapes() {
+    let scene = get_shape_scene();
+
+    let cache_dir = std::env::temp_dir().join(format!("aika_test_mashed_scene_cache_shapes_{}", std::process::id()));
+    let built = MashedScene::from_scene_bvh_cached(&scene, &cache_dir);
+    let loaded = MashedScene::from_scene_bvh_cached(&scene, &cache_dir);
+    assert_eq!(built.get_primitive_count(), loaded.get_primitive_count());
+    assert_eq!(built.get_triangle_count(), loaded.get_triangle_count());
+
+    let ray = Ray::new(Vector3::new(0.2, 0.1, 10.0), Vector3::new(0.0, 0.0, -1.0));
+    let a = built.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    let b = loaded.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    assert_eq!(a.t, b.t);
+
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+}
+
+#[test]
+fn test_mashed_scene_hierarchy() {
+    let mut scene = Scene::new();
+    let mut parent = GameObject::new_empty(String::from("parent"));
+    parent.add_component_owned::<Transform<f64>>(Transform::new(Vector3::new(0.0, 0.0, 2.0), 1.0, Quaternion::one()));
+
+    let mut sphere = GameObject::new_empty(String::from("ellipsoid"));
+    sphere.add_component_owned(ShapeFilter::sphere(1.0));
+    sphere.add_component_owned::<Transform<f64>>(Transform::new_non_uniform(Vector3::new(0.0, 0.0, 1.0), Vector3::new(1.0, 1.0, 0.5), Quaternion::one()));
+    sphere.set_parent(Some(&parent)).unwrap();
+
+    scene.add_game_object(parent);
+    scene.add_game_object(sphere);
+
+    // the ellipsoid is centered at z = 3 with a half height of 0.5
+    let mashed_scene = MashedScene::from_scene_bvh(&scene);
+    let ray = Ray::new(Vector3::new(0.0, 0.0, 10.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = mashed_scene.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    assert!((hit.t - 6.5).abs() < 1e-9);
+
+    // the normal is tilted towards z by the non-uniform scale
+    let ray = Ray::new(Vector3::new(0.5, 0.0, 10.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = mashed_scene.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    let p = hit.get_hit_point(&ray);
+    let n = hit.normal.unwrap();
+    let expected = Vector3::new(p.x, p.y, (p.z - 3.0) * 4.0).normalize();
+    assert!((n - expected).magnitude() < 1e-9);
+}
+
+#[test]
+fn test_mashed_transform_normal() {
+    let transform = aika_math::Transform::rotate_x(cgmath::Deg(30.0))
+        * aika_math::Transform::scale(Vector3::new(1.0, -2.0, 0.5))
+        * aika_math::Transform::translate(Vector3::new(1.0, 2.0, 3.0));
+    let mashed = MashedTransform::new(transform.clone());
+    assert!(mashed.swaps_handedness);
+
+    let n = Vector3::new(0.3, -0.4, 0.8);
+    let expected = transform.transform_normal(n).unwrap();
+    assert!((mashed.transform_normal(n).unwrap() - expected).magnitude() < 1e-9);
+}
+
+#[test]
+fn test_mashed_scene_shading_normal() {
+    let mut scene = Scene::new();
+    let mesh = WavefrontMeshLoader::sphere_smooth::<f64>().unwrap().to_dyn_mesh();
+    let mut go = GameObject::new_empty(String::from("sphere"));
+    go.add_component_owned(MeshFilter::new(mesh));
+    go.add_component_owned::<Transform<f64>>(Transform::new(Vector3::new(0.0, 0.0, 0.0), 1.0, Quaternion::one()));
+    scene.add_game_object(go);
+
+    let mashed_scene = MashedScene::from_scene_bvh(&scene);
+    let ray = Ray::new(Vector3::new(0.3, 0.2, 10.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = mashed_scene.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    let point = hit.get_hit_point(&ray);
+    let primitive = hit.hit_object.unwrap();
+
+    // the interpolated normal follows the sphere closer than the facet does
+    let radial = point.normalize();
+    let shading = primitive.get_shading_normal(point);
+    let geometric = primitive.get_geometric_normal(point);
+    assert!(shading.dot(radial) > geometric.dot(radial));
+    assert!((shading.magnitude() - 1.0).abs() < 1e-9);
+}
+
+/// height equal to u
+struct RampNode;
+
+impl OutputValue<f64, f64> for RampNode {
+    fn get_value(&self, context: &MaterialGraphContext<f64>) -> f64 {
+        context.uv.x
+    }
+}
+
+#[test]
+fn test_mashed_scene_displacement() {
+    let mut scene = Scene::new();
+    let mut go = GameObject::new_plane(String::from("plane"), 1.0, 1.0);
+    go.add_component_owned::<Transform<f64>>(Transform::new(Vector3::new(0.0, 0.0, 0.0), 1.0, Quaternion::one()));
+    let material = Material::new(Box::new(DiffuseBRDFMaterial::new(Vector3::new(0.5, 0.5, 0.5))))
+        .with_displacement(Rc::new(RampNode), 0.5, 2);
+    go.add_component_owned(material);
+    scene.add_game_object(go);
+
+    // mashing again starts from the original mesh
+    for _ in 0..2 {
+        let mashed_scene = MashedScene::from_scene_bvh(&scene);
+        assert_eq!(mashed_scene.get_triangle_count(), 32);
+
+        // u goes from 0 at x = -0.5 to 1 at x = 0.5
+        let ray = Ray::new(Vector3::new(0.3, 0.1, 10.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = mashed_scene.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        let p = hit.get_hit_point(&ray);
+        assert!((p.z - 0.4).abs() < 1e-9);
+        let n = hit.hit_object.unwrap().get_shading_normal(p);
+        assert!((n - Vector3::new(-0.5, 0.0, 1.0).normalize()).magnitude() < 1e-9);
+    }
+}
+
+#[test]
+fn test_mashed_scene_without_normals() {
+    let mut scene = Scene::new();
+    let obj = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+    let mesh = WavefrontMeshLoader::load_wavefront_obj_memory::<f64>(obj).unwrap().remove(0);
+    assert_eq!(mesh.validate().missing_normals, 3);
+    let mut go = GameObject::new_empty(String::from("triangle"));
+    go.add_component_owned(MeshFilter::new(mesh.to_dyn_mesh()));
+    go.add_component_owned::<Transform<f64>>(Transform::new(Vector3::new(0.0, 0.0, 0.0), 1.0, Quaternion::one()));
+    scene.add_game_object(go);
+
+    let mashed_scene = MashedScene::from_scene_bvh(&scene);
+    let ray = Ray::new(Vector3::new(0.2, 0.2, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = mashed_scene.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    assert!(hit.uv.is_none());
+    let p = hit.get_hit_point(&ray);
+    // flat shading
+    let n = hit.hit_object.unwrap().get_shading_normal(p);
+    assert!((n - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+}
+
+#[test]
+fn test_mashed_scene_material_slots() {
+    let mut vertices = Vec::new();
+    for i in 0..3 {
+        for (x, y) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)] {
+            let mut v = CommonVertex::new();
+            v.position = Vector3::new(x + 2.0 * i as f64, y, 0.0);
+            vertices.push(v);
+        }
+    }
+    let mesh = Mesh {
+        vertices,
+        triangles: vec![[0, 1, 2], [3, 4, 5], [6, 7, 8]],
+        sub_mesh: vec![[0, 1], [1, 2], [2, 3]],
+    };
+    assert_eq!(mesh.get_triangle_sub_mesh_indices(), vec![0, 1, 2]);
+
+    let get_material = |ior: f64| Material::new(Box::new(RoughDielectricBSDFMaterial::new(Rc::new(0.5), ior)));
+    let mut scene = Scene::new();
+    let mut go = GameObject::new_empty(String::from("triangles"));
+    go.add_component_owned(MeshFilter::new(mesh.to_dyn_mesh()));
+    go.add_component_owned::<Transform<f64>>(Transform::new(Vector3::new(0.0, 0.0, 0.0), 1.0, Quaternion::one()));
+    go.add_component_owned(MaterialSlots::new(vec![get_material(1.2), get_material(1.4)]));
+    // the third sub mesh has no slot
+    go.add_component_owned(get_material(1.6));
+    scene.add_game_object(go);
+
+    let mashed_scene = MashedScene::from_scene_bvh(&scene);
+    for (i, ior) in [1.2, 1.4, 1.6].into_iter().enumerate() {
+        let ray = Ray::new(Vector3::new(0.2 + 2.0 * i as f64, 0.2, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = mashed_scene.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        let primitive = hit.hit_object.unwrap();
+        assert_eq!(primitive.get_sub_mesh_index(), i);
+        let material = primitive.get_material().unwrap();
+        assert!((material.downcast().material_impl.get_ior().unwrap().x - ior).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_mashed_scene_subdivision() {
+    let mut scene = Scene::new();
+    let obj = b"v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\nv -1 -1 1\nv 1 -1 1\nv 1 1 1\nv -1 1 1\n\
+f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 3 4 8 7\nf 2 3 7 6\nf 4 1 5 8\n";
+    let control_mesh = WavefrontMeshLoader::load_wavefront_obj_polygons_memory::<f64>(obj).unwrap().remove(0);
+    let mut go = GameObject::new_empty(String::from("cube"));
+    go.add_component_owned(MeshFilter::from_polygon_mesh(control_mesh, SubdivisionScheme::CatmullClark, 2).unwrap());
+    go.add_component_owned::<Transform<f64>>(Transform::new(Vector3::new(0.0, 0.0, 0.0), 1.0, Quaternion::one()));
+    scene.add_game_object(go);
+
+    let mashed_scene = MashedScene::from_scene_bvh(&scene);
+    assert_eq!(mashed_scene.get_triangle_count(), 192);
+    // the limit surface shrinks inside of the cube
+    let ray = Ray::new(Vector3::new(0.1, 0.2, 10.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = mashed_scene.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    let p = hit.get_hit_point(&ray);
+    assert!(p.z > 0.5 && p.z < 1.0);
+}
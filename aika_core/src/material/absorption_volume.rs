@@ -41,9 +41,7 @@ impl<F> VolumeTrait<F> for AbsorptionVolume<F> where F: BaseFloat + 'static {
         shading_context: &ShadingContext<F>,
         current_dir: Vector3<F>
     ) -> Result<VolumeSampleResult<F>> {
-        let point = shading_context.point;
-        let normal = shading_context.normal;
-        let ray = Ray::new(point - normal * F::from(1e-3).unwrap(), current_dir);
+        let ray = shading_context.spawn_ray(current_dir);
         // let ray = Ray::new(point, current_dir);
         // println!("Before hit");
         // let hit_result = tracing_service.hit_ray(&ray, F::from(1e-9).unwrap(), F::infinity());
@@ -59,6 +57,8 @@ impl<F> VolumeTrait<F> for AbsorptionVolume<F> where F: BaseFloat + 'static {
             return Ok(VolumeSampleResult {
                 next_direction: current_dir,
                 point: shading_context.point,
+                point_error: shading_context.point_error,
+                normal: shading_context.geometric_normal,
                 weight: Vector3::new(F::one(), F::one(), F::one())
             });
         }
@@ -70,16 +70,11 @@ impl<F> VolumeTrait<F> for AbsorptionVolume<F> where F: BaseFloat + 'static {
         let transmittance = self.transmittance(shading_context.point, hit_point);
         // println!("{:?}", transmittance);
 
-        let next_point_offset = if hit_record.back_facing.unwrap() {
-            f!(1e-3)
-        } else {
-            f!(-1e-3)
-        };
-        let normal = hit_record.normal.unwrap();
-
         let result = VolumeSampleResult {
             next_direction: current_dir,
-            point: hit_point + normal * next_point_offset,
+            point: hit_point,
+            point_error: hit_record.get_point_error(),
+            normal: hit_record.normal.unwrap(),
             weight: transmittance,
         };
 
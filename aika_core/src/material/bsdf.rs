@@ -9,7 +9,6 @@ pub struct BSDFSampleResult<F> {
     // pub value: Vector3<F>,
     /// cos theta of the sampled dir
     pub weight: Vector3<F>,
-    pub next_point: Vector3<F>,
 }
 
 impl<F> BSDFSampleResult<F> where F: BaseFloat {
@@ -31,4 +30,10 @@ pub trait BSDF<F> {
     fn emit(&self, wo: Vector3<F>) -> Option<Vector3<F>> {
         None
     }
+
+    /// The solid angle density of `sample_ray` choosing `wi` from `wo`, in tangent space.
+    /// None if the BSDF does not provide it
+    fn pdf(&self, wi: Vector3<F>, wo: Vector3<F>) -> Option<F> {
+        None
+    }
 }
@@ -53,7 +53,6 @@ impl<F> BSDF<F> for ConductorBRDF<F> where F: BaseFloat {
             // value,
             // cos_theta: current_dir.z,
             weight: Vector3::new(f1, f2, f3),
-            next_point: Vector3::new(f!(0), f!(0), f!(1e-6)),
         })
     }
 }
@@ -25,14 +25,16 @@ impl<F> BSDF<F> for DielectricBSDF<F> where F: BaseFloat + 'static {
     }
 
     fn sample_ray(&self, service: &mut TracingService<F>, current_dir: Vector3<F>) -> Option<BSDFSampleResult<F>> {
+        self.sample_with(current_dir, service.random_0_1())
+    }
+}
+
+impl<F> DielectricBSDF<F> where F: BaseFloat + 'static {
+    /// Same as `sample_ray`, `random` chooses between reflection and refraction
+    pub(crate) fn sample_with(&self, current_dir: Vector3<F>, random: F) -> Option<BSDFSampleResult<F>> {
         let cos_theta = current_dir.z;
         let fresnel = fresnel_dielectric(cos_theta, F::one(), self.relative_ior);
         let vector_one = Vector3::new(F::one(), F::one(), F::one());
-        let reflection_point_bias = if current_dir.z >= F::zero() {
-            f!(1e-3)
-        } else {
-            f!(-1e-3)
-        };
         // println!("{:?}", self.relative_ior);
         if fresnel.is_none() {
             // total internal reflection
@@ -40,14 +42,12 @@ impl<F> BSDF<F> for DielectricBSDF<F> where F: BaseFloat + 'static {
             return Some(BSDFSampleResult {
                 direction: Vector3::new(-current_dir.x, -current_dir.y, current_dir.z),
                 weight: vector_one,
-                next_point: Vector3::new(F::zero(), F::zero(), reflection_point_bias),
             });
         }
         let fresnel = fresnel.unwrap();
         let transmittance = F::one() - fresnel;
 
         // println!("fresnel: {:?}", fresnel);
-        let random = service.random_0_1();
         // let random = F::one();
         if random < fresnel {
             // sample reflect
@@ -56,7 +56,6 @@ impl<F> BSDF<F> for DielectricBSDF<F> where F: BaseFloat + 'static {
             Some(BSDFSampleResult {
                 direction: Vector3::new(-current_dir.x, -current_dir.y, current_dir.z),
                 weight: Vector3::new(w, w, w),
-                next_point: Vector3::new(F::zero(), F::zero(), reflection_point_bias)
             })
         } else {
             // sample transmit
@@ -98,7 +97,6 @@ impl<F> BSDF<F> for DielectricBSDF<F> where F: BaseFloat + 'static {
             Some(BSDFSampleResult {
                 direction: refract_dir,
                 weight: Vector3::new(w, w, w),
-                next_point: Vector3::new(F::zero(), F::zero(), -reflection_point_bias),
             })
         }
     }
@@ -1,10 +1,12 @@
+use std::rc::Rc;
 use std::f64::consts::PI;
 use cgmath::{BaseFloat, InnerSpace, Vector3};
 use aika_math::Ray;
 use crate::material::{BSDF, BSDFSampleResult, MaterialTrait, VolumeTrait};
 use anyhow::Result;
-use aika_math::utils::{get_2pi, sample_uniform_hemisphere};
+use aika_math::utils::{cosine_hemisphere_pdf, sample_cosine_hemisphere};
 use crate::f;
+use crate::material_graph::{MaterialGraphContext, OutputValue};
 use crate::path_tracing::{ShadingContext, TracingService};
 
 #[derive(Clone)]
@@ -34,13 +36,9 @@ impl<F> BSDF<F> for DiffuseBRDF<F> where F: BaseFloat + 'static {
         }
         // assert!(current_dir.z >= F::zero());
 
-        let dir = sample_uniform_hemisphere(service.random_0_1(), service.random_0_1());
-        assert!(dir.z > F::zero());
-        if dir.z <= F::zero() {
-            println!("sampled diffuse brdf dir is under normal {:?}", dir);
-        }
-        let pdf = F::one() / get_2pi();
-        let weight = self.albedo * F::from(2).unwrap() * dir.z;
+        let dir = sample_cosine_hemisphere(service.random_0_1(), service.random_0_1());
+        // f * cos / pdf, the cosines cancel
+        let weight = self.albedo;
 
         let result = BSDFSampleResult {
             // pdf: Vector3::new(pdf, pdf, pdf),
@@ -48,18 +46,31 @@ impl<F> BSDF<F> for DiffuseBRDF<F> where F: BaseFloat + 'static {
             weight,
             // value: self.evaluate(current_dir, dir),
             // cos_theta: dir.z,
-            next_point: Vector3::new(f!(0), f!(0), f!(1e-5))
         };
         Some(result)
     }
+
+    fn pdf(&self, wi: Vector3<F>, wo: Vector3<F>) -> Option<F> {
+        if wi.z <= F::zero() || wo.z <= F::zero() {
+            return Some(F::zero());
+        }
+        Some(cosine_hemisphere_pdf(wi.z))
+    }
 }
 
 pub struct DiffuseBRDFMaterial<F> {
-    pub albedo: Vector3<F>,
+    pub albedo: Rc<dyn OutputValue<F, Vector3<F>>>,
 }
 
-impl<F> DiffuseBRDFMaterial<F> where F: BaseFloat {
+impl<F> DiffuseBRDFMaterial<F> where F: BaseFloat + 'static {
     pub fn new(albedo: Vector3<F>) -> Self {
+        DiffuseBRDFMaterial {
+            albedo: Rc::new(albedo)
+        }
+    }
+
+    /// The albedo is read from the material graph, such as a texture
+    pub fn from_graph(albedo: Rc<dyn OutputValue<F, Vector3<F>>>) -> Self {
         DiffuseBRDFMaterial {
             albedo
         }
@@ -76,7 +87,10 @@ impl<F> MaterialTrait<F> for DiffuseBRDFMaterial<F> where F: BaseFloat + 'static
     }
 
     fn get_bsdf(&self, context: &ShadingContext<F>) -> Option<Box<dyn BSDF<F>>> {
-        Some(Box::new(DiffuseBRDF::new(self.albedo)))
+        let material_graph_context = MaterialGraphContext {
+            uv: context.uv,
+        };
+        Some(Box::new(DiffuseBRDF::new(self.albedo.get_value(&material_graph_context))))
     }
 
     fn get_volume(&self) -> Option<Box<dyn VolumeTrait<F>>> {
@@ -0,0 +1,376 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use cgmath::{BaseFloat, ElementWise, Vector3};
+use num_traits::Zero;
+use aika_math::utils::{cosine_hemisphere_pdf, get_pi, get_z, lerp, max_component_value, refract, sample_cosine_hemisphere, sqr, uniform_sphere_pdf};
+use crate::f;
+use crate::material::{BSDF, BSDFSampleResult, DielectricBSDF, MaterialTrait, RoughDielectricBSDF, VolumeTrait};
+use crate::material_graph::{MaterialGraphContext, OutputValue};
+use crate::path_tracing::{ShadingContext, TracingService};
+use crate::utils::{fresnel_dielectric, RandomGenerator};
+
+/// The dielectric interface on top of a layered BSDF
+pub enum LayerInterface<F> {
+    Smooth(DielectricBSDF<F>),
+    Rough(RoughDielectricBSDF<F>),
+}
+
+impl<F> LayerInterface<F> where F: BaseFloat + 'static {
+    fn get_relative_ior(&self) -> F {
+        match self {
+            LayerInterface::Smooth(bsdf) => bsdf.relative_ior,
+            LayerInterface::Rough(bsdf) => bsdf.get_relative_ior().x,
+        }
+    }
+
+    /// Sample the interface with the random numbers given.
+    /// Radiance crossing the interface is scaled by the squared ratio of the iors, which is applied
+    /// only if `is_radiance`, a path sampled from the light side carries importance instead
+    fn sample(&self, wo: Vector3<F>, random: [F; 3], is_radiance: bool) -> Option<BSDFSampleResult<F>> {
+        let result = match self {
+            LayerInterface::Smooth(bsdf) => bsdf.sample_with(wo, random[0])?,
+            LayerInterface::Rough(bsdf) => bsdf.sample_single_ior_with(wo, 0, random[1], random[2], random[0])?,
+        };
+        // both interfaces scale the transmitted radiance by 1 / etap^2, importance is not scaled
+        if is_radiance || result.direction.z * wo.z >= F::zero() {
+            return Some(result);
+        }
+        let eta = self.get_relative_ior();
+        let etap = if wo.z > F::zero() { eta } else { F::one() / eta };
+        let scale = sqr(etap);
+        Some(BSDFSampleResult {
+            direction: result.direction,
+            weight: result.weight * scale,
+        })
+    }
+
+    /// Reflection off the top of the interface
+    fn evaluate_reflection(&self, wi: Vector3<F>, wo: Vector3<F>) -> Vector3<F> {
+        match self {
+            LayerInterface::Smooth(_) => Vector3::zero(),
+            LayerInterface::Rough(bsdf) => bsdf.evaluate(wi, wo).unwrap_or(Vector3::zero()),
+        }
+    }
+
+    fn pdf_reflection(&self, wi: Vector3<F>, wo: Vector3<F>) -> F {
+        match self {
+            LayerInterface::Smooth(_) => F::zero(),
+            LayerInterface::Rough(bsdf) => bsdf.pdf(wi, wo),
+        }
+    }
+}
+
+/// A dielectric interface over a base BSDF, with an optional absorbing medium between them.
+/// Light bounces between the two layers in a random walk, as in pbrt-v4's `LayeredBxDF`.
+/// The base is treated as opaque and the BSDF is two sided
+pub struct LayeredBSDF<F> {
+    pub interface: LayerInterface<F>,
+    pub base: Box<dyn BSDF<F>>,
+    /// absorption coefficient of the medium
+    pub absorption: Vector3<F>,
+    pub thickness: F,
+    pub max_depth: usize,
+    /// number of random walks `evaluate` and `pdf` average over
+    pub sample_count: usize,
+}
+
+fn flip_z<F: BaseFloat>(w: Vector3<F>) -> Vector3<F> {
+    Vector3::new(w.x, w.y, -w.z)
+}
+
+/// Seed for a random generator that depends on the directions only, so that `evaluate` is deterministic
+fn get_seed<F: BaseFloat>(wi: Vector3<F>, wo: Vector3<F>) -> usize {
+    let mut hasher = DefaultHasher::new();
+    for x in [wi.x, wi.y, wi.z, wo.x, wo.y, wo.z] {
+        x.to_f64().unwrap().to_bits().hash(&mut hasher);
+    }
+    hasher.finish() as usize
+}
+
+impl<F> LayeredBSDF<F> where F: BaseFloat + 'static {
+    pub fn new(interface: LayerInterface<F>, base: Box<dyn BSDF<F>>) -> Self {
+        LayeredBSDF {
+            interface,
+            base,
+            absorption: Vector3::zero(),
+            thickness: f!(0.01),
+            max_depth: 10,
+            sample_count: 1,
+        }
+    }
+
+    pub fn with_medium(mut self, absorption: Vector3<F>, thickness: F) -> Self {
+        self.absorption = absorption;
+        self.thickness = thickness;
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_sample_count(mut self, sample_count: usize) -> Self {
+        self.sample_count = sample_count.max(1);
+        self
+    }
+
+    /// Transmittance of the medium along `w`, from one layer to the other
+    fn get_transmittance(&self, w: Vector3<F>) -> Vector3<F> {
+        if w.z == F::zero() {
+            return Vector3::zero();
+        }
+        let distance = self.thickness / w.z.abs();
+        Vector3::new(
+            (-self.absorption.x * distance).exp(),
+            (-self.absorption.y * distance).exp(),
+            (-self.absorption.z * distance).exp(),
+        )
+    }
+
+    /// Russian roulette on the throughput, returns false if the walk ends
+    fn russian_roulette(&self, beta: &mut Vector3<F>, depth: usize, random: F) -> bool {
+        let max = max_component_value(*beta);
+        if depth > 3 && max < f!(0.25) {
+            let q = (F::one() - max).max(F::zero());
+            if random < q {
+                return false;
+            }
+            *beta /= F::one() - q;
+        }
+        true
+    }
+
+    /// Both directions are on the upper side
+    fn evaluate_upper(&self, wi: Vector3<F>, wo: Vector3<F>) -> Vector3<F> {
+        let n = F::from(self.sample_count).unwrap();
+        let mut result = self.interface.evaluate_reflection(wi, wo) * n;
+
+        let mut generator = RandomGenerator::new(get_seed(wi, wo));
+        let mut random = move || generator.random();
+        for _ in 0..self.sample_count {
+            // a sampled reflection contributes nothing, which keeps the estimate unbiased
+            let wos = match self.interface.sample(wo, [random(), random(), random()], true) {
+                Some(s) if s.direction.z < F::zero() => s,
+                _ => continue,
+            };
+            let wis = match self.interface.sample(wi, [random(), random(), random()], false) {
+                Some(s) if s.direction.z < F::zero() => s,
+                _ => continue,
+            };
+            let exit_weight = wis.weight.mul_element_wise(self.get_transmittance(wis.direction));
+
+            let mut beta = wos.weight;
+            let mut w = wos.direction;
+            for depth in 0..self.max_depth {
+                if !self.russian_roulette(&mut beta, depth, random()) {
+                    break;
+                }
+                beta = beta.mul_element_wise(self.get_transmittance(w));
+
+                // connect the base to the exit direction
+                if let Some(f) = self.base.evaluate(-wis.direction, -w) {
+                    result += beta.mul_element_wise(f).mul_element_wise(exit_weight);
+                }
+
+                // the base can only be evaluated, so the walk continues with cosine sampling
+                let d = sample_cosine_hemisphere(random(), random());
+                let f = self.base.evaluate(d, -w).unwrap_or(Vector3::zero());
+                beta = beta.mul_element_wise(f) * get_pi::<F>();
+                if max_component_value(beta) <= F::zero() {
+                    break;
+                }
+                w = d;
+                beta = beta.mul_element_wise(self.get_transmittance(w));
+
+                // light leaving through the interface is only counted by the connections above
+                let ts = match self.interface.sample(-w, [random(), random(), random()], true) {
+                    Some(s) if s.direction.z < F::zero() => s,
+                    _ => break,
+                };
+                beta = beta.mul_element_wise(ts.weight);
+                w = ts.direction;
+            }
+        }
+
+        result / n
+    }
+
+    /// An approximation of the density of `sample_ray`, as in pbrt-v4.
+    /// The base is assumed to be diffuse, and the result is mixed with a uniform density
+    pub fn pdf(&self, wi: Vector3<F>, wo: Vector3<F>) -> F {
+        let (wi, wo) = if wo.z < F::zero() { (flip_z(wi), flip_z(wo)) } else { (wi, wo) };
+        if wi.z <= F::zero() {
+            return F::zero();
+        }
+
+        let n = F::from(self.sample_count).unwrap();
+        let mut pdf_sum = self.interface.pdf_reflection(wi, wo) * n;
+        let mut generator = RandomGenerator::new(get_seed(wi, wo));
+        let mut random = move || generator.random();
+        for _ in 0..self.sample_count {
+            let wos = self.interface.sample(wo, [random(), random(), random()], true);
+            let wis = self.interface.sample(wi, [random(), random(), random()], false);
+            if let (Some(wos), Some(wis)) = (wos, wis) {
+                if wos.direction.z < F::zero() && wis.direction.z < F::zero() {
+                    pdf_sum += cosine_hemisphere_pdf(-wis.direction.z);
+                }
+            }
+        }
+
+        lerp(f!(0.9), uniform_sphere_pdf::<F>(), pdf_sum / n)
+    }
+}
+
+impl<F> BSDF<F> for LayeredBSDF<F> where F: BaseFloat + 'static {
+    fn evaluate(&self, wi: Vector3<F>, wo: Vector3<F>) -> Option<Vector3<F>> {
+        let (wi, wo) = if wo.z < F::zero() { (flip_z(wi), flip_z(wo)) } else { (wi, wo) };
+        if wi.z <= F::zero() {
+            return Some(Vector3::zero());
+        }
+        Some(self.evaluate_upper(wi, wo))
+    }
+
+    fn sample_ray(&self, service: &mut TracingService<F>, current_dir: Vector3<F>) -> Option<BSDFSampleResult<F>> {
+        let flip = current_dir.z < F::zero();
+        let wo = if flip { flip_z(current_dir) } else { current_dir };
+        let result_of = |direction: Vector3<F>, weight: Vector3<F>| {
+            Some(BSDFSampleResult {
+                direction: if flip { flip_z(direction) } else { direction },
+                weight,
+            })
+        };
+
+        let ws = self.interface.sample(wo, [service.random_0_1(), service.random_0_1(), service.random_0_1()], true)?;
+        if ws.direction.z > F::zero() {
+            return result_of(ws.direction, ws.weight);
+        }
+
+        let mut beta = ws.weight;
+        let mut w = ws.direction;
+        for depth in 0..self.max_depth {
+            if !self.russian_roulette(&mut beta, depth, service.random_0_1()) {
+                return None;
+            }
+            beta = beta.mul_element_wise(self.get_transmittance(w));
+
+            let bs = self.base.sample_ray(service, -w)?;
+            if bs.direction.z <= F::zero() {
+                return None;
+            }
+            beta = beta.mul_element_wise(bs.weight);
+            w = bs.direction;
+            beta = beta.mul_element_wise(self.get_transmittance(w));
+
+            let ts = self.interface.sample(-w, [service.random_0_1(), service.random_0_1(), service.random_0_1()], true)?;
+            beta = beta.mul_element_wise(ts.weight);
+            if ts.direction.z > F::zero() {
+                return result_of(ts.direction, beta);
+            }
+            w = ts.direction;
+        }
+        None
+    }
+
+    /// The emission of the base seen through the coat, refracted at the macro surface even if the interface is rough.
+    /// The part of it reflected back down by the interface is lost
+    fn emit(&self, wo: Vector3<F>) -> Option<Vector3<F>> {
+        let wo = if wo.z < F::zero() { flip_z(wo) } else { wo };
+        let eta = self.interface.get_relative_ior();
+        // pointing down into the coat
+        let w = refract(wo, get_z(), F::one(), eta)?;
+        let emitted = self.base.emit(-w)?;
+        let fresnel = fresnel_dielectric(wo.z, F::one(), eta).unwrap_or(F::one());
+        // radiance leaving the denser coat spreads over the larger solid angle outside
+        let scale = (F::one() - fresnel) / sqr(eta);
+        Some(emitted.mul_element_wise(self.get_transmittance(w)) * scale)
+    }
+
+    fn pdf(&self, wi: Vector3<F>, wo: Vector3<F>) -> Option<F> {
+        Some(LayeredBSDF::pdf(self, wi, wo))
+    }
+}
+
+/// A coat over the BSDF of another material, such as varnish over wood or a lacquer over metal
+pub struct LayeredMaterial<F> {
+    pub base: Box<dyn MaterialTrait<F>>,
+    /// roughness of the coat, a smooth coat if zero
+    pub roughness: Rc<dyn OutputValue<F, F>>,
+    pub ior: F,
+    /// absorption coefficient of the coat
+    pub absorption: Rc<dyn OutputValue<F, Vector3<F>>>,
+    pub thickness: Rc<dyn OutputValue<F, F>>,
+    pub max_depth: usize,
+    pub sample_count: usize,
+}
+
+impl<F> LayeredMaterial<F> where F: BaseFloat + 'static {
+    /// A smooth clear coat
+    pub fn new(base: Box<dyn MaterialTrait<F>>, ior: F) -> Self {
+        LayeredMaterial {
+            base,
+            roughness: Rc::new(F::zero()),
+            ior,
+            absorption: Rc::new(Vector3::zero()),
+            thickness: Rc::new(f!(0.01)),
+            max_depth: 10,
+            sample_count: 1,
+        }
+    }
+
+    pub fn with_roughness(mut self, roughness: Rc<dyn OutputValue<F, F>>) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    pub fn with_medium(mut self, absorption: Rc<dyn OutputValue<F, Vector3<F>>>, thickness: Rc<dyn OutputValue<F, F>>) -> Self {
+        self.absorption = absorption;
+        self.thickness = thickness;
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_sample_count(mut self, sample_count: usize) -> Self {
+        self.sample_count = sample_count.max(1);
+        self
+    }
+}
+
+impl<F> MaterialTrait<F> for LayeredMaterial<F> where F: BaseFloat + 'static {
+    fn has_volume(&self) -> bool {
+        false
+    }
+
+    fn has_bsdf(&self) -> bool {
+        true
+    }
+
+    fn get_bsdf(&self, context: &ShadingContext<F>) -> Option<Box<dyn BSDF<F>>> {
+        let material_graph_context = MaterialGraphContext {
+            uv: context.uv,
+        };
+        let base = self.base.get_bsdf(context)?;
+        // the coated object is opaque and never pushes its ior, so the current ior is outside
+        let relative_ior = self.ior / context.get_current_ior().x;
+        let roughness = self.roughness.get_value(&material_graph_context);
+        let interface = if roughness > F::zero() {
+            LayerInterface::Rough(RoughDielectricBSDF::new(roughness, Vector3::new(relative_ior, relative_ior, relative_ior)))
+        } else {
+            LayerInterface::Smooth(DielectricBSDF::new(relative_ior))
+        };
+        let bsdf = LayeredBSDF::new(interface, base)
+            .with_medium(self.absorption.get_value(&material_graph_context), self.thickness.get_value(&material_graph_context).max(F::zero()))
+            .with_max_depth(self.max_depth)
+            .with_sample_count(self.sample_count);
+        Some(Box::new(bsdf))
+    }
+
+    fn get_volume(&self) -> Option<Box<dyn VolumeTrait<F>>> {
+        None
+    }
+}
@@ -1,6 +1,8 @@
-use cgmath::{BaseFloat, Vector3};
+use std::rc::Rc;
+use cgmath::{BaseFloat, Vector2, Vector3};
 use crate::component::ComponentData;
 use crate::material::{AbsorptionVolume, BSDF, DiffuseBRDF, MaterialType, VolumeTrait};
+use crate::material_graph::{MaterialGraphContext, OutputValue};
 use crate::path_tracing::ShadingContext;
 
 pub trait MaterialTrait<F> {
@@ -17,13 +19,122 @@ pub trait MaterialTrait<F> {
     }
 }
 
+/// A scalar height which perturbs the shading normal, see `Material::apply_bump_map`
+pub struct BumpMap<F> {
+    pub height: Rc<dyn OutputValue<F, F>>,
+    /// height is multiplied by `scale`, in the units of the uv square
+    pub scale: F,
+    /// uv step of the finite differences, about a texel for textures
+    pub delta: F,
+}
+
+/// A scalar height which moves the vertices of the mesh along their normals before the scene is mashed
+pub struct Displacement<F> {
+    pub height: Rc<dyn OutputValue<F, F>>,
+    /// height is multiplied by `scale`, in object space units
+    pub scale: F,
+    /// number of times the mesh is tessellated before the displacement, each one splits a triangle into 4
+    pub subdivision: usize,
+}
+
 pub struct Material<F> {
     pub material_impl: Box<dyn MaterialTrait<F>>,
+    /// Tangent space normal map, with the channels in [0, 1] mapped to [-1, 1]
+    pub normal_map: Option<Rc<dyn OutputValue<F, Vector3<F>>>>,
+    pub bump_map: Option<BumpMap<F>>,
+    /// Tangent space direction of the tangent, which anisotropic BSDFs are aligned to.
+    /// The channels in [0, 1] are mapped to [-1, 1], and only the first two are used
+    pub tangent_map: Option<Rc<dyn OutputValue<F, Vector3<F>>>>,
+    pub displacement: Option<Displacement<F>>,
 }
 
 impl<F> ComponentData for Material<F> where F: BaseFloat + 'static {}
 
 impl<F> Material<F> where F: BaseFloat + 'static {
+    pub fn new(material_impl: Box<dyn MaterialTrait<F>>) -> Self {
+        Material {
+            material_impl,
+            normal_map: None,
+            bump_map: None,
+            tangent_map: None,
+            displacement: None,
+        }
+    }
+
+    pub fn with_normal_map(mut self, normal_map: Rc<dyn OutputValue<F, Vector3<F>>>) -> Self {
+        self.normal_map = Some(normal_map);
+        self
+    }
+
+    pub fn with_bump_map(mut self, height: Rc<dyn OutputValue<F, F>>, scale: F) -> Self {
+        self.bump_map = Some(BumpMap {
+            height,
+            scale,
+            delta: F::from(1.0 / 1024.0).unwrap(),
+        });
+        self
+    }
+
+    pub fn with_tangent_map(mut self, tangent_map: Rc<dyn OutputValue<F, Vector3<F>>>) -> Self {
+        self.tangent_map = Some(tangent_map);
+        self
+    }
+
+    pub fn with_displacement(mut self, height: Rc<dyn OutputValue<F, F>>, scale: F, subdivision: usize) -> Self {
+        self.displacement = Some(Displacement {
+            height,
+            scale,
+            subdivision
+        });
+        self
+    }
+
+    /// Perturb the shading frame with the normal map, this has to be done before `get_bsdf`
+    pub fn apply_normal_map(&self, context: &mut ShadingContext<F>) {
+        if let Some(normal_map) = &self.normal_map {
+            let material_graph_context = MaterialGraphContext {
+                uv: context.uv,
+            };
+            let value = normal_map.get_value(&material_graph_context);
+            let one = Vector3::new(F::one(), F::one(), F::one());
+            let n = value * F::from(2).unwrap() - one;
+            context.apply_normal_map(n);
+        }
+    }
+
+    /// Perturb the shading frame with the slope of the bump map, from central differences in uv.
+    /// Applied after the normal map, this has to be done before `get_bsdf`
+    pub fn apply_bump_map(&self, context: &mut ShadingContext<F>) {
+        if let Some(bump_map) = &self.bump_map {
+            let sample = |du: F, dv: F| {
+                let material_graph_context = MaterialGraphContext {
+                    uv: Vector2::new(context.uv.x + du, context.uv.y + dv),
+                };
+                bump_map.height.get_value(&material_graph_context)
+            };
+            let delta = bump_map.delta;
+            let two = F::from(2).unwrap();
+            let dhdu = (sample(delta, F::zero()) - sample(-delta, F::zero())) / (two * delta);
+            let dhdv = (sample(F::zero(), delta) - sample(F::zero(), -delta)) / (two * delta);
+            let n = Vector3::new(-dhdu * bump_map.scale, -dhdv * bump_map.scale, F::one());
+            context.apply_normal_map(n);
+        }
+    }
+
+    /// Rotate the tangent of the shading frame with the tangent map, after the normal and the bump map.
+    /// Without a tangent map the tangent comes from the mesh
+    pub fn apply_tangent_map(&self, context: &mut ShadingContext<F>) {
+        if let Some(tangent_map) = &self.tangent_map {
+            let material_graph_context = MaterialGraphContext {
+                uv: context.uv,
+            };
+            let value = tangent_map.get_value(&material_graph_context);
+            let two = F::from(2).unwrap();
+            let t = Vector3::new(value.x * two - F::one(), value.y * two - F::one(), F::zero());
+            context.apply_tangent_map(t);
+        }
+    }
+
     // pub fn new_diffuse_brdf(albedo: Vector3<F>) -> Material<F> {
     //     let diffuse_brdf = DiffuseBRDF::new(albedo);
     //     Material {
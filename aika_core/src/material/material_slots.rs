@@ -0,0 +1,64 @@
+use std::ops::Deref;
+use cgmath::BaseFloat;
+use crate::component::{Component, ComponentData, ComponentDowncastRef};
+use crate::material::Material;
+use crate::scene::GameObject;
+
+/// One material for each sub mesh of the `MeshFilter` on the same game object.
+/// Sub meshes without a slot, and analytic shapes, use the `Material` component.
+/// The displacement is only taken from the `Material` component, since it applies to the whole mesh
+pub struct MaterialSlots<F> {
+    pub materials: Vec<Material<F>>,
+}
+
+impl<F> ComponentData for MaterialSlots<F> where F: BaseFloat + 'static {}
+
+impl<F> MaterialSlots<F> where F: BaseFloat + 'static {
+    pub fn new(materials: Vec<Material<F>>) -> Self {
+        MaterialSlots {
+            materials
+        }
+    }
+
+    /// The component which holds the material of a sub mesh of `go`.
+    /// None if neither the slots nor the `Material` component have one
+    pub fn get_material_component(go: &GameObject<F>, sub_mesh_index: usize) -> Option<SubMeshMaterial<F>> {
+        if let Ok(component) = go.get_component::<MaterialSlots<F>>() {
+            if sub_mesh_index < component.downcast::<MaterialSlots<F>>().materials.len() {
+                return Some(SubMeshMaterial::Slot(component, sub_mesh_index));
+            }
+        }
+        go.get_component::<Material<F>>().ok().map(SubMeshMaterial::Single)
+    }
+}
+
+/// Either a `Material` component or a slot of a `MaterialSlots` component
+pub enum SubMeshMaterial<F> {
+    Single(Component<F>),
+    Slot(Component<F>, usize),
+}
+
+impl<F> SubMeshMaterial<F> where F: BaseFloat + 'static {
+    pub fn downcast(&self) -> SubMeshMaterialRef<'_, F> {
+        match self {
+            SubMeshMaterial::Single(component) => SubMeshMaterialRef::Single(component.downcast::<Material<F>>()),
+            SubMeshMaterial::Slot(component, index) => SubMeshMaterialRef::Slot(component.downcast::<MaterialSlots<F>>(), *index),
+        }
+    }
+}
+
+pub enum SubMeshMaterialRef<'a, F> where F: BaseFloat + 'static {
+    Single(ComponentDowncastRef<'a, F, Material<F>>),
+    Slot(ComponentDowncastRef<'a, F, MaterialSlots<F>>, usize),
+}
+
+impl<'a, F> Deref for SubMeshMaterialRef<'a, F> where F: BaseFloat + 'static {
+    type Target = Material<F>;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            SubMeshMaterialRef::Single(material) => material,
+            SubMeshMaterialRef::Slot(slots, index) => &slots.materials[*index],
+        }
+    }
+}
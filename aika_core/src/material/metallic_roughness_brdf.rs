@@ -1,7 +1,7 @@
 use std::rc::Rc;
 use cgmath::{BaseFloat, ElementWise, InnerSpace, Vector3};
-use aika_math::distribution::IsotropicGGXDistribution;
-use aika_math::utils::{average_vector3_value, fresnel_schlick_approximate, get_2pi, get_pi, is_same_hemisphere_canonical, lerp_vector3, max_component_value, new_vector3, reflect, reflect_bias, sample_uniform_hemisphere, scalar_sub_vector3, smith_g2_lagarde};
+use aika_math::distribution::AnisotropicGGXDistribution;
+use aika_math::utils::{average_vector3_value, cosine_hemisphere_pdf, fresnel_schlick_approximate, get_pi, is_same_hemisphere_canonical, lerp_vector3, max_component_value, new_vector3, reflect, sample_cosine_hemisphere, scalar_sub_vector3, smith_g2_lagarde};
 use crate::f;
 use crate::material::{BSDF, BSDFSampleResult, MaterialTrait, VolumeTrait};
 use crate::material_graph::{MaterialGraphContext, OutputValue};
@@ -31,13 +31,13 @@ impl<F> BSDF<F> for MetallicRoughnessBRDF<F> where F: BaseFloat + 'static {
         }
         // assert!(wi.z > F::zero());
         // assert!(wo.z > F::zero());
-        let dist = IsotropicGGXDistribution::new(self.roughness);
+        let dist = AnisotropicGGXDistribution::new_isotropic(self.roughness);
         let wm = (wi + wo).normalize();
         let ndf = dist.evaluate(wm);
         // let g2 = dist.masking_shadowing(wi, wo);
 
         let f0 = lerp_vector3(self.metallic, new_vector3(0.04, 0.04, 0.04), self.color);
-        let fresnel = fresnel_schlick_approximate(self.color, wm.dot(wi));
+        let fresnel = fresnel_schlick_approximate(f0, wm.dot(wi));
         let specular_reflection = fresnel * ndf * smith_g2_lagarde(wi, wo, self.roughness);
 
         let local_sss = scalar_sub_vector3(F::one(), fresnel) * (F::one() - self.metallic);
@@ -54,13 +54,13 @@ impl<F> BSDF<F> for MetallicRoughnessBRDF<F> where F: BaseFloat + 'static {
             return None;
         }
 
-        let dist = IsotropicGGXDistribution::new(self.roughness);
+        let dist = AnisotropicGGXDistribution::new_isotropic(self.roughness);
         let wo = current_dir;
         let wm = dist.sample_wm(wo, service.random_0_1(), service.random_0_1());
         let pdf_wm = dist.distribution_of_visible_normal(wo, wm);
         let wi = reflect(wo, wm);
         let f0 = lerp_vector3(self.metallic, new_vector3(0.04, 0.04, 0.04), self.color);
-        let fresnel = fresnel_schlick_approximate(self.color, wi.dot(wm));
+        let fresnel = fresnel_schlick_approximate(f0, wi.dot(wm));
         let avg_f = average_vector3_value(fresnel);
 
         let random = service.random_0_1();
@@ -84,13 +84,12 @@ impl<F> BSDF<F> for MetallicRoughnessBRDF<F> where F: BaseFloat + 'static {
             Some(BSDFSampleResult {
                 direction: wi,
                 weight: w,
-                next_point: reflect_bias(wo),
             })
         } else {
             // diffuse
 
-            let wi = sample_uniform_hemisphere(service.random_0_1(), service.random_0_1());
-            let pdf = (F::one() - avg_f) / get_2pi();
+            let wi = sample_cosine_hemisphere(service.random_0_1(), service.random_0_1());
+            let pdf = (F::one() - avg_f) * cosine_hemisphere_pdf(wi.z);
             let local_sss = scalar_sub_vector3(F::one(), fresnel) * (F::one() - self.metallic);
             let local_sss = local_sss.mul_element_wise(self.color) / get_pi();
 
@@ -98,7 +97,6 @@ impl<F> BSDF<F> for MetallicRoughnessBRDF<F> where F: BaseFloat + 'static {
             Some(BSDFSampleResult {
                 direction: wi,
                 weight: w,
-                next_point: reflect_bias(wo)
             })
         }
     }
@@ -137,7 +135,7 @@ impl<F> MaterialTrait<F> for MetallicRoughnessBRDFMaterial<F> where F: BaseFloat
             uv: context.uv,
         };
         let roughness = self.roughness.get_value(&material_graph_context);
-        let metallic = self.roughness.get_value(&material_graph_context);
+        let metallic = self.metallic.get_value(&material_graph_context);
         let color = self.color.get_value(&material_graph_context);
         Some(Box::new(MetallicRoughnessBRDF::new(roughness, metallic, color)))
     }
@@ -2,15 +2,19 @@ pub use diffuse_brdf::{DiffuseBRDF, DiffuseBRDFMaterial};
 pub use material_type::MaterialType;
 pub use bsdf::{BSDF, BSDFSampleResult};
 pub use volume::{VolumeTrait, VolumeSampleResult};
-pub use material::{MaterialTrait, Material};
+pub use material::{MaterialTrait, Material, BumpMap, Displacement};
 pub use absorption_volume::{AbsorptionVolume, AbsorptionVolumeMaterial};
 pub use conductor_brdf::{ConductorBRDF, ConductorBRDFMaterial};
 pub use dielectric_bsdf::{DielectricBSDF, DielectricMaterial};
 pub use rough_conductor_brdf::{RoughConductorBRDF, RoughConductorBRDFMaterial};
 pub use constants::MaterialConstants;
 pub use uniform_emit::{UniformEmit, UniformEmitMaterial};
-pub use rough_dielectric_bsdf::RoughDielectricBSDFMaterial;
+pub use rough_dielectric_bsdf::{RoughDielectricBSDF, RoughDielectricBSDFMaterial};
 pub use metallic_roughness_brdf::*;
+pub use material_slots::{MaterialSlots, SubMeshMaterial, SubMeshMaterialRef};
+pub use principled_bsdf::{PrincipledBSDF, PrincipledBSDFMaterial, PrincipledBSDFParameters};
+pub use layered_bsdf::{LayeredBSDF, LayeredMaterial, LayerInterface};
+pub use oren_nayar_brdf::{OrenNayarBRDF, OrenNayarBRDFMaterial, OrenNayarModel};
 
 mod diffuse_brdf;
 mod material_type;
@@ -26,3 +30,10 @@ mod uniform_emit;
 mod rough_dielectric_bsdf;
 mod metallic_roughness_brdf;
 mod input_type;
+mod material_slots;
+mod principled_bsdf;
+mod layered_bsdf;
+mod oren_nayar_brdf;
+
+#[cfg(test)]
+mod test;
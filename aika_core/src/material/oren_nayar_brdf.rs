@@ -0,0 +1,168 @@
+use std::rc::Rc;
+use cgmath::{BaseFloat, InnerSpace, Vector3};
+use aika_math::utils::{get_pi, sample_cosine_hemisphere};
+use crate::f;
+use crate::material::{BSDF, BSDFSampleResult, MaterialTrait, VolumeTrait};
+use crate::material_graph::{MaterialGraphContext, OutputValue};
+use crate::path_tracing::{ShadingContext, TracingService};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrenNayarModel {
+    /// The qualitative model of Oren and Nayar 1994, which loses energy as the roughness grows
+    Qualitative,
+    /// EON, the energy preserving model of Portsmouth et al. 2024, adds the light of multiple bounces
+    EnergyPreserving,
+}
+
+/// Rough diffuse reflection, which is brighter towards the light than Lambertian reflection
+#[derive(Clone)]
+pub struct OrenNayarBRDF<F> {
+    pub albedo: Vector3<F>,
+    /// In [0, 1]. The qualitative model takes it as the standard deviation of the facet angle in radians
+    pub roughness: F,
+    pub model: OrenNayarModel,
+}
+
+/// 1 / 2 - 2 / (3 pi)
+fn get_fon_constant1<F: BaseFloat>() -> F {
+    f!(0.5) - f!(2) / (f!(3) * get_pi::<F>())
+}
+
+/// 2 / 3 - 28 / (15 pi)
+fn get_fon_constant2<F: BaseFloat>() -> F {
+    f!(2.0 / 3.0) - f!(28) / (f!(15) * get_pi::<F>())
+}
+
+impl<F> OrenNayarBRDF<F> where F: BaseFloat {
+    pub fn new(albedo: Vector3<F>, roughness: F, model: OrenNayarModel) -> Self {
+        OrenNayarBRDF {
+            albedo,
+            roughness: roughness.max(F::zero()).min(F::one()),
+            model,
+        }
+    }
+
+    fn evaluate_qualitative(&self, wi: Vector3<F>, wo: Vector3<F>) -> Vector3<F> {
+        let sigma2 = self.roughness * self.roughness;
+        let a = F::one() - sigma2 / (f!(2) * (sigma2 + f!(0.33)));
+        let b = f!(0.45) * sigma2 / (sigma2 + f!(0.09));
+
+        let sin_theta_i = (F::one() - wi.z * wi.z).max(F::zero()).sqrt();
+        let sin_theta_o = (F::one() - wo.z * wo.z).max(F::zero()).sqrt();
+        // cos(phi_i - phi_o) sin_theta_i sin_theta_o
+        let cos_phi_sin_sin = (wi.x * wo.x + wi.y * wo.y).max(F::zero());
+        // sin(alpha) tan(beta), with alpha the larger and beta the smaller of the two angles
+        let (sin_alpha, tan_beta) = if wi.z > wo.z {
+            (sin_theta_o, sin_theta_i / wi.z)
+        } else {
+            (sin_theta_i, sin_theta_o / wo.z)
+        };
+        let max_cos = if sin_theta_i > f!(1e-4) && sin_theta_o > f!(1e-4) {
+            cos_phi_sin_sin / (sin_theta_i * sin_theta_o)
+        } else {
+            F::zero()
+        };
+
+        self.albedo * ((a + b * max_cos * sin_alpha * tan_beta) / get_pi::<F>())
+    }
+
+    /// The directional albedo of the single scattering FON lobe, from the fit of the EON paper
+    fn get_fon_albedo(&self, mu: F) -> F {
+        let mu_complement = F::one() - mu;
+        let g_over_pi = mu_complement * (f!(0.0571085289) + mu_complement * (f!(0.491881867)
+            + mu_complement * (f!(-0.332181442) + mu_complement * f!(0.0714429953))));
+        (F::one() + self.roughness * g_over_pi) / (F::one() + get_fon_constant1::<F>() * self.roughness)
+    }
+
+    fn evaluate_energy_preserving(&self, wi: Vector3<F>, wo: Vector3<F>) -> Vector3<F> {
+        let r = self.roughness;
+        let pi = get_pi::<F>();
+        let epsilon = f!(1e-7);
+
+        // single scattering, the Fujii variant of Oren-Nayar
+        let s = wi.dot(wo) - wi.z * wo.z;
+        let s_over_t = if s > F::zero() { s / wi.z.max(wo.z) } else { s };
+        let af = F::one() / (F::one() + get_fon_constant1::<F>() * r);
+        let single = self.albedo * (af * (F::one() + r * s_over_t) / pi);
+
+        // multiple scattering, which makes up for the energy the single scattering lobe loses
+        let average_albedo = af * (F::one() + get_fon_constant2::<F>() * r);
+        let one_minus_average = (F::one() - average_albedo).max(epsilon);
+        let rho_ms = self.albedo.map(|rho| rho * rho * average_albedo / (F::one() - rho * (F::one() - average_albedo).max(F::zero())));
+        let lost_o = (F::one() - self.get_fon_albedo(wo.z)).max(epsilon);
+        let lost_i = (F::one() - self.get_fon_albedo(wi.z)).max(epsilon);
+        let multiple = rho_ms * (lost_o * lost_i / (one_minus_average * pi));
+
+        single + multiple
+    }
+}
+
+impl<F> BSDF<F> for OrenNayarBRDF<F> where F: BaseFloat + 'static {
+    fn evaluate(&self, wi: Vector3<F>, wo: Vector3<F>) -> Option<Vector3<F>> {
+        if wi.z <= F::zero() || wo.z <= F::zero() {
+            return Some(Vector3::new(F::zero(), F::zero(), F::zero()));
+        }
+        match self.model {
+            OrenNayarModel::Qualitative => Some(self.evaluate_qualitative(wi, wo)),
+            OrenNayarModel::EnergyPreserving => Some(self.evaluate_energy_preserving(wi, wo)),
+        }
+    }
+
+    fn sample_ray(&self, service: &mut TracingService<F>, current_dir: Vector3<F>) -> Option<BSDFSampleResult<F>> {
+        if current_dir.z <= F::zero() {
+            return None;
+        }
+        // f * cos / (cos / pi)
+        let dir = sample_cosine_hemisphere(service.random_0_1(), service.random_0_1());
+        let weight = self.evaluate(dir, current_dir)? * get_pi::<F>();
+        Some(BSDFSampleResult {
+            direction: dir,
+            weight,
+        })
+    }
+}
+
+pub struct OrenNayarBRDFMaterial<F> {
+    pub albedo: Rc<dyn OutputValue<F, Vector3<F>>>,
+    pub roughness: Rc<dyn OutputValue<F, F>>,
+    pub model: OrenNayarModel,
+}
+
+impl<F> OrenNayarBRDFMaterial<F> where F: BaseFloat + 'static {
+    /// The qualitative model
+    pub fn new(albedo: Rc<dyn OutputValue<F, Vector3<F>>>, roughness: Rc<dyn OutputValue<F, F>>) -> Self {
+        OrenNayarBRDFMaterial {
+            albedo,
+            roughness,
+            model: OrenNayarModel::Qualitative,
+        }
+    }
+
+    pub fn with_model(mut self, model: OrenNayarModel) -> Self {
+        self.model = model;
+        self
+    }
+}
+
+impl<F> MaterialTrait<F> for OrenNayarBRDFMaterial<F> where F: BaseFloat + 'static {
+    fn has_volume(&self) -> bool {
+        false
+    }
+
+    fn has_bsdf(&self) -> bool {
+        true
+    }
+
+    fn get_bsdf(&self, context: &ShadingContext<F>) -> Option<Box<dyn BSDF<F>>> {
+        let material_graph_context = MaterialGraphContext {
+            uv: context.uv,
+        };
+        let albedo = self.albedo.get_value(&material_graph_context);
+        let roughness = self.roughness.get_value(&material_graph_context);
+        Some(Box::new(OrenNayarBRDF::new(albedo, roughness, self.model)))
+    }
+
+    fn get_volume(&self) -> Option<Box<dyn VolumeTrait<F>>> {
+        None
+    }
+}
@@ -0,0 +1,437 @@
+use std::rc::Rc;
+use cgmath::{BaseFloat, ElementWise, InnerSpace, Vector3};
+use num_traits::Zero;
+use aika_math::distribution::AnisotropicGGXDistribution;
+use aika_math::utils::{get_2pi, get_pi, lerp, lerp_vector3, reflect, sample_cosine_hemisphere, sqr};
+use crate::f;
+use crate::material::{BSDF, BSDFSampleResult, MaterialTrait, RoughDielectricBSDF, VolumeTrait};
+use crate::material_graph::{MaterialGraphContext, OutputValue};
+use crate::path_tracing::{ShadingContext, TracingService};
+
+fn get_luminance<F: BaseFloat>(c: Vector3<F>) -> F {
+    c.x * f!(0.2126) + c.y * f!(0.7152) + c.z * f!(0.0722)
+}
+
+/// (1 - cos)^5 of the Schlick approximation
+fn schlick_weight<F: BaseFloat>(cos_theta: F) -> F {
+    let m = (F::one() - cos_theta).max(F::zero()).min(F::one());
+    let m2 = m * m;
+    m2 * m2 * m
+}
+
+/// The parameters of `PrincipledBSDFMaterial` at a shading point
+#[derive(Clone)]
+pub struct PrincipledBSDFParameters<F> {
+    pub base_color: Vector3<F>,
+    pub metallic: F,
+    pub roughness: F,
+    pub anisotropy: F,
+    pub specular_tint: F,
+    pub sheen: F,
+    pub sheen_tint: F,
+    pub clearcoat: F,
+    pub clearcoat_roughness: F,
+    pub transmission: F,
+    pub subsurface: F,
+    pub subsurface_color: Vector3<F>,
+    pub emission: Vector3<F>,
+}
+
+/// A Disney style BSDF with a diffuse lobe with retro-reflection, sheen and a subsurface approximation,
+/// an anisotropic GGX specular lobe, a GTR1 clearcoat lobe and a rough glass lobe.
+/// Each lobe is chosen for sampling by its estimated contribution, then importance sampled
+pub struct PrincipledBSDF<F> {
+    pub parameters: PrincipledBSDFParameters<F>,
+    specular_distribution: AnisotropicGGXDistribution<F>,
+    /// the reflectance of the specular lobe at normal incidence
+    specular_f0: Vector3<F>,
+    glass: Option<RoughDielectricBSDF<F>>,
+}
+
+impl<F> PrincipledBSDF<F> where F: BaseFloat + 'static {
+    /// `relative_ior` is the ior of the object over the ior outside of it
+    pub fn new(parameters: PrincipledBSDFParameters<F>, relative_ior: F) -> Self {
+        let p = &parameters;
+        // the roughness is perceptual, alpha is its square
+        let alpha = sqr(p.roughness);
+        let specular_distribution = AnisotropicGGXDistribution::from_anisotropy(alpha.max(f!(1e-4)), p.anisotropy);
+
+        let luminance = get_luminance(p.base_color);
+        let one = Vector3::new(F::one(), F::one(), F::one());
+        let tint = if luminance > F::zero() { p.base_color / luminance } else { one };
+        let dielectric_f0 = sqr((relative_ior - F::one()) / (relative_ior + F::one()));
+        let dielectric_f0 = lerp_vector3(p.specular_tint, one, tint) * dielectric_f0;
+        let specular_f0 = lerp_vector3(p.metallic, dielectric_f0, p.base_color);
+
+        let glass = if p.transmission * (F::one() - p.metallic) > F::zero() {
+            Some(RoughDielectricBSDF::new(alpha.max(f!(1e-3)), Vector3::new(relative_ior, relative_ior, relative_ior)))
+        } else {
+            None
+        };
+
+        PrincipledBSDF {
+            parameters,
+            specular_distribution,
+            specular_f0,
+            glass,
+        }
+    }
+
+    fn get_diffuse_weight(&self) -> F {
+        let p = &self.parameters;
+        (F::one() - p.metallic) * (F::one() - p.transmission)
+    }
+
+    fn get_specular_weight(&self) -> F {
+        let p = &self.parameters;
+        F::one() - p.transmission * (F::one() - p.metallic)
+    }
+
+    fn get_glass_weight(&self) -> F {
+        let p = &self.parameters;
+        p.transmission * (F::one() - p.metallic)
+    }
+
+    /// Burley diffuse with the subsurface approximation, plus the sheen, both in the upper hemisphere
+    fn evaluate_diffuse(&self, wi: Vector3<F>, wo: Vector3<F>) -> Vector3<F> {
+        let p = &self.parameters;
+        let wh = wi + wo;
+        if wh.magnitude2() == F::zero() {
+            return Vector3::zero();
+        }
+        let wh = wh.normalize();
+        let cos_d = wi.dot(wh);
+        let fl = schlick_weight(wi.z);
+        let fv = schlick_weight(wo.z);
+
+        let fd90 = f!(0.5) + f!(2) * cos_d * cos_d * p.roughness;
+        let fd = (F::one() + (fd90 - F::one()) * fl) * (F::one() + (fd90 - F::one()) * fv);
+
+        let fss90 = cos_d * cos_d * p.roughness;
+        let fss = lerp(fl, F::one(), fss90) * lerp(fv, F::one(), fss90);
+        let ss = f!(1.25) * (fss * (F::one() / (wi.z + wo.z) - f!(0.5)) + f!(0.5));
+
+        let diffuse = lerp_vector3(p.subsurface, p.base_color * fd, p.subsurface_color * ss) / get_pi();
+
+        let luminance = get_luminance(p.base_color);
+        let one = Vector3::new(F::one(), F::one(), F::one());
+        let tint = if luminance > F::zero() { p.base_color / luminance } else { one };
+        let sheen = lerp_vector3(p.sheen_tint, one, tint) * (p.sheen * schlick_weight(cos_d));
+
+        diffuse + sheen
+    }
+
+    fn get_specular_fresnel(&self, cos_theta: F) -> Vector3<F> {
+        let one = Vector3::new(F::one(), F::one(), F::one());
+        lerp_vector3(schlick_weight(cos_theta), self.specular_f0, one)
+    }
+
+    fn evaluate_specular(&self, wi: Vector3<F>, wo: Vector3<F>) -> Vector3<F> {
+        let wm = (wi + wo).normalize();
+        let d = self.specular_distribution.evaluate(wm);
+        let g = self.specular_distribution.masking_shadowing(wi, wo);
+        self.get_specular_fresnel(wi.dot(wm)) * (d * g / (f!(4) * wi.z * wo.z))
+    }
+
+    fn get_clearcoat_alpha(&self) -> F {
+        lerp(self.parameters.clearcoat_roughness, f!(0.001), f!(0.1))
+    }
+
+    /// GTR1 distribution of the clearcoat
+    fn evaluate_clearcoat_distribution(&self, cos_theta_m: F) -> F {
+        let a2 = sqr(self.get_clearcoat_alpha());
+        (a2 - F::one()) / (get_pi::<F>() * a2.ln() * (F::one() + (a2 - F::one()) * cos_theta_m * cos_theta_m))
+    }
+
+    fn evaluate_clearcoat(&self, wi: Vector3<F>, wo: Vector3<F>) -> F {
+        let clearcoat = self.parameters.clearcoat;
+        if clearcoat <= F::zero() {
+            return F::zero();
+        }
+        let wm = (wi + wo).normalize();
+        let d = self.evaluate_clearcoat_distribution(wm.z);
+        let fresnel = lerp(schlick_weight(wi.dot(wm)), f!(0.04), F::one());
+        // separable smith G with a fixed alpha of 0.25, which includes 1 / (4 cos_i cos_o)
+        let smith_g = |cos_theta: F| {
+            let a2 = f!(0.0625);
+            F::one() / (cos_theta + (a2 + cos_theta * cos_theta - a2 * cos_theta * cos_theta).sqrt())
+        };
+        f!(0.25) * clearcoat * d * fresnel * smith_g(wi.z) * smith_g(wo.z)
+    }
+
+    /// The probabilities to sample diffuse, specular, clearcoat and glass, estimated from the view direction
+    fn get_lobe_probabilities(&self, wo: Vector3<F>) -> [F; 4] {
+        let p = &self.parameters;
+        if wo.z <= F::zero() {
+            return [F::zero(), F::zero(), F::zero(), if self.glass.is_some() { F::one() } else { F::zero() }];
+        }
+        let diffuse_albedo = lerp_vector3(p.subsurface, p.base_color, p.subsurface_color);
+        let diffuse = self.get_diffuse_weight() * (get_luminance(diffuse_albedo) + p.sheen * f!(0.1));
+        // a floor keeps the highlight of dark dielectrics sampled
+        let specular = self.get_specular_weight() * get_luminance(self.get_specular_fresnel(wo.z)).max(f!(0.04));
+        let clearcoat = f!(0.25) * p.clearcoat * lerp(schlick_weight(wo.z), f!(0.04), F::one());
+        let glass = if self.glass.is_some() { self.get_glass_weight() } else { F::zero() };
+
+        let sum = diffuse + specular + clearcoat + glass;
+        if sum <= F::zero() {
+            return [F::zero(); 4];
+        }
+        [diffuse / sum, specular / sum, clearcoat / sum, glass / sum]
+    }
+
+    fn sample_clearcoat_wm(&self, u1: F, u2: F) -> Vector3<F> {
+        let a2 = sqr(self.get_clearcoat_alpha());
+        let cos_theta = ((F::one() - a2.powf(F::one() - u1)) / (F::one() - a2)).max(F::zero()).sqrt();
+        let sin_theta = (F::one() - cos_theta * cos_theta).max(F::zero()).sqrt();
+        let phi = get_2pi::<F>() * u2;
+        Vector3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+    }
+
+    fn sample_glass(&self, service: &mut TracingService<F>, wo: Vector3<F>) -> Option<BSDFSampleResult<F>> {
+        let result = self.glass.as_ref()?.sample_ray(service, wo)?;
+        let is_transmit = result.direction.z * wo.z < F::zero();
+        let weight = if is_transmit { result.weight.mul_element_wise(self.parameters.base_color) } else { result.weight };
+        Some(BSDFSampleResult {
+            direction: result.direction,
+            weight: weight * self.get_glass_weight(),
+        })
+    }
+}
+
+impl<F> BSDF<F> for PrincipledBSDF<F> where F: BaseFloat + 'static {
+    fn evaluate(&self, wi: Vector3<F>, wo: Vector3<F>) -> Option<Vector3<F>> {
+        let mut result = Vector3::zero();
+        if wi.z > F::zero() && wo.z > F::zero() {
+            result += self.evaluate_diffuse(wi, wo) * self.get_diffuse_weight();
+            result += self.evaluate_specular(wi, wo) * self.get_specular_weight();
+            let clearcoat = self.evaluate_clearcoat(wi, wo);
+            result += Vector3::new(clearcoat, clearcoat, clearcoat);
+        }
+        if let Some(glass) = &self.glass {
+            if let Some(f) = glass.evaluate(wi, wo) {
+                let is_transmit = wi.z * wo.z < F::zero();
+                let f = if is_transmit { f.mul_element_wise(self.parameters.base_color) } else { f };
+                result += f * self.get_glass_weight();
+            }
+        }
+        Some(result)
+    }
+
+    fn sample_ray(&self, service: &mut TracingService<F>, current_dir: Vector3<F>) -> Option<BSDFSampleResult<F>> {
+        let wo = current_dir;
+        let probabilities = self.get_lobe_probabilities(wo);
+        let u = service.random_0_1();
+        let mut lobe = 0;
+        let mut sum = probabilities[0];
+        while lobe < 3 && (u >= sum || probabilities[lobe] == F::zero()) {
+            lobe += 1;
+            sum += probabilities[lobe];
+        }
+        let probability = probabilities[lobe];
+        if probability <= F::zero() {
+            return None;
+        }
+
+        let result = match lobe {
+            0 => {
+                // f * cos / (cos / pi)
+                let wi = sample_cosine_hemisphere(service.random_0_1(), service.random_0_1());
+                let weight = self.evaluate_diffuse(wi, wo) * (self.get_diffuse_weight() * get_pi::<F>());
+                BSDFSampleResult { direction: wi, weight }
+            },
+            1 => {
+                let wm = self.specular_distribution.sample_wm(wo, service.random_0_1(), service.random_0_1());
+                let wi = reflect(wo, wm);
+                if wi.z <= F::zero() {
+                    return None;
+                }
+                // f * cos / pdf reduces to F * G / G1(wo) with visible normal sampling
+                let g_over_g1 = self.specular_distribution.masking_shadowing(wi, wo) / self.specular_distribution.g1(wo);
+                let weight = self.get_specular_fresnel(wi.dot(wm)) * (g_over_g1 * self.get_specular_weight());
+                BSDFSampleResult { direction: wi, weight }
+            },
+            2 => {
+                let wm = self.sample_clearcoat_wm(service.random_0_1(), service.random_0_1());
+                let wi = reflect(wo, wm);
+                if wi.z <= F::zero() || wo.dot(wm) <= F::zero() {
+                    return None;
+                }
+                let pdf = self.evaluate_clearcoat_distribution(wm.z) * wm.z / (f!(4) * wo.dot(wm));
+                let weight = self.evaluate_clearcoat(wi, wo) * wi.z / pdf;
+                BSDFSampleResult { direction: wi, weight: Vector3::new(weight, weight, weight) }
+            },
+            _ => self.sample_glass(service, wo)?,
+        };
+
+        Some(BSDFSampleResult {
+            direction: result.direction,
+            weight: result.weight / probability,
+        })
+    }
+
+    fn emit(&self, _wo: Vector3<F>) -> Option<Vector3<F>> {
+        let e = self.parameters.emission;
+        if e.x > F::zero() || e.y > F::zero() || e.z > F::zero() {
+            Some(e)
+        } else {
+            None
+        }
+    }
+}
+
+/// A principled material, every parameter is read from the material graph.
+/// The medium inside a transmissive object is a constant, since the medium stack does not depend on the shading point
+pub struct PrincipledBSDFMaterial<F> {
+    pub base_color: Rc<dyn OutputValue<F, Vector3<F>>>,
+    pub metallic: Rc<dyn OutputValue<F, F>>,
+    /// perceptual roughness, the alpha of GGX is its square
+    pub roughness: Rc<dyn OutputValue<F, F>>,
+    /// 0 is isotropic, 1 stretches the highlight along the tangent
+    pub anisotropy: Rc<dyn OutputValue<F, F>>,
+    /// tints the dielectric specular towards the hue of the base color
+    pub specular_tint: Rc<dyn OutputValue<F, F>>,
+    pub sheen: Rc<dyn OutputValue<F, F>>,
+    pub sheen_tint: Rc<dyn OutputValue<F, F>>,
+    pub clearcoat: Rc<dyn OutputValue<F, F>>,
+    pub clearcoat_roughness: Rc<dyn OutputValue<F, F>>,
+    pub transmission: Rc<dyn OutputValue<F, F>>,
+    /// blends the diffuse lobe towards a flattened subsurface look in `subsurface_color`
+    pub subsurface: Rc<dyn OutputValue<F, F>>,
+    pub subsurface_color: Rc<dyn OutputValue<F, Vector3<F>>>,
+    pub emission: Rc<dyn OutputValue<F, Vector3<F>>>,
+    /// the ior of the Fresnel term of the specular and the glass lobes
+    pub ior: Rc<dyn OutputValue<F, F>>,
+    /// the ior of the medium inside the object, pushed on the medium stack when a ray enters.
+    /// None for an opaque material, whose transmission is then ignored
+    pub medium_ior: Option<F>,
+}
+
+impl<F> PrincipledBSDFMaterial<F> where F: BaseFloat + 'static {
+    /// An opaque rough dielectric with ior 1.5, the other lobes are off
+    pub fn new(base_color: Rc<dyn OutputValue<F, Vector3<F>>>) -> Self {
+        let zero = Vector3::new(F::zero(), F::zero(), F::zero());
+        PrincipledBSDFMaterial {
+            base_color: base_color.clone(),
+            metallic: Rc::new(F::zero()),
+            roughness: Rc::new(f!(0.5)),
+            anisotropy: Rc::new(F::zero()),
+            specular_tint: Rc::new(F::zero()),
+            sheen: Rc::new(F::zero()),
+            sheen_tint: Rc::new(f!(0.5)),
+            clearcoat: Rc::new(F::zero()),
+            clearcoat_roughness: Rc::new(f!(0.03)),
+            transmission: Rc::new(F::zero()),
+            subsurface: Rc::new(F::zero()),
+            subsurface_color: base_color,
+            emission: Rc::new(zero),
+            ior: Rc::new(f!(1.5)),
+            medium_ior: None,
+        }
+    }
+
+    pub fn with_metallic(mut self, metallic: Rc<dyn OutputValue<F, F>>) -> Self {
+        self.metallic = metallic;
+        self
+    }
+
+    pub fn with_roughness(mut self, roughness: Rc<dyn OutputValue<F, F>>) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    pub fn with_anisotropy(mut self, anisotropy: Rc<dyn OutputValue<F, F>>) -> Self {
+        self.anisotropy = anisotropy;
+        self
+    }
+
+    pub fn with_specular_tint(mut self, specular_tint: Rc<dyn OutputValue<F, F>>) -> Self {
+        self.specular_tint = specular_tint;
+        self
+    }
+
+    pub fn with_sheen(mut self, sheen: Rc<dyn OutputValue<F, F>>, sheen_tint: Rc<dyn OutputValue<F, F>>) -> Self {
+        self.sheen = sheen;
+        self.sheen_tint = sheen_tint;
+        self
+    }
+
+    pub fn with_clearcoat(mut self, clearcoat: Rc<dyn OutputValue<F, F>>, clearcoat_roughness: Rc<dyn OutputValue<F, F>>) -> Self {
+        self.clearcoat = clearcoat;
+        self.clearcoat_roughness = clearcoat_roughness;
+        self
+    }
+
+    /// `medium_ior` is the ior of the inside of the object, usually the same as `ior`
+    pub fn with_transmission(mut self, transmission: Rc<dyn OutputValue<F, F>>, medium_ior: F) -> Self {
+        self.transmission = transmission;
+        self.medium_ior = Some(medium_ior);
+        self
+    }
+
+    pub fn with_subsurface(mut self, subsurface: Rc<dyn OutputValue<F, F>>, subsurface_color: Rc<dyn OutputValue<F, Vector3<F>>>) -> Self {
+        self.subsurface = subsurface;
+        self.subsurface_color = subsurface_color;
+        self
+    }
+
+    pub fn with_emission(mut self, emission: Rc<dyn OutputValue<F, Vector3<F>>>) -> Self {
+        self.emission = emission;
+        self
+    }
+
+    pub fn with_ior(mut self, ior: Rc<dyn OutputValue<F, F>>) -> Self {
+        self.ior = ior;
+        self
+    }
+
+    pub fn get_parameters(&self, context: &MaterialGraphContext<F>) -> PrincipledBSDFParameters<F> {
+        let unit = |x: F| x.max(F::zero()).min(F::one());
+        PrincipledBSDFParameters {
+            base_color: self.base_color.get_value(context),
+            metallic: unit(self.metallic.get_value(context)),
+            roughness: unit(self.roughness.get_value(context)),
+            anisotropy: unit(self.anisotropy.get_value(context)),
+            specular_tint: unit(self.specular_tint.get_value(context)),
+            sheen: self.sheen.get_value(context).max(F::zero()),
+            sheen_tint: unit(self.sheen_tint.get_value(context)),
+            clearcoat: self.clearcoat.get_value(context).max(F::zero()),
+            clearcoat_roughness: unit(self.clearcoat_roughness.get_value(context)),
+            transmission: unit(self.transmission.get_value(context)),
+            subsurface: unit(self.subsurface.get_value(context)),
+            subsurface_color: self.subsurface_color.get_value(context),
+            emission: self.emission.get_value(context),
+        }
+    }
+}
+
+impl<F> MaterialTrait<F> for PrincipledBSDFMaterial<F> where F: BaseFloat + 'static {
+    fn has_volume(&self) -> bool {
+        false
+    }
+
+    fn has_bsdf(&self) -> bool {
+        true
+    }
+
+    fn get_bsdf(&self, context: &ShadingContext<F>) -> Option<Box<dyn BSDF<F>>> {
+        let material_graph_context = MaterialGraphContext {
+            uv: context.uv,
+        };
+        let mut parameters = self.get_parameters(&material_graph_context);
+        if self.medium_ior.is_none() {
+            parameters.transmission = F::zero();
+        }
+        let ior = self.ior.get_value(&material_graph_context).max(F::one());
+        // same as `RoughDielectricBSDFMaterial`
+        let outside_ior = if !context.back_face { context.get_current_ior() } else { context.get_next_top_ior() };
+        Some(Box::new(PrincipledBSDF::new(parameters, ior / outside_ior.x)))
+    }
+
+    fn get_volume(&self) -> Option<Box<dyn VolumeTrait<F>>> {
+        None
+    }
+
+    fn get_ior(&self) -> Option<Vector3<F>> {
+        self.medium_ior.map(|ior| Vector3::new(ior, ior, ior))
+    }
+}
@@ -1,25 +1,31 @@
+use std::rc::Rc;
 use cgmath::{BaseFloat, InnerSpace, Vector3};
 use num_traits::Zero;
 use aika_math::Complex;
-use aika_math::distribution::IsotropicGGXDistribution;
-use aika_math::utils::{is_same_hemisphere, reflect, reflect_bias, smith_g2_lagarde};
+use aika_math::distribution::AnisotropicGGXDistribution;
+use aika_math::utils::{is_same_hemisphere, reflect, sqr};
 use crate::f;
 use crate::material::{BSDF, BSDFSampleResult, MaterialTrait, VolumeTrait};
+use crate::material_graph::{MaterialGraphContext, OutputValue};
 use crate::path_tracing::{ShadingContext, TracingService};
 use crate::utils::fresnel_complex;
 
 pub struct RoughConductorBRDF<F> {
-    pub distribution: IsotropicGGXDistribution<F>,
+    pub distribution: AnisotropicGGXDistribution<F>,
     pub relative_ior: Vector3<Complex<F>>,
-    pub roughness: F,
 }
 
 impl<F> RoughConductorBRDF<F> where F: BaseFloat {
-    pub fn new(roughness: F, relative_ior: Vector3<Complex<F>>) -> Self {
+    /// `alpha` of GGX, the square of the perceptual roughness of `RoughConductorBRDFMaterial`
+    pub fn new(alpha: F, relative_ior: Vector3<Complex<F>>) -> Self {
+        RoughConductorBRDF::new_anisotropic(AnisotropicGGXDistribution::new_isotropic(alpha), relative_ior)
+    }
+
+    /// The distribution is aligned to the tangent frame
+    pub fn new_anisotropic(distribution: AnisotropicGGXDistribution<F>, relative_ior: Vector3<Complex<F>>) -> Self {
         RoughConductorBRDF {
-            distribution: IsotropicGGXDistribution::new(roughness),
+            distribution,
             relative_ior,
-            roughness
         }
     }
 
@@ -43,9 +49,9 @@ impl<F> BSDF<F> for RoughConductorBRDF<F> where F: BaseFloat + 'static {
         let wm = (wi + wo).normalize();
         let fresnel = self.get_fresnel(wi, wm);
 
-        let shadowing_masking = smith_g2_lagarde(wi, wo, self.roughness);
+        let shadowing_masking = self.distribution.masking_shadowing(wi, wo);
         Some(
-            fresnel * self.distribution.evaluate(wm) * shadowing_masking
+            fresnel * (self.distribution.evaluate(wm) * shadowing_masking / (f!(4) * cos_theta_i * cos_theta_o))
         )
     }
 
@@ -55,37 +61,47 @@ impl<F> BSDF<F> for RoughConductorBRDF<F> where F: BaseFloat + 'static {
 
         let wm = self.distribution.sample_wm(wo, service.random_0_1(), service.random_0_1());
         let wi = reflect(wo, wm);
-        let pdf_wm = self.distribution.distribution_of_visible_normal(wo, wm);
         if wi.z <= F::zero() {
             return None;
         }
 
-        let ndf = self.distribution.evaluate(wm);
+        // f * cos / pdf, where the pdf is of the visible normals
         let fresnel = self.get_fresnel(wi, wm);
-        let lar = smith_g2_lagarde(wi, wo, self.roughness);
-        let weight = lar * f!(4) * wi.z.abs() * ndf / pdf_wm;
-        let weight = fresnel * weight;
+        let weight = fresnel * (self.distribution.masking_shadowing(wi, wo) / self.distribution.g1(wo));
 
         Some(BSDFSampleResult {
             direction: wi,
             weight,
-            next_point: reflect_bias(wo),
         })
     }
 }
 
 pub struct RoughConductorBRDFMaterial<F> {
     pub ior: Vector3<Complex<F>>,
-    pub roughness: F
+    /// perceptual roughness, the alpha of GGX is its square, same as `PrincipledBSDFMaterial`
+    pub roughness: Rc<dyn OutputValue<F, F>>,
+    /// 0 is isotropic, 1 stretches the highlight along the tangent
+    pub anisotropy: Rc<dyn OutputValue<F, F>>,
 }
 
-impl<F> RoughConductorBRDFMaterial<F> where F: BaseFloat {
+impl<F> RoughConductorBRDFMaterial<F> where F: BaseFloat + 'static {
     pub fn new(roughness: F, ior: Vector3<Complex<F>>) -> Self {
+        RoughConductorBRDFMaterial::from_graph(Rc::new(roughness), ior)
+    }
+
+    /// The roughness is read from the material graph, such as a texture
+    pub fn from_graph(roughness: Rc<dyn OutputValue<F, F>>, ior: Vector3<Complex<F>>) -> Self {
         Self {
             roughness,
-            ior
+            ior,
+            anisotropy: Rc::new(F::zero()),
         }
     }
+
+    pub fn with_anisotropy(mut self, anisotropy: Rc<dyn OutputValue<F, F>>) -> Self {
+        self.anisotropy = anisotropy;
+        self
+    }
 }
 
 impl<F> MaterialTrait<F> for RoughConductorBRDFMaterial<F> where F: BaseFloat + 'static {
@@ -104,7 +120,13 @@ impl<F> MaterialTrait<F> for RoughConductorBRDFMaterial<F> where F: BaseFloat +
         let relative_ior_b = self.ior[2] / current_ior[2];
         let relative_ior = Vector3::new(relative_ior_r, relative_ior_g, relative_ior_b);
 
-        Some(Box::new(RoughConductorBRDF::new(self.roughness, relative_ior)))
+        let material_context = MaterialGraphContext {
+            uv: context.uv
+        };
+        let roughness = self.roughness.get_value(&material_context);
+        let anisotropy = self.anisotropy.get_value(&material_context);
+        let distribution = AnisotropicGGXDistribution::from_anisotropy(sqr(roughness), anisotropy);
+        Some(Box::new(RoughConductorBRDF::new_anisotropic(distribution, relative_ior)))
     }
 
     fn get_volume(&self) -> Option<Box<dyn VolumeTrait<F>>> {
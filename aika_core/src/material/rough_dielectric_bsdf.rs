@@ -1,8 +1,8 @@
 use std::rc::Rc;
 use cgmath::{BaseFloat, ElementWise, InnerSpace, Vector3};
 use num_traits::Zero;
-use aika_math::distribution::IsotropicGGXDistribution;
-use aika_math::utils::{face_forward, get_generalized_half, get_z, is_same_hemisphere, is_same_hemisphere_canonical, length_square_vector3, reflect, reflect_bias, refract, smith_g2_lagarde, sqr};
+use aika_math::distribution::AnisotropicGGXDistribution;
+use aika_math::utils::{face_forward, get_z, is_same_hemisphere_canonical, length_square_vector3, reflect, refract, sqr};
 use crate::f;
 use crate::material::{BSDF, BSDFSampleResult, MaterialTrait, VolumeTrait};
 use crate::material_graph::{MaterialGraphContext, OutputValue};
@@ -10,31 +10,48 @@ use crate::path_tracing::{ShadingContext, TracingService};
 use crate::utils::fresnel_dielectric;
 
 pub struct RoughDielectricBSDF<F> {
-    ndf: IsotropicGGXDistribution<F>,
+    ndf: AnisotropicGGXDistribution<F>,
     /// the ior out of the normal / ior inside the object
     relative_ior: Vector3<F>,
     is_single_ior: bool,
-    roughness: F,
 }
 
 impl<F> RoughDielectricBSDF<F> where F: BaseFloat + 'static {
     pub fn new(roughness: F, ior: Vector3<F>) -> Self {
-        let is_single_ior = if ior.x == ior.y && ior.x == ior.z {
-            true
-        } else {
-            false
-        };
+        Self::new_anisotropic(AnisotropicGGXDistribution::new_isotropic(roughness), ior)
+    }
+
+    /// The distribution is aligned to the tangent frame
+    pub fn new_anisotropic(ndf: AnisotropicGGXDistribution<F>, ior: Vector3<F>) -> Self {
+        let is_single_ior = ior.x == ior.y && ior.x == ior.z;
         Self {
-            ndf: IsotropicGGXDistribution::new(roughness),
+            ndf,
             relative_ior: ior,
             is_single_ior,
-            roughness
         }
     }
 
+    /// Height correlated masking-shadowing over 4 |cos_i cos_o|
+    fn get_visibility(&self, wi: Vector3<F>, wo: Vector3<F>) -> F {
+        self.ndf.masking_shadowing(wi, wo) / (f!(4) * (wi.z * wo.z).abs())
+    }
+
+    pub fn get_relative_ior(&self) -> Vector3<F> {
+        self.relative_ior
+    }
+
     pub fn sample_ray_single_ior(&self, service: &mut TracingService<F>, wo: Vector3<F>, ior_index: usize) -> Option<BSDFSampleResult<F>> {
+        let r1 = service.random_0_1();
+        let r2 = service.random_0_1();
+        let random = service.random_0_1();
+        self.sample_single_ior_with(wo, ior_index, r1, r2, random)
+    }
+
+    /// Same as `sample_ray_single_ior`, with the random numbers given.
+    /// `r1` and `r2` sample the microfacet normal, `random` chooses between reflection and refraction
+    pub(crate) fn sample_single_ior_with(&self, wo: Vector3<F>, ior_index: usize, r1: F, r2: F, random: F) -> Option<BSDFSampleResult<F>> {
         let eta = self.relative_ior[ior_index];
-        let wm = self.ndf.sample_wm(wo, service.random_0_1(), service.random_0_1());
+        let wm = self.ndf.sample_wm(wo, r1, r2);
         assert!(wm.z > F::zero());
         let pdf_wm = self.ndf.distribution_of_visible_normal(wo, wm);
         let cos_theta_o = wm.dot(wo);
@@ -42,7 +59,6 @@ impl<F> RoughDielectricBSDF<F> where F: BaseFloat + 'static {
         let fresnel = fresnel_dielectric(cos_theta_o, F::one(), eta).unwrap_or(F::one());
         let backface = wo.z < F::zero();
         let transmission = F::one() - fresnel;
-        let random = service.random_0_1();
         // let random = F::one();
         // let random = F::zero();
         if random < fresnel {
@@ -56,13 +72,12 @@ impl<F> RoughDielectricBSDF<F> where F: BaseFloat + 'static {
 
             let ndf = self.ndf.evaluate(wm);
             assert!(ndf > F::zero());
-            let lar = smith_g2_lagarde(wi, wo, self.roughness);
-            let weight = lar * f!(4) * cos_theta_o_abs * ndf / pdf_wm;
+            let lar = self.get_visibility(wi, wo);
+            let weight = lar * f!(4) * cos_theta_o_abs * wi.z.abs() * ndf / pdf_wm;
 
             Some(BSDFSampleResult {
                 direction: wi,
                 weight: Vector3::new(weight, weight, weight),
-                next_point: reflect_bias(wo),
             })
         } else {
             let wi = refract(wo, wm, F::one(), eta);
@@ -92,74 +107,84 @@ impl<F> RoughDielectricBSDF<F> where F: BaseFloat + 'static {
             }
 
             let ndf = self.ndf.evaluate(wm);
-            let lar = smith_g2_lagarde(wi, wo, self.roughness);
+            let lar = self.get_visibility(wi, wo);
             let cos_theta_i_abs = wi.z.abs();
-            let weight = f!(4) * lar * cos_theta_i_abs * wm.dot(wo).abs() * ndf / pdf_wm;
-            // if backface {
-            //     weight = weight / (eta * eta);
-            // } else {
-            //     weight = weight * (eta * eta);
-            // }
+            // radiance is compressed into the smaller solid angle of the denser side
+            let etap = if backface { F::one() / eta } else { eta };
+            let weight = f!(4) * lar * cos_theta_i_abs * wm.dot(wo).abs() * ndf / (pdf_wm * etap * etap);
             Some(BSDFSampleResult {
                 direction: wi,
                 weight: Vector3::new(weight, weight, weight),
-                next_point: -reflect_bias(wo)
             })
         }
     }
 }
 
+impl<F> RoughDielectricBSDF<F> where F: BaseFloat + 'static {
+    /// The microfacet normal between `wi` and `wo` in the positive hemisphere, and the ior ratio across it seen from `wo`.
+    /// None if the microfacet faces away from either direction
+    fn get_half_vector(&self, wi: Vector3<F>, wo: Vector3<F>) -> Option<(Vector3<F>, F)> {
+        let eta = self.relative_ior[0];
+        let is_reflect = wi.z * wo.z > F::zero();
+        let etap = if is_reflect { F::one() } else if wo.z > F::zero() { eta } else { F::one() / eta };
+        let wm = wi * etap + wo;
+        if wi.z == F::zero() || wo.z == F::zero() || length_square_vector3(wm) == F::zero() {
+            return None;
+        }
+        let wm = face_forward(wm.normalize(), get_z());
+        if wm.dot(wi) * wi.z < F::zero() || wm.dot(wo) * wo.z < F::zero() {
+            return None;
+        }
+        Some((wm, etap))
+    }
+
+    /// The density of `sample_ray` choosing `wi` from `wo`, with the first ior.
+    /// Zero for a smooth surface, whose lobes are delta distributions
+    pub fn pdf(&self, wi: Vector3<F>, wo: Vector3<F>) -> F {
+        if self.ndf.is_effectively_smooth() {
+            return F::zero();
+        }
+        let (wm, etap) = match self.get_half_vector(wi, wo) {
+            Some(x) => x,
+            None => return F::zero(),
+        };
+
+        let fresnel = fresnel_dielectric(wo.dot(wm), F::one(), self.relative_ior[0]).unwrap_or(F::one());
+        let pdf_wm = self.ndf.distribution_of_visible_normal(wo, wm);
+        if wi.z * wo.z > F::zero() {
+            pdf_wm / (f!(4) * wo.dot(wm).abs()) * fresnel
+        } else {
+            let denominator = sqr(wi.dot(wm) + wo.dot(wm) / etap);
+            pdf_wm * wi.dot(wm).abs() / denominator * (F::one() - fresnel)
+        }
+    }
+}
+
 impl<F> BSDF<F> for RoughDielectricBSDF<F> where F: BaseFloat + 'static {
+    /// Transmitted radiance is scaled by the squared ratio of the iors, same as `sample_ray`
     fn evaluate(&self, wi: Vector3<F>, wo: Vector3<F>) -> Option<Vector3<F>> {
         if self.ndf.is_effectively_smooth() {
             return Some(Vector3::zero());
         }
+        let (wm, etap) = match self.get_half_vector(wi, wo) {
+            Some(x) => x,
+            None => return Some(Vector3::zero()),
+        };
 
-        let eta = self.relative_ior[0];
-
-        let cos_theta_o = wo.z;
-        let cos_theta_i = wi.z;
-        let reflect = cos_theta_i * cos_theta_o > F::zero();
-        let fresnel = fresnel_dielectric(cos_theta_i.abs(), F::one(), eta);
-        let wm = get_generalized_half(wi, wo, eta)?;
-
-        if reflect {
-            if fresnel.is_none() {
-                println!("fresnel is none but is reflect");
-                return None;
-            }
-            let fresnel = fresnel.unwrap();
-            assert!(is_same_hemisphere(wi, wo, wm));
-            let wm = if wm.z < F::zero() { -wm } else { wm };
-            let ndf = self.ndf.evaluate(wm);
-            let lar = smith_g2_lagarde(wi, wo, self.roughness);
+        // the fresnel term is of the microfacet, as seen from wo
+        let fresnel = fresnel_dielectric(wo.dot(wm), F::one(), self.relative_ior[0]).unwrap_or(F::one());
+        let ndf = self.ndf.evaluate(wm);
+        let lar = self.get_visibility(wi, wo);
+        if wi.z * wo.z > F::zero() {
             let brdf = fresnel * ndf * lar;
             Some(Vector3::new(brdf, brdf, brdf))
-            // Some(Vector3::zero())
         } else {
-            if wm.z <= F::zero() {
-                // println!("wm.z < 0");
-                return None;
-            }
-            assert!(wm.z > F::zero());
-            assert!(fresnel.is_some());
-            let fresnel = fresnel.unwrap();
-            let lar = smith_g2_lagarde(wi, wo, self.roughness);
-            // let etap = if wi.z > F::zero() { F::one() / eta } else { eta };
-            let etap = if wi.z > F::zero() { eta } else { F::one() / eta };
             let wi_dot_wm = wi.dot(wm);
             let wo_dot_wm = wo.dot(wm);
-            let vertical_component_sqr = sqr(wi_dot_wm + etap * wo_dot_wm);
+            let vertical_component_sqr = sqr(wi_dot_wm + wo_dot_wm / etap);
             let transmit = F::one() - fresnel;
-            let ndf = self.ndf.evaluate(wm);
-            let btdf = transmit * f!(4) * lar * ndf * wi_dot_wm.abs() * wo_dot_wm.abs() * etap * etap / vertical_component_sqr;
-            // if wi.z > F::zero() {
-            //     btdf = btdf * (eta * eta);
-            // } else {
-            //     btdf = btdf / (eta * eta);
-            // }
+            let btdf = transmit * f!(4) * lar * ndf * wi_dot_wm.abs() * wo_dot_wm.abs() / (vertical_component_sqr * etap * etap);
             Some(Vector3::new(btdf, btdf, btdf))
-            // Some(Vector3::zero())
         }
     }
 
@@ -177,26 +202,39 @@ impl<F> BSDF<F> for RoughDielectricBSDF<F> where F: BaseFloat + 'static {
                 Some(BSDFSampleResult {
                     direction: r.direction,
                     weight: r.weight.mul_element_wise(mask) * f!(3),
-                    next_point: r.next_point,
                 })
             } else {
                 None
             }
         }
     }
+
+    /// With the first ior, as the inherent `pdf`
+    fn pdf(&self, wi: Vector3<F>, wo: Vector3<F>) -> Option<F> {
+        Some(RoughDielectricBSDF::pdf(self, wi, wo))
+    }
 }
 
 pub struct RoughDielectricBSDFMaterial<F> {
     pub roughness: Rc<dyn OutputValue<F, F>>,
+    /// 0 is isotropic, 1 stretches the highlight along the tangent
+    pub anisotropy: Rc<dyn OutputValue<F, F>>,
     pub ior: F,
 }
 
-impl<F> RoughDielectricBSDFMaterial<F> where F: BaseFloat {
+impl<F> RoughDielectricBSDFMaterial<F> where F: BaseFloat + 'static {
     pub fn new(roughness: Rc<dyn OutputValue<F, F>>, ior: F) -> Self {
         RoughDielectricBSDFMaterial {
-            roughness, ior
+            roughness,
+            anisotropy: Rc::new(F::zero()),
+            ior
         }
     }
+
+    pub fn with_anisotropy(mut self, anisotropy: Rc<dyn OutputValue<F, F>>) -> Self {
+        self.anisotropy = anisotropy;
+        self
+    }
 }
 
 impl<F> MaterialTrait<F> for RoughDielectricBSDFMaterial<F> where F: BaseFloat + 'static {
@@ -223,7 +261,9 @@ impl<F> MaterialTrait<F> for RoughDielectricBSDFMaterial<F> where F: BaseFloat +
         };
 
         let roughness = self.roughness.get_value(&material_context);
-        Some(Box::new(RoughDielectricBSDF::new(roughness, relative_ior)))
+        let anisotropy = self.anisotropy.get_value(&material_context);
+        let ndf = AnisotropicGGXDistribution::from_anisotropy(roughness, anisotropy);
+        Some(Box::new(RoughDielectricBSDF::new_anisotropic(ndf, relative_ior)))
     }
 
     fn get_volume(&self) -> Option<Box<dyn VolumeTrait<F>>> {
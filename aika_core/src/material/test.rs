@@ -0,0 +1,328 @@
+use std::rc::Rc;
+use cgmath::{InnerSpace, Vector2, Vector3};
+use aika_math::utils::{get_2pi, sample_uniform_hemisphere};
+use aika_math::Complex;
+use aika_math::distribution::AnisotropicGGXDistribution;
+use crate::material::{BSDF, DielectricBSDF, DiffuseBRDF, DiffuseBRDFMaterial, LayeredBSDF, LayeredMaterial, LayerInterface, MaterialTrait, MetallicRoughnessBRDFMaterial, OrenNayarBRDF, OrenNayarBRDFMaterial, OrenNayarModel, PrincipledBSDF, PrincipledBSDFMaterial, PrincipledBSDFParameters, RoughConductorBRDF, RoughConductorBRDFMaterial, RoughDielectricBSDF};
+use crate::material_graph::MaterialGraphContext;
+use crate::path_tracing::{ShadingContext, TracingService};
+use crate::component::Transform;
+use crate::scene::{GameObject, Scene};
+
+fn get_parameters(base_color: Vector3<f64>) -> PrincipledBSDFParameters<f64> {
+    let material = PrincipledBSDFMaterial::new(Rc::new(base_color));
+    material.get_parameters(&MaterialGraphContext { uv: Vector2::new(0.0, 0.0) })
+}
+
+/// Only the random numbers of the service are used, it needs a non empty scene for its bvh
+fn get_tracing_service() -> TracingService<f64> {
+    let mut scene = Scene::new();
+    let mut go = GameObject::new_plane(String::from("plane"), 1.0, 1.0);
+    go.add_component_owned::<Transform<f64>>(Transform::default());
+    scene.add_game_object(go);
+    TracingService::new(&scene)
+}
+
+/// The mean of the sample weights, and the mean of f * cos / pdf with uniformly sampled directions
+fn estimate_albedo(bsdf: &dyn BSDF<f64>, wo: Vector3<f64>, count: usize) -> (Vector3<f64>, Vector3<f64>) {
+    let mut service = get_tracing_service();
+    let mut sampled = Vector3::new(0.0, 0.0, 0.0);
+    let mut uniform = Vector3::new(0.0, 0.0, 0.0);
+    for _ in 0..count {
+        if let Some(result) = bsdf.sample_ray(&mut service, wo) {
+            sampled += result.weight;
+        }
+        let wi: Vector3<f64> = sample_uniform_hemisphere(service.random_0_1(), service.random_0_1());
+        if let Some(f) = bsdf.evaluate(wi, wo) {
+            uniform += f * wi.z * get_2pi::<f64>();
+        }
+    }
+    (sampled / count as f64, uniform / count as f64)
+}
+
+#[test]
+fn test_principled_bsdf_sampling_consistent() {
+    let wo = Vector3::new(0.3, -0.2, 0.8).normalize();
+    let mut glossy = get_parameters(Vector3::new(0.8, 0.5, 0.2));
+    glossy.roughness = 0.6;
+    glossy.anisotropy = 0.5;
+    glossy.sheen = 0.5;
+    let mut metal = get_parameters(Vector3::new(0.9, 0.6, 0.3));
+    metal.metallic = 1.0;
+    metal.roughness = 0.7;
+    let mut coated = get_parameters(Vector3::new(0.2, 0.3, 0.8));
+    coated.clearcoat = 1.0;
+    coated.clearcoat_roughness = 0.5;
+    coated.subsurface = 0.5;
+
+    for parameters in [glossy, metal, coated] {
+        let bsdf = PrincipledBSDF::new(parameters, 1.5);
+        let (sampled, uniform) = estimate_albedo(&bsdf, wo, 200000);
+        for k in 0..3 {
+            assert!((sampled[k] - uniform[k]).abs() < 0.03, "sampled: {:?}, uniform: {:?}", sampled, uniform);
+        }
+    }
+}
+
+#[test]
+fn test_principled_bsdf_energy() {
+    let white = Vector3::new(1.0, 1.0, 1.0);
+    for (metallic, roughness, transmission) in [(0.0, 0.3, 0.0), (1.0, 0.5, 0.0), (0.0, 0.5, 1.0), (0.5, 0.8, 0.5)] {
+        let mut parameters = get_parameters(white);
+        parameters.metallic = metallic;
+        parameters.roughness = roughness;
+        parameters.transmission = transmission;
+        let bsdf = PrincipledBSDF::new(parameters, 1.5);
+        for wo in [Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.6, 0.0, 0.8)] {
+            let (sampled, _) = estimate_albedo(&bsdf, wo, 50000);
+            assert!(sampled.x <= 1.05, "metallic: {}, transmission: {}, wo: {:?}, albedo: {:?}", metallic, transmission, wo, sampled);
+        }
+    }
+}
+
+#[test]
+fn test_principled_bsdf_material() {
+    let material = PrincipledBSDFMaterial::new(Rc::new(Vector3::new(0.5, 0.5, 0.5)))
+        .with_emission(Rc::new(Vector3::new(2.0, 1.0, 0.0)))
+        .with_transmission(Rc::new(0.5), 1.33);
+    let bsdf = material.get_bsdf(&ShadingContext::new()).unwrap();
+    assert_eq!(bsdf.emit(Vector3::new(0.0, 0.0, 1.0)), Some(Vector3::new(2.0, 1.0, 0.0)));
+    assert_eq!(material.get_ior(), Some(Vector3::new(1.33, 1.33, 1.33)));
+
+    // an opaque material has no medium inside
+    let material = PrincipledBSDFMaterial::new(Rc::new(Vector3::new(0.5, 0.5, 0.5)));
+    let bsdf = material.get_bsdf(&ShadingContext::new()).unwrap();
+    assert!(bsdf.emit(Vector3::new(0.0, 0.0, 1.0)).is_none());
+    assert!(material.get_ior().is_none());
+
+    // the ior of the Fresnel term is read from the graph, a higher one reflects more at normal incidence
+    let wo = Vector3::new(0.0, 0.0, 1.0);
+    let reflectance = |ior: f64| {
+        let material = PrincipledBSDFMaterial::new(Rc::new(Vector3::new(0.0, 0.0, 0.0)))
+            .with_roughness(Rc::new(0.3))
+            .with_ior(Rc::new(ior));
+        let bsdf = material.get_bsdf(&ShadingContext::new()).unwrap();
+        estimate_albedo(bsdf.as_ref(), wo, 20000).0.x
+    };
+    assert!(reflectance(2.0) > reflectance(1.5) * 2.0);
+}
+
+#[test]
+fn test_metallic_roughness_reads_metallic() {
+    let wi = Vector3::new(0.3, 0.0, 0.9).normalize();
+    let wo = Vector3::new(-0.2, 0.1, 0.9).normalize();
+    let color = Vector3::new(0.9, 0.2, 0.1);
+    let evaluate = |metallic: f64| {
+        let material = MetallicRoughnessBRDFMaterial::new(Rc::new(0.5), Rc::new(metallic), Rc::new(color));
+        material.get_bsdf(&ShadingContext::new()).unwrap().evaluate(wi, wo).unwrap()
+    };
+    let dielectric = evaluate(0.0);
+    let metal = evaluate(1.0);
+    assert!((dielectric - metal).magnitude() > 1e-3);
+    // a metal has no diffuse, and its reflection is tinted by the base color
+    assert!(metal.y < metal.x * 0.5);
+}
+
+#[test]
+fn test_diffuse_cosine_sampling() {
+    let albedo = Vector3::new(0.8, 0.5, 0.2);
+    let bsdf = DiffuseBRDF::new(albedo);
+    let (sampled, uniform) = estimate_albedo(&bsdf, Vector3::new(0.6, 0.0, 0.8), 20000);
+    // with cosine sampling every sample carries the albedo
+    assert!((sampled - albedo).magnitude() < 1e-9);
+    assert!((uniform - albedo).magnitude() < 0.02, "uniform: {:?}", uniform);
+}
+
+fn get_coated_diffuse(albedo: Vector3<f64>, roughness: f64) -> LayeredBSDF<f64> {
+    let interface = if roughness > 0.0 {
+        LayerInterface::Rough(RoughDielectricBSDF::new(roughness, Vector3::new(1.5, 1.5, 1.5)))
+    } else {
+        LayerInterface::Smooth(DielectricBSDF::new(1.5))
+    };
+    LayeredBSDF::new(interface, Box::new(DiffuseBRDF::new(albedo)))
+}
+
+#[test]
+fn test_layered_bsdf_sampling_consistent() {
+    let wo = Vector3::new(0.3, -0.2, 0.8).normalize();
+    let bsdf = get_coated_diffuse(Vector3::new(0.8, 0.5, 0.2), 0.3)
+        .with_medium(Vector3::new(0.0, 5.0, 10.0), 0.05);
+    let (sampled, uniform) = estimate_albedo(&bsdf, wo, 100000);
+    for k in 0..3 {
+        assert!((sampled[k] - uniform[k]).abs() < 0.03, "sampled: {:?}, uniform: {:?}", sampled, uniform);
+    }
+}
+
+#[test]
+fn test_layered_bsdf_energy() {
+    let white = Vector3::new(1.0, 1.0, 1.0);
+    let wo = Vector3::new(0.6, 0.0, 0.8);
+    // nothing is absorbed under a clear coat, apart from the paths cut by the max depth
+    let clear = get_coated_diffuse(white, 0.0).with_max_depth(100);
+    let (albedo, _) = estimate_albedo(&clear, wo, 50000);
+    assert!(albedo.x > 0.95 && albedo.x < 1.02, "albedo: {:?}", albedo);
+    // the back side behaves the same
+    let (albedo, _) = estimate_albedo(&clear, -wo, 50000);
+    assert!(albedo.x > 0.95 && albedo.x < 1.02, "albedo: {:?}", albedo);
+
+    let absorbing = get_coated_diffuse(white, 0.0).with_medium(Vector3::new(0.0, 2.0, 20.0), 0.1);
+    let (albedo, _) = estimate_albedo(&absorbing, wo, 50000);
+    assert!(albedo.x > albedo.y && albedo.y > albedo.z, "albedo: {:?}", albedo);
+    // the specular reflection off the coat is left
+    assert!(albedo.z > 0.03 && albedo.z < 0.1, "albedo: {:?}", albedo);
+}
+
+#[test]
+fn test_layered_bsdf_pdf() {
+    let wo = Vector3::new(0.3, -0.2, 0.8).normalize();
+    let bsdf = get_coated_diffuse(Vector3::new(0.5, 0.5, 0.5), 0.3);
+    assert_eq!(bsdf.pdf(Vector3::new(0.0, 0.6, -0.8), wo), 0.0);
+
+    let scene_free_random = |i: usize, k: usize| ((i * 7919 + k * 104729) % 10007) as f64 / 10007.0;
+    let count = 10000;
+    let mut integral = 0.0;
+    for i in 0..count {
+        let wi: Vector3<f64> = sample_uniform_hemisphere(scene_free_random(i, 0), scene_free_random(i, 1));
+        let pdf = bsdf.pdf(wi, wo);
+        assert!(pdf > 0.0);
+        integral += pdf * get_2pi::<f64>();
+    }
+    let integral = integral / count as f64;
+    assert!(integral > 0.7 && integral < 1.3, "integral: {}", integral);
+}
+
+#[test]
+fn test_layered_material() {
+    let base = DiffuseBRDFMaterial::new(Vector3::new(0.5, 0.5, 0.5));
+    let material = LayeredMaterial::new(Box::new(base), 1.5)
+        .with_roughness(Rc::new(0.2))
+        .with_sample_count(0);
+    assert!(material.get_ior().is_none());
+    assert_eq!(material.sample_count, 1);
+    let bsdf = material.get_bsdf(&ShadingContext::new()).unwrap();
+    let wo = Vector3::new(0.0, 0.0, 1.0);
+    let wi = Vector3::new(0.6, 0.0, 0.8);
+    let f = bsdf.evaluate(wi, wo).unwrap();
+    assert!(f.x > 0.0);
+    // evaluation is deterministic
+    assert_eq!(bsdf.evaluate(wi, wo).unwrap(), f);
+    assert!(bsdf.pdf(wi, wo).unwrap() > 0.0);
+}
+
+#[test]
+fn test_layered_emission() {
+    let base = PrincipledBSDFMaterial::new(Rc::new(Vector3::new(0.5, 0.5, 0.5)))
+        .with_emission(Rc::new(Vector3::new(1.0, 1.0, 1.0)));
+    let material = LayeredMaterial::new(Box::new(base), 1.5)
+        .with_medium(Rc::new(Vector3::new(0.0, 10.0, 20.0)), Rc::new(0.05));
+    let bsdf = material.get_bsdf(&ShadingContext::new()).unwrap();
+
+    // at normal incidence, the Fresnel transmittance 0.96 spread over 1.5^2 times the solid angle, then absorbed by the coat
+    let wo = Vector3::new(0.0, 0.0, 1.0);
+    let expected = Vector3::new(1.0, (-0.5f64).exp(), (-1.0f64).exp()) * (0.96 / 2.25);
+    assert!((bsdf.emit(wo).unwrap() - expected).magnitude() < 1e-9);
+    // the back side is the same, and grazing directions pass a longer way through the coat
+    assert!((bsdf.emit(-wo).unwrap() - expected).magnitude() < 1e-9);
+    let grazing = bsdf.emit(Vector3::new(0.8, 0.0, 0.6)).unwrap();
+    assert!(grazing.z < expected.z && grazing.x < expected.x);
+}
+
+#[test]
+fn test_rough_dielectric_sampling_consistent() {
+    let bsdf = RoughDielectricBSDF::new(0.3, Vector3::new(1.5, 1.5, 1.5));
+    let mut service = get_tracing_service();
+
+    // from outside and from inside, reflection and transmission are compared separately
+    for wo in [Vector3::new(0.6, 0.0, 0.8), Vector3::new(0.6, 0.0, -0.8)] {
+        let count = 100000;
+        let mut sampled = [0.0, 0.0];
+        let mut uniform = [0.0, 0.0];
+        for _ in 0..count {
+            if let Some(result) = bsdf.sample_ray(&mut service, wo) {
+                let k = if result.direction.z * wo.z > 0.0 { 0 } else { 1 };
+                sampled[k] += result.weight.x;
+            }
+            let w: Vector3<f64> = sample_uniform_hemisphere(service.random_0_1(), service.random_0_1());
+            let reflected = Vector3::new(w.x, w.y, w.z * wo.z.signum());
+            let transmitted = Vector3::new(w.x, w.y, -reflected.z);
+            for (k, wi) in [reflected, transmitted].into_iter().enumerate() {
+                uniform[k] += bsdf.evaluate(wi, wo).unwrap().x * w.z * get_2pi::<f64>();
+            }
+        }
+        for k in 0..2 {
+            let (s, u) = (sampled[k] / count as f64, uniform[k] / count as f64);
+            assert!((s - u).abs() < 0.03 * u.max(1.0), "wo: {:?}, sampled: {}, uniform: {}", wo, s, u);
+        }
+    }
+}
+
+#[test]
+fn test_anisotropic_rough_conductor() {
+    let gold = Vector3::new(Complex::new(0.18, 3.4), Complex::new(0.42, 2.35), Complex::new(1.37, 1.77));
+    let wo = Vector3::new(0.3, -0.2, 0.8).normalize();
+    let distribution = AnisotropicGGXDistribution::from_anisotropy(0.3, 0.8);
+    let bsdf = RoughConductorBRDF::new_anisotropic(distribution, gold);
+    let (sampled, uniform) = estimate_albedo(&bsdf, wo, 100000);
+    for k in 0..3 {
+        assert!(sampled[k] <= 1.0);
+        assert!((sampled[k] - uniform[k]).abs() < 0.03, "sampled: {:?}, uniform: {:?}", sampled, uniform);
+    }
+
+    // the highlight is stretched along the tangent
+    let wo = Vector3::new(0.0, 0.0, 1.0);
+    let along_tangent = bsdf.evaluate(Vector3::new(0.5, 0.0, 1.0).normalize(), wo).unwrap();
+    let along_bitangent = bsdf.evaluate(Vector3::new(0.0, 0.5, 1.0).normalize(), wo).unwrap();
+    assert!(along_tangent.x > along_bitangent.x * 2.0);
+}
+
+#[test]
+fn test_rough_conductor_material() {
+    let gold = Vector3::new(Complex::new(0.18, 3.4), Complex::new(0.42, 2.35), Complex::new(1.37, 1.77));
+    let material = RoughConductorBRDFMaterial::new(0.3, gold)
+        .with_anisotropy(Rc::new(0.8));
+    let bsdf = material.get_bsdf(&ShadingContext::new()).unwrap();
+
+    // same as the BSDF built from the constants, alpha is the square of the roughness
+    let distribution = AnisotropicGGXDistribution::from_anisotropy(0.09, 0.8);
+    let expected = RoughConductorBRDF::new_anisotropic(distribution, gold);
+    let wi = Vector3::new(0.5, 0.0, 1.0).normalize();
+    let wo = Vector3::new(0.0, 0.0, 1.0);
+    assert_eq!(bsdf.evaluate(wi, wo), expected.evaluate(wi, wo));
+}
+
+#[test]
+fn test_oren_nayar() {
+    let white = Vector3::new(1.0, 1.0, 1.0);
+    let wo = Vector3::new(0.6, 0.0, 0.8);
+    for model in [OrenNayarModel::Qualitative, OrenNayarModel::EnergyPreserving] {
+        // without roughness both are Lambertian
+        let smooth = OrenNayarBRDF::new(white, 0.0, model);
+        let f = smooth.evaluate(Vector3::new(-0.3, 0.4, 0.5).normalize(), wo).unwrap();
+        assert!((f.x - 1.0 / std::f64::consts::PI).abs() < 1e-6);
+
+        let rough = OrenNayarBRDF::new(white, 0.8, model);
+        // brighter towards the light than away from it
+        let back = rough.evaluate(Vector3::new(0.6, 0.0, 0.8), wo).unwrap();
+        let forward = rough.evaluate(Vector3::new(-0.6, 0.0, 0.8), wo).unwrap();
+        assert!(back.x > forward.x);
+
+        for wo in [Vector3::new(0.0, 0.0, 1.0), wo, Vector3::new(0.95, 0.0, 0.31).normalize()] {
+            let (sampled, uniform) = estimate_albedo(&rough, wo, 50000);
+            assert!((sampled.x - uniform.x).abs() < 0.02, "sampled: {:?}, uniform: {:?}", sampled, uniform);
+            match model {
+                OrenNayarModel::Qualitative => assert!(sampled.x < 1.0),
+                OrenNayarModel::EnergyPreserving => assert!((sampled.x - 1.0).abs() < 0.02, "albedo: {:?}", sampled),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_oren_nayar_material() {
+    let material = OrenNayarBRDFMaterial::new(Rc::new(Vector3::new(0.5, 0.5, 0.5)), Rc::new(0.5))
+        .with_model(OrenNayarModel::EnergyPreserving);
+    let bsdf = material.get_bsdf(&ShadingContext::new()).unwrap();
+    let wo = Vector3::new(0.0, 0.0, 1.0);
+    assert!(bsdf.evaluate(Vector3::new(0.0, 0.6, 0.8), wo).unwrap().x > 0.0);
+    assert_eq!(bsdf.evaluate(Vector3::new(0.0, 0.6, -0.8), wo).unwrap().x, 0.0);
+}
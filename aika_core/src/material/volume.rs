@@ -7,6 +7,10 @@ pub struct VolumeSampleResult<F> {
     pub next_direction: Vector3<F>,
     /// the exit point
     pub point: Vector3<F>,
+    /// error bound of the exit point
+    pub point_error: Vector3<F>,
+    /// geometric normal at the exit point
+    pub normal: Vector3<F>,
     pub weight: Vector3<F>,
     // maybe a nested volume, or some other objects
     // pub hit_object:
@@ -1,7 +1,7 @@
 use std::marker::PhantomData;
 use std::ops::Add;
 use std::rc::Rc;
-use cgmath::{BaseFloat, Vector3, Vector4};
+use cgmath::{BaseFloat, ElementWise, Vector3, Vector4};
 use crate::material_graph::{MaterialGraphContext, OutputValue};
 use crate::texture::Texture2DTrait;
 
@@ -100,3 +100,51 @@ impl<F> OutputValue<F, Vector3<F>> for Vector3<F> where F: BaseFloat {
         *self
     }
 }
+
+/// One channel of a color, such as the roughness stored in the green channel of a texture
+pub struct ChannelNode<F> {
+    pub input: Rc<dyn OutputValue<F, Vector3<F>>>,
+    pub channel: usize,
+}
+
+impl<F> ChannelNode<F> where F: BaseFloat {
+    pub fn new(input: Rc<dyn OutputValue<F, Vector3<F>>>, channel: usize) -> Self {
+        Self {
+            input,
+            channel
+        }
+    }
+}
+
+impl<F> OutputValue<F, F> for ChannelNode<F> where F: BaseFloat {
+    fn get_value(&self, context: &MaterialGraphContext<F>) -> F {
+        self.input.get_value(context)[self.channel]
+    }
+}
+
+/// `input` multiplied by a constant `factor`, per channel for colors
+pub struct MultiplyNode<F, V> {
+    pub input: Rc<dyn OutputValue<F, V>>,
+    pub factor: V,
+}
+
+impl<F, V> MultiplyNode<F, V> where F: BaseFloat {
+    pub fn new(input: Rc<dyn OutputValue<F, V>>, factor: V) -> Self {
+        Self {
+            input,
+            factor
+        }
+    }
+}
+
+impl<F> OutputValue<F, Vector3<F>> for MultiplyNode<F, Vector3<F>> where F: BaseFloat {
+    fn get_value(&self, context: &MaterialGraphContext<F>) -> Vector3<F> {
+        self.input.get_value(context).mul_element_wise(self.factor)
+    }
+}
+
+impl<F> OutputValue<F, F> for MultiplyNode<F, F> where F: BaseFloat {
+    fn get_value(&self, context: &MaterialGraphContext<F>) -> F {
+        self.input.get_value(context) * self.factor
+    }
+}
@@ -46,6 +46,19 @@ impl<V> Mesh<V> where V: VertexBuffer {
     pub fn face_count(&self) -> usize {
         self.triangles.len()
     }
+
+    /// The index of the sub mesh each triangle belongs to.
+    /// Triangles outside of every range belong to the sub mesh 0
+    pub fn get_triangle_sub_mesh_indices(&self) -> Vec<usize> {
+        let mut result = vec![0; self.triangles.len()];
+        // walk backwards so the first range wins when they overlap
+        for (index, &[a, b]) in self.sub_mesh.iter().enumerate().rev() {
+            for item in result[a.min(b)..b.min(self.triangles.len())].iter_mut() {
+                *item = index;
+            }
+        }
+        result
+    }
 }
 
 pub struct MeshTrianglesIterator<'a, V> {
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use anyhow::{bail, Result};
+use cgmath::{BaseFloat, InnerSpace, Vector3};
+use num_traits::Zero;
+use aika_math::utils::get_pi;
+use crate::material_graph::{MaterialGraphContext, OutputValue};
+use crate::mesh::{CommonVertex, Mesh, NormalWeighting, VertexBuffer};
+use crate::mesh::mesh_processing::get_position_key;
+
+fn get_midpoint<F>(a: &CommonVertex<F>, b: &CommonVertex<F>) -> CommonVertex<F> where F: BaseFloat {
+    let half = F::from(0.5).unwrap();
+    CommonVertex {
+        position: (a.position + b.position) * half,
+        normal: a.normal.zip(b.normal).map(|(x, y)| (x + y) * half),
+        uv0: a.uv0.zip(b.uv0).map(|(x, y)| (x + y) * half),
+        uv1: a.uv1.zip(b.uv1).map(|(x, y)| (x + y) * half),
+        // tangents are generated again after the displacement
+        tangent: None,
+        color: a.color.zip(b.color).map(|(x, y)| (x + y) * half),
+    }
+}
+
+impl<V> Mesh<V> where V: VertexBuffer, V::FloatType: BaseFloat {
+    /// Copy the mesh into a mesh with owned vertices
+    pub fn to_common_mesh(&self) -> Mesh<Vec<CommonVertex<V::FloatType>>> {
+        let vertices = (0..self.vertices.vertex_count()).map(|i| CommonVertex {
+            position: self.vertices.get_position(i),
+            normal: self.vertices.get_normal(i),
+            uv0: self.vertices.get_uv0(i),
+            uv1: self.vertices.get_uv1(i),
+            tangent: self.vertices.get_tangent(i),
+            color: self.vertices.get_color(i),
+        }).collect();
+
+        Mesh {
+            vertices,
+            triangles: self.triangles.clone(),
+            sub_mesh: self.sub_mesh.clone(),
+        }
+    }
+}
+
+impl<F> Mesh<Vec<CommonVertex<F>>> where F: BaseFloat {
+    /// Split every triangle into 4 at the midpoints of its edges.
+    /// The midpoints are shared between the triangles of an edge, so the mesh stays connected
+    pub fn tessellate(&self) -> Self {
+        let mut vertices = self.vertices.clone();
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut get_midpoint_index = |a: usize, b: usize, vertices: &mut Vec<CommonVertex<F>>| {
+            let key = (a.min(b), a.max(b));
+            *midpoints.entry(key).or_insert_with(|| {
+                vertices.push(get_midpoint(&vertices[a], &vertices[b]));
+                vertices.len() - 1
+            })
+        };
+
+        let mut triangles = Vec::with_capacity(self.triangles.len() * 4);
+        for &[a, b, c] in self.triangles.iter() {
+            let ab = get_midpoint_index(a, b, &mut vertices);
+            let bc = get_midpoint_index(b, c, &mut vertices);
+            let ca = get_midpoint_index(c, a, &mut vertices);
+            triangles.push([a, ab, ca]);
+            triangles.push([ab, b, bc]);
+            triangles.push([ca, bc, c]);
+            triangles.push([ab, bc, ca]);
+        }
+
+        Mesh {
+            vertices,
+            triangles,
+            // the triangles of each face stay in order
+            sub_mesh: self.sub_mesh.iter().map(|&[a, b]| [a * 4, b * 4]).collect(),
+        }
+    }
+
+    /// Tessellate `subdivision` times, then move each vertex along its normal by `height(uv0) * scale`.
+    /// Vertices at the same position, e.g. split along uv seams or hard edges, are welded:
+    /// they move by the averaged normal and height, so the surface does not crack.
+    /// The normals are computed again from the displaced surface, and so are the tangents
+    pub fn displace(&self, height: &dyn OutputValue<F, F>, scale: F, subdivision: usize) -> Result<Self> {
+        if self.vertices.iter().any(|v| v.uv0.is_none()) {
+            bail!("displacement needs uv0 on every vertex");
+        }
+
+        let mut mesh = Mesh {
+            vertices: self.vertices.clone(),
+            triangles: self.triangles.clone(),
+            sub_mesh: self.sub_mesh.clone(),
+        };
+        if mesh.vertices.iter().any(|v| v.normal.is_none()) {
+            mesh.generate_normals(NormalWeighting::Area, get_pi());
+        }
+        for _ in 0..subdivision {
+            mesh = mesh.tessellate();
+        }
+
+        // sum of the normals, sum of the heights and the count of the vertices at each position
+        let mut offsets: HashMap<[u64; 3], (Vector3<F>, F, usize)> = HashMap::new();
+        for vertex in mesh.vertices.iter() {
+            let Some(normal) = vertex.normal.filter(|n| n.magnitude2() > F::zero()) else {
+                continue;
+            };
+            let context = MaterialGraphContext {
+                uv: vertex.uv0.unwrap(),
+            };
+            let entry = offsets.entry(get_position_key(vertex.position)).or_insert((Vector3::zero(), F::zero(), 0));
+            entry.0 += normal.normalize();
+            entry.1 += height.get_value(&context);
+            entry.2 += 1;
+        }
+
+        for vertex in mesh.vertices.iter_mut() {
+            let Some(&(normal, height, count)) = offsets.get(&get_position_key(vertex.position)) else {
+                continue;
+            };
+            if normal.magnitude2() > F::zero() {
+                vertex.position += normal.normalize() * (height / F::from(count).unwrap() * scale);
+            }
+        }
+
+        // vertices at the same position share the normal, so uv seams do not show up in the shading
+        mesh.generate_normals(NormalWeighting::Area, get_pi());
+        mesh.generate_tangents()?;
+        Ok(mesh)
+    }
+}
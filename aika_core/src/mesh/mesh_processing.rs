@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use anyhow::Result;
+use cgmath::{BaseFloat, InnerSpace, Vector2, Vector3, Vector4};
+use num_traits::Zero;
+use aika_math::utils::get_corner_angle;
+use crate::mesh::{CommonVertex, Mesh};
+
+/// How the normals of the faces around a vertex are weighted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalWeighting {
+    /// by the area of the face, large faces dominate
+    Area,
+    /// by the angle of the face at the vertex, independent of how the faces are triangulated
+    Angle,
+}
+
+/// When normals are generated by `Mesh::process`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalGeneration {
+    Never,
+    /// only if any vertex has no normal
+    IfMissing,
+    Always,
+}
+
+pub struct MeshProcessingOptions<F> {
+    pub remove_degenerate_triangles: bool,
+    /// vertices closer than this, with the same attributes, are merged. None keeps the vertices
+    pub weld_epsilon: Option<F>,
+    pub generate_normals: NormalGeneration,
+    pub normal_weighting: NormalWeighting,
+    /// in radians, faces meeting at a larger angle keep a hard edge
+    pub crease_angle: F,
+}
+
+impl<F> Default for MeshProcessingOptions<F> where F: BaseFloat {
+    fn default() -> Self {
+        MeshProcessingOptions {
+            remove_degenerate_triangles: true,
+            weld_epsilon: None,
+            generate_normals: NormalGeneration::IfMissing,
+            normal_weighting: NormalWeighting::Angle,
+            crease_angle: F::from(30.0f64.to_radians()).unwrap(),
+        }
+    }
+}
+
+/// Problems found in a mesh, see `Mesh::validate`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MeshValidationReport {
+    pub vertex_count: usize,
+    pub triangle_count: usize,
+    /// triangles referring to a vertex which does not exist
+    pub out_of_range_triangles: usize,
+    /// triangles with a repeated vertex or without area
+    pub degenerate_triangles: usize,
+    /// triangles with a NaN or infinite position
+    pub non_finite_triangles: usize,
+    /// vertices not used by any triangle
+    pub unused_vertices: usize,
+    pub missing_normals: usize,
+    pub missing_uvs: usize,
+    /// sub meshes whose range is reversed or out of the triangles
+    pub invalid_sub_meshes: usize,
+}
+
+impl MeshValidationReport {
+    /// Whether the mesh can be rendered. Unused vertices and missing attributes are allowed
+    pub fn is_valid(&self) -> bool {
+        self.out_of_range_triangles == 0
+            && self.degenerate_triangles == 0
+            && self.non_finite_triangles == 0
+            && self.invalid_sub_meshes == 0
+    }
+}
+
+impl Display for MeshValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "vertices: {}, triangles: {}", self.vertex_count, self.triangle_count)?;
+        writeln!(f, "out of range triangles: {}", self.out_of_range_triangles)?;
+        writeln!(f, "degenerate triangles: {}", self.degenerate_triangles)?;
+        writeln!(f, "non finite triangles: {}", self.non_finite_triangles)?;
+        writeln!(f, "unused vertices: {}", self.unused_vertices)?;
+        writeln!(f, "missing normals: {}, missing uvs: {}", self.missing_normals, self.missing_uvs)?;
+        write!(f, "invalid sub meshes: {}", self.invalid_sub_meshes)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TriangleProblem {
+    OutOfRange,
+    NonFinite,
+    Degenerate,
+}
+
+/// Exact key of a position, vertices split by attributes share it
+pub(crate) fn get_position_key<F>(p: Vector3<F>) -> [u64; 3] where F: BaseFloat {
+    [p.x, p.y, p.z].map(|x| x.to_f64().unwrap().to_bits())
+}
+
+fn is_close2<F>(a: Option<Vector2<F>>, b: Option<Vector2<F>>, epsilon: F) -> bool where F: BaseFloat {
+    match (a, b) {
+        (Some(a), Some(b)) => (a - b).magnitude2() <= epsilon * epsilon,
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn is_close3<F>(a: Option<Vector3<F>>, b: Option<Vector3<F>>, epsilon: F) -> bool where F: BaseFloat {
+    match (a, b) {
+        (Some(a), Some(b)) => (a - b).magnitude2() <= epsilon * epsilon,
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn is_close4<F>(a: Option<Vector4<F>>, b: Option<Vector4<F>>, epsilon: F) -> bool where F: BaseFloat {
+    match (a, b) {
+        (Some(a), Some(b)) => (a - b).magnitude2() <= epsilon * epsilon,
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Whether two vertices can be merged into one
+fn can_weld<F>(a: &CommonVertex<F>, b: &CommonVertex<F>, epsilon: F) -> bool where F: BaseFloat {
+    (a.position - b.position).magnitude2() <= epsilon * epsilon
+        && is_close3(a.normal, b.normal, epsilon)
+        && is_close2(a.uv0, b.uv0, epsilon)
+        && is_close2(a.uv1, b.uv1, epsilon)
+        && is_close4(a.tangent, b.tangent, epsilon)
+        && is_close3(a.color, b.color, epsilon)
+}
+
+impl<F> Mesh<Vec<CommonVertex<F>>> where F: BaseFloat {
+    fn get_triangle_problem(&self, triangle: [usize; 3]) -> Option<TriangleProblem> {
+        if triangle.iter().any(|&i| i >= self.vertices.len()) {
+            return Some(TriangleProblem::OutOfRange);
+        }
+        let p = triangle.map(|i| self.vertices[i].position);
+        if p.iter().any(|p| !(p.x.is_finite() && p.y.is_finite() && p.z.is_finite())) {
+            return Some(TriangleProblem::NonFinite);
+        }
+        let [a, b, c] = triangle;
+        if a == b || b == c || c == a || (p[1] - p[0]).cross(p[2] - p[0]).magnitude2() == F::zero() {
+            return Some(TriangleProblem::Degenerate);
+        }
+        None
+    }
+
+    pub fn validate(&self) -> MeshValidationReport {
+        let mut report = MeshValidationReport {
+            vertex_count: self.vertices.len(),
+            triangle_count: self.triangles.len(),
+            ..Default::default()
+        };
+
+        let mut used = vec![false; self.vertices.len()];
+        for &triangle in self.triangles.iter() {
+            match self.get_triangle_problem(triangle) {
+                Some(TriangleProblem::OutOfRange) => report.out_of_range_triangles += 1,
+                Some(TriangleProblem::NonFinite) => report.non_finite_triangles += 1,
+                Some(TriangleProblem::Degenerate) => report.degenerate_triangles += 1,
+                None => {},
+            }
+            for i in triangle {
+                if i < used.len() {
+                    used[i] = true;
+                }
+            }
+        }
+
+        report.unused_vertices = used.iter().filter(|&&u| !u).count();
+        report.missing_normals = self.vertices.iter().filter(|v| v.normal.is_none()).count();
+        report.missing_uvs = self.vertices.iter().filter(|v| v.uv0.is_none()).count();
+        report.invalid_sub_meshes = self.sub_mesh.iter()
+            .filter(|&&[a, b]| a > b || b > self.triangles.len())
+            .count();
+
+        report
+    }
+
+    /// Remove triangles which are out of range, have a NaN or infinite position, or have no area.
+    /// The sub mesh ranges are adjusted, returns the number of removed triangles
+    pub fn remove_degenerate_triangles(&mut self) -> usize {
+        let keep: Vec<bool> = self.triangles.iter().map(|&t| self.get_triangle_problem(t).is_none()).collect();
+
+        // new_start[i] is the new index of the first kept triangle at or after i
+        let mut new_start = Vec::with_capacity(self.triangles.len() + 1);
+        let mut count = 0;
+        for &k in keep.iter() {
+            new_start.push(count);
+            if k {
+                count += 1;
+            }
+        }
+        new_start.push(count);
+
+        let removed = self.triangles.len() - count;
+        let triangle_count = self.triangles.len();
+        for range in self.sub_mesh.iter_mut() {
+            let a = range[0].min(triangle_count);
+            let b = range[1].min(triangle_count).max(a);
+            *range = [new_start[a], new_start[b]];
+        }
+        let mut keep_iter = keep.iter();
+        self.triangles.retain(|_| *keep_iter.next().unwrap());
+
+        removed
+    }
+
+    /// Merge vertices closer than `epsilon` whose attributes also differ by at most `epsilon`,
+    /// and drop the vertices not used by any triangle. Returns the number of removed vertices
+    pub fn weld_vertices(&mut self, epsilon: F) -> usize {
+        let cell_size = if epsilon > F::zero() { epsilon } else { F::one() };
+        let get_cell = |p: Vector3<F>| [p.x, p.y, p.z].map(|x| (x / cell_size).floor().to_i64().unwrap_or(i64::MAX));
+
+        let mut used = vec![false; self.vertices.len()];
+        for triangle in self.triangles.iter() {
+            for &i in triangle.iter() {
+                if i < used.len() {
+                    used[i] = true;
+                }
+            }
+        }
+
+        let mut new_vertices: Vec<CommonVertex<F>> = Vec::new();
+        let mut cells: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+        let mut remap = vec![usize::MAX; self.vertices.len()];
+        for (i, vertex) in self.vertices.iter().enumerate() {
+            if !used[i] {
+                continue;
+            }
+            let cell = get_cell(vertex.position);
+            let mut found = None;
+            // a close vertex may lie in a neighbouring cell
+            'search: for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        let neighbour = [cell[0].saturating_add(dx), cell[1].saturating_add(dy), cell[2].saturating_add(dz)];
+                        let Some(candidates) = cells.get(&neighbour) else {
+                            continue;
+                        };
+                        if let Some(&j) = candidates.iter().find(|&&j| can_weld(&new_vertices[j], vertex, epsilon)) {
+                            found = Some(j);
+                            break 'search;
+                        }
+                    }
+                }
+            }
+
+            remap[i] = match found {
+                Some(j) => j,
+                None => {
+                    new_vertices.push(vertex.clone());
+                    cells.entry(cell).or_default().push(new_vertices.len() - 1);
+                    new_vertices.len() - 1
+                },
+            };
+        }
+
+        for triangle in self.triangles.iter_mut() {
+            for i in triangle.iter_mut() {
+                // out of range indices stay out of range
+                *i = remap.get(*i).copied().unwrap_or(usize::MAX);
+            }
+        }
+
+        let removed = self.vertices.len() - new_vertices.len();
+        self.vertices = new_vertices;
+        removed
+    }
+
+    /// Smooth vertex normals from the normals of the faces around each position.
+    /// Only faces within `crease_angle` (radians) of the face of a corner are averaged,
+    /// vertices on a crease are split. Degenerate triangles do not contribute
+    pub fn generate_normals(&mut self, weighting: NormalWeighting, crease_angle: F) {
+        let face_normals: Vec<Option<Vector3<F>>> = self.triangles.iter().map(|&t| {
+            if self.get_triangle_problem(t).is_some() {
+                return None;
+            }
+            let p = t.map(|i| self.vertices[i].position);
+            let n = (p[1] - p[0]).cross(p[2] - p[0]);
+            Some(n)
+        }).collect();
+
+        // corners of the faces around each position, with the weight of the face at the corner
+        let mut position_faces: HashMap<[u64; 3], Vec<(usize, F)>> = HashMap::new();
+        for (face, triangle) in self.triangles.iter().enumerate() {
+            let Some(n) = face_normals[face] else {
+                continue;
+            };
+            let p = triangle.map(|i| self.vertices[i].position);
+            for corner in 0..3 {
+                let weight = match weighting {
+                    // the length of the cross product is twice the area
+                    NormalWeighting::Area => n.magnitude(),
+                    NormalWeighting::Angle => get_corner_angle(p, corner),
+                };
+                position_faces.entry(get_position_key(p[corner])).or_default().push((face, weight));
+            }
+        }
+
+        let cos_crease = crease_angle.cos();
+        let mut new_vertices: Vec<CommonVertex<F>> = Vec::new();
+        let mut split: HashMap<(usize, [u64; 3]), usize> = HashMap::new();
+        let mut new_triangles = self.triangles.clone();
+        for (face, triangle) in self.triangles.iter().enumerate() {
+            for corner in 0..3 {
+                let vertex_index = triangle[corner];
+                let Some(vertex) = self.vertices.get(vertex_index) else {
+                    // out of range indices stay out of range
+                    new_triangles[face][corner] = usize::MAX;
+                    continue;
+                };
+                let mut vertex = vertex.clone();
+
+                if let Some(face_normal) = face_normals[face] {
+                    let face_normal = face_normal.normalize();
+                    let mut sum = Vector3::zero();
+                    for &(other, weight) in position_faces[&get_position_key(vertex.position)].iter() {
+                        let other_normal = face_normals[other].unwrap().normalize();
+                        if other_normal.dot(face_normal) >= cos_crease {
+                            sum += other_normal * weight;
+                        }
+                    }
+                    vertex.normal = Some(if sum.magnitude2() > F::zero() { sum.normalize() } else { face_normal });
+                }
+
+                let key = (vertex_index, vertex.normal.map(get_position_key).unwrap_or([0; 3]));
+                new_triangles[face][corner] = *split.entry(key).or_insert_with(|| {
+                    new_vertices.push(vertex);
+                    new_vertices.len() - 1
+                });
+            }
+        }
+
+        self.vertices = new_vertices;
+        self.triangles = new_triangles;
+    }
+
+    /// Clean up the mesh as configured in `options`, then validate it.
+    /// Tangents are generated again if every vertex has uv0 and no triangle is out of range
+    pub fn process(&mut self, options: &MeshProcessingOptions<F>) -> Result<MeshValidationReport> {
+        if options.remove_degenerate_triangles {
+            self.remove_degenerate_triangles();
+        }
+        if let Some(epsilon) = options.weld_epsilon {
+            self.weld_vertices(epsilon);
+        }
+
+        let generate_normals = match options.generate_normals {
+            NormalGeneration::Never => false,
+            NormalGeneration::IfMissing => self.vertices.iter().any(|v| v.normal.is_none()),
+            NormalGeneration::Always => true,
+        };
+        if generate_normals {
+            self.generate_normals(options.normal_weighting, options.crease_angle);
+        }
+
+        let report = self.validate();
+        if report.out_of_range_triangles == 0 && report.missing_uvs == 0 && !self.vertices.is_empty() {
+            self.generate_tangents()?;
+            return Ok(self.validate());
+        }
+
+        Ok(report)
+    }
+
+    /// A copy of the mesh with `transform` applied, such as object space to world space.
+    /// Normals use the inverse transpose, and a mirroring transform reverses the winding
+    /// so that the triangles still face the side of their normals
+    pub fn transform(&self, transform: &aika_math::Transform<F>) -> Self {
+        let vertices = self.vertices.iter().map(|v| {
+            let mut result = v.clone();
+            result.position = transform.transform_point(v.position);
+            let normal = v.normal.map(|n| {
+                let t = transform.transform_normal(n).unwrap_or(n);
+                if t.magnitude2() > F::zero() { t.normalize() } else { t }
+            });
+            result.normal = normal;
+            result.tangent = v.tangent.map(|t| {
+                let tangent = transform.transform_vector(t.truncate());
+                let tangent = if tangent.magnitude2() > F::zero() { tangent.normalize() } else { tangent };
+                // keep the bitangent pointing the same way after the transform
+                let w = match (v.normal, normal) {
+                    (Some(n), Some(new_normal)) => {
+                        let bitangent = transform.transform_vector(n.cross(t.truncate()) * t.w);
+                        if new_normal.cross(tangent).dot(bitangent) < F::zero() { -F::one() } else { F::one() }
+                    },
+                    _ => t.w,
+                };
+                tangent.extend(w)
+            });
+            result
+        }).collect();
+
+        let triangles = if transform.swaps_handedness() {
+            self.triangles.iter().map(|&[a, b, c]| [a, c, b]).collect()
+        } else {
+            self.triangles.clone()
+        };
+
+        Mesh {
+            vertices,
+            triangles,
+            sub_mesh: self.sub_mesh.clone(),
+        }
+    }
+}
@@ -0,0 +1,515 @@
+use std::collections::HashMap;
+use anyhow::{bail, Result};
+use cgmath::{BaseFloat, InnerSpace, Vector2, Vector3, VectorSpace, Zero};
+use aika_math::utils::get_pi;
+use crate::mesh::{CommonVertex, PolygonMesh};
+use crate::mesh::mesh_processing::get_position_key;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubdivisionScheme {
+    /// Any polygons, every step turns a polygon of n sides into n quads
+    CatmullClark,
+    /// Triangles only, every step splits a triangle into 4
+    Loop,
+}
+
+fn get_edge_key(a: usize, b: usize) -> [usize; 2] {
+    [a.min(b), a.max(b)]
+}
+
+struct SubdivisionEdge<F> {
+    positions: [usize; 2],
+    faces: Vec<usize>,
+    /// the sharpness of the crease, see `get_sharpness`
+    crease: F,
+}
+
+impl<F> SubdivisionEdge<F> where F: BaseFloat {
+    /// Boundary and non-manifold edges are always sharp
+    fn get_sharpness(&self) -> F {
+        if self.faces.len() == 2 { self.crease } else { F::infinity() }
+    }
+
+    fn get_other(&self, position: usize) -> usize {
+        if self.positions[0] == position { self.positions[1] } else { self.positions[0] }
+    }
+}
+
+/// The connectivity of the positions of a polygon mesh.
+/// Vertices which are split by their attributes, like at uv seams, share a position
+struct Topology<F> {
+    /// the position of each vertex
+    vertex_position: Vec<usize>,
+    positions: Vec<Vector3<F>>,
+    edges: Vec<SubdivisionEdge<F>>,
+    edge_map: HashMap<[usize; 2], usize>,
+    position_edges: Vec<Vec<usize>>,
+    position_faces: Vec<Vec<usize>>,
+}
+
+impl<F> Topology<F> where F: BaseFloat {
+    fn new(mesh: &PolygonMesh<F>) -> Result<Self> {
+        let mut position_map: HashMap<[u64; 3], usize> = HashMap::new();
+        let mut positions = Vec::new();
+        let vertex_position: Vec<usize> = mesh.vertices.iter().map(|v| {
+            *position_map.entry(get_position_key(v.position)).or_insert_with(|| {
+                positions.push(v.position);
+                positions.len() - 1
+            })
+        }).collect();
+
+        let mut topology = Topology {
+            vertex_position,
+            positions,
+            edges: Vec::new(),
+            edge_map: HashMap::new(),
+            position_edges: Vec::new(),
+            position_faces: Vec::new(),
+        };
+        topology.position_edges.resize(topology.positions.len(), Vec::new());
+        topology.position_faces.resize(topology.positions.len(), Vec::new());
+
+        for (face_index, face) in mesh.faces.iter().enumerate() {
+            if face.len() < 3 {
+                bail!("polygon {} has {} vertices", face_index, face.len());
+            }
+            if face.iter().any(|&i| i >= mesh.vertices.len()) {
+                bail!("polygon {} references a vertex out of range", face_index);
+            }
+            for i in 0..face.len() {
+                let a = topology.vertex_position[face[i]];
+                let b = topology.vertex_position[face[(i + 1) % face.len()]];
+                if a == b {
+                    bail!("polygon {} has a degenerate edge", face_index);
+                }
+                topology.position_faces[a].push(face_index);
+
+                let edge_index = match topology.edge_map.get(&get_edge_key(a, b)) {
+                    Some(&e) => e,
+                    None => {
+                        topology.edges.push(SubdivisionEdge {
+                            positions: [a, b],
+                            faces: Vec::new(),
+                            crease: F::zero(),
+                        });
+                        let e = topology.edges.len() - 1;
+                        topology.edge_map.insert(get_edge_key(a, b), e);
+                        topology.position_edges[a].push(e);
+                        topology.position_edges[b].push(e);
+                        e
+                    }
+                };
+                topology.edges[edge_index].faces.push(face_index);
+            }
+        }
+
+        for &([a, b], sharpness) in mesh.creases.iter() {
+            let (Some(&a), Some(&b)) = (topology.vertex_position.get(a), topology.vertex_position.get(b)) else {
+                continue;
+            };
+            if let Some(&e) = topology.edge_map.get(&get_edge_key(a, b)) {
+                topology.edges[e].crease = topology.edges[e].crease.max(sharpness);
+            }
+        }
+
+        Ok(topology)
+    }
+
+    fn get_edge_index(&self, vertex_a: usize, vertex_b: usize) -> usize {
+        self.edge_map[&get_edge_key(self.vertex_position[vertex_a], self.vertex_position[vertex_b])]
+    }
+
+    /// Blend the smooth rule of an edge towards its midpoint by the sharpness
+    fn get_edge_point(&self, edge: usize, smooth: Vector3<F>) -> Vector3<F> {
+        let e = &self.edges[edge];
+        let [a, b] = e.positions;
+        let midpoint = (self.positions[a] + self.positions[b]) * F::from(0.5).unwrap();
+        let sharpness = e.get_sharpness();
+        if sharpness <= F::zero() {
+            smooth
+        } else {
+            smooth.lerp(midpoint, sharpness.min(F::one()))
+        }
+    }
+
+    /// Blend the smooth rule of a position towards the crease rule (2 sharp edges) or the corner rule (more).
+    /// Boundary positions with only 2 edges are corners
+    fn get_vertex_point(&self, position: usize, smooth: Vector3<F>) -> Vector3<F> {
+        let s = self.positions[position];
+        let edges = &self.position_edges[position];
+        if edges.len() == 2 && edges.iter().all(|&e| self.edges[e].faces.len() < 2) {
+            return s;
+        }
+
+        let sharp: Vec<&SubdivisionEdge<F>> = edges.iter()
+            .map(|&e| &self.edges[e])
+            .filter(|e| e.get_sharpness() > F::zero())
+            .collect();
+        if sharp.len() < 2 {
+            return smooth;
+        }
+        let rule = if sharp.len() == 2 {
+            let e1 = self.positions[sharp[0].get_other(position)];
+            let e2 = self.positions[sharp[1].get_other(position)];
+            (e1 + s * F::from(6).unwrap() + e2) / F::from(8).unwrap()
+        } else {
+            s
+        };
+        let weight = sharp.iter().map(|e| e.get_sharpness().min(F::one())).fold(F::zero(), |a, b| a + b)
+            / F::from(sharp.len()).unwrap();
+        smooth.lerp(rule, weight)
+    }
+}
+
+/// The kind of a vertex of the subdivided mesh, with the vertex indices of the coarse mesh it comes from.
+/// Vertices of different attributes at the same position stay apart
+#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+enum ChildVertexKey {
+    Vertex(usize),
+    Edge([usize; 2]),
+    Face(usize),
+}
+
+fn average_option<F, T>(values: impl Iterator<Item = Option<T>>, count: usize) -> Option<T>
+where
+    F: BaseFloat,
+    T: VectorSpace<Scalar = F>
+{
+    let mut sum = T::zero();
+    for v in values {
+        sum = sum + v?;
+    }
+    Some(sum / F::from(count).unwrap())
+}
+
+/// The attributes are interpolated linearly, the normals and tangents are computed again after the subdivision
+fn average_attributes<F>(vertices: &[&CommonVertex<F>]) -> CommonVertex<F> where F: BaseFloat {
+    let n = vertices.len();
+    let mut v = CommonVertex::new();
+    v.uv0 = average_option::<F, Vector2<F>>(vertices.iter().map(|v| v.uv0), n);
+    v.uv1 = average_option::<F, Vector2<F>>(vertices.iter().map(|v| v.uv1), n);
+    v.color = average_option::<F, Vector3<F>>(vertices.iter().map(|v| v.color), n);
+    v
+}
+
+struct ChildMeshBuilder<F> {
+    vertices: Vec<CommonVertex<F>>,
+    vertex_map: HashMap<ChildVertexKey, usize>,
+    faces: Vec<Vec<usize>>,
+}
+
+impl<F> ChildMeshBuilder<F> where F: BaseFloat {
+    fn new() -> Self {
+        ChildMeshBuilder {
+            vertices: Vec::new(),
+            vertex_map: HashMap::new(),
+            faces: Vec::new(),
+        }
+    }
+
+    fn get_vertex<G>(&mut self, key: ChildVertexKey, position: Vector3<F>, get_attributes: G) -> usize where G: FnOnce() -> CommonVertex<F> {
+        *self.vertex_map.entry(key).or_insert_with(|| {
+            let mut v = get_attributes();
+            v.position = position;
+            v.normal = None;
+            v.tangent = None;
+            self.vertices.push(v);
+            self.vertices.len() - 1
+        })
+    }
+
+    fn get_corner(&mut self, mesh: &PolygonMesh<F>, vertex: usize, position: Vector3<F>) -> usize {
+        self.get_vertex(ChildVertexKey::Vertex(vertex), position, || mesh.vertices[vertex].clone())
+    }
+
+    fn get_edge(&mut self, mesh: &PolygonMesh<F>, a: usize, b: usize, position: Vector3<F>) -> usize {
+        self.get_vertex(ChildVertexKey::Edge(get_edge_key(a, b)), position, || {
+            average_attributes(&[&mesh.vertices[a], &mesh.vertices[b]])
+        })
+    }
+
+    /// The creases of the coarse mesh lose one of sharpness, each one becomes two edges
+    fn get_child_creases(&self, topology: &Topology<F>) -> Vec<([usize; 2], F)> {
+        let mut position_vertex = HashMap::new();
+        let mut edge_vertex = HashMap::new();
+        for (key, &v) in self.vertex_map.iter() {
+            match *key {
+                ChildVertexKey::Vertex(old) => {
+                    position_vertex.insert(topology.vertex_position[old], v);
+                },
+                ChildVertexKey::Edge([a, b]) => {
+                    edge_vertex.insert(topology.get_edge_index(a, b), v);
+                },
+                ChildVertexKey::Face(_) => {},
+            }
+        }
+
+        let mut creases = Vec::new();
+        for (edge_index, edge) in topology.edges.iter().enumerate() {
+            if edge.faces.len() != 2 || edge.crease <= F::zero() {
+                continue;
+            }
+            let sharpness = edge.crease - F::one();
+            if sharpness <= F::zero() {
+                continue;
+            }
+            let (Some(&middle), Some(&a), Some(&b)) = (
+                edge_vertex.get(&edge_index),
+                position_vertex.get(&edge.positions[0]),
+                position_vertex.get(&edge.positions[1])
+            ) else {
+                continue;
+            };
+            creases.push(([a, middle], sharpness));
+            creases.push(([middle, b], sharpness));
+        }
+        creases
+    }
+
+    /// `child_counts[i]` is the number of polygons which come from the coarse polygon i
+    fn finish(self, mesh: &PolygonMesh<F>, topology: &Topology<F>, child_counts: &[usize]) -> PolygonMesh<F> {
+        let mut new_start = vec![0];
+        for &count in child_counts.iter() {
+            new_start.push(new_start.last().unwrap() + count);
+        }
+        let face_count = mesh.faces.len();
+        let creases = self.get_child_creases(topology);
+
+        PolygonMesh {
+            vertices: self.vertices,
+            faces: self.faces,
+            sub_mesh: mesh.sub_mesh.iter().map(|&[a, b]| [new_start[a.min(face_count)], new_start[b.min(face_count)]]).collect(),
+            creases,
+        }
+    }
+}
+
+fn catmull_clark_step<F>(mesh: &PolygonMesh<F>) -> Result<PolygonMesh<F>> where F: BaseFloat {
+    let topology = Topology::new(mesh)?;
+    let p = &topology.positions;
+    let face_points: Vec<Vector3<F>> = mesh.faces.iter().map(|face| {
+        face.iter().map(|&v| p[topology.vertex_position[v]]).fold(Vector3::zero(), |a, b| a + b) / F::from(face.len()).unwrap()
+    }).collect();
+
+    let edge_points: Vec<Vector3<F>> = topology.edges.iter().enumerate().map(|(i, e)| {
+        let [a, b] = e.positions;
+        let smooth = if e.faces.len() == 2 {
+            (p[a] + p[b] + face_points[e.faces[0]] + face_points[e.faces[1]]) / F::from(4).unwrap()
+        } else {
+            (p[a] + p[b]) / F::from(2).unwrap()
+        };
+        topology.get_edge_point(i, smooth)
+    }).collect();
+
+    let vertex_points: Vec<Vector3<F>> = (0..p.len()).map(|i| {
+        let edges = &topology.position_edges[i];
+        let faces = &topology.position_faces[i];
+        let n = edges.len();
+        let smooth = if n >= 3 && !faces.is_empty() {
+            let q = faces.iter().map(|&f| face_points[f]).fold(Vector3::zero(), |a, b| a + b) / F::from(faces.len()).unwrap();
+            let r = edges.iter().map(|&e| {
+                let [a, b] = topology.edges[e].positions;
+                (p[a] + p[b]) / F::from(2).unwrap()
+            }).fold(Vector3::zero(), |a, b| a + b) / F::from(n).unwrap();
+            let n_f = F::from(n).unwrap();
+            (q + r * F::from(2).unwrap() + p[i] * (n_f - F::from(3).unwrap())) / n_f
+        } else {
+            p[i]
+        };
+        topology.get_vertex_point(i, smooth)
+    }).collect();
+
+    let mut builder = ChildMeshBuilder::new();
+    for (face_index, face) in mesh.faces.iter().enumerate() {
+        let n = face.len();
+        let face_vertex = builder.get_vertex(ChildVertexKey::Face(face_index), face_points[face_index], || {
+            let corners: Vec<&CommonVertex<F>> = face.iter().map(|&v| &mesh.vertices[v]).collect();
+            average_attributes(&corners)
+        });
+        for i in 0..n {
+            let (prev, v, next) = (face[(i + n - 1) % n], face[i], face[(i + 1) % n]);
+            let corner = builder.get_corner(mesh, v, vertex_points[topology.vertex_position[v]]);
+            let edge_next = builder.get_edge(mesh, v, next, edge_points[topology.get_edge_index(v, next)]);
+            let edge_prev = builder.get_edge(mesh, prev, v, edge_points[topology.get_edge_index(prev, v)]);
+            builder.faces.push(vec![corner, edge_next, face_vertex, edge_prev]);
+        }
+    }
+
+    let child_counts: Vec<usize> = mesh.faces.iter().map(|f| f.len()).collect();
+    Ok(builder.finish(mesh, &topology, &child_counts))
+}
+
+fn loop_step<F>(mesh: &PolygonMesh<F>) -> Result<PolygonMesh<F>> where F: BaseFloat {
+    if !mesh.is_triangle_mesh() {
+        bail!("loop subdivision needs a triangle mesh");
+    }
+    let topology = Topology::new(mesh)?;
+    let p = &topology.positions;
+
+    let edge_points: Vec<Vector3<F>> = topology.edges.iter().enumerate().map(|(i, e)| {
+        let [a, b] = e.positions;
+        let smooth = if e.faces.len() == 2 {
+            // the corners of the two triangles across the edge
+            let opposite: Vector3<F> = e.faces.iter().map(|&f| {
+                let c = mesh.faces[f].iter()
+                    .map(|&v| topology.vertex_position[v])
+                    .find(|&x| x != a && x != b)
+                    .unwrap();
+                p[c]
+            }).fold(Vector3::zero(), |x, y| x + y);
+            (p[a] + p[b]) * F::from(0.375).unwrap() + opposite * F::from(0.125).unwrap()
+        } else {
+            (p[a] + p[b]) / F::from(2).unwrap()
+        };
+        topology.get_edge_point(i, smooth)
+    }).collect();
+
+    let vertex_points: Vec<Vector3<F>> = (0..p.len()).map(|i| {
+        let edges = &topology.position_edges[i];
+        let n = edges.len();
+        let smooth = if n >= 3 {
+            // the weight of the original paper of Loop
+            let n_f = F::from(n).unwrap();
+            let c = F::from(0.375).unwrap() + F::from(0.25).unwrap() * (F::from(2).unwrap() * get_pi::<F>() / n_f).cos();
+            let beta = (F::from(0.625).unwrap() - c * c) / n_f;
+            let neighbors = edges.iter()
+                .map(|&e| p[topology.edges[e].get_other(i)])
+                .fold(Vector3::zero(), |a, b| a + b);
+            p[i] * (F::one() - n_f * beta) + neighbors * beta
+        } else {
+            p[i]
+        };
+        topology.get_vertex_point(i, smooth)
+    }).collect();
+
+    let mut builder = ChildMeshBuilder::new();
+    for face in mesh.faces.iter() {
+        let corners: Vec<usize> = face.iter()
+            .map(|&v| builder.get_corner(mesh, v, vertex_points[topology.vertex_position[v]]))
+            .collect();
+        let edges: Vec<usize> = (0..3).map(|i| {
+            let (a, b) = (face[i], face[(i + 1) % 3]);
+            builder.get_edge(mesh, a, b, edge_points[topology.get_edge_index(a, b)])
+        }).collect();
+        builder.faces.push(vec![corners[0], edges[0], edges[2]]);
+        builder.faces.push(vec![edges[0], corners[1], edges[1]]);
+        builder.faces.push(vec![edges[2], edges[1], corners[2]]);
+        builder.faces.push(vec![edges[0], edges[1], edges[2]]);
+    }
+
+    let child_counts = vec![4; mesh.faces.len()];
+    Ok(builder.finish(mesh, &topology, &child_counts))
+}
+
+fn get_polygon_normal<F>(points: &[Vector3<F>]) -> Vector3<F> where F: BaseFloat {
+    // Newell's method, also for polygons which are not planar
+    let mut n = Vector3::zero();
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        n += Vector3::new((a.y - b.y) * (a.z + b.z), (a.z - b.z) * (a.x + b.x), (a.x - b.x) * (a.y + b.y));
+    }
+    n
+}
+
+fn find_root(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+impl<F> PolygonMesh<F> where F: BaseFloat {
+    /// Smooth normals weighted by the corner angles, which are split across the sharp edges.
+    /// Vertices whose corners fall into different smooth regions are split
+    fn generate_subdivision_normals(&mut self) -> Result<()> {
+        let topology = Topology::new(self)?;
+        let p = &topology.positions;
+
+        // the corners are numbered face by face
+        let mut corner_start = Vec::with_capacity(self.faces.len());
+        let mut corner_count = 0;
+        for face in self.faces.iter() {
+            corner_start.push(corner_count);
+            corner_count += face.len();
+        }
+        let find_corner = |face: usize, position: usize| {
+            let i = self.faces[face].iter().position(|&v| topology.vertex_position[v] == position).unwrap();
+            corner_start[face] + i
+        };
+
+        let mut parent: Vec<usize> = (0..corner_count).collect();
+        for edge in topology.edges.iter() {
+            if edge.faces.len() != 2 || edge.get_sharpness() > F::zero() {
+                continue;
+            }
+            for &position in edge.positions.iter() {
+                let a = find_root(&mut parent, find_corner(edge.faces[0], position));
+                let b = find_root(&mut parent, find_corner(edge.faces[1], position));
+                parent[a] = b;
+            }
+        }
+
+        let mut group_normals: Vec<Vector3<F>> = vec![Vector3::zero(); corner_count];
+        let mut face_normals = Vec::with_capacity(self.faces.len());
+        for (face_index, face) in self.faces.iter().enumerate() {
+            let points: Vec<Vector3<F>> = face.iter().map(|&v| p[topology.vertex_position[v]]).collect();
+            let face_normal = get_polygon_normal(&points);
+            let face_normal = if face_normal.magnitude2() > F::zero() { face_normal.normalize() } else { face_normal };
+            face_normals.push(face_normal);
+            let n = face.len();
+            for i in 0..n {
+                let a = points[(i + n - 1) % n] - points[i];
+                let b = points[(i + 1) % n] - points[i];
+                let denom = (a.magnitude2() * b.magnitude2()).sqrt();
+                let angle = if denom > F::zero() { (a.dot(b) / denom).max(-F::one()).min(F::one()).acos() } else { F::zero() };
+                let root = find_root(&mut parent, corner_start[face_index] + i);
+                group_normals[root] += face_normal * angle;
+            }
+        }
+
+        let mut new_vertices: Vec<CommonVertex<F>> = Vec::new();
+        let mut split: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut first_split: HashMap<usize, usize> = HashMap::new();
+        let mut faces = self.faces.clone();
+        for (face_index, face) in self.faces.iter().enumerate() {
+            for (i, &v) in face.iter().enumerate() {
+                let root = find_root(&mut parent, corner_start[face_index] + i);
+                faces[face_index][i] = *split.entry((v, root)).or_insert_with(|| {
+                    let mut vertex = self.vertices[v].clone();
+                    let n = group_normals[root];
+                    vertex.normal = Some(if n.magnitude2() > F::zero() { n.normalize() } else { face_normals[face_index] });
+                    new_vertices.push(vertex);
+                    first_split.entry(v).or_insert(new_vertices.len() - 1);
+                    new_vertices.len() - 1
+                });
+            }
+        }
+
+        self.creases = self.creases.iter()
+            .filter_map(|&([a, b], s)| Some(([*first_split.get(&a)?, *first_split.get(&b)?], s)))
+            .collect();
+        self.vertices = new_vertices;
+        self.faces = faces;
+        Ok(())
+    }
+
+    /// `levels` steps of the scheme. Face-varying attributes such as the uv and the color are interpolated linearly,
+    /// and the normals are computed from the subdivided surface, smooth except across the sharp edges.
+    /// Boundary edges are sharp, and boundary vertices with only two edges stay at their place
+    pub fn subdivide(&self, scheme: SubdivisionScheme, levels: usize) -> Result<PolygonMesh<F>> {
+        let mut mesh = self.clone();
+        if levels == 0 {
+            return Ok(mesh);
+        }
+        for _ in 0..levels {
+            mesh = match scheme {
+                SubdivisionScheme::CatmullClark => catmull_clark_step(&mesh)?,
+                SubdivisionScheme::Loop => loop_step(&mesh)?,
+            };
+        }
+        mesh.generate_subdivision_normals()?;
+        Ok(mesh)
+    }
+}
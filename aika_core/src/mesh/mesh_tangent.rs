@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use anyhow::{bail, Result};
+use cgmath::{BaseFloat, InnerSpace, Vector3, Vector4};
+use num_traits::Zero;
+use aika_math::utils::{get_corner_angle, get_orthogonal};
+use crate::mesh::{CommonVertex, Mesh};
+
+/// Tangent of a triangle from the derivatives of the positions over uv0.
+/// Returns the unnormalized tangent, and whether the uv mapping preserves the orientation
+fn get_face_tangent<F>(p: [Vector3<F>; 3], uv: [cgmath::Vector2<F>; 3]) -> Option<(Vector3<F>, bool)> where F: BaseFloat {
+    let e1 = p[1] - p[0];
+    let e2 = p[2] - p[0];
+    let duv1 = uv[1] - uv[0];
+    let duv2 = uv[2] - uv[0];
+    let r = duv1.x * duv2.y - duv2.x * duv1.y;
+    if r == F::zero() {
+        return None;
+    }
+    let t = (e1 * duv2.y - e2 * duv1.y) / r;
+    if t.magnitude2() == F::zero() || !t.magnitude2().is_finite() {
+        return None;
+    }
+    Some((t, r > F::zero()))
+}
+
+impl<F> Mesh<Vec<CommonVertex<F>>> where F: BaseFloat {
+    /// Generate per vertex tangents from uv0, following the MikkTSpace conventions:
+    /// face tangents are projected onto the vertex normal and weighted by the corner angle,
+    /// the bitangent is `w * cross(normal, tangent)`,
+    /// and vertices shared by faces of different handedness (mirrored uvs) are split.
+    /// Vertices without a normal use the normal of the faces.
+    pub fn generate_tangents(&mut self) -> Result<()> {
+        if self.vertices.iter().any(|v| v.uv0.is_none()) {
+            bail!("tangents need uv0 on every vertex");
+        }
+        if self.triangles.iter().flatten().any(|&i| i >= self.vertices.len()) {
+            bail!("triangle index out of range");
+        }
+
+        // accumulated tangent of each (vertex, handedness)
+        let mut groups: HashMap<(usize, bool), Vector3<F>> = HashMap::new();
+        let mut corner_groups: Vec<[(usize, bool); 3]> = Vec::with_capacity(self.triangles.len());
+        for tri in self.triangles.iter() {
+            let p = tri.map(|i| self.vertices[i].position);
+            let uv = tri.map(|i| self.vertices[i].uv0.unwrap());
+            let face_normal = (p[1] - p[0]).cross(p[2] - p[0]);
+            let (face_tangent, orientation) = get_face_tangent(p, uv).unwrap_or((Vector3::zero(), true));
+
+            let mut corners = [(0, true); 3];
+            for corner in 0..3 {
+                let vertex_index = tri[corner];
+                let key = (vertex_index, orientation);
+                corners[corner] = key;
+
+                let n = self.vertices[vertex_index].normal.unwrap_or(face_normal);
+                if n.magnitude2() == F::zero() || face_tangent.magnitude2() == F::zero() {
+                    groups.entry(key).or_insert(Vector3::zero());
+                    continue;
+                }
+                let n = n.normalize();
+                let projected = face_tangent - n * n.dot(face_tangent);
+                if projected.magnitude2() == F::zero() {
+                    groups.entry(key).or_insert(Vector3::zero());
+                    continue;
+                }
+                let weight = get_corner_angle(p, corner);
+                *groups.entry(key).or_insert(Vector3::zero()) += projected.normalize() * weight;
+            }
+            corner_groups.push(corners);
+        }
+
+        // the first group of a vertex keeps the vertex, the others get a copy
+        let mut group_vertex: HashMap<(usize, bool), usize> = HashMap::new();
+        let mut used = vec![false; self.vertices.len()];
+        let mut keys: Vec<(usize, bool)> = groups.keys().cloned().collect();
+        keys.sort();
+        for key in keys {
+            let (vertex_index, orientation) = key;
+            let mut vertex = self.vertices[vertex_index].clone();
+            let n = vertex.normal.filter(|n| n.magnitude2() > F::zero()).map(|n| n.normalize());
+
+            let sum = groups[&key];
+            let tangent = if sum.magnitude2() > F::zero() {
+                let t = match n {
+                    Some(n) => sum - n * n.dot(sum),
+                    None => sum
+                };
+                if t.magnitude2() > F::zero() { t.normalize() } else { sum.normalize() }
+            } else {
+                match n {
+                    Some(n) => get_orthogonal(n),
+                    None => Vector3::unit_x()
+                }
+            };
+            let w = if orientation { F::one() } else { -F::one() };
+            vertex.tangent = Some(Vector4::new(tangent.x, tangent.y, tangent.z, w));
+
+            if used[vertex_index] {
+                group_vertex.insert(key, self.vertices.len());
+                self.vertices.push(vertex);
+            } else {
+                used[vertex_index] = true;
+                group_vertex.insert(key, vertex_index);
+                self.vertices[vertex_index] = vertex;
+            }
+        }
+
+        for (tri, corners) in self.triangles.iter_mut().zip(corner_groups.iter()) {
+            for corner in 0..3 {
+                tri[corner] = group_vertex[&corners[corner]];
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,12 +1,29 @@
 pub use mesh::{Mesh, DynMesh, MeshTrianglesIterator};
 pub use sub_mesh::SubMesh;
+pub use mesh_processing::{NormalWeighting, NormalGeneration, MeshProcessingOptions, MeshValidationReport};
 pub use vertex::{VertexBuffer, CommonVertex, BoxDynVertexBuffer};
+pub use polygon_mesh::PolygonMesh;
+pub use mesh_subdivision::SubdivisionScheme;
 pub use simple_mesh::*;
 pub use wavefront::*;
+pub use ply::*;
+pub use stl::*;
 
 mod mesh;
 mod sub_mesh;
 mod vertex;
+mod mesh_tangent;
+mod mesh_displacement;
+mod mesh_processing;
+mod polygon_mesh;
+mod mesh_subdivision;
 mod simple_mesh;
 mod wavefront;
+mod ply;
+mod stl;
+
+#[cfg(test)]
+mod test_mesh;
+#[cfg(test)]
+mod test_subdivision;
 
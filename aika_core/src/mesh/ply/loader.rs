@@ -0,0 +1,327 @@
+use std::fmt::Debug;
+use std::path::Path;
+use anyhow::{anyhow, bail, Result};
+use cgmath::{BaseFloat, Vector2, Vector3};
+use crate::mesh::{CommonVertex, Mesh};
+
+pub struct PlyMeshLoader;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PlyScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl PlyScalarType {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => PlyScalarType::I8,
+            "uchar" | "uint8" => PlyScalarType::U8,
+            "short" | "int16" => PlyScalarType::I16,
+            "ushort" | "uint16" => PlyScalarType::U16,
+            "int" | "int32" => PlyScalarType::I32,
+            "uint" | "uint32" => PlyScalarType::U32,
+            "float" | "float32" => PlyScalarType::F32,
+            "double" | "float64" => PlyScalarType::F64,
+            _ => bail!("unknown ply property type `{}`", name),
+        })
+    }
+
+    /// Integer colors are normalized by the maximum of their type, float colors are kept
+    fn get_color_scale(&self) -> f64 {
+        match *self {
+            PlyScalarType::I8 => i8::MAX as f64,
+            PlyScalarType::U8 => u8::MAX as f64,
+            PlyScalarType::I16 => i16::MAX as f64,
+            PlyScalarType::U16 => u16::MAX as f64,
+            PlyScalarType::I32 => i32::MAX as f64,
+            PlyScalarType::U32 => u32::MAX as f64,
+            PlyScalarType::F32 | PlyScalarType::F64 => 1.0,
+        }
+    }
+}
+
+struct PlyProperty {
+    name: String,
+    value_type: PlyScalarType,
+    /// the type of the item count, for list properties
+    count_type: Option<PlyScalarType>,
+}
+
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+impl PlyElement {
+    fn find_property(&self, names: &[&str]) -> Option<usize> {
+        self.properties.iter().position(|p| p.count_type.is_none() && names.contains(&p.name.as_str()))
+    }
+}
+
+struct PlyHeader {
+    format: PlyFormat,
+    elements: Vec<PlyElement>,
+}
+
+trait PlyValueReader {
+    fn read(&mut self, value_type: PlyScalarType) -> Result<f64>;
+}
+
+struct PlyAsciiReader<'a> {
+    tokens: std::str::SplitAsciiWhitespace<'a>,
+}
+
+impl<'a> PlyValueReader for PlyAsciiReader<'a> {
+    fn read(&mut self, _value_type: PlyScalarType) -> Result<f64> {
+        let token = self.tokens.next().ok_or_else(|| anyhow!("unexpected end of ply data"))?;
+        Ok(token.parse()?)
+    }
+}
+
+struct PlyBinaryReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    big_endian: bool,
+}
+
+impl<'a> PlyBinaryReader<'a> {
+    /// The bytes in little endian order
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let bytes = self.data.get(self.offset..self.offset + N).ok_or_else(|| anyhow!("unexpected end of ply data"))?;
+        self.offset += N;
+        let mut result: [u8; N] = bytes.try_into()?;
+        if self.big_endian {
+            result.reverse();
+        }
+        Ok(result)
+    }
+}
+
+impl<'a> PlyValueReader for PlyBinaryReader<'a> {
+    fn read(&mut self, value_type: PlyScalarType) -> Result<f64> {
+        Ok(match value_type {
+            PlyScalarType::I8 => i8::from_le_bytes(self.take()?) as f64,
+            PlyScalarType::U8 => u8::from_le_bytes(self.take()?) as f64,
+            PlyScalarType::I16 => i16::from_le_bytes(self.take()?) as f64,
+            PlyScalarType::U16 => u16::from_le_bytes(self.take()?) as f64,
+            PlyScalarType::I32 => i32::from_le_bytes(self.take()?) as f64,
+            PlyScalarType::U32 => u32::from_le_bytes(self.take()?) as f64,
+            PlyScalarType::F32 => f32::from_le_bytes(self.take()?) as f64,
+            PlyScalarType::F64 => f64::from_le_bytes(self.take()?),
+        })
+    }
+}
+
+/// Split the data into the header and the body, the body starts after the line of `end_header`
+fn split_header(data: &[u8]) -> Result<(&str, &[u8])> {
+    let marker = b"end_header";
+    let position = data.windows(marker.len())
+        .position(|w| w == marker)
+        .ok_or_else(|| anyhow!("ply header has no end_header"))?;
+    let line_end = data[position..].iter()
+        .position(|&b| b == b'\n')
+        .map(|i| position + i + 1)
+        .unwrap_or(data.len());
+    let header = std::str::from_utf8(&data[..position])?;
+    Ok((header, &data[line_end..]))
+}
+
+fn parse_header(header: &str) -> Result<PlyHeader> {
+    let mut lines = header.lines().map(|l| l.trim()).filter(|l| !l.is_empty());
+    if lines.next() != Some("ply") {
+        bail!("not a ply file");
+    }
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", f, _version] => {
+                format = Some(match *f {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => bail!("unknown ply format `{}`", f),
+                });
+            },
+            ["element", name, count] => {
+                elements.push(PlyElement {
+                    name: name.to_string(),
+                    count: count.parse()?,
+                    properties: Vec::new(),
+                });
+            },
+            ["property", "list", count_type, value_type, name] => {
+                let element = elements.last_mut().ok_or_else(|| anyhow!("ply property before any element"))?;
+                element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    value_type: PlyScalarType::parse(value_type)?,
+                    count_type: Some(PlyScalarType::parse(count_type)?),
+                });
+            },
+            ["property", value_type, name] => {
+                let element = elements.last_mut().ok_or_else(|| anyhow!("ply property before any element"))?;
+                element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    value_type: PlyScalarType::parse(value_type)?,
+                    count_type: None,
+                });
+            },
+            ["comment", ..] | ["obj_info", ..] => {},
+            _ => bail!("invalid ply header line `{}`", line),
+        }
+    }
+
+    Ok(PlyHeader {
+        format: format.ok_or_else(|| anyhow!("ply header has no format"))?,
+        elements,
+    })
+}
+
+fn get_f<F: BaseFloat>(x: f64) -> F {
+    F::from(x).unwrap()
+}
+
+impl PlyMeshLoader {
+    fn parse_body<F>(header: &PlyHeader, reader: &mut dyn PlyValueReader) -> Result<Mesh<Vec<CommonVertex<F>>>> where F: BaseFloat {
+        let mut vertices: Vec<CommonVertex<F>> = Vec::new();
+        let mut triangles = Vec::new();
+
+        for element in header.elements.iter() {
+            let position = [
+                element.find_property(&["x"]),
+                element.find_property(&["y"]),
+                element.find_property(&["z"])
+            ];
+            let normal = [
+                element.find_property(&["nx"]),
+                element.find_property(&["ny"]),
+                element.find_property(&["nz"])
+            ];
+            let uv = [
+                element.find_property(&["u", "s", "texture_u", "texture_s"]),
+                element.find_property(&["v", "t", "texture_v", "texture_t"])
+            ];
+            let color = [
+                element.find_property(&["red", "r", "diffuse_red"]),
+                element.find_property(&["green", "g", "diffuse_green"]),
+                element.find_property(&["blue", "b", "diffuse_blue"])
+            ];
+            let face_indices = element.properties.iter()
+                .position(|p| p.count_type.is_some() && (p.name == "vertex_indices" || p.name == "vertex_index"));
+
+            let mut values = vec![0.0; element.properties.len()];
+            let mut list = Vec::new();
+            for _ in 0..element.count {
+                for (i, property) in element.properties.iter().enumerate() {
+                    match property.count_type {
+                        Some(count_type) => {
+                            let count = reader.read(count_type)? as usize;
+                            let is_face = Some(i) == face_indices;
+                            if is_face {
+                                list.clear();
+                            }
+                            for _ in 0..count {
+                                let value = reader.read(property.value_type)?;
+                                if is_face {
+                                    list.push(value as usize);
+                                }
+                            }
+                        },
+                        None => values[i] = reader.read(property.value_type)?,
+                    }
+                }
+
+                if element.name == "vertex" {
+                    let get = |index: Option<usize>| index.map(|i| values[i]).unwrap_or(0.0);
+                    let mut v = CommonVertex::new();
+                    v.position = Vector3::new(get_f(get(position[0])), get_f(get(position[1])), get_f(get(position[2])));
+                    if normal.iter().all(|p| p.is_some()) {
+                        v.normal = Some(Vector3::new(get_f(get(normal[0])), get_f(get(normal[1])), get_f(get(normal[2]))));
+                    }
+                    if uv.iter().all(|p| p.is_some()) {
+                        v.uv0 = Some(Vector2::new(get_f(get(uv[0])), get_f(get(uv[1]))));
+                    }
+                    if color.iter().all(|p| p.is_some()) {
+                        let get_color = |index: Option<usize>| {
+                            let i = index.unwrap();
+                            get_f::<F>(values[i] / element.properties[i].value_type.get_color_scale())
+                        };
+                        v.color = Some(Vector3::new(get_color(color[0]), get_color(color[1]), get_color(color[2])));
+                    }
+                    vertices.push(v);
+                } else if element.name == "face" && face_indices.is_some() {
+                    // polygons are split into a fan
+                    for i in 2..list.len() {
+                        triangles.push([list[0], list[i - 1], list[i]]);
+                    }
+                }
+            }
+        }
+
+        if triangles.iter().flatten().any(|&i| i >= vertices.len()) {
+            bail!("ply face references a vertex out of range");
+        }
+
+        let triangle_count = triangles.len();
+        let mut mesh = Mesh {
+            vertices,
+            triangles,
+            sub_mesh: vec![[0, triangle_count]]
+        };
+        if !mesh.vertices.is_empty() && mesh.vertices.iter().all(|v| v.uv0.is_some()) {
+            mesh.generate_tangents()?;
+        }
+        Ok(mesh)
+    }
+
+    /// Load an ASCII, binary little endian or binary big endian PLY.
+    /// `vertex` gives the positions, normals (`nx`), uvs (`u` or `s`) and colors (`red`),
+    /// `face` gives polygons, which are triangulated as fans. Other elements are skipped
+    pub fn load_ply_memory<F>(data: &[u8]) -> Result<Mesh<Vec<CommonVertex<F>>>> where F: BaseFloat {
+        let (header, body) = split_header(data)?;
+        let header = parse_header(header)?;
+
+        match header.format {
+            PlyFormat::Ascii => {
+                let mut reader = PlyAsciiReader {
+                    tokens: std::str::from_utf8(body)?.split_ascii_whitespace(),
+                };
+                PlyMeshLoader::parse_body(&header, &mut reader)
+            },
+            PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => {
+                let mut reader = PlyBinaryReader {
+                    data: body,
+                    offset: 0,
+                    big_endian: header.format == PlyFormat::BinaryBigEndian,
+                };
+                PlyMeshLoader::parse_body(&header, &mut reader)
+            },
+        }
+    }
+
+    pub fn load_ply<F, P>(p: P) -> Result<Mesh<Vec<CommonVertex<F>>>>
+        where
+            P: AsRef<Path> + Debug,
+            F: BaseFloat
+    {
+        let data = std::fs::read(p)?;
+        PlyMeshLoader::load_ply_memory(&data)
+    }
+}
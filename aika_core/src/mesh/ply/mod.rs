@@ -0,0 +1,6 @@
+pub use loader::{PlyMeshLoader, PlyFormat};
+pub use writer::PlyMeshWriter;
+
+mod loader;
+mod writer;
+mod test;
@@ -0,0 +1,130 @@
+use cgmath::{InnerSpace, Vector2, Vector3};
+use crate::mesh::{CommonVertex, Mesh, MeshGenerationOptions, PlaneMesh, PlyFormat, PlyMeshLoader, PlyMeshWriter, VertexBuffer};
+use crate::scene::Scene;
+
+const TEST_PLY_ASCII: &str = "ply
+format ascii 1.0
+comment a quad with colors
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property float s
+property float t
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 0 0 1 0 0 255 0 0
+1 0 0 0 0 1 1 0 0 255 0
+1 1 0 0 0 1 1 1 0 0 255
+0 1 0 0 0 1 0 1 255 255 255
+4 0 1 2 3
+";
+
+/// The same layout as the ascii test, without the uvs, written in binary
+fn get_test_ply_binary(big_endian: bool) -> Vec<u8> {
+    let format = if big_endian { "binary_big_endian" } else { "binary_little_endian" };
+    let mut data = format!("ply\nformat {} 1.0\nelement vertex 3\nproperty double x\nproperty double y\nproperty double z\n\
+        property float nx\nproperty float ny\nproperty float nz\nproperty uchar red\nproperty uchar green\nproperty uchar blue\n\
+        element face 1\nproperty list uchar uint vertex_indices\nend_header\n", format).into_bytes();
+
+    let positions = [[0.0f64, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    for (i, position) in positions.iter().enumerate() {
+        for &x in position {
+            data.extend_from_slice(&if big_endian { x.to_be_bytes() } else { x.to_le_bytes() });
+        }
+        for x in [0.0f32, 0.0, 1.0] {
+            data.extend_from_slice(&if big_endian { x.to_be_bytes() } else { x.to_le_bytes() });
+        }
+        data.extend_from_slice(&[if i == 0 { 255 } else { 0 }, 51, 0]);
+    }
+    data.push(3);
+    for i in [0u32, 1, 2] {
+        data.extend_from_slice(&if big_endian { i.to_be_bytes() } else { i.to_le_bytes() });
+    }
+    data
+}
+
+#[test]
+fn test_ply_ascii() {
+    let mesh = PlyMeshLoader::load_ply_memory::<f64>(TEST_PLY_ASCII.as_bytes()).unwrap();
+    assert_eq!(mesh.vertices.len(), 4);
+    // the quad is split into a fan
+    assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    assert_eq!(mesh.sub_mesh, vec![[0, 2]]);
+
+    assert!((mesh.vertices.get_position(2) - Vector3::new(1.0, 1.0, 0.0)).magnitude() < 1e-9);
+    assert!((mesh.vertices.get_normal(1).unwrap() - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+    assert!((mesh.vertices.get_uv0(3).unwrap() - Vector2::new(0.0, 1.0)).magnitude() < 1e-9);
+    assert!((mesh.vertices.get_color(1).unwrap() - Vector3::new(0.0, 1.0, 0.0)).magnitude() < 1e-9);
+    assert!(mesh.vertices.get_tangent(0).is_some());
+}
+
+#[test]
+fn test_ply_binary() {
+    for big_endian in [false, true] {
+        let mesh = PlyMeshLoader::load_ply_memory::<f64>(&get_test_ply_binary(big_endian)).unwrap();
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+        assert!((mesh.vertices.get_position(1) - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-9);
+        assert!((mesh.vertices.get_normal(2).unwrap() - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+        assert!((mesh.vertices.get_color(0).unwrap() - Vector3::new(1.0, 0.2, 0.0)).magnitude() < 1e-9);
+        assert!(mesh.vertices.get_uv0(0).is_none());
+    }
+}
+
+#[test]
+fn test_ply_out_of_range() {
+    let data = TEST_PLY_ASCII.replace("4 0 1 2 3", "3 0 1 4");
+    assert!(PlyMeshLoader::load_ply_memory::<f64>(data.as_bytes()).is_err());
+}
+
+fn get_colored_plane<F: cgmath::BaseFloat>() -> Mesh<Vec<CommonVertex<F>>> {
+    let mut mesh = PlaneMesh::create_subdivided_plane_mesh(F::from(2).unwrap(), F::one(), 3, 2, &MeshGenerationOptions::default());
+    for (i, v) in mesh.vertices.iter_mut().enumerate() {
+        v.color = Some(Vector3::new(F::from(i as f64 / 255.0).unwrap(), F::zero(), F::one()));
+    }
+    mesh
+}
+
+#[test]
+fn test_ply_writer_round_trip() {
+    for format in [PlyFormat::Ascii, PlyFormat::BinaryLittleEndian, PlyFormat::BinaryBigEndian] {
+        let mesh = get_colored_plane::<f64>();
+        let data = PlyMeshWriter::write_ply_memory(&mesh, format).unwrap();
+        let loaded = PlyMeshLoader::load_ply_memory::<f64>(&data).unwrap();
+        assert_eq!(loaded.triangles, mesh.triangles);
+        assert_eq!(loaded.vertices.len(), mesh.vertices.len());
+        for (a, b) in loaded.vertices.iter().zip(mesh.vertices.iter()) {
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.normal, b.normal);
+            assert_eq!(a.uv0, b.uv0);
+            assert!((a.color.unwrap() - b.color.unwrap()).magnitude() < 1e-9);
+        }
+
+        // f32 meshes are written as float
+        let mesh = get_colored_plane::<f32>();
+        let data = PlyMeshWriter::write_ply_memory(&mesh, format).unwrap();
+        assert!(String::from_utf8_lossy(&data).contains("property float x\n"));
+        let loaded = PlyMeshLoader::load_ply_memory::<f32>(&data).unwrap();
+        assert_eq!(loaded.vertices.get_position(5), mesh.vertices.get_position(5));
+    }
+}
+
+#[test]
+fn test_ply_writer_scene() {
+    let scene: Scene<f64> = Scene::new_plane_and_cube();
+    let data = PlyMeshWriter::write_ply_scene_memory(&scene, PlyFormat::BinaryLittleEndian).unwrap();
+    let loaded = PlyMeshLoader::load_ply_memory::<f64>(&data).unwrap();
+    assert_eq!(loaded.triangles.len(), 14);
+    let max_z = loaded.vertices.iter().map(|v| v.position.z).fold(f64::MIN, f64::max);
+    assert!((max_z - 1.0).abs() < 1e-9);
+    // the plane has no vertex colors, so none are written
+    assert!(loaded.vertices.iter().all(|v| v.color.is_none()));
+}
@@ -0,0 +1,187 @@
+use std::fmt::Debug;
+use std::io::Write;
+use std::path::Path;
+use anyhow::Result;
+use cgmath::BaseFloat;
+use crate::mesh::{CommonVertex, Mesh, PlyFormat, VertexBuffer};
+use crate::scene::Scene;
+
+pub struct PlyMeshWriter;
+
+/// Writes the values of the body, separated by spaces on ascii lines
+struct PlyDataWriter<'a> {
+    w: &'a mut Vec<u8>,
+    format: PlyFormat,
+    line_start: bool,
+}
+
+impl<'a> PlyDataWriter<'a> {
+    fn write_ascii(&mut self, value: String) -> Result<()> {
+        if !self.line_start {
+            self.w.push(b' ');
+        }
+        self.w.extend_from_slice(value.as_bytes());
+        self.line_start = false;
+        Ok(())
+    }
+
+    fn write_scalar<F: BaseFloat>(&mut self, x: F) -> Result<()> {
+        let is_double = std::mem::size_of::<F>() == 8;
+        match self.format {
+            PlyFormat::Ascii => self.write_ascii(format!("{:?}", x))?,
+            PlyFormat::BinaryLittleEndian if is_double => self.w.write_all(&x.to_f64().unwrap().to_le_bytes())?,
+            PlyFormat::BinaryLittleEndian => self.w.write_all(&x.to_f32().unwrap().to_le_bytes())?,
+            PlyFormat::BinaryBigEndian if is_double => self.w.write_all(&x.to_f64().unwrap().to_be_bytes())?,
+            PlyFormat::BinaryBigEndian => self.w.write_all(&x.to_f32().unwrap().to_be_bytes())?,
+        }
+        Ok(())
+    }
+
+    fn write_u8(&mut self, x: u8) -> Result<()> {
+        match self.format {
+            PlyFormat::Ascii => self.write_ascii(x.to_string())?,
+            PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => self.w.write_all(&[x])?,
+        }
+        Ok(())
+    }
+
+    fn write_color<F: BaseFloat>(&mut self, x: F) -> Result<()> {
+        let value = (x.to_f64().unwrap() * 255.0).round().clamp(0.0, 255.0);
+        self.write_u8(value as u8)
+    }
+
+    fn write_u32(&mut self, x: u32) -> Result<()> {
+        match self.format {
+            PlyFormat::Ascii => self.write_ascii(x.to_string())?,
+            PlyFormat::BinaryLittleEndian => self.w.write_all(&x.to_le_bytes())?,
+            PlyFormat::BinaryBigEndian => self.w.write_all(&x.to_be_bytes())?,
+        }
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> Result<()> {
+        if self.format == PlyFormat::Ascii {
+            writeln!(self.w)?;
+        }
+        self.line_start = true;
+        Ok(())
+    }
+}
+
+impl PlyMeshWriter {
+    /// Normals (`nx`), uvs (`s`, `t`) and colors (`red`, as uchar) are written if every vertex has them.
+    /// Positions are `float` for f32 meshes and `double` for f64 meshes, the sub meshes are not kept
+    pub fn write_ply_memory<V>(mesh: &Mesh<V>, format: PlyFormat) -> Result<Vec<u8>> where V: VertexBuffer, V::FloatType: BaseFloat {
+        let vertex_count = mesh.vertices.vertex_count();
+        let has_uv = vertex_count > 0 && (0..vertex_count).all(|i| mesh.vertices.get_uv0(i).is_some());
+        let has_normal = vertex_count > 0 && (0..vertex_count).all(|i| mesh.vertices.get_normal(i).is_some());
+        let has_color = vertex_count > 0 && (0..vertex_count).all(|i| mesh.vertices.get_color(i).is_some());
+        let scalar_type = if std::mem::size_of::<V::FloatType>() == 8 { "double" } else { "float" };
+
+        let mut w = Vec::new();
+        writeln!(w, "ply")?;
+        let format_name = match format {
+            PlyFormat::Ascii => "ascii",
+            PlyFormat::BinaryLittleEndian => "binary_little_endian",
+            PlyFormat::BinaryBigEndian => "binary_big_endian",
+        };
+        writeln!(w, "format {} 1.0", format_name)?;
+        writeln!(w, "element vertex {}", vertex_count)?;
+        for name in ["x", "y", "z"] {
+            writeln!(w, "property {} {}", scalar_type, name)?;
+        }
+        if has_normal {
+            for name in ["nx", "ny", "nz"] {
+                writeln!(w, "property {} {}", scalar_type, name)?;
+            }
+        }
+        if has_uv {
+            for name in ["s", "t"] {
+                writeln!(w, "property {} {}", scalar_type, name)?;
+            }
+        }
+        if has_color {
+            for name in ["red", "green", "blue"] {
+                writeln!(w, "property uchar {}", name)?;
+            }
+        }
+        writeln!(w, "element face {}", mesh.triangles.len())?;
+        writeln!(w, "property list uchar uint vertex_indices")?;
+        writeln!(w, "end_header")?;
+
+        let mut data = PlyDataWriter {
+            w: &mut w,
+            format,
+            line_start: true,
+        };
+        for i in 0..vertex_count {
+            let p = mesh.vertices.get_position(i);
+            for k in 0..3 {
+                data.write_scalar(p[k])?;
+            }
+            if has_normal {
+                let n = mesh.vertices.get_normal(i).unwrap();
+                for k in 0..3 {
+                    data.write_scalar(n[k])?;
+                }
+            }
+            if has_uv {
+                let uv = mesh.vertices.get_uv0(i).unwrap();
+                data.write_scalar(uv.x)?;
+                data.write_scalar(uv.y)?;
+            }
+            if has_color {
+                let c = mesh.vertices.get_color(i).unwrap();
+                for k in 0..3 {
+                    data.write_color(c[k])?;
+                }
+            }
+            data.end_line()?;
+        }
+        for t in mesh.triangles.iter() {
+            data.write_u8(3)?;
+            for &index in t.iter() {
+                data.write_u32(index as u32)?;
+            }
+            data.end_line()?;
+        }
+
+        Ok(w)
+    }
+
+    pub fn write_ply<V, P>(mesh: &Mesh<V>, p: P, format: PlyFormat) -> Result<()>
+        where
+            V: VertexBuffer,
+            V::FloatType: BaseFloat,
+            P: AsRef<Path> + Debug
+    {
+        std::fs::write(p, PlyMeshWriter::write_ply_memory(mesh, format)?)?;
+        Ok(())
+    }
+
+    /// All the meshes of the scene merged into one in world space, PLY has no objects
+    pub fn write_ply_scene_memory<F>(scene: &Scene<F>, format: PlyFormat) -> Result<Vec<u8>> where F: BaseFloat + 'static {
+        let mut merged: Mesh<Vec<CommonVertex<F>>> = Mesh {
+            vertices: Vec::new(),
+            triangles: Vec::new(),
+            sub_mesh: Vec::new(),
+        };
+        for (_, mesh) in scene.get_world_meshes() {
+            let offset = merged.vertices.len();
+            merged.vertices.extend(mesh.vertices);
+            merged.triangles.extend(mesh.triangles.iter().map(|t| t.map(|i| i + offset)));
+        }
+        let triangle_count = merged.triangles.len();
+        merged.sub_mesh.push([0, triangle_count]);
+        PlyMeshWriter::write_ply_memory(&merged, format)
+    }
+
+    pub fn write_ply_scene<F, P>(scene: &Scene<F>, p: P, format: PlyFormat) -> Result<()>
+        where
+            F: BaseFloat + 'static,
+            P: AsRef<Path> + Debug
+    {
+        std::fs::write(p, PlyMeshWriter::write_ply_scene_memory(scene, format)?)?;
+        Ok(())
+    }
+}
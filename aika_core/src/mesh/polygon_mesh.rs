@@ -0,0 +1,68 @@
+use anyhow::{bail, Result};
+use cgmath::BaseFloat;
+use crate::mesh::{CommonVertex, Mesh, VertexBuffer};
+
+/// A mesh of polygons with any number of sides, such as the control mesh of a subdivision surface
+#[derive(Clone)]
+pub struct PolygonMesh<F> {
+    pub vertices: Vec<CommonVertex<F>>,
+    /// counter clockwise vertex indices of each polygon
+    pub faces: Vec<Vec<usize>>,
+    /// ranges of `faces`, like `Mesh::sub_mesh`
+    pub sub_mesh: Vec<[usize; 2]>,
+    /// Sharpness of the edges between the positions of two vertices.
+    /// An edge with sharpness n stays sharp for n subdivision steps, an infinite sharpness keeps it sharp
+    pub creases: Vec<([usize; 2], F)>,
+}
+
+impl<F> PolygonMesh<F> where F: BaseFloat {
+    /// Every triangle of the mesh is a polygon
+    pub fn from_mesh<V>(mesh: &Mesh<V>) -> Self where V: VertexBuffer<FloatType = F> {
+        let mesh = mesh.to_common_mesh();
+        PolygonMesh {
+            vertices: mesh.vertices,
+            faces: mesh.triangles.iter().map(|t| t.to_vec()).collect(),
+            sub_mesh: mesh.sub_mesh,
+            creases: Vec::new(),
+        }
+    }
+
+    pub fn with_crease(mut self, a: usize, b: usize, sharpness: F) -> Self {
+        self.creases.push(([a, b], sharpness));
+        self
+    }
+
+    pub fn is_triangle_mesh(&self) -> bool {
+        self.faces.iter().all(|f| f.len() == 3)
+    }
+
+    /// Split the polygons into fans of triangles, polygons with less than 3 vertices are dropped.
+    /// Tangents are generated when every vertex has uv0
+    pub fn to_mesh(&self) -> Result<Mesh<Vec<CommonVertex<F>>>> {
+        if self.faces.iter().flatten().any(|&i| i >= self.vertices.len()) {
+            bail!("polygon references a vertex out of range");
+        }
+
+        // new_start[i] is the index of the first triangle of the face i
+        let mut new_start = Vec::with_capacity(self.faces.len() + 1);
+        let mut triangles = Vec::new();
+        for face in self.faces.iter() {
+            new_start.push(triangles.len());
+            for i in 2..face.len() {
+                triangles.push([face[0], face[i - 1], face[i]]);
+            }
+        }
+        new_start.push(triangles.len());
+
+        let face_count = self.faces.len();
+        let mut mesh = Mesh {
+            vertices: self.vertices.clone(),
+            triangles,
+            sub_mesh: self.sub_mesh.iter().map(|&[a, b]| [new_start[a.min(face_count)], new_start[b.min(face_count)]]).collect(),
+        };
+        if !mesh.vertices.is_empty() && mesh.vertices.iter().all(|v| v.uv0.is_some()) {
+            mesh.generate_tangents()?;
+        }
+        Ok(mesh)
+    }
+}
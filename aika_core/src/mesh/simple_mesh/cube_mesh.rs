@@ -1 +1,36 @@
-// todo
+use cgmath::{BaseFloat, Vector2, Vector3};
+use crate::mesh::{CommonVertex, Mesh, MeshGenerationOptions};
+use crate::mesh::simple_mesh::mesh_builder::MeshBuilder;
+
+pub struct CubeMesh;
+
+impl CubeMesh {
+    /// A box centered on the origin with edges `size`, each face is a grid of `resolution * resolution` quads.
+    /// Every face has the whole uv square and is a sub mesh, in the order +x, -x, +y, -y, +z, -z
+    pub fn create_cube_mesh<F>(size: Vector3<F>, resolution: usize, options: &MeshGenerationOptions) -> Mesh<Vec<CommonVertex<F>>>
+    where
+        F: BaseFloat
+    {
+        let resolution = resolution.max(1);
+        let h = size / F::from(2).unwrap();
+        let (z, o) = (F::zero(), F::one());
+
+        // origin, u edge and v edge of each face, the cross of the edges points outside
+        let faces = [
+            (Vector3::new(h.x, -h.y, -h.z), Vector3::new(z, size.y, z), Vector3::new(z, z, size.z), Vector3::new(o, z, z)),
+            (Vector3::new(-h.x, h.y, -h.z), Vector3::new(z, -size.y, z), Vector3::new(z, z, size.z), Vector3::new(-o, z, z)),
+            (Vector3::new(h.x, h.y, -h.z), Vector3::new(-size.x, z, z), Vector3::new(z, z, size.z), Vector3::new(z, o, z)),
+            (Vector3::new(-h.x, -h.y, -h.z), Vector3::new(size.x, z, z), Vector3::new(z, z, size.z), Vector3::new(z, -o, z)),
+            (Vector3::new(-h.x, -h.y, h.z), Vector3::new(size.x, z, z), Vector3::new(z, size.y, z), Vector3::new(z, z, o)),
+            (Vector3::new(-h.x, h.y, -h.z), Vector3::new(size.x, z, z), Vector3::new(z, -size.y, z), Vector3::new(z, z, -o)),
+        ];
+
+        let mut builder = MeshBuilder::new();
+        for (origin, edge_u, edge_v, normal) in faces {
+            builder.add_grid(resolution, resolution, [false, false], |u, v| {
+                (origin + edge_u * u + edge_v * v, normal, Vector2::new(u, v))
+            });
+        }
+        builder.finish(options)
+    }
+}
@@ -0,0 +1,53 @@
+use cgmath::{BaseFloat, InnerSpace, Vector2, Vector3};
+use aika_math::utils::get_pi;
+use crate::mesh::{CommonVertex, DiskMesh, Mesh, MeshGenerationOptions};
+use crate::mesh::simple_mesh::mesh_builder::MeshBuilder;
+
+pub struct CylinderMesh;
+
+impl CylinderMesh {
+    /// A capped cylinder around the z axis from -height / 2 to height / 2,
+    /// `segments` around the axis and `rings` along it.
+    /// The side, the top cap and the bottom cap are sub meshes, the side is unwrapped around the uv square
+    pub fn create_cylinder_mesh<F>(radius: F, height: F, segments: usize, rings: usize, options: &MeshGenerationOptions) -> Mesh<Vec<CommonVertex<F>>>
+    where
+        F: BaseFloat
+    {
+        let two_pi = get_pi::<F>() * F::from(2).unwrap();
+        let half_height = height / F::from(2).unwrap();
+        let segments = segments.max(3);
+
+        let mut builder = MeshBuilder::new();
+        builder.add_grid(segments, rings.max(1), [false, false], |u, v| {
+            let (sin, cos) = (two_pi * u).sin_cos();
+            let normal = Vector3::new(cos, sin, F::zero());
+            let position = Vector3::new(radius * cos, radius * sin, height * v - half_height);
+            (position, normal, Vector2::new(u, v))
+        });
+        DiskMesh::add_disk(&mut builder, radius, half_height, segments, 1, true);
+        DiskMesh::add_disk(&mut builder, radius, -half_height, segments, 1, false);
+        builder.finish(options)
+    }
+
+    /// A cone around the z axis with the base at -height / 2 and the apex at height / 2.
+    /// The side and the base are sub meshes, the normals of the side are smooth around the axis
+    pub fn create_cone_mesh<F>(radius: F, height: F, segments: usize, rings: usize, options: &MeshGenerationOptions) -> Mesh<Vec<CommonVertex<F>>>
+    where
+        F: BaseFloat
+    {
+        let two_pi = get_pi::<F>() * F::from(2).unwrap();
+        let half_height = height / F::from(2).unwrap();
+        let segments = segments.max(3);
+
+        let mut builder = MeshBuilder::new();
+        builder.add_grid(segments, rings.max(1), [false, true], |u, v| {
+            let (sin, cos) = (two_pi * u).sin_cos();
+            let normal = Vector3::new(height * cos, height * sin, radius).normalize();
+            let r = radius * (F::one() - v);
+            let position = Vector3::new(r * cos, r * sin, height * v - half_height);
+            (position, normal, Vector2::new(u, v))
+        });
+        DiskMesh::add_disk(&mut builder, radius, -half_height, segments, 1, false);
+        builder.finish(options)
+    }
+}
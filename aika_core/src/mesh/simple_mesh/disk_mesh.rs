@@ -0,0 +1,37 @@
+use cgmath::{BaseFloat, Vector2, Vector3};
+use aika_math::utils::get_pi;
+use crate::mesh::{CommonVertex, Mesh, MeshGenerationOptions};
+use crate::mesh::simple_mesh::mesh_builder::MeshBuilder;
+
+pub struct DiskMesh;
+
+impl DiskMesh {
+    /// A disk at height `z`, facing +z or -z. The uv is the planar projection of the circle onto the uv square
+    pub(crate) fn add_disk<F>(builder: &mut MeshBuilder<F>, radius: F, z: F, segments: usize, rings: usize, facing_up: bool)
+    where
+        F: BaseFloat
+    {
+        let two_pi = get_pi::<F>() * F::from(2).unwrap();
+        let half = F::from(0.5).unwrap();
+        let normal_z = if facing_up { F::one() } else { -F::one() };
+        // from the rim to the center when facing up, so that the triangles face +z
+        builder.add_grid(segments, rings, [!facing_up, facing_up], |u, v| {
+            let r = if facing_up { F::one() - v } else { v };
+            let (sin, cos) = (two_pi * u).sin_cos();
+            let position = Vector3::new(radius * r * cos, radius * r * sin, z);
+            let uv = Vector2::new(half + half * r * cos, half + half * r * sin);
+            (position, Vector3::new(F::zero(), F::zero(), normal_z), uv)
+        });
+    }
+
+    /// A disk in the xy plane centered on the origin and facing +z,
+    /// with `segments` around and `rings` from the center to the rim
+    pub fn create_disk_mesh<F>(radius: F, segments: usize, rings: usize, options: &MeshGenerationOptions) -> Mesh<Vec<CommonVertex<F>>>
+    where
+        F: BaseFloat
+    {
+        let mut builder = MeshBuilder::new();
+        DiskMesh::add_disk(&mut builder, radius, F::zero(), segments.max(3), rings.max(1), true);
+        builder.finish(options)
+    }
+}
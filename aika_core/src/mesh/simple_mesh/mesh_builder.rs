@@ -0,0 +1,117 @@
+use cgmath::{BaseFloat, Vector2, Vector3};
+use crate::mesh::{CommonVertex, Mesh};
+
+/// The vertex attributes of the generated meshes
+#[derive(Clone, Debug)]
+pub struct MeshGenerationOptions {
+    pub uv: bool,
+    pub normals: bool,
+    /// only generated when `uv` is on
+    pub tangents: bool,
+}
+
+impl Default for MeshGenerationOptions {
+    fn default() -> Self {
+        MeshGenerationOptions {
+            uv: true,
+            normals: true,
+            tangents: true,
+        }
+    }
+}
+
+/// Collects the parts of a generated mesh, each part is a sub mesh
+pub(crate) struct MeshBuilder<F> {
+    pub mesh: Mesh<Vec<CommonVertex<F>>>,
+}
+
+impl<F> MeshBuilder<F> where F: BaseFloat {
+    pub fn new() -> Self {
+        MeshBuilder {
+            mesh: Mesh {
+                vertices: Vec::new(),
+                triangles: Vec::new(),
+                sub_mesh: Vec::new(),
+            }
+        }
+    }
+
+    pub fn add_vertex(&mut self, position: Vector3<F>, normal: Vector3<F>, uv: Vector2<F>) -> usize {
+        let mut v = CommonVertex::new();
+        v.position = position;
+        v.normal = Some(normal);
+        v.uv0 = Some(uv);
+        self.mesh.vertices.push(v);
+        self.mesh.vertices.len() - 1
+    }
+
+    /// Close the triangles added since the last call into a sub mesh
+    pub fn end_sub_mesh(&mut self) {
+        let start = self.mesh.sub_mesh.last().map(|r| r[1]).unwrap_or(0);
+        self.mesh.sub_mesh.push([start, self.mesh.triangles.len()]);
+    }
+
+    /// A part sampled from a surface `f(u, v) -> (position, normal, uv)` over the unit square, with `nu * nv` quads.
+    /// The triangles face the side of `dp/du x dp/dv`.
+    /// `collapse[0]` and `collapse[1]` tell that the row at v = 0 or v = 1 is a single point, like the poles of a sphere.
+    /// Such a row gets one vertex per quad at the middle u, and the degenerate triangles are left out
+    pub fn add_grid<G>(&mut self, nu: usize, nv: usize, collapse: [bool; 2], f: G)
+    where
+        G: Fn(F, F) -> (Vector3<F>, Vector3<F>, Vector2<F>)
+    {
+        let nu_f = F::from(nu).unwrap();
+        let nv_f = F::from(nv).unwrap();
+        let half = F::from(0.5).unwrap();
+
+        // rows[j][i] is the vertex index of column i in row j
+        let mut rows: Vec<Vec<usize>> = Vec::with_capacity(nv + 1);
+        for j in 0..=nv {
+            let v = F::from(j).unwrap() / nv_f;
+            let is_collapsed = (j == 0 && collapse[0]) || (j == nv && collapse[1]);
+            let row = if is_collapsed {
+                (0..nu).map(|i| {
+                    let (p, n, uv) = f((F::from(i).unwrap() + half) / nu_f, v);
+                    self.add_vertex(p, n, uv)
+                }).collect()
+            } else {
+                (0..=nu).map(|i| {
+                    let (p, n, uv) = f(F::from(i).unwrap() / nu_f, v);
+                    self.add_vertex(p, n, uv)
+                }).collect()
+            };
+            rows.push(row);
+        }
+
+        for j in 0..nv {
+            for i in 0..nu {
+                let (r0, r1) = (&rows[j], &rows[j + 1]);
+                if j == 0 && collapse[0] {
+                    self.mesh.triangles.push([r0[i], r1[i + 1], r1[i]]);
+                } else if j + 1 == nv && collapse[1] {
+                    self.mesh.triangles.push([r0[i], r0[i + 1], r1[i]]);
+                } else {
+                    self.mesh.triangles.push([r0[i], r0[i + 1], r1[i + 1]]);
+                    self.mesh.triangles.push([r0[i], r1[i + 1], r1[i]]);
+                }
+            }
+        }
+        self.end_sub_mesh();
+    }
+
+    pub fn finish(self, options: &MeshGenerationOptions) -> Mesh<Vec<CommonVertex<F>>> {
+        let mut mesh = self.mesh;
+        if options.uv && options.tangents {
+            // every vertex has uv0 and the indices are in range
+            mesh.generate_tangents().unwrap();
+        }
+        for v in mesh.vertices.iter_mut() {
+            if !options.uv {
+                v.uv0 = None;
+            }
+            if !options.normals {
+                v.normal = None;
+            }
+        }
+        mesh
+    }
+}
@@ -1,4 +1,18 @@
 pub use plane_mesh::PlaneMesh;
+pub use cube_mesh::CubeMesh;
+pub use sphere_mesh::SphereMesh;
+pub use cylinder_mesh::CylinderMesh;
+pub use torus_mesh::TorusMesh;
+pub use disk_mesh::DiskMesh;
+pub use mesh_builder::MeshGenerationOptions;
 
 mod plane_mesh;
 mod cube_mesh;
+mod sphere_mesh;
+mod cylinder_mesh;
+mod torus_mesh;
+mod disk_mesh;
+mod mesh_builder;
+
+#[cfg(test)]
+mod test;
@@ -1,6 +1,7 @@
 use cgmath::{BaseFloat, Vector2, Vector3};
-use crate::mesh::{DynMesh, Mesh};
+use crate::mesh::{DynMesh, Mesh, MeshGenerationOptions};
 use crate::mesh::CommonVertex;
+use crate::mesh::simple_mesh::mesh_builder::MeshBuilder;
 
 pub struct PlaneMesh;
 
@@ -48,8 +49,24 @@ impl PlaneMesh {
 
         Mesh {
             vertices: Box::new(vertices),
-            sub_mesh: vec![[0, 4]],
+            sub_mesh: vec![[0, 2]],
             triangles
         }
     }
-}
\ No newline at end of file
+
+    /// Same as `create_plane_mesh`, with `resolution_x * resolution_y` quads
+    pub fn create_subdivided_plane_mesh<F>(edge_x: F, edge_y: F, resolution_x: usize, resolution_y: usize, options: &MeshGenerationOptions) -> Mesh<Vec<CommonVertex<F>>>
+    where
+        F: BaseFloat
+    {
+        let two = F::from(2.0).unwrap();
+        let origin = Vector3::new(-edge_x / two, -edge_y / two, F::zero());
+        let normal = Vector3::new(F::zero(), F::zero(), F::one());
+
+        let mut builder = MeshBuilder::new();
+        builder.add_grid(resolution_x.max(1), resolution_y.max(1), [false, false], |u, v| {
+            (origin + Vector3::new(edge_x * u, edge_y * v, F::zero()), normal, Vector2::new(u, v))
+        });
+        builder.finish(options)
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use cgmath::{BaseFloat, InnerSpace, Vector2, Vector3};
+use aika_math::utils::get_pi;
+use crate::mesh::{CommonVertex, Mesh, MeshGenerationOptions};
+use crate::mesh::simple_mesh::mesh_builder::MeshBuilder;
+
+pub struct SphereMesh;
+
+/// Vertices of the icosahedron, (0, ±1, ±t) and its cyclic permutations
+const ICOSAHEDRON_VERTICES: [[f64; 3]; 12] = [
+    [-1.0, 1.618033988749895, 0.0], [1.0, 1.618033988749895, 0.0], [-1.0, -1.618033988749895, 0.0], [1.0, -1.618033988749895, 0.0],
+    [0.0, -1.0, 1.618033988749895], [0.0, 1.0, 1.618033988749895], [0.0, -1.0, -1.618033988749895], [0.0, 1.0, -1.618033988749895],
+    [1.618033988749895, 0.0, -1.0], [1.618033988749895, 0.0, 1.0], [-1.618033988749895, 0.0, -1.0], [-1.618033988749895, 0.0, 1.0],
+];
+
+const ICOSAHEDRON_TRIANGLES: [[usize; 3]; 20] = [
+    [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+    [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+    [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+    [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+];
+
+/// The uv of a direction, u is the longitude and v goes from the south pole to the north pole
+fn get_spherical_uv<F: BaseFloat>(n: Vector3<F>) -> Vector2<F> {
+    let pi = get_pi::<F>();
+    let half = F::from(0.5).unwrap();
+    let u = half + n.y.atan2(n.x) / (pi * F::from(2).unwrap());
+    let v = half + n.z.max(-F::one()).min(F::one()).asin() / pi;
+    Vector2::new(u, v)
+}
+
+impl SphereMesh {
+    /// A sphere centered on the origin with the poles on the z axis,
+    /// `segments` around the z axis and `rings` from the south pole to the north pole.
+    /// u is the longitude and v the latitude, the seam is at -x
+    pub fn create_uv_sphere_mesh<F>(radius: F, segments: usize, rings: usize, options: &MeshGenerationOptions) -> Mesh<Vec<CommonVertex<F>>>
+    where
+        F: BaseFloat
+    {
+        let pi = get_pi::<F>();
+        let two_pi = pi * F::from(2).unwrap();
+        let mut builder = MeshBuilder::new();
+        builder.add_grid(segments.max(3), rings.max(2), [true, true], |u, v| {
+            let (sin_phi, cos_phi) = (two_pi * u - pi).sin_cos();
+            let (sin_theta, cos_theta) = (pi * v).sin_cos();
+            let normal = Vector3::new(sin_theta * cos_phi, sin_theta * sin_phi, -cos_theta);
+            (normal * radius, normal, Vector2::new(u, v))
+        });
+        builder.finish(options)
+    }
+
+    /// A sphere made from an icosahedron whose triangles are split into 4 `subdivision` times, with 20 * 4^subdivision triangles.
+    /// The triangles are much more even than the uv sphere.
+    /// The uv is the same spherical mapping as the uv sphere, the vertices on the seam are split
+    pub fn create_icosphere_mesh<F>(radius: F, subdivision: usize, options: &MeshGenerationOptions) -> Mesh<Vec<CommonVertex<F>>>
+    where
+        F: BaseFloat
+    {
+        let mut mesh: Mesh<Vec<CommonVertex<F>>> = Mesh {
+            vertices: ICOSAHEDRON_VERTICES.iter().map(|p| {
+                let mut v = CommonVertex::new();
+                v.position = Vector3::new(F::from(p[0]).unwrap(), F::from(p[1]).unwrap(), F::from(p[2]).unwrap()).normalize();
+                v
+            }).collect(),
+            triangles: ICOSAHEDRON_TRIANGLES.to_vec(),
+            sub_mesh: vec![[0, ICOSAHEDRON_TRIANGLES.len()]],
+        };
+        for _ in 0..subdivision {
+            mesh = mesh.tessellate();
+            for v in mesh.vertices.iter_mut() {
+                v.position = v.position.normalize();
+            }
+        }
+
+        let half = F::from(0.5).unwrap();
+        let eps = F::from(1e-9).unwrap();
+        let mut builder = MeshBuilder::new();
+        // the same direction is split by its u
+        let mut vertex_map: HashMap<(usize, u64), usize> = HashMap::new();
+        for triangle in mesh.triangles.iter() {
+            let normals = triangle.map(|i| mesh.vertices[i].position);
+            let mut uv = normals.map(get_spherical_uv);
+            // a triangle across the seam continues beyond u = 1
+            let max_u = uv.iter().map(|x| x.x).fold(F::zero(), F::max);
+            let min_u = uv.iter().map(|x| x.x).fold(F::one(), F::min);
+            if max_u - min_u > half {
+                for x in uv.iter_mut() {
+                    if x.x < half {
+                        x.x += F::one();
+                    }
+                }
+            }
+            // the longitude of a pole is the one of the other corners
+            for k in 0..3 {
+                let n = normals[k];
+                if n.x.abs() < eps && n.y.abs() < eps {
+                    uv[k].x = (uv[(k + 1) % 3].x + uv[(k + 2) % 3].x) * half;
+                }
+            }
+
+            let indices: Vec<usize> = (0..3).map(|k| {
+                let key = (triangle[k], uv[k].x.to_f64().unwrap().to_bits());
+                *vertex_map.entry(key).or_insert_with(|| builder.add_vertex(normals[k] * radius, normals[k], uv[k]))
+            }).collect();
+            builder.mesh.triangles.push([indices[0], indices[1], indices[2]]);
+        }
+        builder.end_sub_mesh();
+        builder.finish(options)
+    }
+}
@@ -0,0 +1,96 @@
+use cgmath::{InnerSpace, Vector3};
+use aika_math::utils::get_pi;
+use crate::mesh::{CommonVertex, CubeMesh, CylinderMesh, DiskMesh, Mesh, MeshGenerationOptions, PlaneMesh, SphereMesh, TorusMesh, VertexBuffer};
+
+fn get_area(mesh: &Mesh<Vec<CommonVertex<f64>>>) -> f64 {
+    mesh.iter_triangles().map(|t| (t.b - t.a).cross(t.c - t.a).magnitude() / 2.0).sum()
+}
+
+/// Valid, with tangents, and every triangle faces the side of its vertex normals
+fn check_mesh(mesh: &Mesh<Vec<CommonVertex<f64>>>) {
+    let report = mesh.validate();
+    assert!(report.is_valid(), "{}", report);
+    assert_eq!(report.degenerate_triangles, 0);
+    assert_eq!(report.unused_vertices, 0);
+    assert_eq!(mesh.sub_mesh.last().unwrap()[1], mesh.triangles.len());
+
+    for (triangle, indices) in mesh.iter_triangles().zip(mesh.iter_triangle_indices()) {
+        let face_normal = (triangle.b - triangle.a).cross(triangle.c - triangle.a);
+        for i in indices {
+            assert!(face_normal.dot(mesh.vertices.get_normal(i).unwrap()) > 0.0);
+            assert!(mesh.vertices.get_tangent(i).is_some());
+        }
+    }
+}
+
+#[test]
+fn test_cube_mesh() {
+    let mesh = CubeMesh::create_cube_mesh(Vector3::new(1.0, 2.0, 3.0), 2, &MeshGenerationOptions::default());
+    check_mesh(&mesh);
+    assert_eq!(mesh.triangles.len(), 6 * 8);
+    assert_eq!(mesh.sub_mesh.len(), 6);
+    assert!((get_area(&mesh) - 2.0 * (2.0 + 3.0 + 6.0)).abs() < 1e-9);
+}
+
+#[test]
+fn test_plane_mesh() {
+    let mesh = PlaneMesh::create_subdivided_plane_mesh(2.0, 4.0, 3, 5, &MeshGenerationOptions::default());
+    check_mesh(&mesh);
+    assert_eq!(mesh.triangles.len(), 30);
+    assert!((get_area(&mesh) - 8.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_sphere_mesh() {
+    let mesh = SphereMesh::create_uv_sphere_mesh(2.0, 64, 32, &MeshGenerationOptions::default());
+    check_mesh(&mesh);
+    assert!(mesh.vertices.iter().all(|v| (v.position.magnitude() - 2.0).abs() < 1e-9));
+    assert!((get_area(&mesh) / (16.0 * get_pi::<f64>()) - 1.0).abs() < 0.01);
+
+    let mesh = SphereMesh::create_icosphere_mesh(2.0, 3, &MeshGenerationOptions::default());
+    check_mesh(&mesh);
+    assert_eq!(mesh.triangles.len(), 20 * 64);
+    assert!(mesh.vertices.iter().all(|v| (v.position.magnitude() - 2.0).abs() < 1e-9));
+    assert!((get_area(&mesh) / (16.0 * get_pi::<f64>()) - 1.0).abs() < 0.01);
+    // no triangle is stretched over the whole uv square by the seam
+    for [a, b, c] in mesh.triangles.iter().copied() {
+        let u = [a, b, c].map(|i| mesh.vertices[i].uv0.unwrap().x);
+        assert!(u.iter().fold(0.0f64, |m, &x| m.max(x)) - u.iter().fold(2.0f64, |m, &x| m.min(x)) < 0.5);
+    }
+}
+
+#[test]
+fn test_cylinder_and_cone_mesh() {
+    let mesh = CylinderMesh::create_cylinder_mesh(1.0, 2.0, 128, 2, &MeshGenerationOptions::default());
+    check_mesh(&mesh);
+    assert_eq!(mesh.sub_mesh.len(), 3);
+    let pi = get_pi::<f64>();
+    assert!((get_area(&mesh) / (2.0 * pi * 2.0 + 2.0 * pi) - 1.0).abs() < 0.01);
+
+    let mesh = CylinderMesh::create_cone_mesh(1.0, 1.0, 128, 2, &MeshGenerationOptions::default());
+    check_mesh(&mesh);
+    assert!((get_area(&mesh) / (pi * 2.0f64.sqrt() + pi) - 1.0).abs() < 0.01);
+}
+
+#[test]
+fn test_torus_and_disk_mesh() {
+    let mesh = TorusMesh::create_torus_mesh(2.0, 0.5, 64, 32, &MeshGenerationOptions::default());
+    check_mesh(&mesh);
+    let pi = get_pi::<f64>();
+    assert!((get_area(&mesh) / (4.0 * pi * pi * 2.0 * 0.5) - 1.0).abs() < 0.01);
+
+    let mesh = DiskMesh::create_disk_mesh(2.0, 128, 3, &MeshGenerationOptions::default());
+    check_mesh(&mesh);
+    assert!((get_area(&mesh) / (4.0 * pi) - 1.0).abs() < 0.01);
+}
+
+#[test]
+fn test_mesh_generation_options() {
+    let options = MeshGenerationOptions {
+        uv: false,
+        normals: false,
+        tangents: true,
+    };
+    let mesh = SphereMesh::create_uv_sphere_mesh(1.0, 8, 4, &options);
+    assert!(mesh.vertices.iter().all(|v| v.uv0.is_none() && v.normal.is_none() && v.tangent.is_none()));
+}
@@ -0,0 +1,26 @@
+use cgmath::{BaseFloat, Vector2, Vector3};
+use aika_math::utils::get_pi;
+use crate::mesh::{CommonVertex, Mesh, MeshGenerationOptions};
+use crate::mesh::simple_mesh::mesh_builder::MeshBuilder;
+
+pub struct TorusMesh;
+
+impl TorusMesh {
+    /// A torus around the z axis, `major_segments` around the z axis and `minor_segments` around the tube.
+    /// u goes around the z axis and v around the tube, starting from the outer equator
+    pub fn create_torus_mesh<F>(major_radius: F, minor_radius: F, major_segments: usize, minor_segments: usize, options: &MeshGenerationOptions) -> Mesh<Vec<CommonVertex<F>>>
+    where
+        F: BaseFloat
+    {
+        let two_pi = get_pi::<F>() * F::from(2).unwrap();
+        let mut builder = MeshBuilder::new();
+        builder.add_grid(major_segments.max(3), minor_segments.max(3), [false, false], |u, v| {
+            let (sin_phi, cos_phi) = (two_pi * u).sin_cos();
+            let (sin_theta, cos_theta) = (two_pi * v).sin_cos();
+            let normal = Vector3::new(cos_theta * cos_phi, cos_theta * sin_phi, sin_theta);
+            let center = Vector3::new(major_radius * cos_phi, major_radius * sin_phi, F::zero());
+            (center + normal * minor_radius, normal, Vector2::new(u, v))
+        });
+        builder.finish(options)
+    }
+}
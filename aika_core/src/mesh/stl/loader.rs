@@ -0,0 +1,130 @@
+use std::fmt::Debug;
+use std::path::Path;
+use anyhow::{anyhow, bail, Result};
+use cgmath::{BaseFloat, InnerSpace, Vector3};
+use crate::mesh::{CommonVertex, Mesh};
+
+pub struct StlMeshLoader;
+
+fn get_f<F: BaseFloat>(x: f32) -> F {
+    F::from(x).unwrap()
+}
+
+/// A binary STL is an 80 bytes header, a triangle count, and 50 bytes per triangle.
+/// ASCII files start with `solid`, but so do the headers of some binary files, so the size decides
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() < 84 {
+        return false;
+    }
+    let count = u32::from_le_bytes(data[80..84].try_into().unwrap()) as usize;
+    84 + count * 50 == data.len()
+}
+
+struct StlFacet<F> {
+    normal: Vector3<F>,
+    positions: [Vector3<F>; 3],
+}
+
+fn parse_binary<F>(data: &[u8]) -> Vec<StlFacet<F>> where F: BaseFloat {
+    let read_vec3 = |chunk: &[u8], offset: usize| {
+        let get = |i: usize| {
+            let bytes = chunk[offset + i * 4..offset + i * 4 + 4].try_into().unwrap();
+            get_f::<F>(f32::from_le_bytes(bytes))
+        };
+        Vector3::new(get(0), get(1), get(2))
+    };
+
+    // the last 2 bytes of a triangle are the attribute byte count, which is ignored
+    data[84..].chunks_exact(50).map(|chunk| StlFacet {
+        normal: read_vec3(chunk, 0),
+        positions: [read_vec3(chunk, 12), read_vec3(chunk, 24), read_vec3(chunk, 36)],
+    }).collect()
+}
+
+fn parse_ascii<F>(data: &[u8]) -> Result<Vec<StlFacet<F>>> where F: BaseFloat {
+    let text = std::str::from_utf8(data)?;
+    let mut tokens = text.split_ascii_whitespace();
+    let read_vec3 = |tokens: &mut std::str::SplitAsciiWhitespace| -> Result<Vector3<F>> {
+        let mut get = || -> Result<F> {
+            let token = tokens.next().ok_or_else(|| anyhow!("unexpected end of stl data"))?;
+            Ok(get_f(token.parse::<f32>()?))
+        };
+        Ok(Vector3::new(get()?, get()?, get()?))
+    };
+
+    let mut facets = Vec::new();
+    let mut normal = Vector3::new(F::zero(), F::zero(), F::zero());
+    let mut positions = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            "normal" => normal = read_vec3(&mut tokens)?,
+            "vertex" => positions.push(read_vec3(&mut tokens)?),
+            "endfacet" => {
+                if positions.len() != 3 {
+                    bail!("stl facet has {} vertices", positions.len());
+                }
+                facets.push(StlFacet {
+                    normal,
+                    positions: [positions[0], positions[1], positions[2]],
+                });
+                positions.clear();
+                normal = Vector3::new(F::zero(), F::zero(), F::zero());
+            },
+            _ => {},
+        }
+    }
+
+    Ok(facets)
+}
+
+impl StlMeshLoader {
+    /// Load an ASCII or binary STL. The triangles do not share vertices, and each vertex has the facet normal.
+    /// Facets with a zero normal get the normal of their winding.
+    /// Use `Mesh::process` with `weld_epsilon` to merge the vertices
+    pub fn load_stl_memory<F>(data: &[u8]) -> Result<Mesh<Vec<CommonVertex<F>>>> where F: BaseFloat {
+        let facets = if is_binary(data) {
+            parse_binary(data)
+        } else if data.trim_ascii_start().starts_with(b"solid") {
+            parse_ascii(data)?
+        } else {
+            bail!("not a stl file");
+        };
+
+        let mut vertices = Vec::with_capacity(facets.len() * 3);
+        let mut triangles = Vec::with_capacity(facets.len());
+        for facet in facets.iter() {
+            let [a, b, c] = facet.positions;
+            let normal = if facet.normal.magnitude2() > F::zero() {
+                facet.normal.normalize()
+            } else {
+                let n = (b - a).cross(c - a);
+                if n.magnitude2() > F::zero() { n.normalize() } else { n }
+            };
+
+            let index = vertices.len();
+            for position in facet.positions {
+                let mut v = CommonVertex::new();
+                v.position = position;
+                v.normal = Some(normal);
+                vertices.push(v);
+            }
+            triangles.push([index, index + 1, index + 2]);
+        }
+
+        let triangle_count = triangles.len();
+        Ok(Mesh {
+            vertices,
+            triangles,
+            sub_mesh: vec![[0, triangle_count]]
+        })
+    }
+
+    pub fn load_stl<F, P>(p: P) -> Result<Mesh<Vec<CommonVertex<F>>>>
+        where
+            P: AsRef<Path> + Debug,
+            F: BaseFloat
+    {
+        let data = std::fs::read(p)?;
+        StlMeshLoader::load_stl_memory(&data)
+    }
+}
@@ -0,0 +1,4 @@
+pub use loader::StlMeshLoader;
+
+mod loader;
+mod test;
@@ -0,0 +1,52 @@
+use cgmath::{InnerSpace, Vector3};
+use crate::mesh::{StlMeshLoader, VertexBuffer};
+
+const TEST_STL_ASCII: &str = "solid test
+facet normal 0 0 1
+  outer loop
+    vertex 0 0 0
+    vertex 1 0 0
+    vertex 0 1 0
+  endloop
+endfacet
+facet normal 0 0 0
+  outer loop
+    vertex 0 0 0
+    vertex 0 1 0
+    vertex 0 0 1
+  endloop
+endfacet
+endsolid test
+";
+
+fn get_test_stl_binary() -> Vec<u8> {
+    // binary files may also start with `solid`
+    let mut data = b"solid binary".to_vec();
+    data.resize(80, 0);
+    data.extend_from_slice(&1u32.to_le_bytes());
+    for x in [0.0f32, 0.0, 2.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.0, 1.0, 1.0] {
+        data.extend_from_slice(&x.to_le_bytes());
+    }
+    data.extend_from_slice(&0u16.to_le_bytes());
+    data
+}
+
+#[test]
+fn test_stl_ascii() {
+    let mesh = StlMeshLoader::load_stl_memory::<f64>(TEST_STL_ASCII.as_bytes()).unwrap();
+    assert_eq!(mesh.vertices.len(), 6);
+    assert_eq!(mesh.triangles, vec![[0, 1, 2], [3, 4, 5]]);
+    assert!((mesh.vertices.get_position(5) - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+    assert!((mesh.vertices.get_normal(0).unwrap() - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+    // a zero facet normal is computed from the winding
+    assert!((mesh.vertices.get_normal(3).unwrap() - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-9);
+}
+
+#[test]
+fn test_stl_binary() {
+    let mesh = StlMeshLoader::load_stl_memory::<f64>(&get_test_stl_binary()).unwrap();
+    assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+    assert!((mesh.vertices.get_position(2) - Vector3::new(0.0, 1.0, 1.0)).magnitude() < 1e-9);
+    // the facet normal is normalized
+    assert!((mesh.vertices.get_normal(1).unwrap() - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+}
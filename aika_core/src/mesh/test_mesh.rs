@@ -0,0 +1,260 @@
+use cgmath::{InnerSpace, Vector2, Vector3};
+use crate::mesh::{CommonVertex, Mesh, MeshProcessingOptions, NormalWeighting, WavefrontMeshLoader};
+use crate::material_graph::{MaterialGraphContext, OutputValue};
+
+fn get_vertex(x: f64, y: f64, u: f64, v: f64) -> CommonVertex<f64> {
+    let mut vertex = CommonVertex::new();
+    vertex.position = Vector3::new(x, y, 0.0);
+    vertex.normal = Some(Vector3::new(0.0, 0.0, 1.0));
+    vertex.uv0 = Some(Vector2::new(u, v));
+    vertex
+}
+
+#[test]
+fn test_generate_tangents_quad() {
+    let mut mesh = Mesh {
+        vertices: vec![
+            get_vertex(0.0, 0.0, 0.0, 0.0),
+            get_vertex(1.0, 0.0, 1.0, 0.0),
+            get_vertex(1.0, 1.0, 1.0, 1.0),
+            get_vertex(0.0, 1.0, 0.0, 1.0),
+        ],
+        triangles: vec![[0, 1, 2], [0, 2, 3]],
+        sub_mesh: vec![[0, 2]],
+    };
+    mesh.generate_tangents().unwrap();
+
+    assert_eq!(mesh.vertices.len(), 4);
+    for vertex in mesh.vertices.iter() {
+        let t = vertex.tangent.unwrap();
+        assert!((t.x - 1.0).abs() < 1e-9 && t.y.abs() < 1e-9 && t.z.abs() < 1e-9);
+        assert_eq!(t.w, 1.0);
+    }
+}
+
+#[test]
+fn test_generate_tangents_mirrored() {
+    // the right half mirrors the uv of the left half, the shared edge has to be split
+    let mut mesh = Mesh {
+        vertices: vec![
+            get_vertex(0.0, 0.0, 0.0, 0.0),
+            get_vertex(1.0, 0.0, 1.0, 0.0),
+            get_vertex(1.0, 1.0, 1.0, 1.0),
+            get_vertex(0.0, 1.0, 0.0, 1.0),
+            get_vertex(2.0, 0.0, 0.0, 0.0),
+            get_vertex(2.0, 1.0, 0.0, 1.0),
+        ],
+        triangles: vec![[0, 1, 2], [0, 2, 3], [1, 4, 5], [1, 5, 2]],
+        sub_mesh: vec![[0, 4]],
+    };
+    mesh.generate_tangents().unwrap();
+
+    assert_eq!(mesh.vertices.len(), 8);
+    for tri in mesh.triangles[2..].iter() {
+        for &i in tri.iter() {
+            let t = mesh.vertices[i].tangent.unwrap();
+            assert!((t.x + 1.0).abs() < 1e-9);
+            assert_eq!(t.w, -1.0);
+        }
+    }
+    for tri in mesh.triangles[..2].iter() {
+        for &i in tri.iter() {
+            assert_eq!(mesh.vertices[i].tangent.unwrap().w, 1.0);
+        }
+    }
+}
+
+#[test]
+fn test_generate_tangents_without_uv() {
+    let mut mesh = Mesh {
+        vertices: vec![CommonVertex::<f64>::new(), CommonVertex::new(), CommonVertex::new()],
+        triangles: vec![[0, 1, 2]],
+        sub_mesh: vec![[0, 1]],
+    };
+    assert!(mesh.generate_tangents().is_err());
+}
+
+#[test]
+fn test_tessellate() {
+    let mesh = Mesh {
+        vertices: vec![
+            get_vertex(0.0, 0.0, 0.0, 0.0),
+            get_vertex(1.0, 0.0, 1.0, 0.0),
+            get_vertex(1.0, 1.0, 1.0, 1.0),
+            get_vertex(0.0, 1.0, 0.0, 1.0),
+        ],
+        triangles: vec![[0, 1, 2], [0, 2, 3]],
+        sub_mesh: vec![[0, 2]],
+    };
+    let tessellated = mesh.tessellate();
+    assert_eq!(tessellated.triangles.len(), 8);
+    // the diagonal midpoint is shared
+    assert_eq!(tessellated.vertices.len(), 9);
+    assert_eq!(tessellated.sub_mesh, vec![[0, 8]]);
+    let center = tessellated.vertices.iter().find(|v| (v.position - Vector3::new(0.5, 0.5, 0.0)).magnitude() < 1e-12).unwrap();
+    assert!((center.uv0.unwrap() - Vector2::new(0.5, 0.5)).magnitude() < 1e-12);
+}
+
+fn get_position_vertex(x: f64, y: f64, z: f64) -> CommonVertex<f64> {
+    let mut vertex = CommonVertex::new();
+    vertex.position = Vector3::new(x, y, z);
+    vertex
+}
+
+#[test]
+fn test_remove_degenerate_triangles() {
+    let mut mesh = Mesh {
+        vertices: vec![
+            get_position_vertex(0.0, 0.0, 0.0),
+            get_position_vertex(1.0, 0.0, 0.0),
+            get_position_vertex(0.0, 1.0, 0.0),
+            get_position_vertex(2.0, 0.0, 0.0),
+            get_position_vertex(f64::NAN, 0.0, 0.0),
+        ],
+        triangles: vec![[0, 1, 2], [0, 0, 1], [0, 1, 3], [0, 1, 4], [0, 1, 9]],
+        sub_mesh: vec![[0, 2], [2, 5]],
+    };
+
+    let report = mesh.validate();
+    assert_eq!(report.degenerate_triangles, 2);
+    assert_eq!(report.non_finite_triangles, 1);
+    assert_eq!(report.out_of_range_triangles, 1);
+    assert_eq!(report.missing_normals, 5);
+    assert!(!report.is_valid());
+
+    assert_eq!(mesh.remove_degenerate_triangles(), 4);
+    assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+    assert_eq!(mesh.sub_mesh, vec![[0, 1], [1, 1]]);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn test_weld_vertices() {
+    // two triangles of a quad, each with its own copy of the shared edge
+    let mut mesh = Mesh {
+        vertices: vec![
+            get_position_vertex(0.0, 0.0, 0.0),
+            get_position_vertex(1.0, 0.0, 0.0),
+            get_position_vertex(1.0, 1.0, 0.0),
+            get_position_vertex(0.0, 0.0, 0.0),
+            get_position_vertex(1.0, 1.0 + 1e-9, 0.0),
+            get_position_vertex(0.0, 1.0, 0.0),
+            get_position_vertex(5.0, 5.0, 5.0),
+        ],
+        triangles: vec![[0, 1, 2], [3, 4, 5]],
+        sub_mesh: vec![[0, 2]],
+    };
+
+    assert_eq!(mesh.weld_vertices(1e-6), 3);
+    assert_eq!(mesh.vertices.len(), 4);
+    assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+}
+
+fn get_corner_mesh() -> Mesh<Vec<CommonVertex<f64>>> {
+    // two faces meeting at a right angle along the y axis
+    Mesh {
+        vertices: vec![
+            get_position_vertex(0.0, 0.0, 0.0),
+            get_position_vertex(0.0, 1.0, 0.0),
+            get_position_vertex(1.0, 0.0, 0.0),
+            get_position_vertex(0.0, 0.0, 1.0),
+        ],
+        triangles: vec![[0, 1, 2], [0, 3, 1]],
+        sub_mesh: vec![[0, 2]],
+    }
+}
+
+#[test]
+fn test_generate_normals_crease() {
+    let mut hard = get_corner_mesh();
+    hard.generate_normals(NormalWeighting::Angle, 30.0f64.to_radians());
+    // the shared edge is split
+    assert_eq!(hard.vertices.len(), 6);
+    for &i in hard.triangles[0].iter() {
+        assert!((hard.vertices[i].normal.unwrap() - Vector3::new(0.0, 0.0, -1.0)).magnitude() < 1e-9);
+    }
+
+    let mut smooth = get_corner_mesh();
+    smooth.generate_normals(NormalWeighting::Area, 100.0f64.to_radians());
+    assert_eq!(smooth.vertices.len(), 4);
+    let expected = Vector3::new(-1.0, 0.0, -1.0).normalize();
+    assert!((smooth.vertices[smooth.triangles[0][0]].normal.unwrap() - expected).magnitude() < 1e-9);
+}
+
+#[test]
+fn test_load_processed() {
+    // no normals, and a face without area
+    let obj = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nv 2 0 0\nf 1 2 3\nf 1 2 4\n";
+    let meshes = WavefrontMeshLoader::load_wavefront_obj_memory_processed::<f64>(obj, &MeshProcessingOptions::default()).unwrap();
+    let mesh = &meshes[0];
+    assert_eq!(mesh.face_count(), 1);
+    let report = mesh.validate();
+    assert!(report.is_valid());
+    assert_eq!(report.missing_normals, 0);
+}
+
+struct UHeight;
+
+impl OutputValue<f64, f64> for UHeight {
+    fn get_value(&self, context: &MaterialGraphContext<f64>) -> f64 {
+        context.uv.x
+    }
+}
+
+#[test]
+fn test_displace_welds_uv_seam() {
+    // the edge at x = 1 is split, the two sides sample different heights
+    let mesh = Mesh {
+        vertices: vec![
+            get_vertex(0.0, 0.0, 0.0, 0.0),
+            get_vertex(1.0, 0.0, 1.0, 0.0),
+            get_vertex(1.0, 1.0, 1.0, 1.0),
+            get_vertex(0.0, 1.0, 0.0, 1.0),
+            get_vertex(1.0, 0.0, 0.0, 0.0),
+            get_vertex(2.0, 0.0, 1.0, 0.0),
+            get_vertex(2.0, 1.0, 1.0, 1.0),
+            get_vertex(1.0, 1.0, 0.0, 1.0),
+        ],
+        triangles: vec![[0, 1, 2], [0, 2, 3], [4, 5, 6], [4, 6, 7]],
+        sub_mesh: vec![[0, 4]],
+    };
+    let displaced = mesh.displace(&UHeight, 1.0, 1).unwrap();
+
+    let seam: Vec<_> = displaced.vertices.iter().filter(|v| (v.position.x - 1.0).abs() < 1e-12).collect();
+    assert!(!seam.is_empty());
+    for vertex in seam.iter() {
+        let twin = seam.iter().filter(|w| (w.position.y - vertex.position.y).abs() < 1e-12).count();
+        assert!(twin >= 2);
+        assert!((vertex.position.z - 0.5).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn test_transform_mirror() {
+    let mut mesh = Mesh {
+        vertices: vec![
+            get_vertex(0.0, 0.0, 0.0, 0.0),
+            get_vertex(1.0, 0.0, 1.0, 0.0),
+            get_vertex(0.0, 1.0, 0.0, 1.0),
+        ],
+        triangles: vec![[0, 1, 2]],
+        sub_mesh: vec![[0, 1]],
+    };
+    mesh.generate_tangents().unwrap();
+
+    let mirror = aika_math::Transform::translate(Vector3::new(0.0, 0.0, 2.0)) * aika_math::Transform::scale(Vector3::new(-1.0, 1.0, 1.0));
+    let transformed = mesh.transform(&mirror);
+    assert_eq!(transformed.triangles, vec![[0, 2, 1]]);
+    assert!((transformed.vertices[1].position - Vector3::new(-1.0, 0.0, 2.0)).magnitude() < 1e-9);
+
+    // the reversed winding still faces the normal
+    let p: Vec<Vector3<f64>> = transformed.triangles[0].iter().map(|&i| transformed.vertices[i].position).collect();
+    let face_normal = (p[1] - p[0]).cross(p[2] - p[0]);
+    let v = &transformed.vertices[0];
+    assert!(face_normal.dot(v.normal.unwrap()) > 0.0);
+    // the bitangent still points along +v, which stays +y
+    let t = v.tangent.unwrap();
+    assert!((t.truncate() - Vector3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-9);
+    let bitangent = v.normal.unwrap().cross(t.truncate()) * t.w;
+    assert!((bitangent - Vector3::new(0.0, 1.0, 0.0)).magnitude() < 1e-9);
+}
@@ -0,0 +1,124 @@
+use cgmath::{InnerSpace, Vector2, Vector3};
+use crate::mesh::{CommonVertex, MeshGenerationOptions, PolygonMesh, SphereMesh, SubdivisionScheme, WavefrontMeshLoader};
+
+const CUBE_OBJ: &[u8] = b"v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\n\
+v -1 -1 1\nv 1 -1 1\nv 1 1 1\nv -1 1 1\n\
+f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 3 4 8 7\nf 2 3 7 6\nf 4 1 5 8\n";
+
+fn get_cube() -> PolygonMesh<f64> {
+    WavefrontMeshLoader::load_wavefront_obj_polygons_memory(CUBE_OBJ).unwrap().remove(0)
+}
+
+fn find_vertex(mesh: &PolygonMesh<f64>, position: Vector3<f64>) -> Option<&CommonVertex<f64>> {
+    mesh.vertices.iter().find(|v| (v.position - position).magnitude() < 1e-9)
+}
+
+#[test]
+fn test_load_polygons() {
+    let cube = get_cube();
+    assert_eq!(cube.vertices.len(), 8);
+    assert_eq!(cube.faces.len(), 6);
+    assert!(cube.faces.iter().all(|f| f.len() == 4));
+    assert!(!cube.is_triangle_mesh());
+    assert_eq!(cube.to_mesh().unwrap().triangles.len(), 12);
+}
+
+#[test]
+fn test_catmull_clark_cube() {
+    let cube = get_cube();
+    let level1 = cube.subdivide(SubdivisionScheme::CatmullClark, 1).unwrap();
+    assert_eq!(level1.faces.len(), 24);
+    // one vertex for each vertex, edge and face of the cube
+    assert_eq!(level1.vertices.len(), 26);
+    // (Q + 2R + (n - 3)S) / n with Q = 1/3 and R = 2/3
+    let c = 5.0 / 9.0;
+    assert!(find_vertex(&level1, Vector3::new(c, c, c)).is_some());
+
+    let level2 = cube.subdivide(SubdivisionScheme::CatmullClark, 2).unwrap();
+    assert_eq!(level2.faces.len(), 96);
+    assert_eq!(level2.sub_mesh, vec![[0, 96]]);
+    for v in level2.vertices.iter() {
+        assert!(v.normal.unwrap().dot(v.position) > 0.0);
+    }
+    assert_eq!(level2.to_mesh().unwrap().triangles.len(), 192);
+}
+
+#[test]
+fn test_catmull_clark_crease() {
+    let cube = get_cube();
+    let mut creased = cube.clone();
+    for face in cube.faces.iter() {
+        for i in 0..4 {
+            creased = creased.with_crease(face[i], face[(i + 1) % 4], f64::INFINITY);
+        }
+    }
+
+    // the sharp cube stays a cube
+    let sharp = creased.subdivide(SubdivisionScheme::CatmullClark, 2).unwrap();
+    for v in sharp.vertices.iter() {
+        let p = v.position;
+        assert!((p.x.abs().max(p.y.abs()).max(p.z.abs()) - 1.0).abs() < 1e-9);
+    }
+    assert!(find_vertex(&sharp, Vector3::new(1.0, 1.0, 1.0)).is_some());
+    // the normals are split at the creases
+    let top = find_vertex(&sharp, Vector3::new(0.5, 0.5, 1.0)).unwrap();
+    assert!((top.normal.unwrap() - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+
+    // a sharpness of 1 keeps the corner for the first step only
+    let mut semi_sharp = cube.clone();
+    semi_sharp.creases = creased.creases.iter().map(|&(e, _)| (e, 1.0)).collect();
+    let semi_sharp_1 = semi_sharp.subdivide(SubdivisionScheme::CatmullClark, 1).unwrap();
+    assert!(find_vertex(&semi_sharp_1, Vector3::new(1.0, 1.0, 1.0)).is_some());
+    let max_coordinate = |mesh: &PolygonMesh<f64>| mesh.vertices.iter().map(|v| v.position.x + v.position.y + v.position.z).fold(f64::MIN, f64::max);
+    let smooth_2 = cube.subdivide(SubdivisionScheme::CatmullClark, 2).unwrap();
+    let semi_sharp_2 = semi_sharp.subdivide(SubdivisionScheme::CatmullClark, 2).unwrap();
+    assert!(max_coordinate(&semi_sharp_2) < 3.0 - 1e-6);
+    assert!(max_coordinate(&semi_sharp_2) > max_coordinate(&smooth_2) + 1e-6);
+}
+
+#[test]
+fn test_subdivide_boundary_uv() {
+    let get_vertex = |x: f64, y: f64| {
+        let mut v = CommonVertex::new();
+        v.position = Vector3::new(x, y, 0.0);
+        v.uv0 = Some(Vector2::new(x, y));
+        v
+    };
+    let quad = PolygonMesh {
+        vertices: vec![get_vertex(0.0, 0.0), get_vertex(1.0, 0.0), get_vertex(1.0, 1.0), get_vertex(0.0, 1.0)],
+        faces: vec![vec![0, 1, 2, 3]],
+        sub_mesh: vec![[0, 1]],
+        creases: Vec::new(),
+    };
+
+    // the boundary corners stay, and a flat grid keeps its uv equal to the position
+    let subdivided = quad.subdivide(SubdivisionScheme::CatmullClark, 2).unwrap();
+    assert_eq!(subdivided.faces.len(), 16);
+    assert_eq!(subdivided.vertices.len(), 25);
+    for v in subdivided.vertices.iter() {
+        let uv = v.uv0.unwrap();
+        assert!((v.position - Vector3::new(uv.x, uv.y, 0.0)).magnitude() < 1e-9);
+        assert!((v.normal.unwrap() - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+    }
+    let mesh = subdivided.to_mesh().unwrap();
+    assert!(mesh.vertices.iter().all(|v| v.tangent.is_some()));
+}
+
+#[test]
+fn test_loop_icosphere() {
+    let icosphere = SphereMesh::create_icosphere_mesh(1.0, 0, &MeshGenerationOptions::default());
+    let control = PolygonMesh::from_mesh(&icosphere);
+    assert!(control.is_triangle_mesh());
+    let subdivided = control.subdivide(SubdivisionScheme::Loop, 2).unwrap();
+    assert_eq!(subdivided.faces.len(), 320);
+    assert_eq!(subdivided.sub_mesh, vec![[0, 320]]);
+    for v in subdivided.vertices.iter() {
+        let r = v.position.magnitude();
+        assert!(r > 0.6 && r < 1.0);
+        assert!(v.normal.unwrap().dot(v.position) > 0.0);
+        // the uv seam is kept
+        assert!(v.uv0.is_some());
+    }
+
+    assert!(get_cube().subdivide(SubdivisionScheme::Loop, 1).is_err());
+}
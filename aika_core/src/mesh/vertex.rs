@@ -4,6 +4,8 @@ use num_traits::Zero;
 pub trait VertexBuffer {
     type FloatType;
 
+    fn vertex_count(&self) -> usize;
+
     fn get_position(&self, index: usize) -> Vector3<Self::FloatType>;
 
     fn get_normal(&self, index: usize) -> Option<Vector3<Self::FloatType>>;
@@ -12,7 +14,8 @@ pub trait VertexBuffer {
 
     fn get_uv1(&self, index: usize) -> Option<Vector2<Self::FloatType>>;
 
-    fn get_tangent(&self, index: usize) -> Option<Vector3<Self::FloatType>>;
+    /// xyz is the tangent, w is the sign of the bitangent `w * cross(normal, tangent)`
+    fn get_tangent(&self, index: usize) -> Option<Vector4<Self::FloatType>>;
 
     fn get_color(&self, index: usize) -> Option<Vector3<Self::FloatType>>;
 }
@@ -23,7 +26,8 @@ pub struct CommonVertex<F> {
     pub normal: Option<Vector3<F>>,
     pub uv0: Option<Vector2<F>>,
     pub uv1: Option<Vector2<F>>,
-    pub tangent: Option<Vector3<F>>,
+    /// xyz is the tangent, w is the sign of the bitangent `w * cross(normal, tangent)`
+    pub tangent: Option<Vector4<F>>,
     pub color: Option<Vector3<F>>,
 }
 
@@ -43,6 +47,10 @@ impl<F> CommonVertex<F> where F: BaseFloat {
 impl<F> VertexBuffer for Vec<CommonVertex<F>> where F: BaseFloat {
     type FloatType = F;
 
+    fn vertex_count(&self) -> usize {
+        self.len()
+    }
+
     fn get_position(&self, index: usize) -> Vector3<Self::FloatType> {
         let v = &self[index];
         v.position
@@ -63,7 +71,7 @@ impl<F> VertexBuffer for Vec<CommonVertex<F>> where F: BaseFloat {
         v.uv1
     }
 
-    fn get_tangent(&self, index: usize) -> Option<Vector3<Self::FloatType>> {
+    fn get_tangent(&self, index: usize) -> Option<Vector4<Self::FloatType>> {
         let v = &self[index];
         v.tangent
     }
@@ -79,6 +87,10 @@ pub type BoxDynVertexBuffer<F> = Box<dyn VertexBuffer<FloatType = F>>;
 impl<F> VertexBuffer for BoxDynVertexBuffer<F> {
     type FloatType = F;
 
+    fn vertex_count(&self) -> usize {
+        self.as_ref().vertex_count()
+    }
+
     fn get_position(&self, index: usize) -> Vector3<Self::FloatType> {
         self.as_ref().get_position(index)
     }
@@ -95,7 +107,7 @@ impl<F> VertexBuffer for BoxDynVertexBuffer<F> {
         self.as_ref().get_uv1(index)
     }
 
-    fn get_tangent(&self, index: usize) -> Option<Vector3<Self::FloatType>> {
+    fn get_tangent(&self, index: usize) -> Option<Vector4<Self::FloatType>> {
         self.as_ref().get_tangent(index)
     }
 
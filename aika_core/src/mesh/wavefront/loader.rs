@@ -1,14 +1,21 @@
 use std::fmt::Debug;
 use std::io::BufReader;
 use std::path::Path;
-use crate::mesh::{CommonVertex, Mesh, VertexBuffer};
-use anyhow::Result;
-use cgmath::{BaseFloat, Vector2, Vector3};
+use crate::component::{MeshFilter, Transform};
+use crate::material::{DiffuseBRDFMaterial, Material, MaterialSlots};
+use crate::mesh::{CommonVertex, Mesh, MeshProcessingOptions, PolygonMesh, VertexBuffer};
+use crate::mesh::wavefront::mtl::MtlConverter;
+use crate::scene::GameObject;
+use anyhow::{anyhow, bail, Result};
+use cgmath::{BaseFloat, One, Quaternion, Vector2, Vector3};
 use num_traits::ToPrimitive;
 use tobj::{LoadError, LoadOptions};
 
 pub struct WavefrontMeshLoader;
 
+/// A material group of an OBJ file: the model name, its mesh and its converted material
+type MaterialGroup<F> = (String, Mesh<Vec<CommonVertex<F>>>, Material<F>);
+
 fn get_vec3<T, F>(buf: &[T], index: usize) -> Vector3<F> where T: Copy + ToPrimitive, F: BaseFloat {
     Vector3::new(
         F::from(buf[3 * index]).unwrap(),
@@ -25,22 +32,28 @@ fn get_vec2<T, F>(buf: &[T], index: usize) -> Vector2<F> where T: Copy + ToPrimi
 }
 
 impl WavefrontMeshLoader {
-    fn parse_model<F>(model: &tobj::Model) -> Mesh<Vec<CommonVertex<F>>> where F: BaseFloat {
-        let mut vertices: Vec<CommonVertex<F>> = Vec::new();
-        let mut triangles = Vec::new();
-
+    fn parse_vertices<F>(model: &tobj::Model) -> Vec<CommonVertex<F>> where F: BaseFloat {
         let vertex_count = model.mesh.positions.len() / 3;
-        for i in 0..vertex_count {
+        (0..vertex_count).map(|i| {
             let mut v: CommonVertex<F> = CommonVertex::new();
             v.position = get_vec3::<_, F>(model.mesh.positions.as_slice(), i);
             if !model.mesh.normals.is_empty() {
-                v.normal = Some(get_vec3(&model.mesh.normals.as_slice(), i));
+                v.normal = Some(get_vec3(model.mesh.normals.as_slice(), i));
             }
             if !model.mesh.texcoords.is_empty() {
-                v.uv0 = Some(get_vec2(&model.mesh.texcoords.as_slice(), i));
+                v.uv0 = Some(get_vec2(model.mesh.texcoords.as_slice(), i));
             }
-            vertices.push(v);
-        }
+            // `v x y z r g b`
+            if !model.mesh.vertex_color.is_empty() {
+                v.color = Some(get_vec3(model.mesh.vertex_color.as_slice(), i));
+            }
+            v
+        }).collect()
+    }
+
+    fn parse_model<F>(model: &tobj::Model, options: Option<&MeshProcessingOptions<F>>) -> Result<Mesh<Vec<CommonVertex<F>>>> where F: BaseFloat {
+        let vertices = WavefrontMeshLoader::parse_vertices(model);
+        let mut triangles = Vec::new();
 
         let triangle_count = model.mesh.indices.len() / 3;
         for i in 0..triangle_count {
@@ -49,17 +62,39 @@ impl WavefrontMeshLoader {
                 model.mesh.indices[i * 3 + 1] as usize,
                 model.mesh.indices[i * 3 + 2] as usize
             ];
-            triangles.push(tri.clone());
+            triangles.push(tri);
         }
 
-        Mesh {
+        let mut mesh = Mesh {
             vertices,
             triangles,
             sub_mesh: vec![[0, triangle_count]]
+        };
+        if let Some(options) = options {
+            // tangents are generated by the processing
+            mesh.process(options)?;
+        } else if !model.mesh.texcoords.is_empty() {
+            mesh.generate_tangents()?;
         }
+        Ok(mesh)
     }
 
     pub fn load_wavefront_obj_memory<F>(data: &[u8]) -> Result<Vec<Mesh<Vec<CommonVertex<F>>>>>
+    where
+        F: BaseFloat
+    {
+        WavefrontMeshLoader::load_wavefront_obj_memory_impl(data, None)
+    }
+
+    /// Same as `load_wavefront_obj_memory`, and each mesh is cleaned up with `Mesh::process`
+    pub fn load_wavefront_obj_memory_processed<F>(data: &[u8], options: &MeshProcessingOptions<F>) -> Result<Vec<Mesh<Vec<CommonVertex<F>>>>>
+    where
+        F: BaseFloat
+    {
+        WavefrontMeshLoader::load_wavefront_obj_memory_impl(data, Some(options))
+    }
+
+    fn load_wavefront_obj_memory_impl<F>(data: &[u8], options: Option<&MeshProcessingOptions<F>>) -> Result<Vec<Mesh<Vec<CommonVertex<F>>>>>
     where
         F: BaseFloat
     {
@@ -72,13 +107,13 @@ impl WavefrontMeshLoader {
         let (models, _materials) = tobj::load_obj_buf(
             &mut reader,
             &load_options,
-            // todo. currently we don't care mtl materials
-            |p| Err(LoadError::OpenFileFailed)
+            // MTL files are referenced by path, use `load_wavefront_obj_game_objects` to import the materials
+            |_| Err(LoadError::OpenFileFailed)
         )?;
 
         let mut result = Vec::new();
         for model in models.iter() {
-            let m = WavefrontMeshLoader::parse_model::<F>(model);
+            let m = WavefrontMeshLoader::parse_model::<F>(model, options)?;
             result.push(m);
         }
 
@@ -89,22 +124,188 @@ impl WavefrontMeshLoader {
         where
             P: AsRef<Path> + Debug,
             F: BaseFloat
+    {
+        WavefrontMeshLoader::load_wavefront_obj_impl(p, None)
+    }
+
+    /// Same as `load_wavefront_obj`, and each mesh is cleaned up with `Mesh::process`
+    pub fn load_wavefront_obj_processed<F, P>(p: P, options: &MeshProcessingOptions<F>) -> Result<Vec<Mesh<Vec<CommonVertex<F>>>>>
+        where
+            P: AsRef<Path> + Debug,
+            F: BaseFloat
+    {
+        WavefrontMeshLoader::load_wavefront_obj_impl(p, Some(options))
+    }
+
+    fn load_wavefront_obj_impl<F, P>(p: P, options: Option<&MeshProcessingOptions<F>>) -> Result<Vec<Mesh<Vec<CommonVertex<F>>>>>
+        where
+            P: AsRef<Path> + Debug,
+            F: BaseFloat
     {
         let load_options = LoadOptions {
             triangulate: true,
+            // the normals and the texcoords are indexed by the position indices
+            single_index: true,
             ..Default::default()
         };
 
         let (models, _materials) = tobj::load_obj(p, &load_options)?;
         let mut result = Vec::new();
         for model in models.iter() {
-            let m = WavefrontMeshLoader::parse_model::<F>(model);
+            let m = WavefrontMeshLoader::parse_model::<F>(model, options)?;
             result.push(m);
         }
 
         Ok(result)
     }
 
+    /// The polygons of a model loaded without triangulation
+    fn parse_polygons<F>(model: &tobj::Model) -> Result<PolygonMesh<F>> where F: BaseFloat {
+        let vertices = WavefrontMeshLoader::parse_vertices(model);
+        let indices: Vec<usize> = model.mesh.indices.iter().map(|&i| i as usize).collect();
+        if indices.iter().any(|&i| i >= vertices.len()) {
+            bail!("obj face references a vertex out of range");
+        }
+
+        // no arities means that all the faces are triangles
+        let faces: Vec<Vec<usize>> = if model.mesh.face_arities.is_empty() {
+            indices.chunks(3).map(|c| c.to_vec()).collect()
+        } else {
+            let mut faces = Vec::with_capacity(model.mesh.face_arities.len());
+            let mut start = 0;
+            for &arity in model.mesh.face_arities.iter() {
+                let end = start + arity as usize;
+                faces.push(indices.get(start..end).ok_or_else(|| anyhow!("obj face arities exceed the indices"))?.to_vec());
+                start = end;
+            }
+            faces
+        };
+
+        let face_count = faces.len();
+        Ok(PolygonMesh {
+            vertices,
+            faces,
+            sub_mesh: vec![[0, face_count]],
+            creases: Vec::new(),
+        })
+    }
+
+    fn load_wavefront_obj_polygons_impl<F>(models: &[tobj::Model]) -> Result<Vec<PolygonMesh<F>>> where F: BaseFloat {
+        models.iter().map(|model| WavefrontMeshLoader::parse_polygons(model)).collect()
+    }
+
+    /// Load the models keeping their quads and other polygons, as control meshes for `PolygonMesh::subdivide`
+    pub fn load_wavefront_obj_polygons_memory<F>(data: &[u8]) -> Result<Vec<PolygonMesh<F>>> where F: BaseFloat {
+        let load_options = LoadOptions {
+            triangulate: false,
+            single_index: true,
+            ..Default::default()
+        };
+        let mut reader = BufReader::new(data);
+        let (models, _materials) = tobj::load_obj_buf(
+            &mut reader,
+            &load_options,
+            |_| Err(LoadError::OpenFileFailed)
+        )?;
+        WavefrontMeshLoader::load_wavefront_obj_polygons_impl(&models)
+    }
+
+    pub fn load_wavefront_obj_polygons<F, P>(p: P) -> Result<Vec<PolygonMesh<F>>>
+        where
+            P: AsRef<Path> + Debug,
+            F: BaseFloat
+    {
+        let load_options = LoadOptions {
+            triangulate: false,
+            single_index: true,
+            ..Default::default()
+        };
+        let (models, _materials) = tobj::load_obj(p, &load_options)?;
+        WavefrontMeshLoader::load_wavefront_obj_polygons_impl(&models)
+    }
+
+    /// The meshes of the material groups, with their converted materials and the model names
+    fn load_wavefront_obj_with_materials<F, P>(p: P) -> Result<Vec<MaterialGroup<F>>>
+        where
+            P: AsRef<Path> + Debug,
+            F: BaseFloat + 'static
+    {
+        let load_options = LoadOptions {
+            triangulate: true,
+            single_index: true,
+            ..Default::default()
+        };
+
+        let base_dir = p.as_ref().parent().unwrap_or(Path::new("")).to_path_buf();
+        let (models, materials) = tobj::load_obj(p, &load_options)?;
+        let materials = materials.unwrap_or_default();
+        let mut converter = MtlConverter::new(&base_dir);
+
+        let mut result = Vec::new();
+        for model in models.iter() {
+            let mesh = WavefrontMeshLoader::parse_model::<F>(model, None)?;
+            let material = match model.mesh.material_id.and_then(|id| materials.get(id)) {
+                Some(mtl) => converter.convert(mtl),
+                None => {
+                    let gray = F::from(0.8).unwrap();
+                    Material::new(Box::new(DiffuseBRDFMaterial::new(Vector3::new(gray, gray, gray))))
+                }
+            };
+            result.push((model.name.clone(), mesh, material));
+        }
+
+        Ok(result)
+    }
+
+    /// Load an OBJ file with its MTL materials.
+    /// Each material group becomes a game object with a `MeshFilter` and a `Material`,
+    /// groups without a material, or whose MTL file is missing, get a gray diffuse material
+    pub fn load_wavefront_obj_game_objects<F, P>(p: P) -> Result<Vec<GameObject<F>>>
+        where
+            P: AsRef<Path> + Debug,
+            F: BaseFloat + 'static
+    {
+        let mut result = Vec::new();
+        for (name, mesh, material) in WavefrontMeshLoader::load_wavefront_obj_with_materials::<F, _>(p)? {
+            let mut go = GameObject::new_empty(name);
+            go.add_component_owned(Transform::new(Vector3::new(F::zero(), F::zero(), F::zero()), F::one(), Quaternion::one()));
+            go.add_component_owned(MeshFilter::new(mesh.to_dyn_mesh()));
+            go.add_component_owned(material);
+            result.push(go);
+        }
+
+        Ok(result)
+    }
+
+    /// Same as `load_wavefront_obj_game_objects`, but all the material groups are merged into one mesh.
+    /// Each group is a sub mesh, and its material is the slot of the same index in `MaterialSlots`
+    pub fn load_wavefront_obj_game_object<F, P>(p: P, name: String) -> Result<GameObject<F>>
+        where
+            P: AsRef<Path> + Debug,
+            F: BaseFloat + 'static
+    {
+        let mut mesh: Mesh<Vec<CommonVertex<F>>> = Mesh {
+            vertices: Vec::new(),
+            triangles: Vec::new(),
+            sub_mesh: Vec::new(),
+        };
+        let mut materials = Vec::new();
+        for (_, group, material) in WavefrontMeshLoader::load_wavefront_obj_with_materials::<F, _>(p)? {
+            let offset = mesh.vertices.len();
+            let start = mesh.triangles.len();
+            mesh.vertices.extend(group.vertices);
+            mesh.triangles.extend(group.triangles.iter().map(|t| t.map(|i| i + offset)));
+            mesh.sub_mesh.push([start, mesh.triangles.len()]);
+            materials.push(material);
+        }
+
+        let mut go = GameObject::new_empty(name);
+        go.add_component_owned(Transform::new(Vector3::new(F::zero(), F::zero(), F::zero()), F::one(), Quaternion::one()));
+        go.add_component_owned(MeshFilter::new(mesh.to_dyn_mesh()));
+        go.add_component_owned(MaterialSlots::new(materials));
+        Ok(go)
+    }
+
     pub fn suzanne<F>() -> Result<Mesh<Vec<CommonVertex<F>>>> where F: BaseFloat {
         let obj_file = include_bytes!("./suzanne.obj");
         let result = WavefrontMeshLoader::load_wavefront_obj_memory(obj_file.as_slice())?;
@@ -1,4 +1,7 @@
 pub use loader::WavefrontMeshLoader;
+pub use writer::WavefrontMeshWriter;
 
 mod loader;
+mod mtl;
+mod writer;
 mod test;
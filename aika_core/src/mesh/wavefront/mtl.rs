@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use cgmath::{BaseFloat, Vector3};
+use crate::material::{BumpMap, DielectricMaterial, DiffuseBRDFMaterial, Material, PrincipledBSDFMaterial, RoughDielectricBSDFMaterial};
+use crate::material_graph::{ChannelNode, MultiplyNode, OutputValue, Texture2DNode};
+use crate::texture::Texture2D;
+
+/// A texture statement of MTL, such as `map_Kd` or `bump`, with its options
+struct MtlTextureMap {
+    file_name: String,
+    /// `-bm`, the multiplier of a bump map
+    bump_multiplier: Option<f64>,
+}
+
+/// The file name is the last word, options such as `-bm 0.5` come before it.
+/// File names with spaces are not supported
+fn parse_texture_map(statement: &str) -> Option<MtlTextureMap> {
+    let words: Vec<&str> = statement.split_whitespace().collect();
+    let file_name = words.last()?.to_string();
+    let bump_multiplier = words.iter()
+        .position(|&w| w == "-bm")
+        .and_then(|i| words.get(i + 1))
+        .and_then(|w| w.parse().ok());
+    Some(MtlTextureMap {
+        file_name,
+        bump_multiplier
+    })
+}
+
+fn get_vec3<F: BaseFloat>(v: [f32; 3]) -> Vector3<F> {
+    Vector3::new(F::from(v[0]).unwrap(), F::from(v[1]).unwrap(), F::from(v[2]).unwrap())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MtlMaterialKind {
+    /// `RoughDielectricBSDFMaterial`, or `DielectricMaterial` if smooth
+    Dielectric,
+    /// `PrincipledBSDFMaterial`, non metallic
+    Principled,
+    /// `DiffuseBRDFMaterial`
+    Diffuse,
+}
+
+/// The constants of an MTL material, mapped onto the parameters of the material it converts to
+#[derive(Clone, Debug)]
+pub(crate) struct MtlParameters<F> {
+    pub kind: MtlMaterialKind,
+    /// `Kd`
+    pub color: Vector3<F>,
+    /// From the Phong exponent `Ns` as `sqrt(2 / (Ns + 2))`, None without `Ns`
+    pub roughness: Option<F>,
+    /// The ior whose Fresnel reflectance at normal incidence is `0.08 * max(Ks)`, as the specular of the Disney BRDF.
+    /// `Ni` if `Ks` is black
+    pub specular_ior: F,
+    /// `1 - d`
+    pub transmission: F,
+    /// `Ni`
+    pub ior: F,
+}
+
+/// Mapping:
+/// * a glass `illum` (4, 6, 7, 9) gives `Dielectric` with the ior `Ni`, smooth without `Ns`
+/// * `d` < 1 or a non black `Ks` gives `Principled`, which transmits `1 - d` of the light
+/// * otherwise `Diffuse`
+pub(crate) fn get_mtl_parameters<F: BaseFloat>(mtl: &tobj::Material) -> MtlParameters<F> {
+    let color: Vector3<F> = mtl.diffuse.map(get_vec3).unwrap_or(Vector3::new(F::one(), F::one(), F::one()));
+    let ks: Vector3<F> = mtl.specular.map(get_vec3).unwrap_or(Vector3::new(F::zero(), F::zero(), F::zero()));
+    let roughness = mtl.shininess.map(|ns| {
+        let ns = F::from(ns).unwrap().max(F::zero());
+        (F::from(2).unwrap() / (ns + F::from(2).unwrap())).sqrt().max(F::from(1e-3).unwrap())
+    });
+    let ior = F::from(mtl.optical_density.unwrap_or(1.5)).unwrap();
+    let transmission = F::one() - F::from(mtl.dissolve.unwrap_or(1.0)).unwrap().max(F::zero()).min(F::one());
+
+    let specular = ks.x.max(ks.y).max(ks.z).max(F::zero()).min(F::one());
+    let specular_ior = if specular > F::zero() {
+        let sqrt_f0 = (F::from(0.08).unwrap() * specular).sqrt();
+        (F::one() + sqrt_f0) / (F::one() - sqrt_f0)
+    } else {
+        ior
+    };
+
+    let kind = if matches!(mtl.illumination_model, Some(4) | Some(6) | Some(7) | Some(9)) {
+        MtlMaterialKind::Dielectric
+    } else if transmission > F::zero() || specular > F::zero() {
+        MtlMaterialKind::Principled
+    } else {
+        MtlMaterialKind::Diffuse
+    };
+
+    MtlParameters {
+        kind,
+        color,
+        roughness,
+        specular_ior,
+        transmission,
+        ior,
+    }
+}
+
+/// Converts MTL materials into `Material`s, the textures are shared between the materials
+pub(crate) struct MtlConverter {
+    /// the directory of the OBJ file, texture paths are relative to it
+    base_dir: PathBuf,
+    textures: HashMap<(PathBuf, bool), Option<Rc<Texture2D>>>,
+}
+
+impl MtlConverter {
+    pub fn new(base_dir: &Path) -> Self {
+        MtlConverter {
+            base_dir: base_dir.to_path_buf(),
+            textures: HashMap::new(),
+        }
+    }
+
+    /// None if the file can not be read, the material then falls back to its constants
+    fn get_texture(&mut self, file_name: &str, is_srgb: bool) -> Option<Rc<Texture2D>> {
+        // some exporters write windows paths
+        let path = self.base_dir.join(file_name.replace('\\', "/"));
+        self.textures.entry((path.clone(), is_srgb)).or_insert_with(|| {
+            let mut texture = Texture2D::from_file(path.to_str()?)?;
+            texture.is_srgb = is_srgb;
+            Some(Rc::new(texture))
+        }).clone()
+    }
+
+    /// `map_Kd` is multiplied by `Kd`, and `bump` (or `map_Bump`) becomes the bump map, scaled by `-bm`.
+    /// See `get_mtl_parameters` for the mapping of the constants
+    pub fn convert<F>(&mut self, mtl: &tobj::Material) -> Material<F> where F: BaseFloat + 'static {
+        let parameters: MtlParameters<F> = get_mtl_parameters(mtl);
+
+        let diffuse_texture = mtl.diffuse_texture.as_deref()
+            .and_then(parse_texture_map)
+            .and_then(|map| self.get_texture(&map.file_name, true));
+        let color: Rc<dyn OutputValue<F, Vector3<F>>> = match diffuse_texture {
+            Some(texture) => Rc::new(MultiplyNode::new(Rc::new(Texture2DNode::new(texture)), parameters.color)),
+            None => Rc::new(parameters.color),
+        };
+
+        let mut material = match parameters.kind {
+            MtlMaterialKind::Dielectric => match parameters.roughness {
+                Some(roughness) => Material::new(Box::new(RoughDielectricBSDFMaterial::new(Rc::new(roughness), parameters.ior))),
+                None => {
+                    let ior = parameters.ior;
+                    Material::new(Box::new(DielectricMaterial::new(Vector3::new(ior, ior, ior))))
+                },
+            },
+            MtlMaterialKind::Principled => {
+                let mut principled = PrincipledBSDFMaterial::new(color)
+                    .with_roughness(Rc::new(parameters.roughness.unwrap_or(F::one())))
+                    .with_ior(Rc::new(parameters.specular_ior));
+                if parameters.transmission > F::zero() {
+                    principled = principled.with_transmission(Rc::new(parameters.transmission), parameters.ior);
+                }
+                Material::new(Box::new(principled))
+            },
+            MtlMaterialKind::Diffuse => Material::new(Box::new(DiffuseBRDFMaterial::from_graph(color))),
+        };
+
+        let bump = mtl.normal_texture.as_deref().and_then(parse_texture_map);
+        if let Some(bump) = bump {
+            if let Some(texture) = self.get_texture(&bump.file_name, false) {
+                // one texel is the step of the finite differences,
+                // and a full range step between texels tilts the normal by 45 degrees when `-bm` is 1
+                let texel = F::one() / F::from(texture.get_width().max(1)).unwrap();
+                let multiplier = F::from(bump.bump_multiplier.unwrap_or(1.0)).unwrap();
+                material.bump_map = Some(BumpMap {
+                    height: Rc::new(ChannelNode::new(Rc::new(Texture2DNode::new(texture)), 0)),
+                    scale: multiplier * texel,
+                    delta: texel,
+                });
+            }
+        }
+
+        material
+    }
+}
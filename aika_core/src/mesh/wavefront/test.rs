@@ -1,4 +1,10 @@
-use crate::mesh::WavefrontMeshLoader;
+use image::{Rgb, RgbImage};
+use crate::component::MeshFilter;
+use crate::material::{Material, MaterialSlots};
+use cgmath::{InnerSpace, Vector3};
+use crate::mesh::wavefront::mtl::{get_mtl_parameters, MtlMaterialKind, MtlParameters};
+use crate::mesh::{CommonVertex, CubeMesh, Mesh, MeshGenerationOptions, PlaneMesh, VertexBuffer, WavefrontMeshLoader, WavefrontMeshWriter};
+use crate::scene::Scene;
 
 #[test]
 fn test_wavefront_obj_loader1() {
@@ -7,3 +13,204 @@ fn test_wavefront_obj_loader1() {
     let result = suzanne.unwrap();
     assert_eq!(result.face_count(), 15488);
 }
+
+const TEST_OBJ: &str = "mtllib test.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+o quad
+usemtl brick
+f 1/1 2/2 3/3
+usemtl glass
+f 1/1 3/3 4/4
+";
+
+const TEST_MTL: &str = "newmtl brick
+Kd 0.5 0.5 0.5
+Ks 0 0 0
+map_Kd brick.png
+bump -bm 0.5 brick_height.png
+
+newmtl glass
+Kd 1 1 1
+Ns 98
+Ni 1.33
+d 0.2
+";
+
+#[test]
+fn test_wavefront_obj_game_objects() {
+    let dir = std::env::temp_dir().join(format!("aika_test_wavefront_mtl_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("test.obj"), TEST_OBJ).unwrap();
+    std::fs::write(dir.join("test.mtl"), TEST_MTL).unwrap();
+    let image = RgbImage::from_pixel(4, 4, Rgb([255, 128, 0]));
+    image.save(dir.join("brick.png")).unwrap();
+    image.save(dir.join("brick_height.png")).unwrap();
+
+    let game_objects = WavefrontMeshLoader::load_wavefront_obj_game_objects::<f64, _>(dir.join("test.obj")).unwrap();
+
+    // one game object per material group
+    assert_eq!(game_objects.len(), 2);
+    for go in game_objects.iter() {
+        let component = go.get_component::<MeshFilter<f64>>().unwrap();
+        let mesh_filter = component.downcast::<MeshFilter<f64>>();
+        assert_eq!(mesh_filter.mesh.triangles.len(), 1);
+        assert!(mesh_filter.mesh.vertices.get_uv0(0).is_some());
+    }
+
+    let brick = game_objects[0].get_component::<Material<f64>>().unwrap();
+    let brick = brick.downcast::<Material<f64>>();
+    assert!(brick.material_impl.has_bsdf());
+    assert!(brick.material_impl.get_ior().is_none());
+    let bump_map = brick.bump_map.as_ref().unwrap();
+    assert!((bump_map.delta - 0.25).abs() < 1e-9);
+    assert!((bump_map.scale - 0.125).abs() < 1e-9);
+
+    let glass = game_objects[1].get_component::<Material<f64>>().unwrap();
+    let glass = glass.downcast::<Material<f64>>();
+    assert!((glass.material_impl.get_ior().unwrap().x - 1.33).abs() < 1e-6);
+    assert!(glass.bump_map.is_none());
+
+    // merged into one game object, a sub mesh per group
+    let go = WavefrontMeshLoader::load_wavefront_obj_game_object::<f64, _>(dir.join("test.obj"), String::from("quad")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    {
+        let component = go.get_component::<MeshFilter<f64>>().unwrap();
+        let mesh_filter = component.downcast::<MeshFilter<f64>>();
+        assert_eq!(mesh_filter.mesh.sub_mesh, vec![[0, 1], [1, 2]]);
+        assert_eq!(mesh_filter.mesh.triangles[1], [3, 4, 5]);
+    }
+    let slots = go.get_component::<MaterialSlots<f64>>().unwrap();
+    let slots = slots.downcast::<MaterialSlots<f64>>();
+    assert_eq!(slots.materials.len(), 2);
+    assert!((slots.materials[1].material_impl.get_ior().unwrap().x - 1.33).abs() < 1e-6);
+}
+
+#[test]
+fn test_wavefront_obj_game_objects_without_mtl() {
+    let dir = std::env::temp_dir().join(format!("aika_test_wavefront_no_mtl_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("test.obj"), TEST_OBJ).unwrap();
+
+    // the missing MTL file falls back to the default material
+    let game_objects = WavefrontMeshLoader::load_wavefront_obj_game_objects::<f64, _>(dir.join("test.obj")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(!game_objects.is_empty());
+    assert!(game_objects.iter().all(|go| go.has_component::<Material<f64>>()));
+}
+
+#[test]
+fn test_mtl_parameters() {
+    // a plastic, Ks 0.5 is the specular of 0.04 at normal incidence
+    let plastic = tobj::Material {
+        diffuse: Some([0.8, 0.2, 0.1]),
+        specular: Some([0.5, 0.5, 0.5]),
+        shininess: Some(48.0),
+        ..Default::default()
+    };
+    let parameters: MtlParameters<f64> = get_mtl_parameters(&plastic);
+    assert_eq!(parameters.kind, MtlMaterialKind::Principled);
+    assert!((parameters.color - Vector3::new(0.8, 0.2, 0.1)).magnitude() < 1e-6);
+    assert!((parameters.roughness.unwrap() - 0.2).abs() < 1e-9);
+    assert!((parameters.specular_ior - 1.5).abs() < 1e-6);
+    assert_eq!(parameters.transmission, 0.0);
+
+    // a partially transparent surface keeps its color, and transmits 1 - d
+    let veil = tobj::Material {
+        diffuse: Some([0.5, 0.5, 0.5]),
+        dissolve: Some(0.25),
+        optical_density: Some(1.33),
+        ..Default::default()
+    };
+    let parameters: MtlParameters<f64> = get_mtl_parameters(&veil);
+    assert_eq!(parameters.kind, MtlMaterialKind::Principled);
+    assert!((parameters.color - Vector3::new(0.5, 0.5, 0.5)).magnitude() < 1e-6);
+    assert!((parameters.transmission - 0.75).abs() < 1e-6);
+    assert!((parameters.specular_ior - 1.33).abs() < 1e-6);
+
+    // only the glass illumination models give a dielectric
+    let glass = tobj::Material {
+        optical_density: Some(1.45),
+        illumination_model: Some(7),
+        ..Default::default()
+    };
+    let parameters: MtlParameters<f64> = get_mtl_parameters(&glass);
+    assert_eq!(parameters.kind, MtlMaterialKind::Dielectric);
+    assert!((parameters.ior - 1.45).abs() < 1e-6);
+    // a glass without `Ns` is smooth
+    assert!(parameters.roughness.is_none());
+    let mirror = tobj::Material {
+        illumination_model: Some(3),
+        ..glass
+    };
+    assert_eq!(get_mtl_parameters::<f64>(&mirror).kind, MtlMaterialKind::Diffuse);
+
+    let matte = tobj::Material {
+        diffuse: Some([0.3, 0.3, 0.3]),
+        specular: Some([0.0, 0.0, 0.0]),
+        ..Default::default()
+    };
+    assert_eq!(get_mtl_parameters::<f64>(&matte).kind, MtlMaterialKind::Diffuse);
+}
+
+fn get_colored_plane() -> Mesh<Vec<CommonVertex<f64>>> {
+    let mut mesh = PlaneMesh::create_subdivided_plane_mesh(2.0, 2.0, 2, 2, &MeshGenerationOptions::default());
+    for (i, v) in mesh.vertices.iter_mut().enumerate() {
+        v.color = Some(Vector3::new(i as f64 / 255.0, 0.5, 1.0));
+    }
+    mesh
+}
+
+#[test]
+fn test_wavefront_obj_writer_round_trip() {
+    let mesh = get_colored_plane();
+    let data = WavefrontMeshWriter::write_wavefront_obj_memory(&mesh).unwrap();
+    let loaded = WavefrontMeshLoader::load_wavefront_obj_memory::<f64>(&data).unwrap().remove(0);
+
+    // tobj numbers the vertices in the order the faces use them
+    assert_eq!(loaded.vertices.len(), mesh.vertices.len());
+    assert_eq!(loaded.triangles.len(), mesh.triangles.len());
+    let corners = |m: &Mesh<Vec<CommonVertex<f64>>>| m.triangles.iter().flatten().map(|&i| m.vertices[i].clone()).collect::<Vec<_>>();
+    for (a, b) in corners(&loaded).iter().zip(corners(&mesh).iter()) {
+        assert!((a.position - b.position).magnitude() < 1e-6);
+        assert!((a.normal.unwrap() - b.normal.unwrap()).magnitude() < 1e-6);
+        assert!((a.uv0.unwrap() - b.uv0.unwrap()).magnitude() < 1e-6);
+        assert!((a.color.unwrap() - b.color.unwrap()).magnitude() < 1e-6);
+    }
+
+    // each sub mesh is a group, and a mesh without attributes only has positions
+    let mut cube = CubeMesh::create_cube_mesh(Vector3::new(1.0f32, 1.0, 1.0), 1, &MeshGenerationOptions::default());
+    for v in cube.vertices.iter_mut() {
+        v.normal = None;
+        v.uv0 = None;
+    }
+    let text = String::from_utf8(WavefrontMeshWriter::write_wavefront_obj_memory(&cube).unwrap()).unwrap();
+    assert!(text.contains("g mesh_5\n"));
+    assert!(text.lines().filter(|l| l.starts_with("f ")).all(|l| !l.contains('/')));
+    assert!(!text.contains("vt ") && !text.contains("vn "));
+    let groups = WavefrontMeshLoader::load_wavefront_obj_memory::<f32>(text.as_bytes()).unwrap();
+    assert_eq!(groups.len(), 6);
+}
+
+#[test]
+fn test_wavefront_obj_writer_scene() {
+    let scene: Scene<f64> = Scene::new_plane_and_cube();
+    let data = WavefrontMeshWriter::write_wavefront_obj_scene_memory(&scene).unwrap();
+    let text = String::from_utf8(data.clone()).unwrap();
+    assert!(text.contains("o plane\n") && text.contains("o cube\n"));
+
+    // the plane, and the 6 faces of the cube
+    let meshes = WavefrontMeshLoader::load_wavefront_obj_memory::<f64>(&data).unwrap();
+    assert_eq!(meshes.len(), 7);
+    assert_eq!(meshes.iter().map(|m| m.triangles.len()).sum::<usize>(), 14);
+    // the cube stands on the plane in world space
+    let cube_z: Vec<f64> = meshes[1..].iter().flat_map(|m| m.vertices.iter().map(|v| v.position.z)).collect();
+    assert!(cube_z.iter().all(|&z| z.abs() < 1e-6 || (z - 1.0).abs() < 1e-6));
+    assert!(cube_z.iter().any(|&z| (z - 1.0).abs() < 1e-6));
+}
@@ -0,0 +1,119 @@
+use std::fmt::Debug;
+use std::io::Write;
+use std::path::Path;
+use anyhow::Result;
+use cgmath::BaseFloat;
+use crate::mesh::{Mesh, VertexBuffer};
+use crate::scene::Scene;
+
+pub struct WavefrontMeshWriter;
+
+/// The number of `v`, `vt` and `vn` lines written so far, OBJ indices count from 1 over the whole file
+#[derive(Default)]
+struct ObjIndexOffsets {
+    position: usize,
+    uv: usize,
+    normal: usize,
+}
+
+impl WavefrontMeshWriter {
+    /// Uvs, normals and colors are written if every vertex has them.
+    /// Colors go after the position, as in `v x y z r g b`, which Blender reads.
+    /// Each sub mesh is a group if there are more than one
+    fn write_object<V, W>(w: &mut W, name: &str, mesh: &Mesh<V>, offsets: &mut ObjIndexOffsets) -> Result<()>
+    where
+        V: VertexBuffer,
+        V::FloatType: BaseFloat,
+        W: Write
+    {
+        let vertex_count = mesh.vertices.vertex_count();
+        let has_uv = vertex_count > 0 && (0..vertex_count).all(|i| mesh.vertices.get_uv0(i).is_some());
+        let has_normal = vertex_count > 0 && (0..vertex_count).all(|i| mesh.vertices.get_normal(i).is_some());
+        let has_color = vertex_count > 0 && (0..vertex_count).all(|i| mesh.vertices.get_color(i).is_some());
+
+        writeln!(w, "o {}", name)?;
+        for i in 0..vertex_count {
+            let p = mesh.vertices.get_position(i);
+            match mesh.vertices.get_color(i).filter(|_| has_color) {
+                Some(c) => writeln!(w, "v {:?} {:?} {:?} {:?} {:?} {:?}", p.x, p.y, p.z, c.x, c.y, c.z)?,
+                None => writeln!(w, "v {:?} {:?} {:?}", p.x, p.y, p.z)?,
+            }
+        }
+        if has_uv {
+            for i in 0..vertex_count {
+                let uv = mesh.vertices.get_uv0(i).unwrap();
+                writeln!(w, "vt {:?} {:?}", uv.x, uv.y)?;
+            }
+        }
+        if has_normal {
+            for i in 0..vertex_count {
+                let n = mesh.vertices.get_normal(i).unwrap();
+                writeln!(w, "vn {:?} {:?} {:?}", n.x, n.y, n.z)?;
+            }
+        }
+
+        let get_index = |i: usize| {
+            let p = offsets.position + i + 1;
+            match (has_uv, has_normal) {
+                (true, true) => format!("{}/{}/{}", p, offsets.uv + i + 1, offsets.normal + i + 1),
+                (true, false) => format!("{}/{}", p, offsets.uv + i + 1),
+                (false, true) => format!("{}//{}", p, offsets.normal + i + 1),
+                (false, false) => format!("{}", p),
+            }
+        };
+        // a mesh without sub meshes is written as a whole
+        let ranges = if mesh.sub_mesh.is_empty() { vec![[0, mesh.triangles.len()]] } else { mesh.sub_mesh.clone() };
+        for (index, &[start, end]) in ranges.iter().enumerate() {
+            if ranges.len() > 1 {
+                writeln!(w, "g {}_{}", name, index)?;
+            }
+            for t in mesh.triangles[start.min(end)..end.min(mesh.triangles.len())].iter() {
+                writeln!(w, "f {} {} {}", get_index(t[0]), get_index(t[1]), get_index(t[2]))?;
+            }
+        }
+
+        offsets.position += vertex_count;
+        if has_uv {
+            offsets.uv += vertex_count;
+        }
+        if has_normal {
+            offsets.normal += vertex_count;
+        }
+        Ok(())
+    }
+
+    pub fn write_wavefront_obj_memory<V>(mesh: &Mesh<V>) -> Result<Vec<u8>> where V: VertexBuffer, V::FloatType: BaseFloat {
+        let mut result = Vec::new();
+        WavefrontMeshWriter::write_object(&mut result, "mesh", mesh, &mut ObjIndexOffsets::default())?;
+        Ok(result)
+    }
+
+    pub fn write_wavefront_obj<V, P>(mesh: &Mesh<V>, p: P) -> Result<()>
+        where
+            V: VertexBuffer,
+            V::FloatType: BaseFloat,
+            P: AsRef<Path> + Debug
+    {
+        std::fs::write(p, WavefrontMeshWriter::write_wavefront_obj_memory(mesh)?)?;
+        Ok(())
+    }
+
+    /// Every game object with a `MeshFilter` is an object, in world space
+    pub fn write_wavefront_obj_scene_memory<F>(scene: &Scene<F>) -> Result<Vec<u8>> where F: BaseFloat + 'static {
+        let mut result = Vec::new();
+        let mut offsets = ObjIndexOffsets::default();
+        for (name, mesh) in scene.get_world_meshes() {
+            WavefrontMeshWriter::write_object(&mut result, &name, &mesh, &mut offsets)?;
+        }
+        Ok(result)
+    }
+
+    pub fn write_wavefront_obj_scene<F, P>(scene: &Scene<F>, p: P) -> Result<()>
+        where
+            F: BaseFloat + 'static,
+            P: AsRef<Path> + Debug
+    {
+        std::fs::write(p, WavefrontMeshWriter::write_wavefront_obj_scene_memory(scene)?)?;
+        Ok(())
+    }
+}
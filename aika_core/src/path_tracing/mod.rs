@@ -7,3 +7,5 @@ mod simple_path_tracing;
 mod tracing_service;
 mod shading_context;
 mod shade_normal;
+#[cfg(test)]
+mod test_shading_context;
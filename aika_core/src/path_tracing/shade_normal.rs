@@ -36,10 +36,9 @@ impl<F> ShadeNormal<F> where F: BaseFloat + 'static {
     fn trace_one_ray(tracing_service: &TracingService<F>, ray: &Ray<F>) -> Vector3<F> {
         let hit_result = tracing_service.hit_ray(&ray, F::from(1e-6).unwrap(), F::infinity());
         if let Some(r) = hit_result {
-            let hit_triangle = r.hit_object.as_ref().unwrap().clone();
+            let hit_primitive = r.hit_object.as_ref().unwrap().clone();
             let hit_point = r.get_hit_point(&ray);
-            let uvw = hit_triangle.triangle.get_bary_centric_coordinate(hit_point);
-            let interpolated_normal = hit_triangle.interpolate_normal(uvw).unwrap().normalize();
+            let interpolated_normal = hit_primitive.get_shading_normal(hit_point);
             // let interpolated_normal = hit_triangle.triangle.get_normal();
             // let interpolated_normal = hit_triangle.get_vertex_normal(0);
             // let interpolated_normal = hit_triangle.get
@@ -1,5 +1,7 @@
-use cgmath::{BaseFloat, Matrix, Matrix3, SquareMatrix, Vector2, Vector3};
+use cgmath::{BaseFloat, InnerSpace, Matrix, Matrix3, SquareMatrix, Vector2, Vector3};
 use num_traits::Zero;
+use aika_math::{Ray, spawn_ray};
+use aika_math::utils::{face_forward, get_orthogonal};
 use crate::scene::GameObject;
 
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
@@ -12,10 +14,15 @@ pub enum RayObjectStatus {
 pub struct ShadingContext<F> {
     pub tangent: Vector3<F>,
     pub bitangent: Vector3<F>,
+    /// shading normal, on the same side as `geometric_normal`
     pub normal: Vector3<F>,
+    /// normal of the hit surface, used to offset spawned rays and to tell the sides apart
+    pub geometric_normal: Vector3<F>,
     /// points in to the surface
     pub ray_dir: Vector3<F>,
     pub point: Vector3<F>,
+    /// error bound of `point`
+    pub point_error: Vector3<F>,
     pub uv: Vector2<F>,
 
     /// ray dir in tangent space
@@ -39,8 +46,10 @@ impl<F> Default for ShadingContext<F> where F: BaseFloat {
             tangent: Vector3::zero(),
             bitangent: Vector3::zero(),
             normal: Vector3::zero(),
+            geometric_normal: Vector3::zero(),
             ray_dir: Vector3::zero(),
             point: Vector3::zero(),
+            point_error: Vector3::zero(),
             ray_dir_tangent_space: Vector3::zero(),
             tbn: Matrix3::identity(),
             tbn_inverse: Matrix3::identity(),
@@ -60,7 +69,7 @@ impl<F> ShadingContext<F> where F: BaseFloat {
     }
 
     pub fn get_current_ior(&self) -> Vector3<F> {
-        if self.ior_stack.len() > 0 {
+        if !self.ior_stack.is_empty() {
             self.ior_stack[self.ior_stack.len() - 1]
         } else {
             Vector3::new(F::one(), F::one(), F::one())
@@ -81,7 +90,7 @@ impl<F> ShadingContext<F> where F: BaseFloat {
     }
 
     pub fn pop_ior(&mut self) {
-        if self.ior_stack.len() > 0 {
+        if !self.ior_stack.is_empty() {
             self.ior_stack.pop();
         }
     }
@@ -94,6 +103,45 @@ impl<F> ShadingContext<F> where F: BaseFloat {
         self.ray_status == RayObjectStatus::Exiting
     }
 
+    /// Build an orthonormal frame around `normal`. `tangent` is projected onto the tangent plane,
+    /// and the bitangent is `bitangent_sign * cross(normal, tangent)`
+    pub fn set_shading_frame(&mut self, normal: Vector3<F>, tangent: Vector3<F>, bitangent_sign: F) {
+        let mut t = tangent - normal * normal.dot(tangent);
+        if t.magnitude2() == F::zero() || !t.magnitude2().is_finite() {
+            // the tangent is parallel to the normal, any direction on the tangent plane does
+            t = get_orthogonal(normal);
+        }
+        let t = t.normalize();
+        let sign = if bitangent_sign < F::zero() { -F::one() } else { F::one() };
+
+        self.normal = normal;
+        self.tangent = t;
+        self.bitangent = normal.cross(t) * sign;
+        self.recalculate_tangent_space();
+    }
+
+    /// Replace the shading normal with `tangent_space_normal` given in the current frame, as sampled from a normal map.
+    /// The handedness of the frame is kept
+    pub fn apply_normal_map(&mut self, tangent_space_normal: Vector3<F>) {
+        let n = self.convert_vector_tangent_to_world(tangent_space_normal);
+        if n.magnitude2() == F::zero() || !n.magnitude2().is_finite() {
+            return;
+        }
+        let n = face_forward(n.normalize(), self.geometric_normal);
+        let sign = self.normal.cross(self.tangent).dot(self.bitangent);
+        self.set_shading_frame(n, self.tangent, sign);
+    }
+
+    /// Replace the tangent with `tangent_space_tangent` given in the current frame, the normal and the handedness are kept
+    pub fn apply_tangent_map(&mut self, tangent_space_tangent: Vector3<F>) {
+        let t = self.convert_vector_tangent_to_world(tangent_space_tangent);
+        if t.magnitude2() == F::zero() || !t.magnitude2().is_finite() {
+            return;
+        }
+        let sign = self.normal.cross(self.tangent).dot(self.bitangent);
+        self.set_shading_frame(self.normal, t, sign);
+    }
+
     pub fn recalculate_tangent_space(&mut self) {
         let tbn = Matrix3::new(
             self.tangent.x, self.bitangent.x, self.normal.x,
@@ -133,6 +181,28 @@ impl<F> ShadingContext<F> where F: BaseFloat {
     //     }
     // }
 
+    /// Spawn a ray from the shading point in world space direction `w`, without hitting the same surface again
+    pub fn spawn_ray(&self, w: Vector3<F>) -> Ray<F> where F: 'static {
+        spawn_ray(self.point, self.point_error, self.geometric_normal, w)
+    }
+
+    /// Whether `w` is on the same side of the shading normal as of the geometric normal.
+    /// Light arriving from a direction that is not would leak through the surface
+    pub fn is_shading_side_consistent(&self, w: Vector3<F>) -> bool {
+        w.dot(self.geometric_normal) * w.dot(self.normal) > F::zero()
+    }
+
+    /// Attenuation of reflected light towards `wi`, hiding the shadow terminator of smooth shaded low poly meshes.
+    /// See Chiang et al. 2019, "Taming the Shadow Terminator"
+    pub fn shadow_terminator_factor(&self, wi: Vector3<F>) -> F {
+        let denom = wi.dot(self.normal) * self.geometric_normal.dot(self.normal);
+        if denom == F::zero() {
+            return F::one();
+        }
+        let g = (wi.dot(self.geometric_normal) / denom).abs().min(F::one());
+        -g * g * g + g * g + g
+    }
+
     pub fn convert_vector_to_tangent_space(&self, dir: Vector3<F>) -> Vector3<F> {
         self.tbn * dir
     }
@@ -1,9 +1,9 @@
 use std::marker::PhantomData;
 use std::ops::Div;
-use cgmath::{BaseFloat, ElementWise, InnerSpace, Matrix3, MetricSpace, Vector3};
+use cgmath::{BaseFloat, ElementWise, InnerSpace, Matrix3, MetricSpace, Vector2, Vector3};
 use image::{Rgb, RgbImage};
 use num_traits::{Num, Zero};
-use aika_math::{Hittable, Ray};
+use aika_math::{Hittable, Ray, spawn_ray};
 use crate::camera::PerspectiveCamera;
 use crate::component::Transform;
 use crate::scene::{Scene};
@@ -12,7 +12,7 @@ use crate::material::{BSDF, Material};
 use crate::path_tracing::{ShadingContext, TracingService};
 use anyhow::Result;
 use indicatif::ProgressBar;
-use aika_math::utils::{get_vector3_one, is_same_hemisphere, visualize_unit_vector};
+use aika_math::utils::{face_forward, get_vector3_one, is_same_hemisphere, visualize_unit_vector};
 use crate::f;
 use crate::path_tracing::shading_context::RayObjectStatus;
 use crate::utils::vector3_to_rgb_clamped;
@@ -52,28 +52,26 @@ impl<F> SimplePathTracing<F> where F: BaseFloat + 'static {
             // let hit_result = tracing_service.hit_ray(&current_ray, F::from(1e-6).unwrap(), F::infinity());
             let hit_result = tracing_service.hit_ray(&current_ray, F::zero(), F::infinity());
             if let Some(r) = hit_result {
-                let hit_triangle = r.hit_object.as_ref().unwrap().clone();
+                let hit_primitive = r.hit_object.as_ref().unwrap().clone();
                 let hit_point = r.get_hit_point(&current_ray);
-                let uvw = hit_triangle.triangle.get_bary_centric_coordinate(hit_point);
-                // let interpolated_normal = hit_triangle.interpolate_normal(uvw).unwrap().normalize();
-                let interpolated_normal = hit_triangle.triangle.get_normal();
-                let go = hit_triangle.go.clone();
+                let geometric_normal = hit_primitive.get_geometric_normal(hit_point);
+                // the shading normal may point to the other side when the vertex normals disagree with the winding
+                let interpolated_normal = face_forward(hit_primitive.get_shading_normal(hit_point), geometric_normal);
+                let go = hit_primitive.get_game_object().clone();
                 shading_context.go_stack.push(go.clone());
                 shading_context.hit_point_stack.push(hit_point);
 
-                if go.has_component::<Material<F>>() {
-                    shading_context.normal = interpolated_normal;
-                    let tangent = (hit_triangle.triangle.a - hit_triangle.triangle.b).normalize();
-                    let tangent = (tangent - interpolated_normal * interpolated_normal.dot(tangent)).normalize();
-                    let bitangent = interpolated_normal.cross(tangent).normalize();
-                    shading_context.tangent = tangent;
-                    shading_context.bitangent = bitangent;
+                if let Some(material_component) = hit_primitive.get_material() {
+                    let tangent = hit_primitive.get_tangent(hit_point);
                     shading_context.ray_dir = current_ray.direction;
                     shading_context.point = hit_point;
-                    shading_context.uv = r.uv.unwrap();
-                    shading_context.recalculate_tangent_space();
+                    shading_context.point_error = r.get_point_error();
+                    shading_context.geometric_normal = geometric_normal;
+                    // meshes without uv0 are shaded with the corner of the uv square
+                    shading_context.uv = r.uv.unwrap_or(Vector2::zero());
+                    shading_context.set_shading_frame(interpolated_normal, tangent.truncate(), tangent.w);
 
-                    let back_face = current_ray.direction.dot(interpolated_normal) > F::zero();
+                    let back_face = current_ray.direction.dot(geometric_normal) > F::zero();
                     // println!("depth: {}, back_face: {:?}", ray_iter, r.back_facing.unwrap());
                     // let back_face = back_face > F::zero();
                     // println!("{:?}", r.back_facing);
@@ -82,11 +80,13 @@ impl<F> SimplePathTracing<F> where F: BaseFloat + 'static {
                     // println!("{:?}: {}", pixel, r.back_facing.unwrap());
                     shading_context.back_face = back_face;
 
-                    let material_component = go.get_component::<Material<F>>().unwrap();
-                    let material = material_component.downcast::<Material<F>>();
+                    let material = material_component.downcast();
+                    material.apply_normal_map(&mut shading_context);
+                    material.apply_bump_map(&mut shading_context);
+                    material.apply_tangent_map(&mut shading_context);
 
                     let mut sampled_ray_dir_ws = current_ray.direction;
-                    let mut sampled_ray_point = shading_context.point;
+                    let mut next_ray: Option<Ray<F>> = None;
                     let mut is_transmit = true;
 
                     if material.material_impl.has_bsdf() {
@@ -106,7 +106,7 @@ impl<F> SimplePathTracing<F> where F: BaseFloat + 'static {
                             let light_sample_result = tracing_service.sample_light(&shading_context);
                             if let Some(result) = light_sample_result {
                                 // return Ok(visualize_unit_vector(shading_context.normal));
-                                if result.wi.dot(shading_context.normal) > F::zero() {
+                                if result.wi.dot(shading_context.normal) > F::zero() && shading_context.is_shading_side_consistent(result.wi) {
                                     let light_dir_ts = shading_context.convert_vector_to_tangent_space(result.wi);
 
                                     // if pixel.0 == 149 && pixel.1 == 155 {
@@ -114,8 +114,7 @@ impl<F> SimplePathTracing<F> where F: BaseFloat + 'static {
                                     // }
                                     let f = bsdf.evaluate(light_dir_ts, wo);
                                     if let Some(f) = f {
-                                        let offset = if back_face { f!(-1e-3) } else { f!(1e-3) };
-                                        let shadow_ray = Ray::new(hit_point + interpolated_normal * offset, result.wi);
+                                        let shadow_ray = shading_context.spawn_ray(result.wi);
                                         // let shadow_ray = Ray::new(hit_point, result.wi);
                                         let ray_transmission = tracing_service.get_ray_transmission(&shadow_ray, result.distance);
                                         // let ray_transmission = F::one();
@@ -125,7 +124,10 @@ impl<F> SimplePathTracing<F> where F: BaseFloat + 'static {
                                         // } else {
                                         //     return Ok(Vector3::new(F::one(), F::one(), F::one()));
                                         // }
-                                        let contribution = f.mul_element_wise(result.radiance).mul_element_wise(result.weight) * light_dir_ts.z.abs();
+                                        let mut contribution = f.mul_element_wise(result.radiance).mul_element_wise(result.weight) * light_dir_ts.z.abs();
+                                        if result.wi.dot(geometric_normal) * current_ray.direction.dot(geometric_normal) < F::zero() {
+                                            contribution *= shading_context.shadow_terminator_factor(result.wi);
+                                        }
                                         radiance += throughput.mul_element_wise(contribution).mul_element_wise(ray_transmission);
                                         // if ray_transmission.x == F::zero() {
                                         //     println!("{:?}", pixel);
@@ -153,17 +155,19 @@ impl<F> SimplePathTracing<F> where F: BaseFloat + 'static {
                             break;
                         }
                         let sample_result = sample_result.unwrap();
-                        // if wo.z <= F::zero() {
-                        //     println!("{:?}, iter: {}, pixel: {:?}, backface: {}", wo, ray_iter, pixel, back_face);
-                        // }
 
                         sampled_ray_dir_ws = shading_context.convert_vector_tangent_to_world(sample_result.direction).normalize();
+                        if !shading_context.is_shading_side_consistent(sampled_ray_dir_ws) {
+                            // the sampled direction leaks through the surface
+                            break;
+                        }
                         throughput = throughput.mul_element_wise(sample_result.get_weight());
-                        let next_point_bias = shading_context.convert_vector_tangent_to_world(sample_result.next_point);
-                        sampled_ray_point += next_point_bias;
 
-                        is_transmit = sampled_ray_dir_ws.dot(shading_context.normal)
-                            * current_ray.direction.dot(shading_context.normal) > F::zero();
+                        is_transmit = sampled_ray_dir_ws.dot(geometric_normal)
+                            * current_ray.direction.dot(geometric_normal) > F::zero();
+                        if !is_transmit {
+                            throughput *= shading_context.shadow_terminator_factor(sampled_ray_dir_ws);
+                        }
 
                         // shading_context.ray_status = RayObjectStatus::Unknown;
                         // if back_face && is_transmit {
@@ -171,47 +175,31 @@ impl<F> SimplePathTracing<F> where F: BaseFloat + 'static {
                         // } else if !back_face && is_transmit {
                         //     shading_context.ray_status = RayObjectStatus::Entering;
                         // }
-                        // println!("is transmit: {}, {}, back face: {}", is_transmit, ray_iter, back_face);
                         if is_transmit && !back_face {
                             if let Some(ior) = material.material_impl.get_ior() {
-                                // println!("entering ior: {:?}, {}", ior, ray_iter);
-                                // println!("ray: {:?}", sampled_ray_dir_ws.dot(shading_context.normal));
                                 shading_context.push_ior(ior);
                             }
-                        } else if is_transmit && back_face {
-                            if material.material_impl.get_ior().is_some() {
-                                shading_context.pop_ior();
-                            }
-                            // println!("exit ior: {}", ray_iter);
+                        } else if is_transmit && back_face && material.material_impl.get_ior().is_some() {
+                            shading_context.pop_ior();
                         }
                     }
 
                     shading_context.ray_dir = sampled_ray_dir_ws;
 
-                    // println!("{}", is_transmit);
-                    // let is_transmit = true;
-                    // if is_transmit {
-                    //     println!("transmit");
-                    // }
-
-                    if is_transmit {
-                        if material.material_impl.has_volume() {
-                            let volume = material.material_impl.get_volume().unwrap();
-                            let sample_result = volume.sample_ray(
-                                &tracing_service, &shading_context, sampled_ray_dir_ws
-                            )?;
-                            // println!("before: {:?}", throughput);
-                            throughput.mul_assign_element_wise(sample_result.weight);
-                            // throughput = Vector3::zero();
-                            // println!("after: {:?}", throughput);
-                            // return Ok(throughput);
-                            sampled_ray_dir_ws = sample_result.next_direction;
-                            sampled_ray_point = sample_result.point;
-                        }
+                    if is_transmit && material.material_impl.has_volume() {
+                        let volume = material.material_impl.get_volume().unwrap();
+                        let sample_result = volume.sample_ray(
+                            tracing_service, &shading_context, sampled_ray_dir_ws
+                        )?;
+                        throughput.mul_assign_element_wise(sample_result.weight);
+                        sampled_ray_dir_ws = sample_result.next_direction;
+                        next_ray = Some(spawn_ray(sample_result.point, sample_result.point_error, sample_result.normal, sampled_ray_dir_ws));
                     }
 
-                    let next_ray = Ray::new(sampled_ray_point, sampled_ray_dir_ws);
-                    current_ray = next_ray.clone();
+                    current_ray = match next_ray {
+                        Some(ray) => ray,
+                        None => shading_context.spawn_ray(sampled_ray_dir_ws)
+                    };
                     // let indir_color = SimplePathTracing::shade_one_ray(
                     //     &tracing_service, &next_ray, depth - 1, pixel
                     // )?;
@@ -239,7 +227,7 @@ impl<F> SimplePathTracing<F> where F: BaseFloat + 'static {
 
         let pb = ProgressBar::new((width * height) as u64);
 
-        for (ray, (i, j)) in camera.iter_ray(&camera_transform, width, height) {
+        for (ray, (i, j)) in camera.iter_ray(camera_transform, width, height) {
             let mut sum = Vector3::zero();
             let spp = 3;
             for k in 0..spp {
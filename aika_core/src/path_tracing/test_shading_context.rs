@@ -0,0 +1,86 @@
+use std::rc::Rc;
+use cgmath::{InnerSpace, Vector2, Vector3};
+use crate::material::{DiffuseBRDFMaterial, Material};
+use crate::material_graph::{MaterialGraphContext, OutputValue};
+use crate::path_tracing::ShadingContext;
+
+fn get_tilted_context() -> ShadingContext<f64> {
+    let mut context = ShadingContext::new();
+    context.geometric_normal = Vector3::new(0.0, 0.0, 1.0);
+    context.normal = Vector3::new(0.5, 0.0, 1.0).normalize();
+    context
+}
+
+#[test]
+fn test_shading_side_consistent() {
+    let context = get_tilted_context();
+    assert!(context.is_shading_side_consistent(Vector3::new(0.0, 0.0, 1.0)));
+    assert!(context.is_shading_side_consistent(Vector3::new(0.0, 0.0, -1.0)));
+    // above the shading normal, but below the surface
+    assert!(!context.is_shading_side_consistent(Vector3::new(1.0, 0.0, -0.1).normalize()));
+}
+
+#[test]
+fn test_shadow_terminator_factor() {
+    let context = get_tilted_context();
+    // no attenuation where the geometric cosine is larger
+    assert_eq!(context.shadow_terminator_factor(Vector3::new(0.0, 0.0, 1.0)), 1.0);
+    // grazing to the surface while the shading normal still faces the light
+    let grazing = context.shadow_terminator_factor(Vector3::new(1.0, 0.0, 0.01).normalize());
+    assert!(grazing > 0.0 && grazing < 0.1);
+}
+
+#[test]
+fn test_apply_normal_map() {
+    let mut context = ShadingContext::new();
+    context.geometric_normal = Vector3::new(0.0, 0.0, 1.0);
+    context.set_shading_frame(Vector3::new(0.0, 0.0, 1.0), Vector3::new(1.0, 0.0, 0.0), -1.0);
+    assert!((context.bitangent - Vector3::new(0.0, -1.0, 0.0)).magnitude() < 1e-12);
+
+    // a flat normal map keeps the frame
+    context.apply_normal_map(Vector3::new(0.0, 0.0, 1.0));
+    assert!((context.normal - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-12);
+
+    // tilting towards +v follows the bitangent, including its sign
+    context.apply_normal_map(Vector3::new(0.0, 1.0, 1.0));
+    let expected = Vector3::new(0.0, -1.0, 1.0).normalize();
+    assert!((context.normal - expected).magnitude() < 1e-12);
+    assert!(context.normal.cross(context.tangent).dot(context.bitangent) < 0.0);
+}
+
+#[test]
+fn test_apply_tangent_map() {
+    let mut context = ShadingContext::new();
+    context.geometric_normal = Vector3::new(0.0, 0.0, 1.0);
+    context.set_shading_frame(Vector3::new(0.0, 0.0, 1.0), Vector3::new(1.0, 0.0, 0.0), -1.0);
+
+    // the tangent turns towards the bitangent, the normal and the handedness are kept
+    context.apply_tangent_map(Vector3::new(1.0, 1.0, 0.0));
+    let expected = Vector3::new(1.0, -1.0, 0.0).normalize();
+    assert!((context.tangent - expected).magnitude() < 1e-12);
+    assert!((context.normal - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-12);
+    assert!(context.normal.cross(context.tangent).dot(context.bitangent) < 0.0);
+}
+
+struct RampNode;
+
+impl OutputValue<f64, f64> for RampNode {
+    fn get_value(&self, context: &MaterialGraphContext<f64>) -> f64 {
+        context.uv.x
+    }
+}
+
+#[test]
+fn test_apply_bump_map() {
+    let material = Material::new(Box::new(DiffuseBRDFMaterial::new(Vector3::new(0.5, 0.5, 0.5))))
+        .with_bump_map(Rc::new(RampNode), 2.0);
+    let mut context = ShadingContext::new();
+    context.geometric_normal = Vector3::new(0.0, 0.0, 1.0);
+    context.uv = Vector2::new(0.5, 0.5);
+    context.set_shading_frame(Vector3::new(0.0, 0.0, 1.0), Vector3::new(1.0, 0.0, 0.0), 1.0);
+
+    // the normal leans against the slope of the height
+    material.apply_bump_map(&mut context);
+    let expected = Vector3::new(-2.0, 0.0, 1.0).normalize();
+    assert!((context.normal - expected).magnitude() < 1e-9);
+}
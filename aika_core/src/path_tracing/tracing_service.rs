@@ -1,12 +1,13 @@
 use std::cell::RefCell;
 use std::rc::Rc;
-use cgmath::{BaseFloat, ElementWise, Vector3};
+use cgmath::{BaseFloat, ElementWise, InnerSpace, Vector3};
 use num_traits::Zero;
-use aika_math::{HitRecord, Hittable, Ray};
+use aika_math::{HitRecord, Hittable, Ray, spawn_ray};
+use aika_spatial_structure::stats::TraversalStats;
 use crate::f;
 use crate::lighting::{DirectionalLight, DirectionalLightComponent, LightSampleResult, PointLight, PointLightComponent, SphericalLight, SphericalLightComponent, UniformLightSampler};
-use crate::mashed_scene::{MashedScene, MashedTriangle};
-use crate::material::Material;
+use crate::mashed_scene::{MashedPrimitive, MashedScene};
+use crate::material::{Material, MaterialSlots};
 use crate::path_tracing::ShadingContext;
 use crate::scene::{GameObject, Scene};
 use crate::utils::RandomGenerator;
@@ -15,15 +16,38 @@ pub struct TracingService<F> {
     mashed_scene: MashedScene<F>,
     random_generator: RefCell<RandomGenerator<F>>,
     light_sampler: UniformLightSampler<F>,
+    has_volume: bool,
 }
 
 impl<F> TracingService<F> where F: BaseFloat + 'static {
-    pub fn hit_ray(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, Rc<MashedTriangle<F>>>> {
+    pub fn hit_ray(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, Rc<MashedPrimitive<F>>>> {
         let result = self.mashed_scene.hit(ray, min, max);
         result
     }
 
+    pub fn hit_ray_with_stats(&self, ray: &Ray<F>, min: F, max: F, stats: &mut TraversalStats) -> Option<HitRecord<F, Rc<MashedPrimitive<F>>>> {
+        self.mashed_scene.hit_with_stats(ray, min, max, stats)
+    }
+
+    /// Returns true if there is an opaque surface on the ray within [min, max]
+    /// Surfaces without bsdf (e.g. volume boundaries) are skipped
+    pub fn occluded(&self, ray: &Ray<F>, min: F, max: F) -> bool {
+        self.mashed_scene.occluded(ray, min, max, &|r| {
+            match r.hit_object.as_ref().unwrap().get_material() {
+                Some(component) => component.downcast().material_impl.has_bsdf(),
+                None => true
+            }
+        })
+    }
+
     pub fn get_ray_transmission(&self, ray: &Ray<F>, max: F) -> Vector3<F> {
+        if self.occluded(ray, F::zero(), max) {
+            return Vector3::zero();
+        }
+        if !self.has_volume {
+            return Vector3::new(F::one(), F::one(), F::one());
+        }
+
         let mut result = Vector3::new(F::one(), F::one(), F::one());
 
         // let mut t = F::zero();
@@ -32,12 +56,11 @@ impl<F> TracingService<F> where F: BaseFloat + 'static {
         while remain > F::zero() {
             if let Some(r) = self.hit_ray(&ray, F::zero(), max) {
                 // let mashed_triangle = r.hit_object.unwrap().clone();
-                let go = r.hit_object.as_ref().unwrap().go.clone();
                 remain -= r.t;
 
-                let material_component = go.get_component::<Material<F>>();
-                if material_component.is_ok() {
-                    let material = material_component.as_ref().unwrap().downcast::<Material<F>>();
+                let material_component = r.hit_object.as_ref().unwrap().get_material();
+                if let Some(material_component) = material_component {
+                    let material = material_component.downcast();
                     if material.material_impl.has_bsdf() {
                         return Vector3::zero();
                     } else {
@@ -46,8 +69,7 @@ impl<F> TracingService<F> where F: BaseFloat + 'static {
                             let hit_point = r.get_hit_point(&ray);
                             // we don't need interpolated normal here
                             let normal = r.normal.unwrap();
-                            let offset = if r.back_facing.unwrap() { f!(1e-3) } else { f!(-1e-3) };
-                            let ray2 = Ray::new(hit_point + normal * offset, ray.direction);
+                            let ray2 = spawn_ray(hit_point, r.get_point_error(), normal, ray.direction);
 
                             let hit_result2 = self.hit_ray(&ray2, F::zero(), remain);
                             if hit_result2.is_none() {
@@ -61,13 +83,10 @@ impl<F> TracingService<F> where F: BaseFloat + 'static {
                                 let normal2 = hr2.normal.unwrap();
                                 remain -= hr2.t;
 
-                                let offset2 = if hr2.back_facing.unwrap() { f!(1e-3) } else { f!(-1e-3) };
-
                                 let transmittance = volume.transmittance(hit_point, hit_point2);
                                 result = result.mul_element_wise(transmittance);
 
-                                ray.origin = hit_point2 + normal2 * offset2;
-                                ray.direction = ray2.direction;
+                                ray = spawn_ray(hit_point2, hr2.get_point_error(), normal2, ray2.direction);
                             }
                         }
                     }
@@ -83,7 +102,7 @@ impl<F> TracingService<F> where F: BaseFloat + 'static {
         result
     }
 
-    pub fn hit_ray_0_inf(&self, ray: &Ray<F>) -> Option<HitRecord<F, Rc<MashedTriangle<F>>>> {
+    pub fn hit_ray_0_inf(&self, ray: &Ray<F>) -> Option<HitRecord<F, Rc<MashedPrimitive<F>>>> {
         self.hit_ray(ray, F::zero(), F::infinity())
     }
 
@@ -110,7 +129,7 @@ impl<F> TracingService<F> where F: BaseFloat + 'static {
                 let component = go.get_component::<PointLightComponent<F>>().unwrap();
                 let point_light_component = component.downcast::<PointLightComponent<F>>();
                 let point_light = PointLight {
-                    position: go.get_transform().unwrap().position,
+                    position: go.get_world_transform().transform_point(Vector3::zero()),
                     color: point_light_component.color
                 };
                 light_sampler.add_light(Box::new(point_light));
@@ -122,8 +141,8 @@ impl<F> TracingService<F> where F: BaseFloat + 'static {
             for go in game_objects.iter() {
                 let component = go.get_component::<DirectionalLightComponent<F>>().unwrap();
                 let directional_light_component = component.downcast::<DirectionalLightComponent<F>>();
-                let transform = go.get_transform().unwrap();
-                let direction = transform.transform_direction(Vector3::new(F::zero(), F::zero(), F::one()));
+                let transform = go.get_world_transform();
+                let direction = transform.transform_vector(Vector3::new(F::zero(), F::zero(), F::one())).normalize();
                 let directional_light = DirectionalLight {
                     color: directional_light_component.color,
                     dir: direction
@@ -137,9 +156,9 @@ impl<F> TracingService<F> where F: BaseFloat + 'static {
             for go in game_objects.iter() {
                 let component = go.get_component::<SphericalLightComponent<F>>().unwrap();
                 let s_light_component = component.downcast::<SphericalLightComponent<F>>();
-                let transform = go.get_transform().unwrap();
+                let transform = go.get_world_transform();
                 let s_light = SphericalLight {
-                    position: transform.position,
+                    position: transform.transform_point(Vector3::zero()),
                     radius: s_light_component.radius,
                     color: s_light_component.color
                 };
@@ -147,10 +166,25 @@ impl<F> TracingService<F> where F: BaseFloat + 'static {
             }
         }
 
+        let mut has_volume = false;
+        for go in scene.get_game_objects_of_type::<Material<F>>().iter() {
+            let component = go.get_component::<Material<F>>().unwrap();
+            if component.downcast::<Material<F>>().material_impl.has_volume() {
+                has_volume = true;
+            }
+        }
+        for go in scene.get_game_objects_of_type::<MaterialSlots<F>>().iter() {
+            let component = go.get_component::<MaterialSlots<F>>().unwrap();
+            if component.downcast::<MaterialSlots<F>>().materials.iter().any(|m| m.material_impl.has_volume()) {
+                has_volume = true;
+            }
+        }
+
         TracingService {
             mashed_scene,
             random_generator: RefCell::new(RandomGenerator::new(10)),
-            light_sampler
+            light_sampler,
+            has_volume,
         }
     }
 }
@@ -1,3 +1,8 @@
 pub use texcoords_renderer::TexcoordsRenderer;
+pub use traversal_heatmap_renderer::{TraversalHeatmapRenderer, TraversalHeatmap};
 
 mod texcoords_renderer;
+mod traversal_heatmap_renderer;
+
+#[cfg(test)]
+mod test;
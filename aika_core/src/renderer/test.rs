@@ -0,0 +1,23 @@
+use cgmath::{One, Quaternion, Vector3};
+use crate::camera::PerspectiveCamera;
+use crate::component::Transform;
+use crate::renderer::TraversalHeatmapRenderer;
+use crate::scene::{GameObject, Scene};
+
+#[test]
+fn test_traversal_heatmap() {
+    let mut scene = Scene::new();
+    let mut go = GameObject::new_plane(String::from("plane"), 1.0, 1.0);
+    go.add_component_owned::<Transform<f64>>(Transform::new(Vector3::new(0.0, 0.0, 0.0), 1.0, Quaternion::one()));
+    scene.add_game_object(go);
+
+    let camera = PerspectiveCamera::new(std::f64::consts::FRAC_PI_3, 0.01, 1000.0, 1.0);
+    let camera_transform = Transform::new(Vector3::new(0.0, 0.0, 5.0), 1.0, Quaternion::one());
+    let heatmap = TraversalHeatmapRenderer::default().render(&scene, 16, 16, &camera, &camera_transform);
+
+    // no triangle is tested by the rays which miss the plane
+    let zero = heatmap.primitives_tested.get_pixel(0, 0);
+    assert_eq!(heatmap.primitives_tested.get_pixel(15, 15), zero);
+    assert_ne!(heatmap.primitives_tested.get_pixel(8, 8), zero);
+    assert!(heatmap.stats.primitives_tested > 0);
+}
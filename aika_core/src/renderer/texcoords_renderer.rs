@@ -1,5 +1,5 @@
 use std::marker::PhantomData;
-use cgmath::{BaseFloat, InnerSpace, Vector3};
+use cgmath::{BaseFloat, InnerSpace, Vector2, Vector3};
 use image::RgbImage;
 use indicatif::ProgressBar;
 use num_traits::Zero;
@@ -29,7 +29,7 @@ impl<F> TexcoordsRenderer<F> where F: BaseFloat + 'static {
             let hit_triangle = r.hit_object.as_ref().unwrap().clone();
             let hit_point = r.get_hit_point(&ray);
 
-            let tex_coords = r.uv.unwrap();
+            let tex_coords = r.uv.unwrap_or(Vector2::zero());
 
             // let uvw = hit_triangle.triangle.get_bary_centric_coordinate(hit_point);
             // let interpolated_normal = hit_triangle.interpolate_normal(uvw).unwrap().normalize();
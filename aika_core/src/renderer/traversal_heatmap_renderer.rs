@@ -0,0 +1,96 @@
+use std::marker::PhantomData;
+use cgmath::BaseFloat;
+use image::{Rgb, RgbImage};
+use indicatif::ProgressBar;
+use aika_spatial_structure::stats::TraversalStats;
+use crate::camera::PerspectiveCamera;
+use crate::component::Transform;
+use crate::path_tracing::TracingService;
+use crate::scene::Scene;
+
+pub struct TraversalHeatmap {
+    /// nodes visited per pixel
+    pub nodes_visited: RgbImage,
+    /// intersection tests per pixel
+    pub primitives_tested: RgbImage,
+    /// accumulated over all the camera rays
+    pub stats: TraversalStats,
+}
+
+/// Renders the cost of the primary rays in the spatial structure as heatmaps
+pub struct TraversalHeatmapRenderer<F> {
+    /// counts at or above this value are shown in red, if None, the max count of the image is used
+    pub max_nodes_visited: Option<usize>,
+    pub max_primitives_tested: Option<usize>,
+    _phantom: PhantomData<F>,
+}
+
+/// blue -> cyan -> green -> yellow -> red
+fn heatmap_color(x: f64) -> Rgb<u8> {
+    let x = x.clamp(0.0, 1.0) * 4.0;
+    let (r, g, b) = if x < 1.0 {
+        (0.0, x, 1.0)
+    } else if x < 2.0 {
+        (0.0, 1.0, 2.0 - x)
+    } else if x < 3.0 {
+        (x - 2.0, 1.0, 0.0)
+    } else {
+        (1.0, 4.0 - x, 0.0)
+    };
+    Rgb([(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8])
+}
+
+fn counts_to_image(counts: &[usize], width: usize, height: usize, max: Option<usize>) -> RgbImage {
+    let max = max.unwrap_or_else(|| counts.iter().copied().max().unwrap_or(0)).max(1);
+    let mut result = RgbImage::new(width as u32, height as u32);
+    for (index, &count) in counts.iter().enumerate() {
+        let i = index % width;
+        let j = index / width;
+        let color = heatmap_color(count as f64 / max as f64);
+        result.put_pixel(i as u32, height as u32 - 1 - j as u32, color);
+    }
+    result
+}
+
+impl<F> Default for TraversalHeatmapRenderer<F> where F: BaseFloat + 'static {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F> TraversalHeatmapRenderer<F> where F: BaseFloat + 'static {
+    pub fn new() -> Self {
+        Self {
+            max_nodes_visited: None,
+            max_primitives_tested: None,
+            _phantom: PhantomData
+        }
+    }
+
+    pub fn render(&self, scene: &Scene<F>, width: usize, height: usize, camera: &PerspectiveCamera<F>, camera_transform: &Transform<F>) -> TraversalHeatmap {
+        let tracing_service = TracingService::new(scene);
+        let mut nodes_visited = vec![0; width * height];
+        let mut primitives_tested = vec![0; width * height];
+        let mut total = TraversalStats::default();
+
+        let pb = ProgressBar::new((width * height) as u64);
+
+        for (ray, (i, j)) in camera.iter_ray(camera_transform, width, height) {
+            let mut stats = TraversalStats::default();
+            tracing_service.hit_ray_with_stats(&ray, F::from(1e-6).unwrap(), F::infinity(), &mut stats);
+            nodes_visited[j * width + i] = stats.nodes_visited;
+            primitives_tested[j * width + i] = stats.primitives_tested;
+            total.add(&stats);
+
+            pb.inc(1);
+        }
+
+        pb.finish();
+
+        TraversalHeatmap {
+            nodes_visited: counts_to_image(&nodes_visited, width, height, self.max_nodes_visited),
+            primitives_tested: counts_to_image(&primitives_tested, width, height, self.max_primitives_tested),
+            stats: total,
+        }
+    }
+}
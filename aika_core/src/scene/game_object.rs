@@ -2,7 +2,7 @@ use std::any::{Any, TypeId};
 use std::cell::{Ref, RefCell};
 use std::collections::HashMap;
 use std::ops::{Deref, DerefMut};
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use cgmath::BaseFloat;
 use crate::component::{ComponentData, MeshFilter, Transform, Component};
 use crate::mesh::{CommonVertex, DynMesh, Mesh, PlaneMesh, VertexBuffer};
@@ -11,6 +11,8 @@ use anyhow::Result;
 pub struct GameObjectInternal<F> {
     pub components: HashMap<TypeId, Component<F>>,
     pub name: String,
+    /// The transform of the game object is relative to the parent's world transform
+    pub parent: Option<Weak<RefCell<GameObjectInternal<F>>>>,
 }
 
 pub struct GameObject<F> {
@@ -26,6 +28,30 @@ impl<F> GameObject<F> where F: BaseFloat {
     pub fn set_name(&mut self, name: &str) {
         self.go.borrow_mut().name = String::from(name);
     }
+
+    pub fn get_parent(&self) -> Option<GameObject<F>> {
+        let parent = self.go.borrow().parent.as_ref()?.upgrade()?;
+        Some(GameObject {
+            go: parent
+        })
+    }
+
+    /// Attach to `parent`, or detach with `None`.
+    /// The parent is held weakly, it has to be kept alive by the scene
+    pub fn set_parent(&mut self, parent: Option<&GameObject<F>>) -> Result<()> {
+        if let Some(p) = parent {
+            let mut ancestor = Some(p.clone());
+            while let Some(a) = ancestor {
+                if Rc::ptr_eq(&a.go, &self.go) {
+                    return Err(anyhow::anyhow!("Setting parent of `{}` would create a cycle", self.go.borrow().name));
+                }
+                ancestor = a.get_parent();
+            }
+        }
+
+        self.go.borrow_mut().parent = parent.map(|p| Rc::downgrade(&p.go));
+        Ok(())
+    }
 }
 
 impl<F> GameObject<F> where F: BaseFloat + 'static {
@@ -34,6 +60,7 @@ impl<F> GameObject<F> where F: BaseFloat + 'static {
         let go = Rc::new(RefCell::new(GameObjectInternal {
             components: HashMap::new(),
             name,
+            parent: None,
             // _float_phantom: PhantomData
         }));
 
@@ -54,9 +81,7 @@ impl<F> GameObject<F> where F: BaseFloat + 'static {
     pub fn new_plane(name: String, width_x: F, width_y: F) -> GameObject<F> {
         let mesh = PlaneMesh::create_plane_mesh(width_x, width_y);
         let mut go = GameObject::new_empty(name);
-        let mesh_filter = MeshFilter {
-            mesh
-        };
+        let mesh_filter = MeshFilter::new(mesh);
 
         go.add_component_owned(mesh_filter);
         go
@@ -85,6 +110,19 @@ impl<F> GameObject<F> where F: BaseFloat + 'static {
         self.go.borrow().components.contains_key(&type_id)
     }
 
+    /// Object space to world space, composed through the parents.
+    /// A game object without a transform component is placed at its parent's origin
+    pub fn get_world_transform(&self) -> aika_math::Transform<F> {
+        let local = match self.get_transform() {
+            Some(t) => t.get_math_transform(),
+            None => aika_math::Transform::new(),
+        };
+        match self.get_parent() {
+            Some(parent) => parent.get_world_transform() * local,
+            None => local,
+        }
+    }
+
     pub fn get_transform(&self) -> Option<Transform<F>> {
         let component = self.get_component::<Transform<F>>();
         if let Ok(c) = component {
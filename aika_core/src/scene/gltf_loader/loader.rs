@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+use anyhow::{anyhow, Result};
+use cgmath::{BaseFloat, Deg, Quaternion, Rotation3, Vector2, Vector3, Vector4};
+use gltf::khr_lights_punctual::Kind;
+use gltf::mesh::Mode;
+use image::RgbImage;
+use crate::camera::PerspectiveCamera;
+use crate::component::{MeshFilter, Transform};
+use crate::lighting::{DirectionalLightComponent, PointLightComponent};
+use crate::material::{Material, MetallicRoughnessBRDFMaterial};
+use crate::material_graph::{ChannelNode, MultiplyNode, OutputValue, Texture2DNode};
+use crate::mesh::{CommonVertex, Mesh, MeshProcessingOptions};
+use crate::scene::{GameObject, Scene};
+use crate::texture::Texture2D;
+
+/// Imports a glTF 2.0 scene (.gltf or .glb).
+/// Nodes become game objects with the same hierarchy, the coordinates are kept as they are (+y up).
+///
+/// Mapping:
+/// * every primitive of a mesh becomes a game object with a `MeshFilter` and a `Material`,
+///   a child of the node if the mesh has more than one primitive
+/// * perspective cameras become `PerspectiveCamera` components, orthographic cameras are skipped with a warning
+/// * KHR_lights_punctual directional and point lights become `DirectionalLightComponent` and `PointLightComponent`,
+///   the color is multiplied by the intensity. Spot lights are skipped with a warning
+/// * PBR metallic roughness materials become `MetallicRoughnessBRDFMaterial`, with the normal texture as normal map.
+///   Only the first texture coordinate set is used
+pub struct GltfSceneLoader;
+
+fn get_f<F: BaseFloat>(x: f32) -> F {
+    F::from(x).unwrap()
+}
+
+fn get_vec3<F: BaseFloat>(v: [f32; 3]) -> Vector3<F> {
+    Vector3::new(get_f(v[0]), get_f(v[1]), get_f(v[2]))
+}
+
+fn get_rgb_image(data: &gltf::image::Data) -> Result<RgbImage> {
+    use gltf::image::Format;
+    let pixel_count = (data.width * data.height) as usize;
+    // 16 bit channels are stored little endian, the high byte is kept
+    let (channels, bytes) = match data.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        f => return Err(anyhow!("unsupported image format {:?}", f)),
+    };
+    if data.pixels.len() < pixel_count * channels * bytes {
+        return Err(anyhow!("image data is too short"));
+    }
+
+    let mut rgb = Vec::with_capacity(pixel_count * 3);
+    for i in 0..pixel_count {
+        let get_channel = |c: usize| data.pixels[(i * channels + c) * bytes + bytes - 1];
+        match channels {
+            // gray, or gray with alpha
+            1 | 2 => rgb.extend_from_slice(&[get_channel(0); 3]),
+            _ => rgb.extend_from_slice(&[get_channel(0), get_channel(1), get_channel(2)]),
+        }
+    }
+    RgbImage::from_raw(data.width, data.height, rgb).ok_or(anyhow!("invalid image size"))
+}
+
+struct ImportContext<'a> {
+    buffers: &'a [gltf::buffer::Data],
+    images: &'a [gltf::image::Data],
+    /// textures are shared by materials, keyed by (image index, is srgb)
+    textures: HashMap<(usize, bool), Rc<Texture2D>>,
+}
+
+impl<'a> ImportContext<'a> {
+    fn get_texture<F>(&mut self, texture: &gltf::Texture, is_srgb: bool) -> Result<Rc<dyn OutputValue<F, Vector3<F>>>> where F: BaseFloat + 'static {
+        let index = texture.source().index();
+        let texture = match self.textures.get(&(index, is_srgb)) {
+            Some(t) => t.clone(),
+            None => {
+                let data = self.images.get(index).ok_or(anyhow!("image {} not found", index))?;
+                let t = Rc::new(Texture2D::from_image(get_rgb_image(data)?, is_srgb));
+                self.textures.insert((index, is_srgb), t.clone());
+                t
+            },
+        };
+        Ok(Rc::new(Texture2DNode::new(texture)))
+    }
+
+    fn get_material<F>(&mut self, material: &gltf::Material) -> Result<Material<F>> where F: BaseFloat + 'static {
+        let pbr = material.pbr_metallic_roughness();
+        let base_color_factor = pbr.base_color_factor();
+        let base_color_factor = Vector3::new(get_f(base_color_factor[0]), get_f(base_color_factor[1]), get_f(base_color_factor[2]));
+        let color: Rc<dyn OutputValue<F, Vector3<F>>> = match pbr.base_color_texture() {
+            Some(info) => Rc::new(MultiplyNode::new(self.get_texture(&info.texture(), true)?, base_color_factor)),
+            None => Rc::new(base_color_factor),
+        };
+
+        let metallic_factor: F = get_f(pbr.metallic_factor());
+        let roughness_factor: F = get_f(pbr.roughness_factor());
+        let mut metallic: Rc<dyn OutputValue<F, F>> = Rc::new(metallic_factor);
+        let mut roughness: Rc<dyn OutputValue<F, F>> = Rc::new(roughness_factor);
+        if let Some(info) = pbr.metallic_roughness_texture() {
+            // roughness in green, metallic in blue
+            let texture = self.get_texture(&info.texture(), false)?;
+            metallic = Rc::new(MultiplyNode::new(Rc::new(ChannelNode::new(texture.clone(), 2)), metallic_factor));
+            roughness = Rc::new(MultiplyNode::new(Rc::new(ChannelNode::new(texture, 1)), roughness_factor));
+        }
+
+        let mut result = Material::new(Box::new(MetallicRoughnessBRDFMaterial::new(roughness, metallic, color)));
+        if let Some(normal_texture) = material.normal_texture() {
+            result = result.with_normal_map(self.get_texture(&normal_texture.texture(), false)?);
+        }
+        Ok(result)
+    }
+
+    fn get_mesh<F>(&self, primitive: &gltf::Primitive) -> Result<Option<Mesh<Vec<CommonVertex<F>>>>> where F: BaseFloat {
+        let reader = primitive.reader(|buffer| self.buffers.get(buffer.index()).map(|b| &b.0[..]));
+        let Some(positions) = reader.read_positions() else {
+            return Ok(None);
+        };
+        let mut vertices: Vec<CommonVertex<F>> = positions.map(|p| {
+            let mut v = CommonVertex::new();
+            v.position = get_vec3(p);
+            v
+        }).collect();
+
+        if let Some(normals) = reader.read_normals() {
+            for (v, n) in vertices.iter_mut().zip(normals) {
+                v.normal = Some(get_vec3(n));
+            }
+        }
+        // the origin of the uv of glTF is at the top left, the textures here start at the bottom left
+        if let Some(uvs) = reader.read_tex_coords(0) {
+            for (v, uv) in vertices.iter_mut().zip(uvs.into_f32()) {
+                v.uv0 = Some(Vector2::new(get_f(uv[0]), F::one() - get_f(uv[1])));
+            }
+        }
+        if let Some(uvs) = reader.read_tex_coords(1) {
+            for (v, uv) in vertices.iter_mut().zip(uvs.into_f32()) {
+                v.uv1 = Some(Vector2::new(get_f(uv[0]), F::one() - get_f(uv[1])));
+            }
+        }
+        // v is flipped, so is the bitangent
+        if let Some(tangents) = reader.read_tangents() {
+            for (v, t) in vertices.iter_mut().zip(tangents) {
+                v.tangent = Some(Vector4::new(get_f(t[0]), get_f(t[1]), get_f(t[2]), -get_f::<F>(t[3])));
+            }
+        }
+        if let Some(colors) = reader.read_colors(0) {
+            for (v, c) in vertices.iter_mut().zip(colors.into_rgb_f32()) {
+                v.color = Some(get_vec3(c));
+            }
+        }
+
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..vertices.len()).collect(),
+        };
+        let triangles: Vec<[usize; 3]> = match primitive.mode() {
+            Mode::Triangles => indices.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect(),
+            Mode::TriangleStrip => (2..indices.len()).map(|i| {
+                // every other triangle is flipped to keep the winding
+                if i % 2 == 0 {
+                    [indices[i - 2], indices[i - 1], indices[i]]
+                } else {
+                    [indices[i - 1], indices[i - 2], indices[i]]
+                }
+            }).collect(),
+            Mode::TriangleFan => (2..indices.len()).map(|i| [indices[0], indices[i - 1], indices[i]]).collect(),
+            // points and lines have no surface
+            _ => return Ok(None),
+        };
+
+        let triangle_count = triangles.len();
+        let mut mesh = Mesh {
+            vertices,
+            triangles,
+            sub_mesh: vec![[0, triangle_count]],
+        };
+        if mesh.vertices.iter().any(|v| v.normal.is_none()) {
+            // generates the tangents as well
+            mesh.process(&MeshProcessingOptions::default())?;
+        } else if mesh.vertices.iter().any(|v| v.tangent.is_none()) && mesh.vertices.iter().all(|v| v.uv0.is_some()) {
+            mesh.generate_tangents()?;
+        }
+        Ok(Some(mesh))
+    }
+
+    fn add_node<F>(&mut self, scene: &mut Scene<F>, node: &gltf::Node, parent: Option<&GameObject<F>>) -> Result<()> where F: BaseFloat + 'static {
+        let name = node.name().map(String::from).unwrap_or_else(|| format!("node {}", node.index()));
+        let mut go = GameObject::new_empty(name.clone());
+        let (translation, rotation, scale) = node.transform().decomposed();
+        let rotation: Quaternion<F> = Quaternion::new(get_f(rotation[3]), get_f(rotation[0]), get_f(rotation[1]), get_f(rotation[2]));
+        go.add_component_owned(Transform::new_non_uniform(get_vec3(translation), get_vec3(scale), rotation));
+        go.set_parent(parent)?;
+        scene.add_game_object(go.clone());
+
+        if let Some(mesh) = node.mesh() {
+            let primitives: Vec<_> = mesh.primitives().collect();
+            for (i, primitive) in primitives.iter().enumerate() {
+                let Some(m) = self.get_mesh::<F>(primitive)? else {
+                    continue;
+                };
+                let material = self.get_material::<F>(&primitive.material())?;
+                let mut target = if primitives.len() == 1 {
+                    go.clone()
+                } else {
+                    // without a transform, the child is placed at the node
+                    let mut child = GameObject::new_empty(format!("{} primitive {}", name, i));
+                    child.set_parent(Some(&go))?;
+                    scene.add_game_object(child.clone());
+                    child
+                };
+                target.add_component_owned(MeshFilter::new(m.to_dyn_mesh()));
+                target.add_component_owned(material);
+            }
+        }
+
+        if let Some(camera) = node.camera() {
+            match camera.projection() {
+                gltf::camera::Projection::Perspective(p) => {
+                    let far = p.zfar().map(get_f).unwrap_or(F::infinity());
+                    let aspect = p.aspect_ratio().map(get_f).unwrap_or(F::one());
+                    go.add_component_owned(PerspectiveCamera::new(get_f(p.yfov()), get_f(p.znear()), far, aspect));
+                },
+                gltf::camera::Projection::Orthographic(_) => {
+                    log::warn!("{}: orthographic cameras are not supported, skipped", name);
+                },
+            }
+        }
+
+        if let Some(light) = node.light() {
+            let color = get_vec3::<F>(light.color()) * get_f(light.intensity());
+            match light.kind() {
+                Kind::Directional => {
+                    // glTF lights point to -z, the directional light component points to +z
+                    let mut child = GameObject::new_empty(format!("{} light", name));
+                    let flip = Quaternion::from_angle_y(Deg(F::from(180).unwrap()));
+                    child.add_component_owned(Transform::new(Vector3::new(F::zero(), F::zero(), F::zero()), F::one(), flip));
+                    child.add_component_owned(DirectionalLightComponent::new(color));
+                    child.set_parent(Some(&go))?;
+                    scene.add_game_object(child);
+                },
+                Kind::Point => {
+                    go.add_component_owned(PointLightComponent {
+                        color,
+                        radius: None
+                    });
+                },
+                Kind::Spot { .. } => {
+                    log::warn!("{}: spot lights are not supported, skipped", name);
+                },
+            }
+        }
+
+        for child in node.children() {
+            self.add_node(scene, &child, Some(&go))?;
+        }
+        Ok(())
+    }
+}
+
+impl GltfSceneLoader {
+    fn create_scene<F>(document: &gltf::Document, buffers: &[gltf::buffer::Data], images: &[gltf::image::Data]) -> Result<Scene<F>> where F: BaseFloat + 'static {
+        let mut context = ImportContext {
+            buffers,
+            images,
+            textures: HashMap::new(),
+        };
+        let gltf_scene = document.default_scene()
+            .or_else(|| document.scenes().next())
+            .ok_or(anyhow!("no scene in the glTF file"))?;
+
+        let mut scene = Scene::new();
+        for node in gltf_scene.nodes() {
+            context.add_node(&mut scene, &node, None)?;
+        }
+        Ok(scene)
+    }
+
+    pub fn load_gltf<F, P>(p: P) -> Result<Scene<F>> where F: BaseFloat + 'static, P: AsRef<Path> {
+        let (document, buffers, images) = gltf::import(p)?;
+        GltfSceneLoader::create_scene(&document, &buffers, &images)
+    }
+
+    /// A .glb file, or a .gltf file whose buffers and images are embedded as data uris
+    pub fn load_gltf_memory<F>(data: &[u8]) -> Result<Scene<F>> where F: BaseFloat + 'static {
+        let (document, buffers, images) = gltf::import_slice(data)?;
+        GltfSceneLoader::create_scene(&document, &buffers, &images)
+    }
+}
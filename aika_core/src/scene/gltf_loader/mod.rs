@@ -0,0 +1,5 @@
+pub use loader::GltfSceneLoader;
+
+mod loader;
+#[cfg(test)]
+mod test;
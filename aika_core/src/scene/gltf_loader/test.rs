@@ -0,0 +1,119 @@
+use cgmath::{InnerSpace, Vector2, Vector3};
+use aika_math::Ray;
+use crate::camera::PerspectiveCamera;
+use crate::component::MeshFilter;
+use crate::lighting::{DirectionalLightComponent, PointLightComponent};
+use crate::mashed_scene::MashedScene;
+use crate::material::Material;
+use crate::mesh::VertexBuffer;
+use crate::scene::{GameObject, GltfSceneLoader, Scene};
+
+const TEST_GLTF_JSON: &str = r#"{
+    "asset": { "version": "2.0" },
+    "extensionsUsed": ["KHR_lights_punctual"],
+    "extensions": { "KHR_lights_punctual": { "lights": [
+        { "type": "point", "color": [1.0, 0.5, 0.25], "intensity": 4.0 },
+        { "type": "directional", "intensity": 2.0 }
+    ] } },
+    "scene": 0,
+    "scenes": [{ "nodes": [0, 2] }],
+    "nodes": [
+        { "name": "root", "translation": [1.0, 2.0, 3.0], "mesh": 0, "children": [1] },
+        { "name": "lamp", "translation": [0.0, 1.0, 0.0], "extensions": { "KHR_lights_punctual": { "light": 0 } } },
+        { "name": "camera", "camera": 0, "extensions": { "KHR_lights_punctual": { "light": 1 } } }
+    ],
+    "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.8, "znear": 0.1, "zfar": 100.0, "aspectRatio": 1.5 } }],
+    "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0, "TEXCOORD_0": 1 }, "indices": 2, "material": 0 }] }],
+    "materials": [{ "pbrMetallicRoughness": { "baseColorFactor": [0.5, 0.25, 1.0, 1.0], "metallicFactor": 0.0, "roughnessFactor": 0.7 } }],
+    "buffers": [{ "byteLength": 66 }],
+    "bufferViews": [
+        { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 36, "byteLength": 24 },
+        { "buffer": 0, "byteOffset": 60, "byteLength": 6 }
+    ],
+    "accessors": [
+        { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0] },
+        { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC2" },
+        { "bufferView": 2, "componentType": 5123, "count": 3, "type": "SCALAR" }
+    ]
+}"#;
+
+/// A binary glTF with one triangle, a point light under the mesh node, and a camera carrying a directional light
+fn get_test_glb() -> Vec<u8> {
+    let mut bin: Vec<u8> = Vec::new();
+    for x in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+        bin.extend_from_slice(&x.to_le_bytes());
+    }
+    for x in [0.0f32, 0.0, 1.0, 0.0, 0.0, 1.0] {
+        bin.extend_from_slice(&x.to_le_bytes());
+    }
+    for i in [0u16, 1, 2] {
+        bin.extend_from_slice(&i.to_le_bytes());
+    }
+    while bin.len() % 4 != 0 {
+        bin.push(0);
+    }
+    let mut json = TEST_GLTF_JSON.as_bytes().to_vec();
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+
+    let mut glb = Vec::new();
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json);
+    glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(&bin);
+    glb
+}
+
+fn find<'a>(scene: &'a Scene<f64>, name: &str) -> &'a GameObject<f64> {
+    scene.game_objects.iter().find(|go| go.go.borrow().name == name).unwrap()
+}
+
+#[test]
+fn test_gltf_loader() {
+    let scene = GltfSceneLoader::load_gltf_memory::<f64>(&get_test_glb()).unwrap();
+    assert_eq!(scene.game_objects.len(), 4);
+
+    let root = find(&scene, "root");
+    assert!(root.has_component::<Material<f64>>());
+    {
+        let component = root.get_component::<MeshFilter<f64>>().unwrap();
+        let mesh_filter = component.downcast::<MeshFilter<f64>>();
+        let vertices = &mesh_filter.mesh.vertices;
+        // missing normals are generated, v is flipped
+        assert!((vertices.get_normal(0).unwrap() - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+        assert!((vertices.get_uv0(2).unwrap() - Vector2::new(0.0, 0.0)).magnitude() < 1e-9);
+        assert!(vertices.get_tangent(0).is_some());
+    }
+
+    let lamp = find(&scene, "lamp");
+    let position = lamp.get_world_transform().transform_point(Vector3::new(0.0, 0.0, 0.0));
+    assert!((position - Vector3::new(1.0, 3.0, 3.0)).magnitude() < 1e-9);
+    let color = lamp.get_component::<PointLightComponent<f64>>().unwrap().downcast::<PointLightComponent<f64>>().color;
+    assert!((color - Vector3::new(4.0, 2.0, 1.0)).magnitude() < 1e-6);
+
+    let camera = find(&scene, "camera");
+    {
+        let component = camera.get_component::<PerspectiveCamera<f64>>().unwrap();
+        let perspective_camera = component.downcast::<PerspectiveCamera<f64>>();
+        assert!((perspective_camera.fovy - 0.8).abs() < 1e-6);
+        assert!((perspective_camera.aspect - 1.5).abs() < 1e-6);
+    }
+
+    // the directional light points to -z of the camera node
+    let light = find(&scene, "camera light");
+    assert!(light.has_component::<DirectionalLightComponent<f64>>());
+    let direction = light.get_world_transform().transform_vector(Vector3::new(0.0, 0.0, 1.0));
+    assert!((direction - Vector3::new(0.0, 0.0, -1.0)).magnitude() < 1e-9);
+
+    let mashed_scene = MashedScene::from_scene_bvh(&scene);
+    let ray = Ray::new(Vector3::new(1.2, 2.2, 10.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = mashed_scene.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    assert!((hit.get_hit_point(&ray).z - 3.0).abs() < 1e-9);
+}
@@ -1,6 +1,8 @@
 pub use game_object::{GameObjectInternal, GameObject};
 pub use scene::Scene;
+pub use gltf_loader::GltfSceneLoader;
 
 mod game_object;
 mod scene;
-mod test;
\ No newline at end of file
+mod gltf_loader;
+mod test;
@@ -1,9 +1,10 @@
 use std::any::Any;
 use std::cell::RefCell;
 use std::rc::Rc;
-use cgmath::BaseFloat;
-use crate::component::ComponentData;
-use crate::mesh::VertexBuffer;
+use cgmath::{BaseFloat, One, Quaternion, Vector3};
+use crate::component::{ComponentData, MeshFilter, Transform};
+use crate::material::{DiffuseBRDFMaterial, Material};
+use crate::mesh::{CommonVertex, CubeMesh, Mesh, MeshGenerationOptions, VertexBuffer};
 use crate::scene::{GameObject, GameObjectInternal};
 
 // todo more complicated management
@@ -26,8 +27,27 @@ impl<F> Scene<F> where F: BaseFloat + 'static {
     /// with plane of width and height 10, and cube of edge 1
     /// the plane centered on the origin, and the plane's normal is (0, 0, 1)
     /// the cube is right on the plane, thus have center position (0, 0, 0.5)
+    /// both have a gray diffuse material, so the scene can be rendered as it is
     pub fn new_plane_and_cube() -> Self {
-        todo!()
+        let mut scene = Scene::new();
+        let zero = Vector3::new(F::zero(), F::zero(), F::zero());
+        let ten = F::from(10).unwrap();
+        let gray = F::from(0.8).unwrap();
+        let get_material = || Material::new(Box::new(DiffuseBRDFMaterial::new(Vector3::new(gray, gray, gray))));
+
+        let mut plane = GameObject::new_plane(String::from("plane"), ten, ten);
+        plane.add_component_owned(Transform::new(zero, F::one(), Quaternion::one()));
+        plane.add_component_owned(get_material());
+        scene.add_game_object(plane);
+
+        let cube_mesh = CubeMesh::create_cube_mesh(Vector3::new(F::one(), F::one(), F::one()), 1, &MeshGenerationOptions::default());
+        let mut cube = GameObject::new_empty(String::from("cube"));
+        cube.add_component_owned(Transform::new(Vector3::new(F::zero(), F::zero(), F::from(0.5).unwrap()), F::one(), Quaternion::one()));
+        cube.add_component_owned(MeshFilter::new(cube_mesh.to_dyn_mesh()));
+        cube.add_component_owned(get_material());
+        scene.add_game_object(cube);
+
+        scene
     }
 
     pub fn get_game_objects_of_type<C: ComponentData>(&self) -> Vec<GameObject<F>> {
@@ -40,4 +60,17 @@ impl<F> Scene<F> where F: BaseFloat + 'static {
 
         ret
     }
+
+    /// The meshes of the `MeshFilter` game objects in world space, with the names of the game objects.
+    /// The built meshes are used if the scene has been mashed
+    pub fn get_world_meshes(&self) -> Vec<(String, Mesh<Vec<CommonVertex<F>>>)> {
+        let mut result = Vec::new();
+        for go in self.get_game_objects_of_type::<MeshFilter<F>>() {
+            let mesh_component = go.get_component::<MeshFilter<F>>().unwrap();
+            let mesh = mesh_component.downcast::<MeshFilter<F>>().get_mesh().to_common_mesh();
+            let transform = go.get_world_transform();
+            result.push((go.go.borrow().name.clone(), mesh.transform(&transform)));
+        }
+        result
+    }
 }
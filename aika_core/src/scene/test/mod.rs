@@ -1,4 +1,9 @@
 use std::rc::Rc;
+use cgmath::{Deg, InnerSpace, One, Quaternion, Rotation3, Vector3};
+use aika_math::Ray;
+use crate::component::Transform;
+use crate::material::Material;
+use crate::mashed_scene::MashedScene;
 use crate::scene::{GameObject, GameObjectInternal, Scene};
 
 #[test]
@@ -7,3 +12,43 @@ fn test_scene1() {
     scene.add_game_object(GameObject::new_plane(String::from("plane"), 1.0, 1.0));
     assert_eq!(scene.game_objects.len(), 1);
 }
+
+#[test]
+fn test_game_object_hierarchy() {
+    let mut parent: GameObject<f64> = GameObject::new_empty(String::from("parent"));
+    parent.add_component_owned(Transform::new(Vector3::new(1.0, 0.0, 0.0), 2.0, Quaternion::from_angle_z(Deg(90.0))));
+    let mut child: GameObject<f64> = GameObject::new_empty(String::from("child"));
+    child.add_component_owned(Transform::new_non_uniform(Vector3::new(1.0, 0.0, 0.0), Vector3::new(1.0, 3.0, 1.0), Quaternion::one()));
+    child.set_parent(Some(&parent)).unwrap();
+
+    // the child's origin is (1, 0, 0) in the parent, scaled by 2 and rotated onto the y axis
+    let world = child.get_world_transform();
+    let p = world.transform_point(Vector3::new(0.0, 1.0, 0.0));
+    assert!((p - Vector3::new(-5.0, 2.0, 0.0)).magnitude() < 1e-9);
+
+    let n = world.transform_normal(Vector3::new(1.0, 1.0, 0.0)).unwrap().normalize();
+    let tangent = world.transform_vector(Vector3::new(1.0, -1.0, 0.0));
+    assert!(n.dot(tangent).abs() < 1e-9);
+
+    // a game object can not be its own ancestor
+    assert!(parent.set_parent(Some(&child)).is_err());
+    child.set_parent(None).unwrap();
+    assert!(child.get_parent().is_none());
+}
+
+#[test]
+fn test_scene_plane_and_cube() {
+    let scene: Scene<f64> = Scene::new_plane_and_cube();
+    assert_eq!(scene.game_objects.len(), 2);
+    assert_eq!(scene.get_game_objects_of_type::<Material<f64>>().len(), 2);
+
+    let mashed_scene = MashedScene::from_scene_bvh(&scene);
+    assert_eq!(mashed_scene.get_triangle_count(), 14);
+    // the top of the cube, and the plane next to it
+    let ray = Ray::new(Vector3::new(0.1, 0.2, 10.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = mashed_scene.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    assert!((hit.get_hit_point(&ray).z - 1.0).abs() < 1e-9);
+    let ray = Ray::new(Vector3::new(3.0, 0.2, 10.0), Vector3::new(0.0, 0.0, -1.0));
+    let hit = mashed_scene.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    assert!(hit.get_hit_point(&ray).z.abs() < 1e-9);
+}
@@ -20,6 +20,20 @@ impl Texture2D {
         })
     }
 
+    pub fn from_image(image: RgbImage, is_srgb: bool) -> Texture2D {
+        Texture2D {
+            image,
+            is_srgb
+        }
+    }
+
+    /// Load a texture which stores data rather than colors, such as a normal map
+    pub fn from_file_linear(file_name: &str) -> Option<Texture2D> {
+        let mut texture = Texture2D::from_file(file_name)?;
+        texture.is_srgb = false;
+        Some(texture)
+    }
+
     pub fn get_width(&self) -> usize {
         self.image.width() as usize
     }
@@ -31,12 +45,14 @@ impl Texture2D {
     /// x from left bottom to right bottom
     /// y from left bottom to left top
     /// this is consistent with uv coordinate
+    /// the channels are in [0, 1]
     pub fn get_pixel<F: BaseFloat>(&self, x: usize, y: usize) -> Vector3<F> {
         let height = self.get_height();
         let rgb = self.image.get_pixel(x as u32, (height - y - 1) as u32);
-        let r = F::from(rgb.0[0]).unwrap();
-        let g = F::from(rgb.0[1]).unwrap();
-        let b = F::from(rgb.0[2]).unwrap();
+        let max = F::from(255).unwrap();
+        let r = F::from(rgb.0[0]).unwrap() / max;
+        let g = F::from(rgb.0[1]).unwrap() / max;
+        let b = F::from(rgb.0[2]).unwrap() / max;
         let mut v = Vector3::new(r, g, b);
         let gamma = F::from(2.2).unwrap();
         if self.is_srgb {
@@ -49,12 +65,15 @@ impl Texture2D {
 }
 
 impl<F> Texture2DTrait<F> for Texture2D where F: BaseFloat {
+    /// Nearest sampling, uv outside of [0, 1] repeats
     fn sample(&self, uv: Vector2<F>) -> Vector3<F> {
-        let width = self.get_width() as i32;
-        let height = self.get_height() as i32;
-        let x = (f!(width) * uv[0]).to_i32().unwrap();
-        let y = (f!(height) + uv[1]).to_i32().unwrap();
+        let width = self.get_width();
+        let height = self.get_height();
+        let u = uv[0] - uv[0].floor();
+        let v = uv[1] - uv[1].floor();
+        let x = (f!(width) * u).to_usize().unwrap_or(0).min(width - 1);
+        let y = (f!(height) * v).to_usize().unwrap_or(0).min(height - 1);
 
-        self.get_pixel(x as usize, y as usize)
+        self.get_pixel(x, y)
     }
 }
@@ -0,0 +1,108 @@
+use std::f64::consts::PI;
+use cgmath::{BaseFloat, InnerSpace, Vector3};
+use crate::utils::{lerp, sample_uniform_disk_polar};
+
+/// Trowbridge-Reitz (GGX) distribution with alpha_x along the tangent and alpha_y along the bitangent
+#[derive(Clone, Copy, Debug)]
+pub struct AnisotropicGGXDistribution<F> {
+    pub alpha_x: F,
+    pub alpha_y: F,
+}
+
+impl<F> AnisotropicGGXDistribution<F> where F: BaseFloat {
+    pub fn new(alpha_x: F, alpha_y: F) -> Self {
+        Self {
+            alpha_x,
+            alpha_y,
+        }
+    }
+
+    pub fn new_isotropic(alpha: F) -> Self {
+        Self::new(alpha, alpha)
+    }
+
+    /// `anisotropy` in [0, 1] stretches the highlight along the tangent, keeping the average of the alphas about `alpha`
+    pub fn from_anisotropy(alpha: F, anisotropy: F) -> Self {
+        let anisotropy = anisotropy.max(F::zero()).min(F::one());
+        let aspect = (F::one() - F::from(0.9).unwrap() * anisotropy).sqrt();
+        Self::new(alpha / aspect, alpha * aspect)
+    }
+
+    pub fn is_effectively_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < F::from(1e-3).unwrap()
+    }
+
+    /// The distribution is symmetric, D(wm) = D(-wm)
+    pub fn evaluate(&self, wm: Vector3<F>) -> F {
+        let pi = F::from(PI).unwrap();
+        let x = wm.x / self.alpha_x;
+        let y = wm.y / self.alpha_y;
+        let temp = x * x + y * y + wm.z * wm.z;
+        F::one() / (pi * self.alpha_x * self.alpha_y * temp * temp)
+    }
+
+    fn lambda(&self, w: Vector3<F>) -> F {
+        let cos_theta_2 = w.z * w.z;
+        if cos_theta_2 == F::zero() {
+            return F::zero();
+        }
+        let x = self.alpha_x * w.x;
+        let y = self.alpha_y * w.y;
+        let alpha_2_tan_theta_2 = (x * x + y * y) / cos_theta_2;
+
+        let one = F::one();
+        let h = F::from(0.5).unwrap();
+        ((one + alpha_2_tan_theta_2).sqrt() - one) * h
+    }
+
+    pub fn g1(&self, w: Vector3<F>) -> F {
+        F::one() / (F::one() + self.lambda(w))
+    }
+
+    /// Smith height correlated masking-shadowing
+    pub fn masking_shadowing(&self, wi: Vector3<F>, wo: Vector3<F>) -> F {
+        let one = F::one();
+        one / (one + self.lambda(wi) + self.lambda(wo))
+    }
+
+    /// The density of `sample_wm`. wo can be in any hemisphere, wm is in the positive hemisphere.
+    /// Normals facing away from wo are not visible
+    pub fn distribution_of_visible_normal(&self, wo: Vector3<F>, wm: Vector3<F>) -> F {
+        let cos_theta_o = wo.z.abs();
+        let cos_theta_m = if wo.z < F::zero() { -wo.dot(wm) } else { wo.dot(wm) };
+        if cos_theta_o == F::zero() || cos_theta_m <= F::zero() {
+            return F::zero();
+        }
+        self.g1(wo) / cos_theta_o * self.evaluate(wm) * cos_theta_m
+    }
+
+    /// Sample a normal visible from `w`, which is flipped to the positive hemisphere first.
+    /// See Heitz 2018, "Sampling the GGX Distribution of Visible Normals"
+    pub fn sample_wm(&self, w: Vector3<F>, r1: F, r2: F) -> Vector3<F> {
+        let one = F::one();
+        let two = F::from(2).unwrap();
+
+        // to the hemisphere configuration
+        let mut wh = Vector3::new(self.alpha_x * w.x, self.alpha_y * w.y, w.z).normalize();
+        if wh.z < F::zero() {
+            wh = -wh;
+        }
+        let t1 = if wh.z < F::from(0.99999).unwrap() {
+            Vector3::new(F::zero(), F::zero(), F::one()).cross(wh).normalize()
+        } else {
+            Vector3::new(F::one(), F::zero(), F::zero())
+        };
+        let t2 = wh.cross(t1);
+
+        // a uniform disk warped to the projection of the visible hemisphere
+        let mut p = sample_uniform_disk_polar(r1, r2);
+        let h = (one - p.x * p.x).max(F::zero()).sqrt();
+        p.y = lerp((one + wh.z) / two, h, p.y);
+
+        let pz = (one - p.x * p.x - p.y * p.y).max(F::zero()).sqrt();
+        let nh = t1 * p.x + t2 * p.y + wh * pz;
+
+        // back to the ellipsoid configuration
+        Vector3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(F::from(1e-6).unwrap())).normalize()
+    }
+}
@@ -1,7 +1,12 @@
 pub use hemi_spherical_distribution::{HemiSphericalDistribution, HemiSphericalDistributionSampleResult};
 pub use uniform_hemi_spherical_distribution::UniformHemiSphericalDistribution;
-pub use ggx::IsotropicGGXDistribution;
+pub use anisotropic_ggx::AnisotropicGGXDistribution;
+
+#[deprecated(note = "use `AnisotropicGGXDistribution::new_isotropic`")]
+pub type IsotropicGGXDistribution<F> = AnisotropicGGXDistribution<F>;
 
 mod hemi_spherical_distribution;
 mod uniform_hemi_spherical_distribution;
-mod ggx;
+mod anisotropic_ggx;
+#[cfg(test)]
+mod test_ggx;
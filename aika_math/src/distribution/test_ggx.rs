@@ -0,0 +1,54 @@
+use cgmath::{InnerSpace, Vector3};
+use crate::distribution::AnisotropicGGXDistribution;
+use crate::utils::{get_2pi, sample_uniform_hemisphere};
+
+const N: usize = 300;
+
+/// Integrate `f` over the upper hemisphere, on a grid of uniform samples
+fn integrate_hemisphere(f: impl Fn(Vector3<f64>) -> f64) -> f64 {
+    let mut sum = 0.0;
+    for i in 0..N {
+        for j in 0..N {
+            let w = sample_uniform_hemisphere((i as f64 + 0.5) / N as f64, (j as f64 + 0.5) / N as f64);
+            sum += f(w);
+        }
+    }
+    sum * get_2pi::<f64>() / (N * N) as f64
+}
+
+/// Average `f` over the normals sampled on the same grid
+fn average_sampled(distribution: &AnisotropicGGXDistribution<f64>, wo: Vector3<f64>, f: impl Fn(Vector3<f64>) -> f64) -> f64 {
+    let mut sum = 0.0;
+    for i in 0..N {
+        for j in 0..N {
+            let wm = distribution.sample_wm(wo, (i as f64 + 0.5) / N as f64, (j as f64 + 0.5) / N as f64);
+            sum += f(wm);
+        }
+    }
+    sum / (N * N) as f64
+}
+
+#[test]
+fn test_anisotropic_ggx_normalized() {
+    let wo = Vector3::new(0.4, 0.3, 0.6).normalize();
+    for distribution in [AnisotropicGGXDistribution::new(0.5, 0.5), AnisotropicGGXDistribution::new(0.6, 0.3)] {
+        // the projected area of the microfacets is the area of the surface
+        let projected = integrate_hemisphere(|wm| distribution.evaluate(wm) * wm.z);
+        assert!((projected - 1.0).abs() < 0.02, "{}", projected);
+
+        let visible = integrate_hemisphere(|wm| distribution.distribution_of_visible_normal(wo, wm));
+        assert!((visible - 1.0).abs() < 0.02, "{}", visible);
+    }
+}
+
+#[test]
+fn test_anisotropic_ggx_sample_visible_normal() {
+    let distribution = AnisotropicGGXDistribution::from_anisotropy(0.4, 0.8);
+    assert!(distribution.alpha_x > distribution.alpha_y);
+    for wo in [Vector3::new(0.4, 0.3, 0.6).normalize(), Vector3::new(-0.7, 0.1, -0.3).normalize()] {
+        let g = |wm: Vector3<f64>| wm.x * wm.x + 0.5 * wm.y;
+        let expected = integrate_hemisphere(|wm| g(wm) * distribution.distribution_of_visible_normal(wo, wm));
+        let sampled = average_sampled(&distribution, wo, g);
+        assert!((expected - sampled).abs() < 0.01, "expected: {}, sampled: {}", expected, sampled);
+    }
+}
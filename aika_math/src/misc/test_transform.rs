@@ -14,4 +14,13 @@ fn test_transform_1() {
 // #[test]
 // fn test_transform_normal() {
 //
-// }
\ No newline at end of file
+// }
+
+#[test]
+fn test_transform_normal_non_uniform_scale() {
+    let t = Transform::scale(Vector3::new(2.0, 1.0, 1.0));
+    // the plane x + y = 0 becomes 0.5x + y = 0 after scaling
+    let n = t.transform_normal(Vector3::new(1.0, 1.0, 0.0)).unwrap();
+    let tangent = t.transform_vector(Vector3::new(1.0, -1.0, 0.0));
+    assert_eq!(n.x * tangent.x + n.y * tangent.y + n.z * tangent.z, 0.0);
+}
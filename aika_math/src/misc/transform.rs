@@ -1,7 +1,8 @@
 use std::ops::Mul;
 use cgmath::{Angle, BaseFloat, Euler, Matrix, Matrix3, Matrix4, Quaternion, Rad, SquareMatrix, Vector3, Vector4};
-use crate::utils::rotate_from_to;
+use crate::utils::{gamma, rotate_from_to};
 
+#[derive(Clone, Debug)]
 pub struct Transform<F> {
     pub mat: Matrix4<F>,
     pub mat_inv: Option<Matrix4<F>>,
@@ -81,12 +82,40 @@ impl<F: BaseFloat> Transform<F> {
         }
     }
 
+    /// Whether the transform mirrors, which flips the handedness of coordinate frames
+    pub fn swaps_handedness(&self) -> bool {
+        let m = &self.mat;
+        let upper = Matrix3::new(
+            m[0][0], m[0][1], m[0][2],
+            m[1][0], m[1][1], m[1][2],
+            m[2][0], m[2][1], m[2][2],
+        );
+        upper.determinant() < F::zero()
+    }
+
     pub fn transform_point(&self, p: Vector3<F>) -> Vector3<F> {
         let p = Vector4::new(p.x, p.y, p.z, F::one());
         let pp = self.mat * p;
         Vector3::new(pp.x, pp.y, pp.z)
     }
 
+    /// Transform a point together with a conservative bound of its absolute error
+    pub fn transform_point_with_error(&self, p: Vector3<F>, error: Vector3<F>) -> (Vector3<F>, Vector3<F>) {
+        let m = &self.mat;
+        let g3 = gamma::<F>(3);
+        let mut out_error = Vector3::new(F::zero(), F::zero(), F::zero());
+        for i in 0..3 {
+            let mut propagated = F::zero();
+            let mut rounding = m[3][i].abs();
+            for j in 0..3 {
+                propagated += m[j][i].abs() * error[j];
+                rounding += (m[j][i] * p[j]).abs();
+            }
+            out_error[i] = (g3 + F::one()) * propagated + g3 * rounding;
+        }
+        (self.transform_point(p), out_error)
+    }
+
     pub fn transform_point_inverse(&self, p: Vector3<F>) -> Option<Vector3<F>> {
         if let Some(m) = &self.mat_inv {
             let p = Vector4::new(p.x, p.y, p.z, F::one());
@@ -89,6 +89,34 @@ impl<T> AABB<T> where T: BaseFloat {
         Self::from_min_max(new_min, new_max)
     }
 
+    /// Returns the parametric range [t0, t1] of the ray inside the box, clipped to [min, max]
+    pub fn hit_interval(&self, ray: &Ray<T>, min: T, max: T) -> Option<(T, T)> {
+        let bb_min = self.min();
+        let bb_max = self.max();
+        let mut t0 = min;
+        let mut t1 = max;
+        for i in 0..3 {
+            if ray.direction[i] == T::zero() {
+                if ray.origin[i] < bb_min[i] || ray.origin[i] > bb_max[i] {
+                    return None;
+                }
+                continue;
+            }
+            let inv_d = T::one() / ray.direction[i];
+            let mut t_near = (bb_min[i] - ray.origin[i]) * inv_d;
+            let mut t_far = (bb_max[i] - ray.origin[i]) * inv_d;
+            if t_near > t_far {
+                std::mem::swap(&mut t_near, &mut t_far);
+            }
+            t0 = t0.max(t_near);
+            t1 = t1.min(t_far);
+            if t0 > t1 {
+                return None;
+            }
+        }
+        Some((t0, t1))
+    }
+
     pub fn get_vertices(&self) -> [Vector3<T>; 8] {
         let mut result = [Vector3::zero(); 8];
         let c = self.center;
@@ -224,6 +252,8 @@ impl<F> Hittable<F, ()> for AABB<F> where F: BaseFloat {
                 back_facing: None,
                 hit_object: None,
                 uv: None,
+                point: None,
+                point_error: None,
             })
         } else {
             None
@@ -0,0 +1,169 @@
+use cgmath::{BaseFloat, InnerSpace, Vector2, Vector3};
+use crate::*;
+use crate::utils::{abs_vector3, gamma, get_2pi};
+
+/// An open cylinder (without caps) around the z axis through `center`,
+/// spanning `height / 2` on both sides of the center
+#[derive(Debug)]
+pub struct Cylinder<F> {
+    pub center: Vector3<F>,
+    pub radius: F,
+    pub height: F,
+}
+
+impl<F> Cylinder<F> where F: BaseFloat {
+    pub fn new(center: Vector3<F>, radius: F, height: F) -> Self {
+        Self {
+            center,
+            radius,
+            height
+        }
+    }
+
+    pub fn z_min(&self) -> F {
+        self.center.z - self.height / F::from(2).unwrap()
+    }
+
+    pub fn z_max(&self) -> F {
+        self.center.z + self.height / F::from(2).unwrap()
+    }
+
+    /// The outward normal at a point on the surface
+    pub fn get_normal(&self, point: Vector3<F>) -> Vector3<F> {
+        Vector3::new(point.x - self.center.x, point.y - self.center.y, F::zero()).normalize()
+    }
+
+    /// u follows phi around the axis, v goes from the bottom to the top
+    pub fn get_uv(&self, point: Vector3<F>) -> Vector2<F> {
+        let mut phi = (point.y - self.center.y).atan2(point.x - self.center.x);
+        if phi < F::zero() {
+            phi += get_2pi::<F>();
+        }
+        Vector2::new(phi / get_2pi::<F>(), (point.z - self.z_min()) / self.height)
+    }
+}
+
+impl<F> Bounded<AABB<F>> for Cylinder<F> where F: BaseFloat {
+    fn get_bv(&self) -> AABB<F> {
+        AABB {
+            center: self.center,
+            extent: Vector3::new(self.radius, self.radius, self.height / F::from(2).unwrap())
+        }
+    }
+}
+
+impl<F> Hittable<F, ()> for Cylinder<F> where F: BaseFloat {
+    fn hit(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, ()>> {
+        let ox = ray.origin.x - self.center.x;
+        let oy = ray.origin.y - self.center.y;
+        let dx = ray.direction.x;
+        let dy = ray.direction.y;
+
+        let two = F::from(2).unwrap();
+        let a = dx * dx + dy * dy;
+        if a == F::zero() {
+            return None;
+        }
+        let b = (dx * ox + dy * oy) * two;
+        let c = ox * ox + oy * oy - self.radius * self.radius;
+
+        let delta = b * b - a * c * F::from(4).unwrap();
+        if delta < F::zero() {
+            return None;
+        }
+        let sqrt_delta = delta.sqrt();
+        // avoid the cancellation of `-b + sqrt_delta`
+        let q = if b < F::zero() { -(b - sqrt_delta) / two } else { -(b + sqrt_delta) / two };
+        let (mut t0, mut t1) = (q / a, c / q);
+        if q == F::zero() {
+            t0 = -b / (two * a);
+            t1 = t0;
+        }
+        if t0 > t1 {
+            std::mem::swap(&mut t0, &mut t1);
+        }
+
+        for t in [t0, t1] {
+            if t < min || t > max {
+                continue;
+            }
+            let p = ray.origin + ray.direction * t;
+            if p.z < self.z_min() || p.z > self.z_max() {
+                continue;
+            }
+
+            // refine the hit point onto the surface
+            let local = Vector3::new(p.x - self.center.x, p.y - self.center.y, F::zero());
+            let local = local * (self.radius / local.magnitude());
+            let point = Vector3::new(self.center.x + local.x, self.center.y + local.y, p.z);
+            let point_error = abs_vector3(local) * gamma::<F>(3);
+            let normal = local / self.radius;
+
+            return Some(HitRecord {
+                t,
+                normal: Some(normal),
+                uv: Some(self.get_uv(point)),
+                back_facing: Some(normal.dot(ray.direction) > F::zero()),
+                point: Some(point),
+                point_error: Some(point_error),
+                hit_object: None,
+            });
+        }
+
+        None
+    }
+}
+
+impl<F> HaveCenter<F> for Cylinder<F> where F: BaseFloat {
+    fn get_center(&self) -> Vector3<F> {
+        self.center
+    }
+}
+
+impl<F> HaveArea<F> for Cylinder<F> where F: BaseFloat {
+    fn area(&self) -> F {
+        get_2pi::<F>() * self.radius * self.height
+    }
+}
+
+impl<F> SampleShape<F> for Cylinder<F> where F: BaseFloat {
+    fn sample_shape(&self, r1: F, r2: F) -> Option<SampleShapeResult<F>> {
+        let z = self.z_min() + r1 * self.height;
+        let (sin_phi, cos_phi) = (get_2pi::<F>() * r2).sin_cos();
+        let normal = Vector3::new(cos_phi, sin_phi, F::zero());
+        let position = Vector3::new(self.center.x, self.center.y, z) + normal * self.radius;
+        Some(SampleShapeResult {
+            position,
+            pdf: F::one() / self.area(),
+            normal
+        })
+    }
+}
+
+impl<F> PrimitiveTrait<F> for Cylinder<F> where F: BaseFloat {}
+
+#[cfg(test)]
+mod test {
+    use cgmath::Vector3;
+    use crate::{Cylinder, Hittable, Ray};
+
+    #[test]
+    fn test_cylinder_hit() {
+        let cylinder = Cylinder::new(Vector3::new(0.0, 0.0, 0.0), 1.0f64, 2.0);
+        let ray = Ray::new(Vector3::new(-3.0, 0.0, 0.5), Vector3::new(1.0, 0.0, 0.0));
+        let r = cylinder.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((r.t - 2.0).abs() < 1e-12);
+        assert!((r.normal.unwrap() - Vector3::new(-1.0, 0.0, 0.0)).x.abs() < 1e-12);
+        assert!((r.uv.unwrap().y - 0.75).abs() < 1e-12);
+
+        // from inside, the far wall is hit
+        let inside = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let r = cylinder.hit(&inside, 0.0, f64::INFINITY).unwrap();
+        assert!((r.t - 1.0).abs() < 1e-12);
+        assert_eq!(r.back_facing, Some(true));
+
+        // the cylinder has no caps
+        let axis = Ray::new(Vector3::new(0.0, 0.0, -3.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(cylinder.hit(&axis, 0.0, f64::INFINITY).is_none());
+    }
+}
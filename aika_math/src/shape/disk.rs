@@ -0,0 +1,124 @@
+use cgmath::{BaseFloat, InnerSpace, Vector2, Vector3};
+use crate::*;
+use crate::utils::{get_2pi, get_pi, get_z};
+
+/// A disk in the plane `z = center.z`, facing +z
+#[derive(Debug)]
+pub struct Disk<F> {
+    pub center: Vector3<F>,
+    pub radius: F,
+}
+
+impl<F> Disk<F> where F: BaseFloat {
+    pub fn new(center: Vector3<F>, radius: F) -> Self {
+        Self {
+            center,
+            radius
+        }
+    }
+
+    pub fn get_normal(&self) -> Vector3<F> {
+        get_z()
+    }
+
+    /// u follows phi around the center, v is 0 on the rim and 1 at the center
+    pub fn get_uv(&self, point: Vector3<F>) -> Vector2<F> {
+        let dx = point.x - self.center.x;
+        let dy = point.y - self.center.y;
+        let mut phi = dy.atan2(dx);
+        if phi < F::zero() {
+            phi += get_2pi::<F>();
+        }
+        let r = (dx * dx + dy * dy).sqrt();
+        Vector2::new(phi / get_2pi::<F>(), (self.radius - r) / self.radius)
+    }
+}
+
+impl<F> Bounded<AABB<F>> for Disk<F> where F: BaseFloat {
+    fn get_bv(&self) -> AABB<F> {
+        AABB {
+            center: self.center,
+            extent: Vector3::new(self.radius, self.radius, F::zero())
+        }
+    }
+}
+
+impl<F> Hittable<F, ()> for Disk<F> where F: BaseFloat {
+    fn hit(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, ()>> {
+        if ray.direction.z == F::zero() {
+            return None;
+        }
+        let t = (self.center.z - ray.origin.z) / ray.direction.z;
+        if t < min || t > max {
+            return None;
+        }
+
+        let p = ray.origin + ray.direction * t;
+        let dx = p.x - self.center.x;
+        let dy = p.y - self.center.y;
+        if dx * dx + dy * dy > self.radius * self.radius {
+            return None;
+        }
+
+        // the hit point is snapped onto the plane, so the error along z is zero
+        let point = Vector3::new(p.x, p.y, self.center.z);
+        let normal = self.get_normal();
+        Some(HitRecord {
+            t,
+            normal: Some(normal),
+            uv: Some(self.get_uv(point)),
+            back_facing: Some(normal.dot(ray.direction) > F::zero()),
+            point: Some(point),
+            point_error: Some(Vector3::new(F::zero(), F::zero(), F::zero())),
+            hit_object: None,
+        })
+    }
+}
+
+impl<F> HaveCenter<F> for Disk<F> where F: BaseFloat {
+    fn get_center(&self) -> Vector3<F> {
+        self.center
+    }
+}
+
+impl<F> HaveArea<F> for Disk<F> where F: BaseFloat {
+    fn area(&self) -> F {
+        get_pi::<F>() * self.radius * self.radius
+    }
+}
+
+impl<F> SampleShape<F> for Disk<F> where F: BaseFloat {
+    fn sample_shape(&self, r1: F, r2: F) -> Option<SampleShapeResult<F>> {
+        let r = self.radius * r1.sqrt();
+        let (sin_phi, cos_phi) = (get_2pi::<F>() * r2).sin_cos();
+        let position = self.center + Vector3::new(r * cos_phi, r * sin_phi, F::zero());
+        Some(SampleShapeResult {
+            position,
+            pdf: F::one() / self.area(),
+            normal: self.get_normal()
+        })
+    }
+}
+
+impl<F> PrimitiveTrait<F> for Disk<F> where F: BaseFloat {}
+
+#[cfg(test)]
+mod test {
+    use cgmath::Vector3;
+    use crate::{Disk, Hittable, Ray};
+
+    #[test]
+    fn test_disk_hit() {
+        let disk = Disk::new(Vector3::new(0.0, 0.0, 1.0), 1.0f64);
+        let ray = Ray::new(Vector3::new(0.5, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let r = disk.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((r.t - 1.0).abs() < 1e-12);
+        assert_eq!(r.back_facing, Some(true));
+        let uv = r.uv.unwrap();
+        assert!(uv.x.abs() < 1e-12);
+        assert!((uv.y - 0.5).abs() < 1e-12);
+
+        let miss = Ray::new(Vector3::new(1.5, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(disk.hit(&miss, 0.0, f64::INFINITY).is_none());
+    }
+}
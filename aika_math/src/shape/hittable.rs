@@ -8,13 +8,25 @@ pub struct HitRecord<T, H> {
     pub normal: Option<Vector3<T>>,
     pub uv: Option<Vector2<T>>,
     pub back_facing: Option<bool>,
+    /// The hit point computed by the shape, more accurate than `origin + t * direction`
+    pub point: Option<Vector3<T>>,
+    /// Conservative bound of the absolute error of `point` on each axis
+    pub point_error: Option<Vector3<T>>,
 
     pub hit_object: Option<H>
 }
 
 impl<T, H> HitRecord<T, H> where T: BaseFloat {
     pub fn get_hit_point(&self, ray: &Ray<T>) -> Vector3<T> {
-        ray.origin + ray.direction * self.t
+        match self.point {
+            Some(p) => p,
+            None => ray.origin + ray.direction * self.t
+        }
+    }
+
+    /// Zero if the shape does not report an error bound
+    pub fn get_point_error(&self) -> Vector3<T> {
+        self.point_error.unwrap_or(Vector3::new(T::zero(), T::zero(), T::zero()))
     }
 
     pub fn new() -> Self {
@@ -24,6 +36,8 @@ impl<T, H> HitRecord<T, H> where T: BaseFloat {
             back_facing: None,
             hit_object: None,
             uv: None,
+            point: None,
+            point_error: None,
         }
     }
 
@@ -32,6 +46,8 @@ impl<T, H> HitRecord<T, H> where T: BaseFloat {
         target.normal = self.normal.clone();
         target.back_facing = self.back_facing.clone();
         target.uv = self.uv.clone();
+        target.point = self.point;
+        target.point_error = self.point_error;
     }
 }
 
@@ -44,4 +60,22 @@ pub trait Hittable<F, H> {
     fn is_hit(&self, ray: &Ray<F>, min: F, max: F) -> bool {
         self.hit(ray, min, max).is_some()
     }
+
+    /// Any-hit query, returns true if there is any hit in [min, max]
+    /// Implementations can stop at the first hit instead of searching for the closest one
+    fn occluded(&self, ray: &Ray<F>, min: F, max: F) -> bool {
+        self.occluded_filtered(ray, min, max, &|_| true)
+    }
+
+    /// Same as `occluded`, but only the hits accepted by `filter` count,
+    /// e.g. to skip volumes or alpha-masked surfaces.
+    /// The default only filters the closest hit, so it is only correct for a single primitive.
+    /// Structures holding several objects, such as the spatial structures, must override it,
+    /// otherwise an occluder behind a rejected hit is missed
+    fn occluded_filtered(&self, ray: &Ray<F>, min: F, max: F, filter: &dyn Fn(&HitRecord<F, H>) -> bool) -> bool {
+        match self.hit(ray, min, max) {
+            Some(r) => filter(&r),
+            None => false
+        }
+    }
 }
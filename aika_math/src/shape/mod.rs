@@ -2,10 +2,12 @@ pub use traits::*;
 pub use aabb::AABB;
 pub use axis::Axis;
 pub use hittable::{HitRecord, Hittable};
-pub use ray::Ray;
+pub use ray::{Ray, offset_ray_origin, spawn_ray};
 pub use sphere::Sphere;
 pub use triangle::Triangle;
 pub use rectangle::Rectangle;
+pub use disk::Disk;
+pub use cylinder::Cylinder;
 
 mod traits;
 mod aabb;
@@ -15,3 +17,5 @@ mod ray;
 mod sphere;
 mod triangle;
 mod rectangle;
+mod disk;
+mod cylinder;
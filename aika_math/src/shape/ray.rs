@@ -1,4 +1,5 @@
 use cgmath::{BaseFloat, InnerSpace, Vector3};
+use crate::utils::{abs_vector3, next_float_down, next_float_up};
 
 #[derive(Debug, Clone)]
 pub struct Ray<T> {
@@ -14,3 +15,31 @@ impl<F> Ray<F> where F: BaseFloat {
         }
     }
 }
+
+/// Move `p` along the geometric normal `n` to the side `w` points to,
+/// far enough that the error box of `p` is not intersected again
+/// See https://pbr-book.org/4ed/Shapes/Managing_Rounding_Error
+pub fn offset_ray_origin<F>(p: Vector3<F>, p_error: Vector3<F>, n: Vector3<F>, w: Vector3<F>) -> Vector3<F> where F: BaseFloat + 'static {
+    let d = abs_vector3(n).dot(p_error);
+    let mut offset = n * d;
+    if w.dot(n) < F::zero() {
+        offset = -offset;
+    }
+
+    let mut po = p + offset;
+    // round away from p
+    for i in 0..3 {
+        if offset[i] > F::zero() {
+            po[i] = next_float_up(po[i]);
+        } else if offset[i] < F::zero() {
+            po[i] = next_float_down(po[i]);
+        }
+    }
+    po
+}
+
+/// Spawn a ray leaving a surface point, which will not hit the same surface again
+/// `p_error` is the error bound of `p`, `n` is the geometric normal
+pub fn spawn_ray<F>(p: Vector3<F>, p_error: Vector3<F>, n: Vector3<F>, w: Vector3<F>) -> Ray<F> where F: BaseFloat + 'static {
+    Ray::new(offset_ray_origin(p, p_error, n, w), w)
+}
@@ -1,6 +1,6 @@
-use cgmath::{BaseFloat, Matrix4, Quaternion, Rotation, Vector3};
-use crate::{AABB, Bounded, HaveArea, HaveCenter, SampleShape, SampleShapeResult};
-use crate::utils::get_z;
+use cgmath::{BaseFloat, InnerSpace, Matrix4, Quaternion, Rotation, Vector2, Vector3};
+use crate::{AABB, Bounded, HaveArea, HaveCenter, HitRecord, Hittable, Ray, SampleShape, SampleShapeResult};
+use crate::utils::{abs_vector3, gamma, get_z};
 
 pub struct Rectangle<F> {
     pub position: Vector3<F>,
@@ -41,6 +41,49 @@ impl<F> Rectangle<F> where F: BaseFloat {
         let p4 = self.rotation.rotate_vector(p4) + self.position;
         [p1, p2, p3, p4]
     }
+
+    /// The point in the local frame of the rectangle, where the rectangle spans the xy plane
+    pub fn to_local(&self, point: Vector3<F>) -> Vector3<F> {
+        self.rotation.invert().rotate_vector(point - self.position)
+    }
+
+    /// (0, 0) at the (-x, -y) corner, (1, 1) at the (x, y) corner
+    pub fn get_uv(&self, point: Vector3<F>) -> Vector2<F> {
+        let local = self.to_local(point);
+        let half = F::from(0.5).unwrap();
+        Vector2::new(local.x / self.x_width + half, local.y / self.y_width + half)
+    }
+}
+
+impl<F> Hittable<F, ()> for Rectangle<F> where F: BaseFloat {
+    fn hit(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, ()>> {
+        let normal = self.get_normal();
+        let denom = normal.dot(ray.direction);
+        if denom == F::zero() {
+            return None;
+        }
+        let t = normal.dot(self.position - ray.origin) / denom;
+        if t < min || t > max {
+            return None;
+        }
+
+        let point = ray.origin + ray.direction * t;
+        let local = self.to_local(point);
+        let half = F::from(0.5).unwrap();
+        if local.x.abs() > self.x_width * half || local.y.abs() > self.y_width * half {
+            return None;
+        }
+
+        Some(HitRecord {
+            t,
+            normal: Some(normal),
+            uv: Some(self.get_uv(point)),
+            back_facing: Some(denom > F::zero()),
+            point: Some(point),
+            point_error: Some(abs_vector3(point) * gamma::<F>(7)),
+            hit_object: None,
+        })
+    }
 }
 
 impl<F> Bounded<AABB<F>> for Rectangle<F> where F: BaseFloat {
@@ -2,7 +2,7 @@ use std::f64::consts::PI;
 use cgmath::{BaseFloat, InnerSpace, Matrix4, SquareMatrix, Vector2, Vector3};
 use num_traits::Float;
 use crate::*;
-use crate::utils::{compose_frame, get_2pi, get_4pi, get_spherical_direction, length_square_vector3, length_vector3, safe_sqrt, sqr};
+use crate::utils::{abs_vector3, compose_frame, gamma, get_2pi, get_4pi, get_spherical_direction, length_square_vector3, length_vector3, safe_sqrt, sqr};
 
 #[derive(Debug)]
 pub struct Sphere<T> {
@@ -17,6 +17,18 @@ impl<T> Sphere<T> where T: BaseFloat {
             radius,
         }
     }
+
+    /// u follows phi around the z axis, v follows theta from +z to -z
+    pub fn get_uv(&self, point: Vector3<T>) -> Vector2<T> {
+        let local = point - self.center;
+        let mut phi = local.y.atan2(local.x);
+        if phi < T::zero() {
+            phi += get_2pi::<T>();
+        }
+        let cos_theta = (local.z / self.radius).max(-T::one()).min(T::one());
+        let theta = cos_theta.acos();
+        Vector2::new(phi / get_2pi::<T>(), theta / T::from(PI).unwrap())
+    }
 }
 
 impl<F> Bounded<AABB<F>> for Sphere<F> where F: BaseFloat {
@@ -63,13 +75,19 @@ impl<F> Hittable<F, ()> for Sphere<F> where F: BaseFloat {
                 }
                 let hit_point = ray.origin + ray.direction * t;
                 let normal = (hit_point - self.center).normalize();
+                // refine the hit point onto the surface
+                let local = normal * self.radius;
+                let point = self.center + local;
+                let point_error = abs_vector3(local) * gamma::<F>(5) + abs_vector3(point) * gamma::<F>(1);
 
                 return Some(HitRecord {
                     t,
                     normal: Some(normal),
-                    back_facing: Some(normal.dot(ray.direction) < F::zero()),
+                    back_facing: Some(normal.dot(ray.direction) > F::zero()),
                     hit_object: None,
-                    uv: None,
+                    uv: Some(self.get_uv(point)),
+                    point: Some(point),
+                    point_error: Some(point_error),
                 })
             }
 
@@ -143,6 +143,11 @@ impl<F> Hittable<F, TriangleIntersectResult<F>> for Triangle<F> where F: BaseFlo
             return None;
         }
 
+        // interpolating the vertices is more accurate than evaluating the ray
+        let point = self.a * b0 + self.b * b1 + self.c * b2;
+        let abs_sum = abs_vector3(self.a * b0) + abs_vector3(self.b * b1) + abs_vector3(self.c * b2);
+        let point_error = abs_sum * gamma::<F>(7);
+
         Some(HitRecord {
             t,
             normal: Some(n),
@@ -151,6 +156,8 @@ impl<F> Hittable<F, TriangleIntersectResult<F>> for Triangle<F> where F: BaseFlo
                 barycentric_coordinates: Vector3::new(b0, b1, b2),
             }),
             uv: None,
+            point: Some(point),
+            point_error: Some(point_error),
         })
     }
 }
@@ -282,4 +289,31 @@ mod test {
         assert!(hit.is_some());
         // assert_eq!(hit.unwrap().t, 1.0);
     }
+
+    #[test]
+    fn test_triangle_spawn_ray() {
+        // far from the origin, where a fixed epsilon is too small
+        let triangle = Triangle {
+            a: Vector3 { x: 10000.0_f32, y: 10000.0, z: 10000.0 },
+            b: Vector3 { x: 10001.7, y: 10000.3, z: 10000.1 },
+            c: Vector3 { x: 10000.2, y: 10001.9, z: 10000.7 },
+        };
+        let n = triangle.get_normal();
+        for i in 0..100 {
+            let target = triangle.a * 0.3 + triangle.b * (0.006 * i as f32) + triangle.c * (0.7 - 0.006 * i as f32);
+            let origin = target + Vector3::new(0.3, -0.2, 1.0 + 0.01 * i as f32);
+            let ray = Ray::new(origin, target - origin);
+            let hit = triangle.hit(&ray, 0.0, f32::infinity()).unwrap();
+
+            let p = hit.get_hit_point(&ray);
+            let error = hit.get_point_error();
+            assert!(error.x > 0.0 && error.y > 0.0 && error.z > 0.0);
+
+            // leaving on either side must not hit the triangle again
+            for w in [n, -n, (n + Vector3::new(0.5, 0.1, 0.0)).normalize(), (-n + Vector3::new(0.0, 0.4, 0.2)).normalize()] {
+                let spawned = spawn_ray(p, error, n, w);
+                assert!(triangle.hit(&spawned, 0.0, f32::infinity()).is_none());
+            }
+        }
+    }
 }
@@ -17,8 +17,7 @@ pub fn get_4pi<F: BaseFloat>() -> F {
 }
 
 pub fn length_square_vector3<F: BaseFloat>(v: Vector3<F>) -> F {
-    let l = v.x * v.x + v.y * v.y + v.z * v.z;
-    l
+    v.x * v.x + v.y * v.y + v.z * v.z
 }
 
 pub fn length_vector3<F: BaseFloat>(v: Vector3<F>) -> F {
@@ -153,14 +152,6 @@ pub fn refract<F: BaseFloat>(v: Vector3<F>, axis: Vector3<F>, ior_normal: F, ior
     Some(ret)
 }
 
-pub fn reflect_bias<F: BaseFloat>(wi: Vector3<F>) -> Vector3<F> {
-    if wi.z > F::zero() {
-        Vector3::new(F::zero(), F::zero(), F::from(1e-6).unwrap())
-    } else {
-        Vector3::new(F::zero(), F::zero(), F::from(-1e-6).unwrap())
-    }
-}
-
 pub fn new_vector3<F, G1, G2, G3>(a: G1, b: G2, c: G3) -> Vector3<F>
 where
     F: BaseFloat,
@@ -218,6 +209,21 @@ pub fn get_generalized_half<F: BaseFloat>(wi: Vector3<F>, wo: Vector3<F>, eta: F
     }
 }
 
+/// The angle between two normalized vectors, accurate also for nearly parallel vectors
+pub fn angle_between<F: BaseFloat>(v1: Vector3<F>, v2: Vector3<F>) -> F {
+    let two = F::from(2).unwrap();
+    if v1.dot(v2) < F::zero() {
+        F::from(PI).unwrap() - two * ((v1 + v2).magnitude() / two).min(F::one()).asin()
+    } else {
+        two * ((v2 - v1).magnitude() / two).min(F::one()).asin()
+    }
+}
+
+/// The part of `v` perpendicular to the normalized `w`
+pub fn gram_schmidt<F: BaseFloat>(v: Vector3<F>, w: Vector3<F>) -> Vector3<F> {
+    v - w * v.dot(w)
+}
+
 pub fn is_parallel<F: BaseFloat>(a: Vector3<F>, b: Vector3<F>) -> bool {
     let cross = a.cross(b);
     let len2 = length_square_vector3(cross);
@@ -248,6 +254,23 @@ pub fn compose_frame<F: BaseFloat>(z: Vector3<F>) -> (Matrix3<F>, Matrix3<F>) {
     (world_to_local, local_to_world)
 }
 
+/// Any unit vector orthogonal to the unit vector `n`
+pub fn get_orthogonal<F: BaseFloat>(n: Vector3<F>) -> Vector3<F> {
+    let axis = if n.x.abs() < F::from(0.9).unwrap() { Vector3::unit_x() } else { Vector3::unit_y() };
+    (axis - n * n.dot(axis)).normalize()
+}
+
+/// The angle at vertex `corner` of the triangle `p`, zero if an adjacent edge is degenerate
+pub fn get_corner_angle<F: BaseFloat>(p: [Vector3<F>; 3], corner: usize) -> F {
+    let a = p[(corner + 1) % 3] - p[corner];
+    let b = p[(corner + 2) % 3] - p[corner];
+    let denom = (a.magnitude2() * b.magnitude2()).sqrt();
+    if denom == F::zero() {
+        return F::zero();
+    }
+    (a.dot(b) / denom).max(-F::one()).min(F::one()).acos()
+}
+
 pub fn get_spherical_direction<F: BaseFloat>(sin_theta: F, cos_theta: F, phi: F) -> Vector3<F> {
     let (sin_phi, cos_phi) = phi.sin_cos();
     let x = sin_theta * cos_phi;
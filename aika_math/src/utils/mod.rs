@@ -12,3 +12,5 @@ mod float_utils;
 mod test_float_utils;
 #[cfg(test)]
 mod test_math;
+#[cfg(test)]
+mod test_sample;
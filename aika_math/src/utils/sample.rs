@@ -1,10 +1,10 @@
 use std::f64::consts::PI;
 use cgmath::{BaseFloat, InnerSpace, Vector2, Vector3};
-use crate::utils::{get_max_value_below_one, next_float_down, safe_sqrt};
+use crate::utils::{angle_between, get_2pi, get_4pi, get_max_value_below_one, get_pi, gram_schmidt, lerp, next_float_down, safe_sqrt};
 
 pub fn sample_uniform_disk_polar<F>(u1: F, u2: F) -> Vector2<F> where F: BaseFloat {
     let pi = F::from(PI).unwrap();
-    let r = u1;
+    let r = u1.sqrt();
     let theta = F::from(2).unwrap() * pi * u2;
     Vector2::new(r * theta.cos(), r * theta.sin())
 }
@@ -23,6 +23,229 @@ pub fn sample_uniform_hemisphere<F>(r1: F, r2: F) -> Vector3<F> where F: BaseFlo
     dir
 }
 
+pub fn uniform_hemisphere_pdf<F: BaseFloat>() -> F {
+    F::one() / get_2pi::<F>()
+}
+
+/// Malley's method, a uniform disk sample lifted to the hemisphere
+pub fn sample_cosine_hemisphere<F: BaseFloat>(u1: F, u2: F) -> Vector3<F> {
+    let d = sample_uniform_disk_polar(u1, u2);
+    let z = safe_sqrt(F::one() - d.x * d.x - d.y * d.y).max(F::from(1e-6).unwrap());
+    Vector3::new(d.x, d.y, z)
+}
+
+pub fn cosine_hemisphere_pdf<F: BaseFloat>(cos_theta: F) -> F {
+    cos_theta.max(F::zero()) / get_pi::<F>()
+}
+
+pub fn sample_uniform_sphere<F: BaseFloat>(u1: F, u2: F) -> Vector3<F> {
+    let z = F::one() - F::from(2).unwrap() * u1;
+    let r = safe_sqrt(F::one() - z * z);
+    let (sin_phi, cos_phi) = (get_2pi::<F>() * u2).sin_cos();
+    Vector3::new(r * cos_phi, r * sin_phi, z)
+}
+
+pub fn uniform_sphere_pdf<F: BaseFloat>() -> F {
+    F::one() / get_4pi::<F>()
+}
+
+/// Directions within `acos(cos_theta_max)` of the z axis
+pub fn sample_uniform_cone<F: BaseFloat>(u1: F, u2: F, cos_theta_max: F) -> Vector3<F> {
+    let cos_theta = (F::one() - u1) + u1 * cos_theta_max;
+    let sin_theta = safe_sqrt(F::one() - cos_theta * cos_theta);
+    let (sin_phi, cos_phi) = (get_2pi::<F>() * u2).sin_cos();
+    Vector3::new(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta)
+}
+
+pub fn uniform_cone_pdf<F: BaseFloat>(cos_theta_max: F) -> F {
+    F::one() / (get_2pi::<F>() * (F::one() - cos_theta_max))
+}
+
+/// The solid angle of the spherical triangle of the normalized vectors a, b and c
+pub fn get_spherical_triangle_area<F: BaseFloat>(a: Vector3<F>, b: Vector3<F>, c: Vector3<F>) -> F {
+    let two = F::from(2).unwrap();
+    (two * a.dot(b.cross(c)).atan2(F::one() + a.dot(b) + a.dot(c) + b.dot(c))).abs()
+}
+
+pub struct SampleSphericalTriangleReturnValue<F: BaseFloat> {
+    pub direction: Vector3<F>,
+    /// The barycentric coordinates of the point the direction hits on the triangle
+    pub barycentric: Vector3<F>,
+    pub pdf: F,
+}
+
+/// Sample a direction from `p` uniformly over the solid angle of the triangle `v`.
+/// See Arvo 1995, "Stratified Sampling of Spherical Triangles"
+pub fn sample_spherical_triangle<F: BaseFloat>(v: [Vector3<F>; 3], p: Vector3<F>, u1: F, u2: F) -> Option<SampleSphericalTriangleReturnValue<F>> {
+    let a = (v[0] - p).normalize();
+    let b = (v[1] - p).normalize();
+    let c = (v[2] - p).normalize();
+
+    let n_ab = a.cross(b);
+    let n_bc = b.cross(c);
+    let n_ca = c.cross(a);
+    if n_ab.magnitude2() == F::zero() || n_bc.magnitude2() == F::zero() || n_ca.magnitude2() == F::zero() {
+        return None;
+    }
+    let n_ab = n_ab.normalize();
+    let n_bc = n_bc.normalize();
+    let n_ca = n_ca.normalize();
+
+    // the area is the sum of the angles minus pi
+    let alpha = angle_between(n_ab, -n_ca);
+    let beta = angle_between(n_bc, -n_ab);
+    let gamma = angle_between(n_ca, -n_bc);
+    let pi = get_pi::<F>();
+    let area = alpha + beta + gamma - pi;
+    if area <= F::zero() {
+        return None;
+    }
+
+    // the sub triangle with the area fraction u1 ends at c' on the arc from a to c
+    let area_pi = lerp(u1, pi, alpha + beta + gamma);
+    let (sin_alpha, cos_alpha) = alpha.sin_cos();
+    let (sin_area_pi, cos_area_pi) = area_pi.sin_cos();
+    let sin_phi = sin_area_pi * cos_alpha - cos_area_pi * sin_alpha;
+    let cos_phi = cos_area_pi * cos_alpha + sin_area_pi * sin_alpha;
+    let k1 = cos_phi + cos_alpha;
+    let k2 = sin_phi - sin_alpha * a.dot(b);
+    let cos_b = ((k2 + (k2 * cos_phi - k1 * sin_phi) * cos_alpha) / ((k2 * sin_phi + k1 * cos_phi) * sin_alpha))
+        .max(-F::one()).min(F::one());
+    let sin_b = safe_sqrt(F::one() - cos_b * cos_b);
+    let c_prime = a * cos_b + gram_schmidt(c, a).normalize() * sin_b;
+
+    // uniform in the cosine along the arc from b to c'
+    let cos_theta = F::one() - u2 * (F::one() - c_prime.dot(b));
+    let sin_theta = safe_sqrt(F::one() - cos_theta * cos_theta);
+    let direction = b * cos_theta + gram_schmidt(c_prime, b).normalize() * sin_theta;
+
+    // intersect the triangle for the barycentric coordinates
+    let e1 = v[1] - v[0];
+    let e2 = v[2] - v[0];
+    let s1 = direction.cross(e2);
+    let divisor = s1.dot(e1);
+    let third = F::one() / F::from(3).unwrap();
+    let barycentric = if divisor == F::zero() {
+        Vector3::new(third, third, third)
+    } else {
+        let s = p - v[0];
+        let mut b1 = (s.dot(s1) / divisor).max(F::zero()).min(F::one());
+        let mut b2 = (direction.dot(s.cross(e1)) / divisor).max(F::zero()).min(F::one());
+        let sum = b1 + b2;
+        if sum > F::one() {
+            b1 /= sum;
+            b2 /= sum;
+        }
+        Vector3::new(F::one() - b1 - b2, b1, b2)
+    };
+
+    Some(SampleSphericalTriangleReturnValue {
+        direction,
+        barycentric,
+        pdf: F::one() / area,
+    })
+}
+
+pub fn spherical_triangle_pdf<F: BaseFloat>(v: [Vector3<F>; 3], p: Vector3<F>) -> F {
+    let area = get_spherical_triangle_area((v[0] - p).normalize(), (v[1] - p).normalize(), (v[2] - p).normalize());
+    if area <= F::zero() {
+        F::zero()
+    } else {
+        F::one() / area
+    }
+}
+
+pub struct SampleSphericalRectangleReturnValue<F: BaseFloat> {
+    pub point: Vector3<F>,
+    pub pdf: F,
+}
+
+/// Sample a point of the rectangle `s + u * ex + v * ey`, with perpendicular edges, uniformly over its solid angle from `p`.
+/// The pdf is zero if the rectangle is too small to sample by the solid angle, and the point is then sampled by area.
+/// See Ureña et al. 2013, "An Area-Preserving Parametrization for Spherical Rectangles"
+pub fn sample_spherical_rectangle<F: BaseFloat + 'static>(p: Vector3<F>, s: Vector3<F>, ex: Vector3<F>, ey: Vector3<F>, u1: F, u2: F) -> SampleSphericalRectangleReturnValue<F> {
+    let exl = ex.magnitude();
+    let eyl = ey.magnitude();
+    let x_axis = ex / exl;
+    let y_axis = ey / eyl;
+    let mut z_axis = x_axis.cross(y_axis);
+
+    // the local frame with the rectangle below the reference point
+    let d = s - p;
+    let mut z0 = d.dot(z_axis);
+    if z0 > F::zero() {
+        z_axis = -z_axis;
+        z0 = -z0;
+    }
+    let x0 = d.dot(x_axis);
+    let y0 = d.dot(y_axis);
+    let x1 = x0 + exl;
+    let y1 = y0 + eyl;
+
+    let v00 = Vector3::new(x0, y0, z0);
+    let v01 = Vector3::new(x0, y1, z0);
+    let v10 = Vector3::new(x1, y0, z0);
+    let v11 = Vector3::new(x1, y1, z0);
+    let n0 = v00.cross(v10).normalize();
+    let n1 = v10.cross(v11).normalize();
+    let n2 = v11.cross(v01).normalize();
+    let n3 = v01.cross(v00).normalize();
+    let g0 = angle_between(-n0, n1);
+    let g1 = angle_between(-n1, n2);
+    let g2 = angle_between(-n2, n3);
+    let g3 = angle_between(-n3, n0);
+
+    let solid_angle = g0 + g1 + g2 + g3 - get_2pi::<F>();
+    if solid_angle.is_nan() || solid_angle < F::from(1e-3).unwrap() {
+        return SampleSphericalRectangleReturnValue {
+            point: s + ex * u1 + ey * u2,
+            pdf: F::zero(),
+        };
+    }
+
+    // the x coordinate from the area fraction u1
+    let b0 = n0.z;
+    let b1 = n2.z;
+    let au = u1 * solid_angle - g2 - g3;
+    let fu = (au.cos() * b0 - b1) / au.sin();
+    let one_minus_epsilon = get_max_value_below_one::<F>();
+    let cu = F::one() / (fu * fu + b0 * b0).sqrt();
+    let cu = if fu < F::zero() { -cu } else { cu };
+    let cu = cu.max(-one_minus_epsilon).min(one_minus_epsilon);
+    let xu = (-(cu * z0) / safe_sqrt(F::one() - cu * cu)).max(x0).min(x1);
+
+    // the y coordinate, uniform in the projected height
+    let dd = (xu * xu + z0 * z0).sqrt();
+    let h0 = y0 / (dd * dd + y0 * y0).sqrt();
+    let h1 = y1 / (dd * dd + y1 * y1).sqrt();
+    let hv = h0 + u2 * (h1 - h0);
+    let hv2 = hv * hv;
+    let yv = if hv2 < F::one() - F::from(1e-6).unwrap() {
+        hv * dd / (F::one() - hv2).sqrt()
+    } else {
+        y1
+    };
+
+    SampleSphericalRectangleReturnValue {
+        point: p + x_axis * xu + y_axis * yv + z_axis * z0,
+        pdf: F::one() / solid_angle,
+    }
+}
+
+/// The density of `sample_spherical_rectangle` over directions, zero where it falls back to sampling by area
+pub fn spherical_rectangle_pdf<F: BaseFloat>(p: Vector3<F>, s: Vector3<F>, ex: Vector3<F>, ey: Vector3<F>) -> F {
+    let v00 = (s - p).normalize();
+    let v10 = (s + ex - p).normalize();
+    let v11 = (s + ex + ey - p).normalize();
+    let v01 = (s + ey - p).normalize();
+    let solid_angle = get_spherical_triangle_area(v00, v10, v11) + get_spherical_triangle_area(v00, v11, v01);
+    if solid_angle.is_nan() || solid_angle < F::from(1e-3).unwrap() {
+        F::zero()
+    } else {
+        F::one() / solid_angle
+    }
+}
+
 pub struct SampleDiscreteReturnValue<F: BaseFloat> {
     pub offset: usize,
     pub prob_mass_function: F,
@@ -31,7 +254,7 @@ pub struct SampleDiscreteReturnValue<F: BaseFloat> {
 }
 
 pub fn sample_discrete<F: BaseFloat + 'static>(weights: &[F], u: F) -> Option<SampleDiscreteReturnValue<F>> {
-    if weights.len() == 0 {
+    if weights.is_empty() {
         return None;
     }
 
@@ -59,5 +282,3 @@ pub fn sample_discrete<F: BaseFloat + 'static>(weights: &[F], u: F) -> Option<Sa
         u_remapped: ((up - sum) / weights[offset]).min(get_max_value_below_one()),
     })
 }
-
-// pub fn sample_uniform_sphere<F: BaseFloat>(r1)
\ No newline at end of file
@@ -1,5 +1,5 @@
 use cgmath::Vector3;
-use crate::utils::rotate_from_to;
+use crate::utils::{rotate_from_to, sample_uniform_disk_polar};
 
 #[test]
 fn test_rotate_from_to() {
@@ -9,3 +9,24 @@ fn test_rotate_from_to() {
     let v22 = r * v1;
     assert_eq!(v2, v22);
 }
+
+#[test]
+fn test_sample_uniform_disk_polar() {
+    let n = 200;
+    let mut inner = 0;
+    let mut sum_r2 = 0.0;
+    for i in 0..n {
+        for j in 0..n {
+            let p = sample_uniform_disk_polar((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+            let r2 = p.x * p.x + p.y * p.y;
+            assert!(r2 <= 1.0);
+            if r2 < 0.25 {
+                inner += 1;
+            }
+            sum_r2 += r2;
+        }
+    }
+    // uniform by area, a quarter of the samples lie within half the radius
+    assert!((inner as f64 / (n * n) as f64 - 0.25).abs() < 1e-3);
+    assert!((sum_r2 / (n * n) as f64 - 0.5).abs() < 1e-3);
+}
@@ -0,0 +1,118 @@
+use cgmath::{InnerSpace, Vector3};
+use crate::utils::{cosine_hemisphere_pdf, get_4pi, sample_cosine_hemisphere, sample_spherical_rectangle, sample_spherical_triangle, sample_uniform_cone, sample_uniform_sphere, spherical_rectangle_pdf, spherical_triangle_pdf, uniform_cone_pdf, uniform_sphere_pdf};
+
+const N: usize = 200;
+
+/// The mean of `f` over the samples of a stratified grid
+fn average_grid<T>(sample: impl Fn(f64, f64) -> T, f: impl Fn(T) -> Vector3<f64>) -> Vector3<f64> {
+    let mut sum = Vector3::new(0.0, 0.0, 0.0);
+    for i in 0..N {
+        for j in 0..N {
+            sum += f(sample((i as f64 + 0.5) / N as f64, (j as f64 + 0.5) / N as f64));
+        }
+    }
+    sum / (N * N) as f64
+}
+
+/// Integrate `f` over the sphere with uniform samples
+fn integrate_sphere(f: impl Fn(Vector3<f64>) -> Vector3<f64>) -> Vector3<f64> {
+    average_grid(sample_uniform_sphere, f) / uniform_sphere_pdf::<f64>()
+}
+
+fn is_in_triangle(a: Vector3<f64>, b: Vector3<f64>, c: Vector3<f64>, w: Vector3<f64>) -> bool {
+    let orientation = a.cross(b).dot(c).signum();
+    a.cross(b).dot(w) * orientation > 0.0 && b.cross(c).dot(w) * orientation > 0.0 && c.cross(a).dot(w) * orientation > 0.0
+}
+
+#[test]
+fn test_sample_sphere_and_hemisphere() {
+    let mean = average_grid(sample_uniform_sphere, |w| Vector3::new(w.magnitude(), w.z, w.z * w.z));
+    assert!((mean.x - 1.0).abs() < 1e-9);
+    assert!(mean.y.abs() < 1e-3);
+    assert!((mean.z - 1.0 / 3.0).abs() < 1e-3);
+    assert!((uniform_sphere_pdf::<f64>() * get_4pi::<f64>() - 1.0).abs() < 1e-12);
+
+    // E[cos] = 2 / 3 for cosine weighted directions
+    let mean = average_grid(sample_cosine_hemisphere, |w| Vector3::new(w.magnitude(), w.z, w.x));
+    assert!((mean.x - 1.0).abs() < 1e-6);
+    assert!((mean.y - 2.0 / 3.0).abs() < 1e-3);
+    assert!(mean.z.abs() < 1e-3);
+    let pdf_integral = integrate_sphere(|w| Vector3::new(cosine_hemisphere_pdf(w.z), 0.0, 0.0));
+    assert!((pdf_integral.x - 1.0).abs() < 0.01);
+    assert_eq!(cosine_hemisphere_pdf(-0.5), 0.0);
+}
+
+#[test]
+fn test_sample_uniform_cone() {
+    let cos_theta_max = 0.8;
+    let expected = integrate_sphere(|w| if w.z >= cos_theta_max { Vector3::new(1.0, w.z, w.x) } else { Vector3::new(0.0, 0.0, 0.0) });
+    let expected = expected * uniform_cone_pdf(cos_theta_max);
+    let sampled = average_grid(|u1, u2| sample_uniform_cone(u1, u2, cos_theta_max), |w| Vector3::new(1.0, w.z, w.x));
+    assert!((sampled - expected).magnitude() < 0.01, "sampled: {:?}, expected: {:?}", sampled, expected);
+    let sampled = average_grid(|u1, u2| sample_uniform_cone(u1, u2, cos_theta_max), |w| Vector3::new(w.magnitude(), w.z.min(cos_theta_max), 0.0));
+    assert!((sampled.x - 1.0).abs() < 1e-9 && (sampled.y - cos_theta_max).abs() < 1e-9);
+}
+
+#[test]
+fn test_sample_spherical_triangle() {
+    let v = [Vector3::new(-1.0, -0.5, 1.0), Vector3::new(1.5, 0.0, 0.8), Vector3::new(0.2, 1.0, 1.5)];
+    let p = Vector3::new(0.1, 0.1, 0.2);
+    let (a, b, c) = ((v[0] - p).normalize(), (v[1] - p).normalize(), (v[2] - p).normalize());
+
+    let inside = |w: Vector3<f64>| if is_in_triangle(a, b, c, w) { Vector3::new(1.0, w.x, w.y) } else { Vector3::new(0.0, 0.0, 0.0) };
+    let integral = integrate_sphere(inside);
+    let pdf = spherical_triangle_pdf(v, p);
+    assert!((integral.x * pdf - 1.0).abs() < 0.02, "solid angle: {}, pdf: {}", integral.x, pdf);
+
+    let sampled = average_grid(|u1, u2| sample_spherical_triangle(v, p, u1, u2).unwrap(), |result| {
+        assert!((result.pdf - pdf).abs() < 1e-6 * pdf);
+        // the direction hits the point of the barycentric coordinates
+        let point = v[0] * result.barycentric.x + v[1] * result.barycentric.y + v[2] * result.barycentric.z;
+        assert!(((point - p).normalize() - result.direction).magnitude() < 1e-6);
+        Vector3::new(1.0, result.direction.x, result.direction.y)
+    });
+    let expected = integral * pdf;
+    assert!((sampled.y - expected.y).abs() < 0.01 && (sampled.z - expected.z).abs() < 0.01, "sampled: {:?}, expected: {:?}", sampled, expected);
+
+    assert!(sample_spherical_triangle([v[0], v[0], v[1]], p, 0.5, 0.5).is_none());
+}
+
+#[test]
+fn test_sample_spherical_rectangle() {
+    let s = Vector3::new(-0.5, -1.0, 1.0);
+    let ex = Vector3::new(1.5, 0.0, 0.3);
+    let ey = Vector3::new(0.0, 2.0, 0.0);
+    for p in [Vector3::new(0.2, 0.3, 0.0), Vector3::new(0.0, 0.0, 3.0)] {
+        let n = ex.cross(ey).normalize();
+        // the rectangle as two triangles for the reference
+        let corners = [s - p, s + ex - p, s + ex + ey - p, s + ey - p].map(|c| c.normalize());
+        let inside = |w: Vector3<f64>| {
+            if is_in_triangle(corners[0], corners[1], corners[2], w) || is_in_triangle(corners[0], corners[2], corners[3], w) {
+                Vector3::new(1.0, w.x, w.y)
+            } else {
+                Vector3::new(0.0, 0.0, 0.0)
+            }
+        };
+        let integral = integrate_sphere(inside);
+        let pdf = spherical_rectangle_pdf(p, s, ex, ey);
+        assert!((integral.x * pdf - 1.0).abs() < 0.02, "solid angle: {}, pdf: {}", integral.x, pdf);
+
+        let sampled = average_grid(|u1, u2| sample_spherical_rectangle(p, s, ex, ey, u1, u2), |result| {
+            assert!((result.pdf - pdf).abs() < 1e-6 * pdf);
+            // the point is on the rectangle
+            let d = result.point - s;
+            assert!(d.dot(n).abs() < 1e-9);
+            let (u, v) = (d.dot(ex) / ex.magnitude2(), d.dot(ey) / ey.magnitude2());
+            assert!(u > -1e-9 && u < 1.0 + 1e-9 && v > -1e-9 && v < 1.0 + 1e-9);
+            let w = (result.point - p).normalize();
+            Vector3::new(1.0, w.x, w.y)
+        });
+        let expected = integral * pdf;
+        assert!((sampled.y - expected.y).abs() < 0.01 && (sampled.z - expected.z).abs() < 0.01, "sampled: {:?}, expected: {:?}", sampled, expected);
+    }
+
+    // too small to sample by the solid angle
+    let far = Vector3::new(0.0, 0.0, 1e5);
+    assert_eq!(spherical_rectangle_pdf(far, s, ex, ey), 0.0);
+    assert_eq!(sample_spherical_rectangle(far, s, ex, ey, 0.5, 0.5).pdf, 0.0);
+}
@@ -1,10 +1,14 @@
 use std::cell::RefCell;
 use std::marker::PhantomData;
 use std::rc::Rc;
-use cgmath::BaseFloat;
+use std::thread;
+use cgmath::{BaseFloat, Vector3};
 use aika_math::*;
 use crate::bvh::{BVHNode, BVHSplitHeuristic, BVHTree};
 
+/// Subtrees with fewer objects than this are always built on the current thread
+const PARALLEL_BUILD_THRESHOLD: usize = 4096;
+
 pub struct BVHBuilder<F, B, G, GH> {
     pub max_span: usize,
 
@@ -14,6 +18,126 @@ pub struct BVHBuilder<F, B, G, GH> {
     _geometry_hittable_phantom: PhantomData<GH>,
 }
 
+/// The tree topology, built over an index array
+/// A leaf references the range `start..end` of the reordered index array
+enum BuildNode<B> {
+    Leaf {
+        bounding_volume: B,
+        start: usize,
+        end: usize,
+    },
+    Interior {
+        bounding_volume: B,
+        left: Box<BuildNode<B>>,
+        right: Box<BuildNode<B>>,
+    },
+}
+
+impl<B> BuildNode<B> {
+    fn bounding_volume(&self) -> &B {
+        match self {
+            BuildNode::Leaf { bounding_volume, .. } => bounding_volume,
+            BuildNode::Interior { bounding_volume, .. } => bounding_volume,
+        }
+    }
+}
+
+fn build_leaf<B>(bvs: &[B], indices: &[usize], offset: usize) -> BuildNode<B>
+where
+    B: Mergeable<B, Result = B> + Clone,
+{
+    let mut bv = bvs[indices[0]].clone();
+    for &i in indices.iter().skip(1) {
+        bv = bv.merge(&bvs[i]);
+    }
+    BuildNode::Leaf {
+        bounding_volume: bv,
+        start: offset,
+        end: offset + indices.len(),
+    }
+}
+
+fn build_interior<B>(left: BuildNode<B>, right: BuildNode<B>) -> BuildNode<B>
+where
+    B: Mergeable<B, Result = B>,
+{
+    let bv = left.bounding_volume().merge(right.bounding_volume());
+    BuildNode::Interior {
+        bounding_volume: bv,
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
+
+/// bvs: bounding volumes of all the objects
+/// centers: centers of all the objects
+/// indices: the objects of this subtree, will be reordered
+/// offset: the position of `indices[0]` in the whole index array
+fn build_range<F, B, H>(
+    max_span: usize,
+    bvs: &[B],
+    centers: &[Vector3<F>],
+    indices: &mut [usize],
+    offset: usize,
+    split_heuristic: &mut H,
+) -> BuildNode<B>
+where
+    F: BaseFloat,
+    B: Mergeable<B, Result = B> + Clone,
+    H: BVHSplitHeuristic + Clone,
+{
+    if indices.len() <= max_span {
+        return build_leaf(bvs, indices, offset);
+    }
+
+    let mid = split_heuristic.split(centers, indices);
+    // the right subtree always gets a copy of the heuristic at this point,
+    // so the serial and the parallel build see exactly the same heuristic states
+    let mut right_heuristic = split_heuristic.clone();
+    let (left_indices, right_indices) = indices.split_at_mut(mid);
+
+    let left = build_range(max_span, bvs, centers, left_indices, offset, split_heuristic);
+    let right = build_range(max_span, bvs, centers, right_indices, offset + mid, &mut right_heuristic);
+    build_interior(left, right)
+}
+
+/// Same as `build_range`, but the right subtree is built on a new thread
+/// parallel_depth: how many more levels can spawn a thread
+fn build_range_parallel<F, B, H>(
+    max_span: usize,
+    bvs: &[B],
+    centers: &[Vector3<F>],
+    indices: &mut [usize],
+    offset: usize,
+    split_heuristic: &mut H,
+    parallel_depth: usize,
+) -> BuildNode<B>
+where
+    F: BaseFloat + Sync,
+    B: Mergeable<B, Result = B> + Clone + Send + Sync,
+    H: BVHSplitHeuristic + Clone + Send,
+{
+    if parallel_depth == 0 || indices.len() < PARALLEL_BUILD_THRESHOLD {
+        return build_range(max_span, bvs, centers, indices, offset, split_heuristic);
+    }
+    if indices.len() <= max_span {
+        return build_leaf(bvs, indices, offset);
+    }
+
+    let mid = split_heuristic.split(centers, indices);
+    let mut right_heuristic = split_heuristic.clone();
+    let (left_indices, right_indices) = indices.split_at_mut(mid);
+
+    let (left, right) = thread::scope(|s| {
+        let handle = s.spawn(|| {
+            build_range_parallel(max_span, bvs, centers, right_indices, offset + mid, &mut right_heuristic, parallel_depth - 1)
+        });
+        let left = build_range_parallel(max_span, bvs, centers, left_indices, offset, split_heuristic, parallel_depth - 1);
+        (left, handle.join().unwrap())
+    });
+    build_interior(left, right)
+}
+
 impl<F, G, B, GH> BVHBuilder<F, B, G, GH> {
     pub fn new(max_span: usize) -> Self {
         Self {
@@ -39,47 +163,80 @@ impl<F, G, B, GH> BVHBuilder<F, B, G, GH> {
 impl<B, G, F, GH> BVHBuilder<F, B, G, GH>
 where
     F: BaseFloat,
-    B: Mergeable<B, Result = B> + Hittable<F, ()>,
+    B: Mergeable<B, Result = B> + Hittable<F, ()> + Clone,
     G: Bounded<B> + HaveCenter<F>,
 {
-    fn build_helper<H>(&self, objects: &[Rc<G>], split_heuristic: &mut H) -> BVHNode<F, B, G, GH>
-    where
-        H: BVHSplitHeuristic,
-    {
-        if objects.len() <= self.max_span {
-            let mut bv = objects[0].get_bv();
-            for i in objects.iter().skip(1) {
-                bv = bv.merge(&i.get_bv());
-            }
-            BVHNode {
+    fn convert_node(&self, node: BuildNode<B>, indices: &[usize]) -> BVHNode<F, B, G, GH> {
+        match node {
+            BuildNode::Leaf { bounding_volume, start, end } => BVHNode {
                 left: None,
                 right: None,
-                objects: objects.iter().cloned().collect(),
-                bounding_volume: bv,
+                objects: indices[start..end].iter().map(|&i| self.objects[i].clone()).collect(),
+                bounding_volume,
                 _float_phantom: PhantomData,
-            }
-        } else {
-            let (vec1, vec2) = split_heuristic.split(objects);
-            let left = self.build_helper(&vec1, split_heuristic);
-            let right = self.build_helper(&vec2, split_heuristic);
-            let bv = left.bounding_volume.merge(&right.bounding_volume);
-            BVHNode {
-                left: Some(Rc::new(RefCell::new(left))),
-                right: Some(Rc::new(RefCell::new(right))),
+            },
+            BuildNode::Interior { bounding_volume, left, right } => BVHNode {
+                left: Some(Rc::new(RefCell::new(self.convert_node(*left, indices)))),
+                right: Some(Rc::new(RefCell::new(self.convert_node(*right, indices)))),
                 objects: Vec::new(),
-                bounding_volume: bv,
+                bounding_volume,
                 _float_phantom: PhantomData,
-            }
+            },
         }
     }
 
-    pub fn build<H>(&self, split_heuristic: &mut H) -> BVHTree<F, B, G, GH>
-    where
-        H: BVHSplitHeuristic,
-    {
-        let root = self.build_helper(&self.objects, split_heuristic);
+    /// The geometry is only touched here, the topology is built over plain data
+    /// Returns (bounding volumes, centers, indices)
+    fn prepare(&self) -> (Vec<B>, Vec<Vector3<F>>, Vec<usize>) {
+        let bvs = self.objects.iter().map(|obj| obj.get_bv()).collect::<Vec<_>>();
+        let centers = self.objects.iter().map(|obj| obj.get_center()).collect::<Vec<_>>();
+        let indices = (0..self.objects.len()).collect::<Vec<_>>();
+        (bvs, centers, indices)
+    }
+
+    fn finish(&self, root: BuildNode<B>, indices: &[usize]) -> BVHTree<F, B, G, GH> {
+        let root = self.convert_node(root, indices);
         BVHTree {
             root: Rc::new(RefCell::new(root))
         }
     }
+
+    pub fn build<H>(&self, split_heuristic: &mut H) -> BVHTree<F, B, G, GH>
+    where
+        H: BVHSplitHeuristic + Clone,
+    {
+        let (bvs, centers, mut indices) = self.prepare();
+        let root = build_range(self.max_span, &bvs, &centers, &mut indices, 0, split_heuristic);
+        self.finish(root, &indices)
+    }
+}
+
+impl<B, G, F, GH> BVHBuilder<F, B, G, GH>
+where
+    F: BaseFloat + Sync,
+    B: Mergeable<B, Result = B> + Hittable<F, ()> + Clone + Send + Sync,
+    G: Bounded<B> + HaveCenter<F>,
+{
+    /// Build the subtrees on multiple threads
+    /// The result is identical to `build` for the same input
+    pub fn build_parallel<H>(&self, split_heuristic: &mut H) -> BVHTree<F, B, G, GH>
+    where
+        H: BVHSplitHeuristic + Clone + Send,
+    {
+        let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        // each level doubles the number of running tasks
+        let parallel_depth = threads.next_power_of_two().trailing_zeros() as usize;
+        self.build_parallel_with_depth(split_heuristic, parallel_depth)
+    }
+
+    /// parallel_depth: how many levels of the tree can spawn a thread, 0 builds on the calling thread only.
+    /// Ranges smaller than `PARALLEL_BUILD_THRESHOLD` are always built on one thread
+    pub fn build_parallel_with_depth<H>(&self, split_heuristic: &mut H, parallel_depth: usize) -> BVHTree<F, B, G, GH>
+    where
+        H: BVHSplitHeuristic + Clone + Send,
+    {
+        let (bvs, centers, mut indices) = self.prepare();
+        let root = build_range_parallel(self.max_span, &bvs, &centers, &mut indices, 0, split_heuristic, parallel_depth);
+        self.finish(root, &indices)
+    }
 }
@@ -5,6 +5,7 @@ use std::rc::Rc;
 use cgmath::BaseFloat;
 
 use aika_math::*;
+use crate::stats::TraversalStats;
 
 /// F: Float type
 /// B: Bounding volume type
@@ -43,13 +44,14 @@ where
     }
 }
 
-impl<B, G, F, GH> Hittable<F, Rc<G>> for BVHNode<F, B, G, GH>
+impl<B, G, F, GH> BVHNode<F, B, G, GH>
 where
     F: BaseFloat,
     B: Mergeable<B, Result = B> + Hittable<F, ()>,
     G: Hittable<F, GH>
 {
-    fn hit(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, Rc<G>>> {
+    pub fn hit_counted(&self, ray: &Ray<F>, min: F, max: F, stats: &mut TraversalStats) -> Option<HitRecord<F, Rc<G>>> {
+        stats.nodes_visited += 1;
         let hit_bv_result = self.hit_bv(ray, min, max);
         // println!("{:?}", hit_bv_result);
         if hit_bv_result.is_none() {
@@ -60,7 +62,7 @@ where
         let mut hr: HitRecord<F, Rc<G>> = HitRecord::new();
         hr.t = F::infinity();
         if let Some(n) = &self.right {
-            let result = n.borrow().hit(ray, min, max);
+            let result = n.borrow().hit_counted(ray, min, max, stats);
             if let Some(r) = result {
                 // max = r.t;
                 if r.t < hr.t {
@@ -70,7 +72,7 @@ where
             }
         }
         if let Some(n) = &self.left {
-            let result = n.borrow().hit(ray, min, max);
+            let result = n.borrow().hit_counted(ray, min, max, stats);
             if let Some(r) = result {
                 // max = r.t;
                 if r.t < hr.t {
@@ -80,6 +82,7 @@ where
             }
         }
         for obj in self.objects.iter() {
+            stats.primitives_tested += 1;
             let result = obj.hit(ray, min, max);
             if let Some(r) = result {
                 max = r.t;
@@ -98,4 +101,45 @@ where
             Some(hr)
         }
     }
+}
+
+impl<B, G, F, GH> Hittable<F, Rc<G>> for BVHNode<F, B, G, GH>
+where
+    F: BaseFloat,
+    B: Mergeable<B, Result = B> + Hittable<F, ()>,
+    G: Hittable<F, GH>
+{
+    fn hit(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, Rc<G>>> {
+        self.hit_counted(ray, min, max, &mut TraversalStats::default())
+    }
+
+    fn occluded_filtered(&self, ray: &Ray<F>, min: F, max: F, filter: &dyn Fn(&HitRecord<F, Rc<G>>) -> bool) -> bool {
+        if self.hit_bv(ray, min, max).is_none() {
+            return false;
+        }
+
+        for obj in self.objects.iter() {
+            if let Some(r) = obj.hit(ray, min, max) {
+                let mut hr = HitRecord::new();
+                r.copy_except_hit_object(&mut hr);
+                hr.hit_object = Some(obj.clone());
+                if filter(&hr) {
+                    return true;
+                }
+            }
+        }
+
+        if let Some(n) = &self.left {
+            if n.borrow().occluded_filtered(ray, min, max, filter) {
+                return true;
+            }
+        }
+        if let Some(n) = &self.right {
+            if n.borrow().occluded_filtered(ray, min, max, filter) {
+                return true;
+            }
+        }
+
+        false
+    }
 }
\ No newline at end of file
@@ -0,0 +1,113 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::rc::Rc;
+use anyhow::{bail, Result};
+use cgmath::BaseFloat;
+use aika_math::AABB;
+use crate::bvh::{BVHNode, BVHTree};
+use crate::serialize::*;
+
+const BVH_MAGIC: &[u8; 8] = b"AIKABVH\0";
+const BVH_VERSION: u32 = 1;
+
+const NODE_LEAF: u32 = 0;
+const NODE_INTERIOR: u32 = 1;
+
+/// Binary layout (little endian):
+/// magic, version, then the nodes in pre-order
+/// leaf: tag, aabb (center, extent), object count, object indices
+/// interior: tag, aabb, followed by the left subtree and the right subtree
+impl<F, G, GH> BVHTree<F, AABB<F>, G, GH> where F: BaseFloat {
+    /// Objects are stored as indices into `objects`, which should contain every object in the tree
+    pub fn write_to<W: Write>(&self, objects: &[Rc<G>], writer: &mut W) -> Result<()> {
+        let mut object_index = HashMap::new();
+        for (index, obj) in objects.iter().enumerate() {
+            object_index.insert(Rc::as_ptr(obj), index);
+        }
+
+        write_header(writer, BVH_MAGIC, BVH_VERSION)?;
+        write_node(&self.root.borrow(), &object_index, writer)
+    }
+
+    /// `objects` should be the same array passed to `write_to`
+    pub fn read_from<R: Read>(objects: &[Rc<G>], reader: &mut R) -> Result<Self> {
+        read_header(reader, BVH_MAGIC, BVH_VERSION)?;
+        let root = read_node(objects, reader)?;
+        Ok(BVHTree {
+            root: Rc::new(RefCell::new(root))
+        })
+    }
+}
+
+fn write_node<F, G, GH, W>(node: &BVHNode<F, AABB<F>, G, GH>, object_index: &HashMap<*const G, usize>, writer: &mut W) -> Result<()>
+where
+    F: BaseFloat,
+    W: Write,
+{
+    let tag = if node.is_leaf() { NODE_LEAF } else { NODE_INTERIOR };
+    write_u32(writer, tag)?;
+    write_vector3(writer, node.bounding_volume.center)?;
+    write_vector3(writer, node.bounding_volume.extent)?;
+
+    if node.is_leaf() {
+        write_u64(writer, node.objects.len() as u64)?;
+        for obj in node.objects.iter() {
+            let index = match object_index.get(&Rc::as_ptr(obj)) {
+                Some(i) => *i,
+                None => bail!("object in the tree is not in the object array"),
+            };
+            write_u64(writer, index as u64)?;
+        }
+    } else {
+        write_node(&node.left.as_ref().unwrap().borrow(), object_index, writer)?;
+        write_node(&node.right.as_ref().unwrap().borrow(), object_index, writer)?;
+    }
+
+    Ok(())
+}
+
+fn read_node<F, G, GH, R>(objects: &[Rc<G>], reader: &mut R) -> Result<BVHNode<F, AABB<F>, G, GH>>
+where
+    F: BaseFloat,
+    R: Read,
+{
+    let tag = read_u32(reader)?;
+    let center = read_vector3(reader)?;
+    let extent = read_vector3(reader)?;
+    let bounding_volume = AABB { center, extent };
+
+    match tag {
+        NODE_LEAF => {
+            let count = read_u64(reader)? as usize;
+            let mut node_objects = Vec::with_capacity(count.min(objects.len()));
+            for _ in 0..count {
+                let index = read_u64(reader)? as usize;
+                if index >= objects.len() {
+                    bail!("object index {} out of range", index);
+                }
+                node_objects.push(objects[index].clone());
+            }
+            Ok(BVHNode {
+                left: None,
+                right: None,
+                objects: node_objects,
+                bounding_volume,
+                _float_phantom: PhantomData,
+            })
+        },
+        NODE_INTERIOR => {
+            let left = read_node(objects, reader)?;
+            let right = read_node(objects, reader)?;
+            Ok(BVHNode {
+                left: Some(Rc::new(RefCell::new(left))),
+                right: Some(Rc::new(RefCell::new(right))),
+                objects: Vec::new(),
+                bounding_volume,
+                _float_phantom: PhantomData,
+            })
+        },
+        _ => bail!("invalid node tag {}", tag),
+    }
+}
@@ -1,7 +1,8 @@
-use std::rc::Rc;
-use cgmath::BaseFloat;
-use aika_math::HaveCenter;
+use cgmath::{BaseFloat, Vector3};
 
 pub trait BVHSplitHeuristic {
-    fn split<F: BaseFloat, G: HaveCenter<F>>(&mut self, objects: &[Rc<G>]) -> (Vec<Rc<G>>, Vec<Rc<G>>);
+    /// Reorder `indices` in place, the first `n` indices go to the left child, and the rest go to the right child,
+    /// where `n` is the returned value
+    /// `centers` is indexed by the values in `indices`
+    fn split<F: BaseFloat>(&mut self, centers: &[Vector3<F>], indices: &mut [usize]) -> usize;
 }
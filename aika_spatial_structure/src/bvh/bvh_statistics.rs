@@ -0,0 +1,94 @@
+use std::fmt::{Display, Formatter};
+use cgmath::BaseFloat;
+use aika_math::*;
+use crate::bvh::{BVHNode, BVHTree};
+
+#[derive(Clone, Debug)]
+pub struct BVHStatistics<F> {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub object_count: usize,
+    /// depth of the deepest leaf, the root has depth 0
+    pub max_depth: usize,
+    /// `leaf_size_histogram[i]` is the number of leaves with i objects
+    pub leaf_size_histogram: Vec<usize>,
+    /// expected cost of a random ray, relative to the root bounding volume
+    pub sah_cost: F,
+}
+
+impl<F> Display for BVHStatistics<F> where F: BaseFloat + Display {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "nodes: {}, leaves: {}, objects: {}", self.node_count, self.leaf_count, self.object_count)?;
+        writeln!(f, "max depth: {}", self.max_depth)?;
+        writeln!(f, "SAH cost: {}", self.sah_cost)?;
+        write!(f, "leaf sizes:")?;
+        for (size, count) in self.leaf_size_histogram.iter().enumerate() {
+            if *count > 0 {
+                write!(f, " {}:{}", size, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<B, G, F, GH> BVHTree<F, B, G, GH>
+where
+    F: BaseFloat,
+    B: HaveArea<F>,
+{
+    /// `traversal_cost` and `intersect_cost` are the costs of visiting a node and of testing an object
+    pub fn statistics(&self, traversal_cost: F, intersect_cost: F) -> BVHStatistics<F> {
+        let mut result = BVHStatistics {
+            node_count: 0,
+            leaf_count: 0,
+            object_count: 0,
+            max_depth: 0,
+            leaf_size_histogram: Vec::new(),
+            sah_cost: F::zero(),
+        };
+
+        let root = self.root.borrow();
+        let root_area = root.bounding_volume.area();
+        collect_statistics(&root, 0, root_area, traversal_cost, intersect_cost, &mut result);
+
+        result
+    }
+}
+
+fn collect_statistics<F, B, G, GH>(
+    node: &BVHNode<F, B, G, GH>,
+    depth: usize,
+    root_area: F,
+    traversal_cost: F,
+    intersect_cost: F,
+    result: &mut BVHStatistics<F>
+)
+where
+    F: BaseFloat,
+    B: HaveArea<F>,
+{
+    result.node_count += 1;
+    // probability of a ray hitting the root also hitting this node
+    let p = if root_area > F::zero() {
+        node.bounding_volume.area() / root_area
+    } else {
+        F::one()
+    };
+
+    if node.left.is_none() && node.right.is_none() {
+        let size = node.objects.len();
+        result.leaf_count += 1;
+        result.object_count += size;
+        result.max_depth = result.max_depth.max(depth);
+        if result.leaf_size_histogram.len() <= size {
+            result.leaf_size_histogram.resize(size + 1, 0);
+        }
+        result.leaf_size_histogram[size] += 1;
+        result.sah_cost += p * intersect_cost * F::from(size).unwrap();
+    } else {
+        result.sah_cost += p * traversal_cost;
+        for child in [&node.left, &node.right].into_iter().flatten() {
+            collect_statistics(&child.borrow(), depth + 1, root_area, traversal_cost, intersect_cost, result);
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use std::rc::Rc;
 use cgmath::{InnerSpace, Vector3};
 use num_traits::{Float, Zero};
-use aika_math::{Sphere, Hittable, Ray};
+use aika_math::{Sphere, Hittable, Ray, AABB};
 use crate::bvh::*;
+use crate::stats::{HittableWithStats, TraversalStats};
+use crate::test_utils::{assert_same_as_naive, get_test_spheres};
 
 #[test]
 fn test_bvh_hit1() {
@@ -21,6 +23,18 @@ fn test_bvh_hit1() {
     assert_eq!(result.unwrap().t, 1.0);
 }
 
+#[test]
+fn test_bvh_same_as_naive() {
+    let objects = get_test_spheres();
+    let mut heuristic = DefaultBVHSplitHeuristic::default();
+    let mut builder = BVHBuilder::new(2);
+    for object in objects.iter() {
+        builder.add_object(object.clone());
+    }
+    let tree = builder.build(&mut heuristic);
+    assert_same_as_naive(&objects, &tree);
+}
+
 #[test]
 fn test_bvh_hit2() {
     let ball = Sphere::new(Vector3::zero(), 1.0_f32);
@@ -54,4 +68,111 @@ fn test_bvh_hit3() {
     };
     let result = tree.hit(&ray, 0.0_f32, f32::infinity());
     assert!(result.is_none());
-}
\ No newline at end of file
+}
+
+fn assert_same_tree(a: &BVHNode<f32, AABB<f32>, Sphere<f32>, ()>, b: &BVHNode<f32, AABB<f32>, Sphere<f32>, ()>) {
+    assert_eq!(a.bounding_volume, b.bounding_volume);
+    assert_eq!(a.objects.len(), b.objects.len());
+    for (x, y) in a.objects.iter().zip(b.objects.iter()) {
+        assert!(Rc::ptr_eq(x, y));
+    }
+    assert_eq!(a.is_leaf(), b.is_leaf());
+    if !a.is_leaf() {
+        assert_same_tree(&a.left.as_ref().unwrap().borrow(), &b.left.as_ref().unwrap().borrow());
+        assert_same_tree(&a.right.as_ref().unwrap().borrow(), &b.right.as_ref().unwrap().borrow());
+    }
+}
+
+#[test]
+fn test_bvh_parallel_build() {
+    let mut builder = BVHBuilder::new(4);
+    // a deterministic point set with duplicated centers
+    for i in 0..20000 {
+        let x = ((i * 7919) % 1013) as f32;
+        let y = ((i * 104729) % 877) as f32;
+        let z = (i % 37) as f32;
+        builder.add_object(Rc::new(Sphere::new(Vector3::new(x, y, z), 0.5_f32)));
+    }
+
+    let serial = builder.build(&mut DefaultBVHSplitHeuristic::default());
+    // an explicit depth, so the threads are spawned whatever the number of cores is
+    let parallel = builder.build_parallel_with_depth(&mut DefaultBVHSplitHeuristic::default(), 2);
+    assert_same_tree(&serial.root.borrow(), &parallel.root.borrow());
+    let parallel_default = builder.build_parallel(&mut DefaultBVHSplitHeuristic::default());
+    assert_same_tree(&serial.root.borrow(), &parallel_default.root.borrow());
+
+    let ray = Ray::new(Vector3::new(-10.0, 3.0, 5.0), Vector3::new(1.0, 0.0, 0.0));
+    let r1 = serial.hit(&ray, 0.0, f32::infinity());
+    let r2 = parallel.hit(&ray, 0.0, f32::infinity());
+    assert_eq!(r1.map(|r| r.t), r2.map(|r| r.t));
+}
+
+#[test]
+fn test_bvh_occluded() {
+    let mut heuristic = DefaultBVHSplitHeuristic::default();
+    let mut builder = BVHBuilder::new(1);
+    builder.add_object(Rc::new(Sphere::new(Vector3::zero(), 1.0_f32)));
+    builder.add_object(Rc::new(Sphere::new(Vector3::new(4.0, 0.0, 0.0), 1.0_f32)));
+    let tree = builder.build(&mut heuristic);
+
+    let ray = Ray {
+        origin: Vector3 { x: -3.0, y: 0.0, z: 0.0 },
+        direction: Vector3 { x: 1.0, y: 0.0, z: 0.0 },
+    };
+    assert!(tree.occluded(&ray, 0.0, f32::infinity()));
+    assert!(!tree.occluded(&ray, 0.0, 1.5));
+
+    // skip the first sphere, the second one still blocks the ray
+    let first = tree.hit(&ray, 0.0, f32::infinity()).unwrap().hit_object.unwrap();
+    assert!(tree.occluded_filtered(&ray, 0.0, f32::infinity(), &|r| !Rc::ptr_eq(r.hit_object.as_ref().unwrap(), &first)));
+    assert!(!tree.occluded_filtered(&ray, 0.0, 5.0, &|r| !Rc::ptr_eq(r.hit_object.as_ref().unwrap(), &first)));
+}
+
+#[test]
+fn test_bvh_serialize() {
+    let mut objects = Vec::new();
+    for i in 0..100 {
+        let x = (i % 10) as f32 * 3.0;
+        let y = (i / 10) as f32 * 3.0;
+        objects.push(Rc::new(Sphere::new(Vector3::new(x, y, 0.0), 1.0_f32)));
+    }
+    let mut heuristic = DefaultBVHSplitHeuristic::default();
+    let mut builder = BVHBuilder::new(4);
+    builder.add_objects(&objects);
+    let tree = builder.build(&mut heuristic);
+
+    let mut buffer = Vec::new();
+    tree.write_to(&objects, &mut buffer).unwrap();
+    let loaded: BVHTree<f32, AABB<f32>, Sphere<f32>, ()> = BVHTree::read_from(&objects, &mut buffer.as_slice()).unwrap();
+    assert_same_tree(&tree.root.borrow(), &loaded.root.borrow());
+
+    // truncated data should be rejected
+    assert!(BVHTree::<f32, AABB<f32>, Sphere<f32>, ()>::read_from(&objects, &mut &buffer[..buffer.len() - 1]).is_err());
+}
+
+#[test]
+fn test_bvh_statistics() {
+    let mut objects = Vec::new();
+    for i in 0..64 {
+        objects.push(Rc::new(Sphere::new(Vector3::new(i as f32 * 3.0, 0.0, 0.0), 1.0_f32)));
+    }
+    let mut heuristic = DefaultBVHSplitHeuristic::default();
+    let mut builder = BVHBuilder::new(4);
+    builder.add_objects(&objects);
+    let tree = builder.build(&mut heuristic);
+
+    let stats = tree.statistics(1.0, 1.0);
+    assert_eq!(stats.leaf_count, 16);
+    assert_eq!(stats.node_count, 31);
+    assert_eq!(stats.object_count, 64);
+    assert_eq!(stats.max_depth, 4);
+    assert_eq!(stats.leaf_size_histogram[4], 16);
+    assert!(stats.sah_cost > 1.0);
+
+    let ray = Ray::new(Vector3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+    let mut traversal = TraversalStats::default();
+    assert!(tree.hit_with_stats(&ray, 0.0, f32::infinity(), &mut traversal).is_some());
+    assert_eq!(traversal.ray_count, 1);
+    assert!(traversal.nodes_visited > 0 && traversal.nodes_visited <= 31);
+    assert!(traversal.primitives_tested > 0 && traversal.primitives_tested <= 64);
+}
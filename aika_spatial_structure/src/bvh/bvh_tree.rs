@@ -3,6 +3,7 @@ use std::rc::Rc;
 use cgmath::BaseFloat;
 use aika_math::*;
 use crate::bvh::BVHNode;
+use crate::stats::{HittableWithStats, TraversalStats};
 
 #[derive(Debug)]
 pub struct BVHTree<F, B, G, GH> {
@@ -18,4 +19,20 @@ where
     fn hit(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, Rc<G>>> {
         self.root.borrow().hit(ray, min, max)
     }
+
+    fn occluded_filtered(&self, ray: &Ray<F>, min: F, max: F, filter: &dyn Fn(&HitRecord<F, Rc<G>>) -> bool) -> bool {
+        self.root.borrow().occluded_filtered(ray, min, max, filter)
+    }
+}
+
+impl<B, G, F, GH> HittableWithStats<F, Rc<G>> for BVHTree<F, B, G, GH>
+where
+    F: BaseFloat,
+    B: Mergeable<B, Result = B> + Hittable<F, ()>,
+    G: Hittable<F, GH>,
+{
+    fn hit_with_stats(&self, ray: &Ray<F>, min: F, max: F, stats: &mut TraversalStats) -> Option<HitRecord<F, Rc<G>>> {
+        stats.ray_count += 1;
+        self.root.borrow().hit_counted(ray, min, max, stats)
+    }
 }
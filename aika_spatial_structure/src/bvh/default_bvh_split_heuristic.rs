@@ -1,8 +1,8 @@
-use std::rc::Rc;
-use cgmath::BaseFloat;
-use aika_math::{Axis, HaveCenter};
+use cgmath::{BaseFloat, Vector3};
+use aika_math::Axis;
 use crate::bvh::BVHSplitHeuristic;
 
+#[derive(Clone)]
 pub struct DefaultBVHSplitHeuristic {
     next_axis: Axis
 }
@@ -16,30 +16,15 @@ impl Default for DefaultBVHSplitHeuristic {
 }
 
 impl BVHSplitHeuristic for DefaultBVHSplitHeuristic {
-
-    fn split<F: BaseFloat, G: HaveCenter<F>>(&mut self, objects: &[Rc<G>]) -> (Vec<Rc<G>>, Vec<Rc<G>>) {
-        let mut objects_with_positions = objects.iter().map(|obj| {
-            (obj.clone(), obj.get_center())
-        }).collect::<Vec<_>>();
-        objects_with_positions.sort_by(|a, b| {
-            let va = self.next_axis.extract_value_vec3(a.1);
-            let vb = self.next_axis.extract_value_vec3(b.1);
+    fn split<F: BaseFloat>(&mut self, centers: &[Vector3<F>], indices: &mut [usize]) -> usize {
+        // a stable sort, so that objects with the same center keep the input order
+        indices.sort_by(|&a, &b| {
+            let va = self.next_axis.extract_value_vec3(centers[a]);
+            let vb = self.next_axis.extract_value_vec3(centers[b]);
 
             va.partial_cmp(&vb).unwrap()
         });
 
-        let mid = objects_with_positions.len() / 2;
-
-        let vec1 = objects_with_positions.iter()
-            .take(mid)
-            .map(|x| &x.0)
-            .cloned()
-            .collect::<Vec<_>>();
-        let vec2 = objects_with_positions.iter()
-            .skip(mid)
-            .map(|x| &x.0)
-            .cloned()
-            .collect::<Vec<_>>();
-        (vec1, vec2)
+        indices.len() / 2
     }
-}
\ No newline at end of file
+}
@@ -3,10 +3,14 @@ pub use bvh_split_heuristic::BVHSplitHeuristic;
 pub use default_bvh_split_heuristic::DefaultBVHSplitHeuristic;
 pub use bvh_tree::BVHTree;
 pub use bvh_builder::BVHBuilder;
+pub use bvh_statistics::BVHStatistics;
 
 mod bvh_node;
 mod bvh_tree;
 mod bvh_split_heuristic;
 mod default_bvh_split_heuristic;
 mod bvh_builder;
+mod bvh_serialize;
+mod bvh_statistics;
+#[cfg(test)]
 mod bvh_test;
@@ -0,0 +1,12 @@
+use aika_math::Sphere;
+use crate::grid::*;
+use crate::test_utils::{assert_same_as_naive, get_test_spheres};
+
+#[test]
+fn test_uniform_grid_same_as_naive() {
+    let objects = get_test_spheres();
+    let mut builder = UniformGridBuilder::new();
+    builder.add_objects(&objects);
+    let grid: UniformGrid<f64, Sphere<f64>, ()> = builder.build();
+    assert_same_as_naive(&objects, &grid);
+}
@@ -0,0 +1,7 @@
+pub use uniform_grid::UniformGrid;
+pub use uniform_grid_builder::UniformGridBuilder;
+
+mod uniform_grid;
+mod uniform_grid_builder;
+#[cfg(test)]
+mod grid_test;
@@ -0,0 +1,173 @@
+use std::marker::PhantomData;
+use std::rc::Rc;
+use cgmath::{BaseFloat, Vector3};
+use aika_math::*;
+use crate::stats::{HittableWithStats, TraversalStats};
+
+/// F: Float type
+/// G: Geometry type
+/// GH: Geometry Hittable data
+pub struct UniformGrid<F, G, GH> {
+    pub bounds: AABB<F>,
+    pub resolution: [usize; 3],
+    pub cell_size: Vector3<F>,
+    /// indices into `objects`, x changes fastest
+    pub cells: Vec<Vec<usize>>,
+    pub objects: Vec<Rc<G>>,
+
+    pub _geometry_hittable_phantom: PhantomData<GH>,
+}
+
+impl<F, G, GH> UniformGrid<F, G, GH> where F: BaseFloat {
+    pub fn cell_index(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.resolution[1] + y) * self.resolution[0] + x
+    }
+
+    /// The cell containing `p` along `axis`, clamped into the grid
+    pub fn position_to_cell(&self, p: F, axis: usize) -> usize {
+        let bb_min = self.bounds.min();
+        let cell = ((p - bb_min[axis]) / self.cell_size[axis]).floor();
+        if cell < F::zero() {
+            0
+        } else {
+            cell.to_usize().unwrap_or(usize::MAX).min(self.resolution[axis] - 1)
+        }
+    }
+}
+
+impl<F, G, GH> UniformGrid<F, G, GH>
+where
+    F: BaseFloat,
+    G: Hittable<F, GH>,
+{
+    /// Visit the cells along the ray front to back with 3D DDA
+    /// `visit_cell` returns the new max t of the ray, traversal stops when it returns None
+    fn traverse<V>(&self, ray: &Ray<F>, min: F, max: F, stats: &mut TraversalStats, mut visit_cell: V)
+    where
+        V: FnMut(&[usize], F) -> Option<F>
+    {
+        if self.objects.is_empty() {
+            return;
+        }
+        let (t0, t1) = match self.bounds.hit_interval(ray, min, max) {
+            Some(x) => x,
+            None => return
+        };
+
+        let bb_min = self.bounds.min();
+        let entry = ray.origin + ray.direction * t0;
+        let mut pos = [0_isize; 3];
+        let mut step = [0_isize; 3];
+        let mut out = [0_isize; 3];
+        let mut next_t = [F::infinity(); 3];
+        let mut delta_t = [F::zero(); 3];
+        for axis in 0..3 {
+            pos[axis] = self.position_to_cell(entry[axis], axis) as isize;
+            let d = ray.direction[axis];
+            if d > F::zero() {
+                let next_border = bb_min[axis] + F::from(pos[axis] + 1).unwrap() * self.cell_size[axis];
+                next_t[axis] = t0 + (next_border - entry[axis]) / d;
+                delta_t[axis] = self.cell_size[axis] / d;
+                step[axis] = 1;
+                out[axis] = self.resolution[axis] as isize;
+            } else if d < F::zero() {
+                let next_border = bb_min[axis] + F::from(pos[axis]).unwrap() * self.cell_size[axis];
+                next_t[axis] = t0 + (next_border - entry[axis]) / d;
+                delta_t[axis] = -self.cell_size[axis] / d;
+                step[axis] = -1;
+                out[axis] = -1;
+            }
+        }
+
+        let mut ray_max = max;
+        loop {
+            stats.nodes_visited += 1;
+            let cell = self.cell_index(pos[0] as usize, pos[1] as usize, pos[2] as usize);
+            match visit_cell(&self.cells[cell], ray_max) {
+                Some(t) => ray_max = t,
+                None => return
+            }
+
+            let axis = if next_t[0] < next_t[1] && next_t[0] < next_t[2] {
+                0
+            } else if next_t[1] < next_t[2] {
+                1
+            } else {
+                2
+            };
+            // the closest hit is inside this cell
+            if ray_max <= next_t[axis] || next_t[axis] > t1 {
+                break;
+            }
+            pos[axis] += step[axis];
+            if pos[axis] == out[axis] {
+                break;
+            }
+            next_t[axis] += delta_t[axis];
+        }
+    }
+}
+
+impl<F, G, GH> Hittable<F, Rc<G>> for UniformGrid<F, G, GH>
+where
+    F: BaseFloat,
+    G: Hittable<F, GH>,
+{
+    fn hit(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, Rc<G>>> {
+        self.hit_with_stats(ray, min, max, &mut TraversalStats::default())
+    }
+
+    fn occluded_filtered(&self, ray: &Ray<F>, min: F, max: F, filter: &dyn Fn(&HitRecord<F, Rc<G>>) -> bool) -> bool {
+        let mut occluded = false;
+        self.traverse(ray, min, max, &mut TraversalStats::default(), |indices, ray_max| {
+            for &i in indices.iter() {
+                let obj = &self.objects[i];
+                if let Some(r) = obj.hit(ray, min, ray_max) {
+                    let mut hr = HitRecord::new();
+                    r.copy_except_hit_object(&mut hr);
+                    hr.hit_object = Some(obj.clone());
+                    if filter(&hr) {
+                        occluded = true;
+                        return None;
+                    }
+                }
+            }
+            Some(ray_max)
+        });
+        occluded
+    }
+}
+
+impl<F, G, GH> HittableWithStats<F, Rc<G>> for UniformGrid<F, G, GH>
+where
+    F: BaseFloat,
+    G: Hittable<F, GH>,
+{
+    fn hit_with_stats(&self, ray: &Ray<F>, min: F, max: F, stats: &mut TraversalStats) -> Option<HitRecord<F, Rc<G>>> {
+        stats.ray_count += 1;
+        let mut hr: HitRecord<F, Rc<G>> = HitRecord::new();
+        let mut is_hit = false;
+        let mut primitives_tested = 0;
+        self.traverse(ray, min, max, stats, |indices, ray_max| {
+            let mut ray_max = ray_max;
+            primitives_tested += indices.len();
+            for &i in indices.iter() {
+                let obj = &self.objects[i];
+                if let Some(r) = obj.hit(ray, min, ray_max) {
+                    ray_max = r.t;
+                    r.copy_except_hit_object(&mut hr);
+                    hr.hit_object = Some(obj.clone());
+                    is_hit = true;
+                }
+            }
+            Some(ray_max)
+        });
+        stats.primitives_tested += primitives_tested;
+
+        if is_hit {
+            Some(hr)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::marker::PhantomData;
+use std::rc::Rc;
+use cgmath::{BaseFloat, Vector3};
+use aika_math::*;
+use crate::grid::UniformGrid;
+
+pub struct UniformGridBuilder<F, G, GH> {
+    /// cells along the longest axis per cube root of the object count
+    pub density: F,
+    pub max_resolution: usize,
+
+    pub objects: Vec<Rc<G>>,
+    _geometry_hittable_phantom: PhantomData<GH>,
+}
+
+impl<F, G, GH> Default for UniformGridBuilder<F, G, GH> where F: BaseFloat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F, G, GH> UniformGridBuilder<F, G, GH> where F: BaseFloat {
+    pub fn new() -> Self {
+        Self {
+            density: F::from(3.0).unwrap(),
+            max_resolution: 64,
+            objects: Vec::new(),
+            _geometry_hittable_phantom: PhantomData,
+        }
+    }
+
+    pub fn add_object(&mut self, obj: Rc<G>) {
+        self.objects.push(obj);
+    }
+
+    pub fn add_objects(&mut self, obj: &[Rc<G>]) {
+        for item in obj.iter() {
+            self.objects.push(item.clone());
+        }
+    }
+}
+
+impl<F, G, GH> UniformGridBuilder<F, G, GH>
+where
+    F: BaseFloat,
+    G: Bounded<AABB<F>>,
+{
+    pub fn build(&self) -> UniformGrid<F, G, GH> {
+        let bvs = self.objects.iter().map(|obj| obj.get_bv()).collect::<Vec<_>>();
+        let bounds = match bvs.first() {
+            Some(first) => bvs.iter().skip(1).fold(first.clone(), |acc, bv| acc.union(bv)),
+            None => AABB::zero(),
+        };
+
+        let diag = bounds.max() - bounds.min();
+        let max_width = diag.x.max(diag.y).max(diag.z);
+        let n = F::from(bvs.len()).unwrap();
+        let cells_per_unit = if max_width > F::zero() {
+            self.density * n.cbrt() / max_width
+        } else {
+            F::zero()
+        };
+
+        let mut resolution = [1_usize; 3];
+        let mut cell_size = Vector3::new(F::one(), F::one(), F::one());
+        for axis in 0..3 {
+            let r = (diag[axis] * cells_per_unit).round().to_usize().unwrap_or(1);
+            resolution[axis] = r.clamp(1, self.max_resolution);
+            if diag[axis] > F::zero() {
+                cell_size[axis] = diag[axis] / F::from(resolution[axis]).unwrap();
+            }
+        }
+
+        let mut grid = UniformGrid {
+            bounds,
+            resolution,
+            cell_size,
+            cells: vec![Vec::new(); resolution[0] * resolution[1] * resolution[2]],
+            objects: self.objects.clone(),
+            _geometry_hittable_phantom: PhantomData,
+        };
+
+        for (i, bv) in bvs.iter().enumerate() {
+            let bv_min = bv.min();
+            let bv_max = bv.max();
+            let lo = [0, 1, 2].map(|axis| grid.position_to_cell(bv_min[axis], axis));
+            let hi = [0, 1, 2].map(|axis| grid.position_to_cell(bv_max[axis], axis));
+            for z in lo[2]..=hi[2] {
+                for y in lo[1]..=hi[1] {
+                    for x in lo[0]..=hi[0] {
+                        let index = grid.cell_index(x, y, z);
+                        grid.cells[index].push(i);
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+}
@@ -0,0 +1,202 @@
+use std::marker::PhantomData;
+use std::rc::Rc;
+use cgmath::BaseFloat;
+use aika_math::*;
+use crate::kd_tree::KdTree;
+use crate::kd_tree::sah_kd_tree::KdNode;
+
+/// Builds a kd-tree with the surface area heuristic
+pub struct KdTreeBuilder<F, G, GH> {
+    pub intersect_cost: F,
+    pub traversal_cost: F,
+    /// cost reduction of splits that leave one side empty
+    pub empty_bonus: F,
+    pub max_objects: usize,
+    /// if None, 8 + 1.3 log(n) is used
+    pub max_depth: Option<usize>,
+
+    pub objects: Vec<Rc<G>>,
+    _geometry_hittable_phantom: PhantomData<GH>,
+}
+
+struct BoundEdge<F> {
+    t: F,
+    object: usize,
+    is_end: bool,
+}
+
+struct KdBuildState<F> {
+    nodes: Vec<KdNode<F>>,
+    object_indices: Vec<usize>,
+}
+
+impl<F, G, GH> Default for KdTreeBuilder<F, G, GH> where F: BaseFloat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F, G, GH> KdTreeBuilder<F, G, GH> where F: BaseFloat {
+    pub fn new() -> Self {
+        Self {
+            intersect_cost: F::from(80.0).unwrap(),
+            traversal_cost: F::one(),
+            empty_bonus: F::from(0.5).unwrap(),
+            max_objects: 1,
+            max_depth: None,
+            objects: Vec::new(),
+            _geometry_hittable_phantom: PhantomData,
+        }
+    }
+
+    pub fn add_object(&mut self, obj: Rc<G>) {
+        self.objects.push(obj);
+    }
+
+    pub fn add_objects(&mut self, obj: &[Rc<G>]) {
+        for item in obj.iter() {
+            self.objects.push(item.clone());
+        }
+    }
+}
+
+impl<F, G, GH> KdTreeBuilder<F, G, GH>
+where
+    F: BaseFloat,
+    G: Bounded<AABB<F>>,
+{
+    fn make_leaf(state: &mut KdBuildState<F>, indices: &[usize]) {
+        let start = state.object_indices.len();
+        state.object_indices.extend_from_slice(indices);
+        state.nodes.push(KdNode::Leaf {
+            start,
+            end: state.object_indices.len()
+        });
+    }
+
+    fn build_node(&self, state: &mut KdBuildState<F>, bvs: &[AABB<F>], node_bounds: &AABB<F>, indices: Vec<usize>, depth: usize, bad_refines: usize) {
+        let n = indices.len();
+        if n <= self.max_objects || depth == 0 {
+            KdTreeBuilder::<F, G, GH>::make_leaf(state, &indices);
+            return;
+        }
+
+        let two = F::from(2.0).unwrap();
+        let node_min = node_bounds.min();
+        let node_max = node_bounds.max();
+        let diag = node_max - node_min;
+        let inv_total_area = F::one() / node_bounds.area();
+        let old_cost = self.intersect_cost * F::from(n).unwrap();
+
+        let mut best_cost = F::infinity();
+        let mut best: Option<(usize, usize)> = None;
+        let mut edges: Vec<BoundEdge<F>> = Vec::with_capacity(2 * n);
+
+        // try the longest axis first
+        let mut axis = if diag.x > diag.y && diag.x > diag.z { 0 } else if diag.y > diag.z { 1 } else { 2 };
+        for _ in 0..3 {
+            edges.clear();
+            for &i in indices.iter() {
+                edges.push(BoundEdge { t: bvs[i].min()[axis], object: i, is_end: false });
+                edges.push(BoundEdge { t: bvs[i].max()[axis], object: i, is_end: true });
+            }
+            // at the same position, start edges go first
+            edges.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap().then(a.is_end.cmp(&b.is_end)));
+
+            let other0 = (axis + 1) % 3;
+            let other1 = (axis + 2) % 3;
+            let mut n_below = 0;
+            let mut n_above = n;
+            for (k, edge) in edges.iter().enumerate() {
+                if edge.is_end {
+                    n_above -= 1;
+                }
+                let t = edge.t;
+                if t > node_min[axis] && t < node_max[axis] {
+                    let below_area = two * (diag[other0] * diag[other1] + (t - node_min[axis]) * (diag[other0] + diag[other1]));
+                    let above_area = two * (diag[other0] * diag[other1] + (node_max[axis] - t) * (diag[other0] + diag[other1]));
+                    let p_below = below_area * inv_total_area;
+                    let p_above = above_area * inv_total_area;
+                    let eb = if n_above == 0 || n_below == 0 { self.empty_bonus } else { F::zero() };
+                    let cost = self.traversal_cost
+                        + self.intersect_cost * (F::one() - eb) * (p_below * F::from(n_below).unwrap() + p_above * F::from(n_above).unwrap());
+                    if cost < best_cost {
+                        best_cost = cost;
+                        best = Some((axis, k));
+                    }
+                }
+                if !edge.is_end {
+                    n_below += 1;
+                }
+            }
+
+            if best.is_some() {
+                break;
+            }
+            axis = (axis + 1) % 3;
+        }
+
+        let mut bad_refines = bad_refines;
+        if best_cost > old_cost {
+            bad_refines += 1;
+        }
+        let (axis, offset) = match best {
+            Some(x) if !((best_cost > F::from(4.0).unwrap() * old_cost && n < 16) || bad_refines == 3) => x,
+            _ => {
+                KdTreeBuilder::<F, G, GH>::make_leaf(state, &indices);
+                return;
+            }
+        };
+
+        // `edges` are the edges of the best axis, since the search stops at the first axis with a valid split
+        let split = edges[offset].t;
+        let below = edges[..offset].iter().filter(|e| !e.is_end).map(|e| e.object).collect::<Vec<_>>();
+        let above = edges[offset + 1..].iter().filter(|e| e.is_end).map(|e| e.object).collect::<Vec<_>>();
+        drop(edges);
+        drop(indices);
+
+        let mut below_max = node_max;
+        below_max[axis] = split;
+        let mut above_min = node_min;
+        above_min[axis] = split;
+
+        let node_index = state.nodes.len();
+        state.nodes.push(KdNode::Interior {
+            axis,
+            split,
+            above_child: 0
+        });
+        self.build_node(state, bvs, &AABB::from_min_max(node_min, below_max), below, depth - 1, bad_refines);
+        let above_child = state.nodes.len();
+        if let KdNode::Interior { above_child: c, .. } = &mut state.nodes[node_index] {
+            *c = above_child;
+        }
+        self.build_node(state, bvs, &AABB::from_min_max(above_min, node_max), above, depth - 1, bad_refines);
+    }
+
+    pub fn build(&self) -> KdTree<F, G, GH> {
+        let bvs = self.objects.iter().map(|obj| obj.get_bv()).collect::<Vec<_>>();
+        let mut state = KdBuildState {
+            nodes: Vec::new(),
+            object_indices: Vec::new(),
+        };
+
+        let bounds = match bvs.first() {
+            Some(first) => bvs.iter().skip(1).fold(first.clone(), |acc, bv| acc.union(bv)),
+            None => AABB::zero(),
+        };
+        if !bvs.is_empty() {
+            let n = bvs.len() as f64;
+            let max_depth = self.max_depth.unwrap_or((8.0 + 1.3 * n.log2()).round() as usize);
+            self.build_node(&mut state, &bvs, &bounds, (0..bvs.len()).collect(), max_depth, 0);
+        }
+
+        KdTree {
+            nodes: state.nodes,
+            object_indices: state.object_indices,
+            objects: self.objects.clone(),
+            bounds,
+            _geometry_hittable_phantom: PhantomData,
+        }
+    }
+}
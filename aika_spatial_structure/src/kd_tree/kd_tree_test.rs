@@ -0,0 +1,12 @@
+use aika_math::Sphere;
+use crate::kd_tree::*;
+use crate::test_utils::{assert_same_as_naive, get_test_spheres};
+
+#[test]
+fn test_kd_tree_same_as_naive() {
+    let objects = get_test_spheres();
+    let mut builder = KdTreeBuilder::new();
+    builder.add_objects(&objects);
+    let tree: KdTree<f64, Sphere<f64>, ()> = builder.build();
+    assert_same_as_naive(&objects, &tree);
+}
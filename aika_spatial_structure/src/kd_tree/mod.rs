@@ -0,0 +1,7 @@
+pub use sah_kd_tree::KdTree;
+pub use kd_tree_builder::KdTreeBuilder;
+
+mod sah_kd_tree;
+mod kd_tree_builder;
+#[cfg(test)]
+mod kd_tree_test;
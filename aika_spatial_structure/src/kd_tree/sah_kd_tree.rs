@@ -0,0 +1,177 @@
+use std::marker::PhantomData;
+use std::rc::Rc;
+use cgmath::BaseFloat;
+use aika_math::*;
+use crate::stats::{HittableWithStats, TraversalStats};
+
+pub enum KdNode<F> {
+    /// objects are `object_indices[start..end]`
+    Leaf {
+        start: usize,
+        end: usize,
+    },
+    /// the child below the split plane is the next node,
+    /// the child above the split plane is `nodes[above_child]`
+    Interior {
+        axis: usize,
+        split: F,
+        above_child: usize,
+    },
+}
+
+/// F: Float type
+/// G: Geometry type
+/// GH: Geometry Hittable data
+pub struct KdTree<F, G, GH> {
+    pub nodes: Vec<KdNode<F>>,
+    /// an object can be referenced by more than one leaf
+    pub object_indices: Vec<usize>,
+    pub objects: Vec<Rc<G>>,
+    pub bounds: AABB<F>,
+
+    pub _geometry_hittable_phantom: PhantomData<GH>,
+}
+
+struct KdTraversalItem<F> {
+    node: usize,
+    t_min: F,
+    t_max: F,
+}
+
+impl<F, G, GH> KdTree<F, G, GH>
+where
+    F: BaseFloat,
+    G: Hittable<F, GH>,
+{
+    /// Visit the leaves along the ray front to back
+    /// `visit_leaf` returns the new max t of the ray, traversal stops when it returns None
+    fn traverse<V>(&self, ray: &Ray<F>, min: F, max: F, stats: &mut TraversalStats, mut visit_leaf: V)
+    where
+        V: FnMut(&[usize], F) -> Option<F>
+    {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let (mut t_min, mut t_max) = match self.bounds.hit_interval(ray, min, max) {
+            Some(x) => x,
+            None => return
+        };
+
+        let inv_dir = ray.direction.map(|x| F::one() / x);
+        let mut ray_max = max;
+        let mut stack: Vec<KdTraversalItem<F>> = Vec::new();
+        let mut node = 0;
+        loop {
+            if ray_max < t_min {
+                break;
+            }
+            stats.nodes_visited += 1;
+            match &self.nodes[node] {
+                KdNode::Interior { axis, split, above_child } => {
+                    let axis = *axis;
+                    let t_plane = (*split - ray.origin[axis]) * inv_dir[axis];
+                    let below_first = ray.origin[axis] < *split || (ray.origin[axis] == *split && ray.direction[axis] <= F::zero());
+                    let (first, second) = if below_first {
+                        (node + 1, *above_child)
+                    } else {
+                        (*above_child, node + 1)
+                    };
+
+                    if t_plane > t_max || t_plane <= F::zero() {
+                        node = first;
+                    } else if t_plane < t_min {
+                        node = second;
+                    } else {
+                        stack.push(KdTraversalItem {
+                            node: second,
+                            t_min: t_plane,
+                            t_max
+                        });
+                        node = first;
+                        t_max = t_plane;
+                    }
+                    continue;
+                },
+                KdNode::Leaf { start, end } => {
+                    match visit_leaf(&self.object_indices[*start..*end], ray_max) {
+                        Some(t) => ray_max = t,
+                        None => return
+                    }
+                }
+            }
+
+            match stack.pop() {
+                Some(item) => {
+                    node = item.node;
+                    t_min = item.t_min;
+                    t_max = item.t_max;
+                },
+                None => break
+            }
+        }
+    }
+}
+
+impl<F, G, GH> Hittable<F, Rc<G>> for KdTree<F, G, GH>
+where
+    F: BaseFloat,
+    G: Hittable<F, GH>,
+{
+    fn hit(&self, ray: &Ray<F>, min: F, max: F) -> Option<HitRecord<F, Rc<G>>> {
+        self.hit_with_stats(ray, min, max, &mut TraversalStats::default())
+    }
+
+    fn occluded_filtered(&self, ray: &Ray<F>, min: F, max: F, filter: &dyn Fn(&HitRecord<F, Rc<G>>) -> bool) -> bool {
+        let mut occluded = false;
+        self.traverse(ray, min, max, &mut TraversalStats::default(), |indices, ray_max| {
+            for &i in indices.iter() {
+                let obj = &self.objects[i];
+                if let Some(r) = obj.hit(ray, min, ray_max) {
+                    let mut hr = HitRecord::new();
+                    r.copy_except_hit_object(&mut hr);
+                    hr.hit_object = Some(obj.clone());
+                    if filter(&hr) {
+                        occluded = true;
+                        return None;
+                    }
+                }
+            }
+            Some(ray_max)
+        });
+        occluded
+    }
+}
+
+impl<F, G, GH> HittableWithStats<F, Rc<G>> for KdTree<F, G, GH>
+where
+    F: BaseFloat,
+    G: Hittable<F, GH>,
+{
+    fn hit_with_stats(&self, ray: &Ray<F>, min: F, max: F, stats: &mut TraversalStats) -> Option<HitRecord<F, Rc<G>>> {
+        stats.ray_count += 1;
+        let mut hr: HitRecord<F, Rc<G>> = HitRecord::new();
+        let mut is_hit = false;
+        let mut primitives_tested = 0;
+        self.traverse(ray, min, max, stats, |indices, ray_max| {
+            let mut ray_max = ray_max;
+            primitives_tested += indices.len();
+            for &i in indices.iter() {
+                let obj = &self.objects[i];
+                if let Some(r) = obj.hit(ray, min, ray_max) {
+                    ray_max = r.t;
+                    r.copy_except_hit_object(&mut hr);
+                    hr.hit_object = Some(obj.clone());
+                    is_hit = true;
+                }
+            }
+            Some(ray_max)
+        });
+        stats.primitives_tested += primitives_tested;
+
+        if is_hit {
+            Some(hr)
+        } else {
+            None
+        }
+    }
+}
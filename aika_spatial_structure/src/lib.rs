@@ -2,3 +2,10 @@
 
 pub mod bvh;
 pub mod naive;
+pub mod serialize;
+pub mod kd_tree;
+pub mod grid;
+pub mod point_kd_tree;
+pub mod stats;
+#[cfg(test)]
+mod test_utils;
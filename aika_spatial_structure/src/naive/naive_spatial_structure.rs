@@ -2,6 +2,7 @@ use std::marker::PhantomData;
 use std::rc::Rc;
 use cgmath::BaseFloat;
 use aika_math::{HitRecord, Hittable, Ray};
+use crate::stats::{HittableWithStats, TraversalStats};
 
 pub struct NaiveSpatialStructure<F, G, GH> {
     pub items: Vec<Rc<G>>,
@@ -38,8 +39,8 @@ where
         let mut max = max;
         let mut hr: HitRecord<F, Rc<G>> = HitRecord::new();
         let mut is_hit = false;
-        for (_index, item) in self.items.iter().enumerate() {
-            let hit_result = item.hit(&ray, min, max);
+        for item in self.items.iter() {
+            let hit_result = item.hit(ray, min, max);
             if let Some(r) = hit_result {
                 max = r.t;
                 r.copy_except_hit_object(&mut hr);
@@ -54,4 +55,31 @@ where
             None
         }
     }
+
+    fn occluded_filtered(&self, ray: &Ray<F>, min: F, max: F, filter: &dyn Fn(&HitRecord<F, Rc<G>>) -> bool) -> bool {
+        for item in self.items.iter() {
+            if let Some(r) = item.hit(ray, min, max) {
+                let mut hr = HitRecord::new();
+                r.copy_except_hit_object(&mut hr);
+                hr.hit_object = Some(item.clone());
+                if filter(&hr) {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+}
+
+impl<F, G, GH> HittableWithStats<F, Rc<G>> for NaiveSpatialStructure<F, G, GH>
+where
+    F: BaseFloat,
+    G: Hittable<F, GH>
+{
+    fn hit_with_stats(&self, ray: &Ray<F>, min: F, max: F, stats: &mut TraversalStats) -> Option<HitRecord<F, Rc<G>>> {
+        stats.ray_count += 1;
+        stats.primitives_tested += self.items.len();
+        self.hit(ray, min, max)
+    }
 }
@@ -0,0 +1,5 @@
+pub use point_tree::{PointKdTree, PointQueryResult};
+
+mod point_tree;
+#[cfg(test)]
+mod point_kd_tree_test;
@@ -0,0 +1,64 @@
+use cgmath::{InnerSpace, Vector3};
+use crate::point_kd_tree::*;
+
+fn test_points() -> Vec<(Vector3<f64>, usize)> {
+    (0..1000).map(|i| {
+        let x = ((i * 37) % 101) as f64 * 0.1;
+        let y = ((i * 53) % 89) as f64 * 0.1;
+        let z = ((i * 29) % 97) as f64 * 0.1;
+        (Vector3::new(x, y, z), i)
+    }).collect()
+}
+
+fn brute_force(points: &[(Vector3<f64>, usize)], p: Vector3<f64>) -> Vec<(f64, usize)> {
+    let mut result = points.iter().map(|(q, i)| ((q - p).magnitude2(), *i)).collect::<Vec<_>>();
+    result.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    result
+}
+
+#[test]
+fn test_point_kd_tree_nearest_k() {
+    let points = test_points();
+    let tree = PointKdTree::build(points.clone());
+    assert_eq!(tree.len(), 1000);
+
+    for j in 0..50 {
+        let p = Vector3::new(j as f64 * 0.2, 5.0 - j as f64 * 0.1, 3.3);
+        let expected = brute_force(&points, p);
+        let result = tree.nearest_k(p, 8);
+        assert_eq!(result.len(), 8);
+        for (r, e) in result.iter().zip(expected.iter()) {
+            assert_eq!(r.distance_squared, e.0);
+        }
+    }
+}
+
+#[test]
+fn test_point_kd_tree_within_radius() {
+    let points = test_points();
+    let tree = PointKdTree::build(points.clone());
+
+    let p = Vector3::new(5.0, 4.0, 3.0);
+    let mut result = tree.within_radius(p, 1.5).iter().map(|r| *r.payload).collect::<Vec<_>>();
+    result.sort();
+    let mut expected = brute_force(&points, p).into_iter().filter(|x| x.0 <= 1.5 * 1.5).map(|x| x.1).collect::<Vec<_>>();
+    expected.sort();
+    assert!(!expected.is_empty());
+    assert_eq!(result, expected);
+
+    let mut count = 0;
+    tree.for_each_within_radius(p, 1.5, |_, _, _| count += 1);
+    assert_eq!(count, expected.len());
+}
+
+#[test]
+fn test_point_kd_tree_parallel() {
+    let tree = PointKdTree::build(test_points());
+    let queries = (0..100).map(|i| Vector3::new(i as f64 * 0.1, 2.0, 7.0)).collect::<Vec<_>>();
+    let result = tree.nearest_k_parallel(&queries, 4);
+    assert_eq!(result.len(), queries.len());
+    for (q, r) in queries.iter().zip(result.iter()) {
+        let serial = tree.nearest_k(*q, 4);
+        assert_eq!(r.iter().map(|x| *x.payload).collect::<Vec<_>>(), serial.iter().map(|x| *x.payload).collect::<Vec<_>>());
+    }
+}
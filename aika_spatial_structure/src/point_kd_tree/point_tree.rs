@@ -0,0 +1,221 @@
+use std::thread;
+use cgmath::{BaseFloat, InnerSpace, Vector3};
+use num_traits::float::TotalOrder;
+
+/// A balanced kd-tree over points, each point carries a payload of type T
+/// The tree is implicit: for a range of `items`, the median item is the node,
+/// the items before it are the left subtree and the items after it are the right subtree
+pub struct PointKdTree<F, T> {
+    positions: Vec<Vector3<F>>,
+    payloads: Vec<T>,
+    /// split axis of the node at the same index
+    axes: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct PointQueryResult<'a, F, T> {
+    pub distance_squared: F,
+    pub position: Vector3<F>,
+    pub payload: &'a T,
+}
+
+impl<'a, F, T> Clone for PointQueryResult<'a, F, T> where F: Copy {
+    fn clone(&self) -> Self {
+        Self {
+            distance_squared: self.distance_squared,
+            position: self.position,
+            payload: self.payload,
+        }
+    }
+}
+
+fn build_range<F: BaseFloat + TotalOrder>(positions: &[Vector3<F>], order: &mut [usize], axes: &mut [u8]) {
+    if order.len() <= 1 {
+        return;
+    }
+
+    let mut min = positions[order[0]];
+    let mut max = positions[order[0]];
+    for &i in order.iter().skip(1) {
+        let p = positions[i];
+        for axis in 0..3 {
+            min[axis] = min[axis].min(p[axis]);
+            max[axis] = max[axis].max(p[axis]);
+        }
+    }
+    let diag = max - min;
+    let axis = if diag.x > diag.y && diag.x > diag.z { 0 } else if diag.y > diag.z { 1 } else { 2 };
+
+    let mid = order.len() / 2;
+    order.select_nth_unstable_by(mid, |&a, &b| positions[a][axis].total_cmp(&positions[b][axis]));
+    axes[mid] = axis as u8;
+
+    let (left_order, right_order) = order.split_at_mut(mid);
+    let (left_axes, right_axes) = axes.split_at_mut(mid);
+    build_range(positions, left_order, left_axes);
+    build_range(positions, &mut right_order[1..], &mut right_axes[1..]);
+}
+
+impl<F, T> PointKdTree<F, T> where F: BaseFloat {
+    /// Build the tree from all the points at once
+    pub fn build(points: Vec<(Vector3<F>, T)>) -> Self where F: TotalOrder {
+        let (positions, payloads): (Vec<_>, Vec<_>) = points.into_iter().unzip();
+        let mut order = (0..positions.len()).collect::<Vec<_>>();
+        let mut axes = vec![0_u8; positions.len()];
+        build_range(&positions, &mut order, &mut axes);
+
+        // move the points into tree order
+        let mut payloads = payloads.into_iter().map(Some).collect::<Vec<_>>();
+        let sorted_positions = order.iter().map(|&i| positions[i]).collect();
+        let sorted_payloads = order.iter().map(|&i| payloads[i].take().unwrap()).collect();
+
+        PointKdTree {
+            positions: sorted_positions,
+            payloads: sorted_payloads,
+            axes,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    fn visit_radius<V>(&self, start: usize, end: usize, p: Vector3<F>, radius_squared: F, visit: &mut V)
+    where
+        V: FnMut(usize, F)
+    {
+        if start >= end {
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let axis = self.axes[mid] as usize;
+        let position = self.positions[mid];
+
+        let d2 = (position - p).magnitude2();
+        if d2 <= radius_squared {
+            visit(mid, d2);
+        }
+
+        let delta = p[axis] - position[axis];
+        let (near, far) = if delta <= F::zero() {
+            ((start, mid), (mid + 1, end))
+        } else {
+            ((mid + 1, end), (start, mid))
+        };
+        self.visit_radius(near.0, near.1, p, radius_squared, visit);
+        if delta * delta <= radius_squared {
+            self.visit_radius(far.0, far.1, p, radius_squared, visit);
+        }
+    }
+
+    /// Call `visit` for every point within `radius` of `p`, without allocation
+    /// The arguments are (position, payload, distance squared)
+    pub fn for_each_within_radius<V>(&self, p: Vector3<F>, radius: F, mut visit: V)
+    where
+        V: FnMut(Vector3<F>, &T, F)
+    {
+        self.visit_radius(0, self.len(), p, radius * radius, &mut |i, d2| {
+            visit(self.positions[i], &self.payloads[i], d2);
+        });
+    }
+
+    /// All the points within `radius` of `p`, in no particular order
+    pub fn within_radius(&self, p: Vector3<F>, radius: F) -> Vec<PointQueryResult<'_, F, T>> {
+        let mut result = Vec::new();
+        self.visit_radius(0, self.len(), p, radius * radius, &mut |i, d2| {
+            result.push(self.make_result(i, d2));
+        });
+        result
+    }
+
+    /// The `k` nearest points of `p`, sorted by distance
+    pub fn nearest_k(&self, p: Vector3<F>, k: usize) -> Vec<PointQueryResult<'_, F, T>> {
+        self.nearest_k_within_radius(p, k, F::infinity())
+    }
+
+    /// The `k` nearest points of `p` within `max_radius`, sorted by distance
+    pub fn nearest_k_within_radius(&self, p: Vector3<F>, k: usize, max_radius: F) -> Vec<PointQueryResult<'_, F, T>> {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        // (distance squared, index), sorted, at most k items
+        let mut found: Vec<(F, usize)> = Vec::with_capacity(k + 1);
+        self.visit_nearest(0, self.len(), p, k, max_radius * max_radius, &mut found);
+
+        found.into_iter().map(|(d2, i)| self.make_result(i, d2)).collect()
+    }
+
+    fn visit_nearest(&self, start: usize, end: usize, p: Vector3<F>, k: usize, max_radius_squared: F, found: &mut Vec<(F, usize)>) {
+        if start >= end {
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let axis = self.axes[mid] as usize;
+        let position = self.positions[mid];
+
+        let d2 = (position - p).magnitude2();
+        if d2 <= max_radius_squared && (found.len() < k || d2 < found[found.len() - 1].0) {
+            let index = found.partition_point(|x| x.0 <= d2);
+            found.insert(index, (d2, mid));
+            found.truncate(k);
+        }
+
+        let delta = p[axis] - position[axis];
+        let (near, far) = if delta <= F::zero() {
+            ((start, mid), (mid + 1, end))
+        } else {
+            ((mid + 1, end), (start, mid))
+        };
+        self.visit_nearest(near.0, near.1, p, k, max_radius_squared, found);
+
+        let bound = if found.len() < k {
+            max_radius_squared
+        } else {
+            found[found.len() - 1].0.min(max_radius_squared)
+        };
+        if delta * delta <= bound {
+            self.visit_nearest(far.0, far.1, p, k, max_radius_squared, found);
+        }
+    }
+
+    fn make_result(&self, index: usize, distance_squared: F) -> PointQueryResult<'_, F, T> {
+        PointQueryResult {
+            distance_squared,
+            position: self.positions[index],
+            payload: &self.payloads[index],
+        }
+    }
+}
+
+impl<F, T> PointKdTree<F, T> where F: BaseFloat + Send + Sync, T: Sync {
+    /// Run `query` for every point in `queries` on multiple threads, results are in the same order as `queries`
+    pub fn query_parallel<'a, R, Q>(&'a self, queries: &[Vector3<F>], query: Q) -> Vec<R>
+    where
+        R: Send,
+        Q: Fn(&'a Self, Vector3<F>) -> R + Sync,
+    {
+        let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let chunk_size = queries.len().div_ceil(threads).max(1);
+
+        let query = &query;
+        thread::scope(|s| {
+            let handles = queries.chunks(chunk_size).map(|chunk| {
+                s.spawn(move || chunk.iter().map(|&p| query(self, p)).collect::<Vec<_>>())
+            }).collect::<Vec<_>>();
+            handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+        })
+    }
+
+    pub fn nearest_k_parallel(&self, queries: &[Vector3<F>], k: usize) -> Vec<Vec<PointQueryResult<'_, F, T>>> {
+        self.query_parallel(queries, |tree, p| tree.nearest_k(p, k))
+    }
+
+    pub fn within_radius_parallel(&self, queries: &[Vector3<F>], radius: F) -> Vec<Vec<PointQueryResult<'_, F, T>>> {
+        self.query_parallel(queries, |tree, p| tree.within_radius(p, radius))
+    }
+}
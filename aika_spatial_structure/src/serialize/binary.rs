@@ -0,0 +1,71 @@
+use std::io::{Read, Write};
+use anyhow::{bail, Result};
+use cgmath::{BaseFloat, Vector3};
+
+// All the values are stored in little endian, floats are always stored as f64
+
+pub fn write_u32<W: Write>(writer: &mut W, value: u32) -> Result<()> {
+    writer.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+pub fn write_u64<W: Write>(writer: &mut W, value: u64) -> Result<()> {
+    writer.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+pub fn write_float<W: Write, F: BaseFloat>(writer: &mut W, value: F) -> Result<()> {
+    writer.write_all(&value.to_f64().unwrap().to_le_bytes())?;
+    Ok(())
+}
+
+pub fn write_vector3<W: Write, F: BaseFloat>(writer: &mut W, value: Vector3<F>) -> Result<()> {
+    write_float(writer, value.x)?;
+    write_float(writer, value.y)?;
+    write_float(writer, value.z)?;
+    Ok(())
+}
+
+pub fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut buf = [0_u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+pub fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut buf = [0_u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub fn read_float<R: Read, F: BaseFloat>(reader: &mut R) -> Result<F> {
+    let mut buf = [0_u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(F::from(f64::from_le_bytes(buf)).unwrap())
+}
+
+pub fn read_vector3<R: Read, F: BaseFloat>(reader: &mut R) -> Result<Vector3<F>> {
+    let x = read_float(reader)?;
+    let y = read_float(reader)?;
+    let z = read_float(reader)?;
+    Ok(Vector3::new(x, y, z))
+}
+
+/// Check the magic bytes and the version at the beginning of a file
+pub fn read_header<R: Read>(reader: &mut R, magic: &[u8; 8], version: u32) -> Result<()> {
+    let mut buf = [0_u8; 8];
+    reader.read_exact(&mut buf)?;
+    if &buf != magic {
+        bail!("invalid magic");
+    }
+    let v = read_u32(reader)?;
+    if v != version {
+        bail!("unsupported version {}, expected {}", v, version);
+    }
+    Ok(())
+}
+
+pub fn write_header<W: Write>(writer: &mut W, magic: &[u8; 8], version: u32) -> Result<()> {
+    writer.write_all(magic)?;
+    write_u32(writer, version)
+}
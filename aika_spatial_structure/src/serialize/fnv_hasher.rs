@@ -0,0 +1,28 @@
+use std::hash::Hasher;
+
+/// 64-bit FNV-1a
+/// Unlike `DefaultHasher`, the result is stable across runs and rust versions, so it can be used as a cache key
+pub struct FnvHasher {
+    state: u64,
+}
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        FnvHasher {
+            state: 0xcbf29ce484222325
+        }
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.state
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes.iter() {
+            self.state ^= b as u64;
+            self.state = self.state.wrapping_mul(0x100000001b3);
+        }
+    }
+}
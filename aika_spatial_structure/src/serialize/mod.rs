@@ -0,0 +1,5 @@
+pub use binary::*;
+pub use fnv_hasher::FnvHasher;
+
+mod binary;
+mod fnv_hasher;
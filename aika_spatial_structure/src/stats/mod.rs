@@ -0,0 +1,3 @@
+pub use traversal_stats::{TraversalStats, HittableWithStats};
+
+mod traversal_stats;
@@ -0,0 +1,40 @@
+use aika_math::{HitRecord, Hittable, Ray};
+
+/// Counters of ray traversals, can be accumulated over many rays
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraversalStats {
+    pub ray_count: usize,
+    /// nodes (or grid cells) whose bounds are tested
+    pub nodes_visited: usize,
+    /// ray-primitive intersection tests
+    pub primitives_tested: usize,
+}
+
+impl TraversalStats {
+    pub fn add(&mut self, other: &TraversalStats) {
+        self.ray_count += other.ray_count;
+        self.nodes_visited += other.nodes_visited;
+        self.primitives_tested += other.primitives_tested;
+    }
+
+    pub fn average_nodes_visited(&self) -> f64 {
+        if self.ray_count == 0 {
+            0.0
+        } else {
+            self.nodes_visited as f64 / self.ray_count as f64
+        }
+    }
+
+    pub fn average_primitives_tested(&self) -> f64 {
+        if self.ray_count == 0 {
+            0.0
+        } else {
+            self.primitives_tested as f64 / self.ray_count as f64
+        }
+    }
+}
+
+pub trait HittableWithStats<F, H>: Hittable<F, H> {
+    /// Same as `hit`, and counts the traversal of this ray into `stats`
+    fn hit_with_stats(&self, ray: &Ray<F>, min: F, max: F, stats: &mut TraversalStats) -> Option<HitRecord<F, H>>;
+}
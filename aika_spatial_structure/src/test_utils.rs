@@ -0,0 +1,39 @@
+use std::rc::Rc;
+use cgmath::{InnerSpace, Vector3};
+use aika_math::{Hittable, Ray, Sphere};
+use crate::naive::NaiveSpatialStructure;
+
+/// Spheres of different sizes scattered over a box
+pub(crate) fn get_test_spheres() -> Vec<Rc<Sphere<f64>>> {
+    let mut objects = Vec::new();
+    for i in 0..200 {
+        let x = ((i * 37) % 23) as f64 - 11.0;
+        let y = ((i * 11) % 17) as f64 - 8.0;
+        let z = ((i * 7) % 13) as f64 - 6.0;
+        objects.push(Rc::new(Sphere::new(Vector3::new(x, y, z), 0.2 + (i % 5) as f64 * 0.3)));
+    }
+    objects
+}
+
+/// Check that `structure` built over `objects` finds the same closest hits and occlusion as a naive list
+pub(crate) fn assert_same_as_naive<H>(objects: &[Rc<Sphere<f64>>], structure: &impl Hittable<f64, H>) {
+    let mut naive: NaiveSpatialStructure<f64, Sphere<f64>, ()> = NaiveSpatialStructure::new();
+    naive.add_objects(objects.iter().cloned());
+
+    let mut hit_count = 0;
+    for i in 0..500 {
+        let origin = Vector3::new(((i * 13) % 29) as f64 - 14.0, ((i * 5) % 19) as f64 - 9.0, -20.0);
+        let direction = Vector3::new(((i * 3) % 7) as f64 - 3.0, ((i * 17) % 11) as f64 - 5.0, 10.0).normalize();
+        let ray = Ray::new(origin, direction);
+
+        let a = structure.hit(&ray, 0.0, f64::INFINITY);
+        let b = naive.hit(&ray, 0.0, f64::INFINITY);
+        assert_eq!(a.is_some(), b.is_some());
+        if let (Some(a), Some(b)) = (a, b) {
+            assert!((a.t - b.t).abs() < 1e-9);
+            hit_count += 1;
+        }
+        assert_eq!(structure.occluded(&ray, 0.0, 15.0), naive.occluded(&ray, 0.0, 15.0));
+    }
+    assert!(hit_count > 100);
+}